@@ -0,0 +1,126 @@
+// A terminal frontend built on the library API alone. The library has no rendering or input of
+// its own, so a frontend decides how to show the display and where key presses come from; here
+// that's split into a Renderer and an InputSource so either side can be swapped out.
+//
+//     cargo run --example custom_frontend -- [path/to/rom.ch8]
+//
+// Without a ROM it shows the built-in splash screen. Keys are scripted rather than read from the
+// terminal, which keeps the example free of platform-specific terminal handling.
+
+use ch8emu::chip8::{Chip8, StopReason, TIMER_HZ};
+use ch8emu::splash::SPLASH_ROM;
+use std::io::Write;
+use std::process::ExitCode;
+use std::time::Duration;
+
+const FRAME_DURATION: Duration = Duration::from_micros(1_000_000 / TIMER_HZ);
+const FRAMES: u64 = 3 * TIMER_HZ;
+
+trait Renderer {
+    fn render(&mut self, system: &Chip8);
+}
+
+trait InputSource {
+    // The keypad key (0x0 to 0xF) held down during `frame`.
+    fn key_for_frame(&mut self, frame: u64) -> Option<u8>;
+}
+
+// Redraws the whole screen with ANSI escapes, two pixels per character cell.
+struct TerminalRenderer {
+    out: std::io::Stdout,
+}
+
+impl Renderer for TerminalRenderer {
+    fn render(&mut self, system: &Chip8) {
+        let display = system.display();
+        let mut frame = String::from("\x1B[H");
+        for y in (0..display.height).step_by(2) {
+            for x in 0..display.width {
+                let top = display.is_pixel_set(x, y);
+                let bottom = y + 1 < display.height && display.is_pixel_set(x, y + 1);
+                frame.push(match (top, bottom) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                });
+            }
+            frame.push('\n');
+        }
+        let mut out = self.out.lock();
+        // A closed terminal only loses the picture, the emulation can carry on.
+        let _ = out.write_all(frame.as_bytes());
+        let _ = out.flush();
+    }
+}
+
+// Presses each key for one frame at a fixed interval.
+struct ScriptedInput {
+    keys: Vec<u8>,
+    frames_per_key: u64,
+}
+
+impl InputSource for ScriptedInput {
+    fn key_for_frame(&mut self, frame: u64) -> Option<u8> {
+        if !frame.is_multiple_of(self.frames_per_key) {
+            return None;
+        }
+        let index = (frame / self.frames_per_key) as usize % self.keys.len();
+        Some(self.keys[index])
+    }
+}
+
+fn run(system: &mut Chip8, renderer: &mut dyn Renderer, input: &mut dyn InputSource) -> ExitCode {
+    let mut last_generation = None;
+    for frame in 0..FRAMES {
+        let pressed = match input.key_for_frame(frame) {
+            Some(key) => system.press_key(key),
+            None => {
+                system.release_key();
+                Ok(())
+            }
+        };
+        let summary = match pressed.and_then(|()| system.run_for(FRAME_DURATION)) {
+            Ok(summary) => summary,
+            Err(err) => {
+                eprintln!("Emulation failed: {:?}", err);
+                return ExitCode::FAILURE;
+            }
+        };
+        if last_generation != Some(system.display_generation()) {
+            renderer.render(system);
+            last_generation = Some(system.display_generation());
+        }
+        if summary.stopped == Some(StopReason::Halted) {
+            break;
+        }
+        std::thread::sleep(FRAME_DURATION);
+    }
+    ExitCode::SUCCESS
+}
+
+fn main() -> ExitCode {
+    let mut system = Chip8::booted();
+    let loaded = match std::env::args().nth(1) {
+        Some(rom_path) => system
+            .load_rom_from_file(&rom_path)
+            .map_err(|err| format!("Failed to load {}: {}", rom_path, err)),
+        None => system
+            .load_rom(&SPLASH_ROM)
+            .map_err(|err| format!("Failed to load the splash ROM: {:?}", err)),
+    };
+    if let Err(message) = loaded {
+        eprintln!("{}", message);
+        return ExitCode::FAILURE;
+    }
+
+    print!("\x1B[2J");
+    let mut renderer = TerminalRenderer {
+        out: std::io::stdout(),
+    };
+    let mut input = ScriptedInput {
+        keys: vec![0x5, 0x4, 0x6, 0x8],
+        frames_per_key: TIMER_HZ / 4,
+    };
+    run(&mut system, &mut renderer, &mut input)
+}
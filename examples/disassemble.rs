@@ -0,0 +1,37 @@
+// Prints a ROM's instructions and data. Only code reachable from the start is decoded, unless
+// --linear asks for every word to be decoded.
+// Without a ROM it disassembles the built-in splash screen.
+//
+//     cargo run --example disassemble -- [--linear] [path/to/rom.ch8]
+
+use ch8emu::chip8::{disassemble, Sweep};
+use ch8emu::splash::SPLASH_ROM;
+use std::process::ExitCode;
+
+const START: u16 = 0x200;
+
+fn main() -> ExitCode {
+    let mut sweep = Sweep::Reachable;
+    let mut rom_path = None;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--linear" => sweep = Sweep::Linear,
+            path => rom_path = Some(path.to_string()),
+        }
+    }
+    let rom = match rom_path {
+        Some(rom_path) => match std::fs::read(&rom_path) {
+            Ok(rom) => rom,
+            Err(err) => {
+                eprintln!("Failed to load {}: {}", rom_path, err);
+                return ExitCode::FAILURE;
+            }
+        },
+        None => SPLASH_ROM.to_vec(),
+    };
+
+    for line in disassemble(&rom, START, sweep) {
+        println!("{}", line);
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,56 @@
+// Runs a ROM for a number of instructions without a window and prints the final screen.
+//
+//     cargo run --example headless_run -- path/to/rom.ch8 [instructions]
+
+use ch8emu::chip8::{BlockReason, Chip8, StepOutcome};
+use std::process::ExitCode;
+
+const DEFAULT_INSTRUCTIONS: u64 = 1000;
+// Instructions per 60 Hz timer tick at the emulator's default speed of 600 per second.
+const INSTRUCTIONS_PER_TICK: u64 = 10;
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let Some(rom_path) = args.next() else {
+        eprintln!("Usage: headless_run <rom> [instructions]");
+        return ExitCode::FAILURE;
+    };
+    let instructions = match args.next().map(|value| value.parse()) {
+        None => DEFAULT_INSTRUCTIONS,
+        Some(Ok(instructions)) => instructions,
+        Some(Err(_)) => {
+            eprintln!("Invalid instruction count");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut system = Chip8::booted();
+    if let Err(err) = system.load_rom_from_file(&rom_path) {
+        eprintln!("Failed to load {}: {}", rom_path, err);
+        return ExitCode::FAILURE;
+    }
+
+    for count in 1..=instructions {
+        match system.step() {
+            Ok(StepOutcome::Blocked(BlockReason::WaitingForKey(_))) => {
+                println!("Waiting for a key after {} instructions", count);
+                break;
+            }
+            Ok(StepOutcome::Halted(reason)) => {
+                println!("Halted after {} instructions: {:?}", count, reason);
+                break;
+            }
+            Ok(_) => {}
+            Err(err) => {
+                eprintln!("Failed after {} instructions: {:?}", count, err);
+                return ExitCode::FAILURE;
+            }
+        }
+        if count % INSTRUCTIONS_PER_TICK == 0 {
+            system.tick_timers();
+        }
+    }
+
+    println!("{}", system.render_ascii_with_status());
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,60 @@
+// Prints each of the first 200 instructions a ROM executes, decoded, with the address it ran at
+// and its encoding.
+// Without a ROM it traces the built-in splash screen.
+//
+//     cargo run --example trace_rom -- [path/to/rom.ch8]
+
+use ch8emu::chip8::{Chip8, StepOutcome};
+use ch8emu::splash::SPLASH_ROM;
+use std::process::ExitCode;
+
+const TRACED_INSTRUCTIONS: usize = 200;
+
+fn main() -> ExitCode {
+    let mut system = Chip8::booted();
+    let loaded = match std::env::args().nth(1) {
+        Some(rom_path) => system
+            .load_rom_from_file(&rom_path)
+            .map_err(|err| format!("Failed to load {}: {}", rom_path, err)),
+        None => system
+            .load_rom(&SPLASH_ROM)
+            .map_err(|err| format!("Failed to load the splash ROM: {:?}", err)),
+    };
+    if let Err(message) = loaded {
+        eprintln!("{}", message);
+        return ExitCode::FAILURE;
+    }
+
+    for _ in 0..TRACED_INSTRUCTIONS {
+        let peeked = match system.peek_instruction() {
+            Ok(peeked) => peeked,
+            Err(err) => {
+                eprintln!("{:#05X}: can't decode: {:?}", system.pc(), err);
+                return ExitCode::FAILURE;
+            }
+        };
+        let outcome = match system.step() {
+            Ok(outcome) => outcome,
+            Err(err) => {
+                eprintln!("{:#05X}: {:?} failed: {:?}", peeked.address, peeked.op, err);
+                return ExitCode::FAILURE;
+            }
+        };
+        let encoding = match peeked.operand {
+            Some(operand) => format!("{:04X} {:04X}", peeked.word, operand),
+            None => format!("{:04X}", peeked.word),
+        };
+        println!(
+            "{:#05X}: {:<9} {:<28} {:?}",
+            peeked.address,
+            encoding,
+            format!("{:?}", peeked.op),
+            outcome
+        );
+        // Nothing changes after these, so the rest of the trace would repeat the same line.
+        if matches!(outcome, StepOutcome::Halted(_) | StepOutcome::Blocked(_)) {
+            break;
+        }
+    }
+    ExitCode::SUCCESS
+}
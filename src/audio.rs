@@ -0,0 +1,532 @@
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+const RETRY_INTERVAL: Duration = Duration::from_secs(2);
+
+pub trait AudioSink {
+    fn start(&mut self);
+    fn stop(&mut self);
+}
+
+#[derive(Debug, PartialEq)]
+pub enum AudioError {
+    NoDevice,
+    DeviceLost(String),
+}
+
+// A single opened output. Any error is treated as the device having gone away.
+pub trait OutputStream {
+    fn start(&mut self) -> Result<(), AudioError>;
+    fn stop(&mut self) -> Result<(), AudioError>;
+}
+
+pub trait OutputDevices {
+    type Stream: OutputStream;
+
+    fn list(&self) -> Vec<String>;
+    // Opens the first device whose name contains the filter, or the default device.
+    fn open(&mut self, name_filter: Option<&str>) -> Result<Self::Stream, AudioError>;
+}
+
+// Rings the terminal bell once at the start of every beep.
+#[derive(Debug)]
+pub struct TerminalBell;
+
+impl OutputStream for TerminalBell {
+    fn start(&mut self) -> Result<(), AudioError> {
+        let mut stdout = std::io::stdout();
+        stdout
+            .write_all(b"\x07")
+            .and_then(|_| stdout.flush())
+            .map_err(|err| AudioError::DeviceLost(err.to_string()))
+    }
+
+    fn stop(&mut self) -> Result<(), AudioError> {
+        Ok(())
+    }
+}
+
+// The only devices there are for now: playing a tone needs an audio library, which the crate
+// doesn't depend on yet. One goes in as another OutputDevices, with ResilientSink unchanged.
+#[derive(Debug)]
+pub struct TerminalDevices;
+
+const TERMINAL_BELL_NAME: &str = "terminal bell";
+
+impl OutputDevices for TerminalDevices {
+    type Stream = TerminalBell;
+
+    fn list(&self) -> Vec<String> {
+        vec![TERMINAL_BELL_NAME.to_string()]
+    }
+
+    fn open(&mut self, name_filter: Option<&str>) -> Result<TerminalBell, AudioError> {
+        match name_filter {
+            Some(filter) if !TERMINAL_BELL_NAME.contains(filter) => Err(AudioError::NoDevice),
+            _ => Ok(TerminalBell),
+        }
+    }
+}
+
+// Audio sink that survives the output device failing or disappearing: it falls back to silence
+// and periodically tries to reacquire a device, resuming the tone if it's still supposed to play.
+#[derive(Debug)]
+pub struct ResilientSink<D: OutputDevices> {
+    devices: D,
+    name_filter: Option<String>,
+    stream: Option<D::Stream>,
+    playing: bool,
+    last_attempt: Option<Instant>,
+}
+
+impl<D: OutputDevices> ResilientSink<D> {
+    pub fn new(devices: D, name_filter: Option<String>, now: Instant) -> Self {
+        let mut sink = ResilientSink {
+            devices,
+            name_filter,
+            stream: None,
+            playing: false,
+            last_attempt: None,
+        };
+        sink.poll(now);
+        sink
+    }
+
+    // Retries opening a device if the sink is running silent and the retry interval has passed.
+    pub fn poll(&mut self, now: Instant) {
+        if self.stream.is_some() {
+            return;
+        }
+        let first_attempt = self.last_attempt.is_none();
+        if let Some(last_attempt) = self.last_attempt {
+            if now.duration_since(last_attempt) < RETRY_INTERVAL {
+                return;
+            }
+        }
+        self.last_attempt = Some(now);
+
+        match self.devices.open(self.name_filter.as_deref()) {
+            Ok(stream) => {
+                println!("Audio device acquired");
+                self.stream = Some(stream);
+                if self.playing {
+                    self.start();
+                }
+            }
+            Err(err) if first_attempt => {
+                println!("Audio device not available ({:?}), running silent", err)
+            }
+            Err(_) => {}
+        }
+    }
+
+    // Whether there's a device to play on, rather than running silent.
+    pub fn is_available(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    fn with_stream<F>(&mut self, action: F)
+    where
+        F: FnOnce(&mut D::Stream) -> Result<(), AudioError>,
+    {
+        if let Some(stream) = &mut self.stream {
+            if let Err(err) = action(stream) {
+                println!("Audio device lost ({:?}), running silent", err);
+                self.stream = None;
+            }
+        }
+    }
+}
+
+impl<D: OutputDevices> AudioSink for ResilientSink<D> {
+    fn start(&mut self) {
+        self.playing = true;
+        self.with_stream(|stream| stream.start());
+    }
+
+    fn stop(&mut self) {
+        self.playing = false;
+        self.with_stream(|stream| stream.stop());
+    }
+}
+
+// Drives an audio sink from the sound timer state, only forwarding transitions. While paused the
+// sink is kept silent, and the tone is resumed on unpause if the sound timer is still active.
+#[derive(Debug)]
+pub struct Beeper<S: AudioSink> {
+    sink: S,
+    sound_active: bool,
+    playing: bool,
+    paused: bool,
+    muted: bool,
+}
+
+impl<S: AudioSink> Beeper<S> {
+    pub fn new(sink: S) -> Self {
+        Beeper {
+            sink,
+            sound_active: false,
+            playing: false,
+            paused: false,
+            muted: false,
+        }
+    }
+
+    pub fn sink_mut(&mut self) -> &mut S {
+        &mut self.sink
+    }
+
+    pub fn update(&mut self, sound_active: bool) {
+        self.sound_active = sound_active;
+        self.sync();
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        self.sync();
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.muted
+    }
+
+    // Muting only silences the output, the sound timer keeps running as the ROM expects.
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+        self.sync();
+    }
+
+    fn sync(&mut self) {
+        let should_play = self.sound_active && !self.paused && !self.muted;
+        if should_play && !self.playing {
+            self.sink.start();
+        } else if !should_play && self.playing {
+            self.sink.stop();
+        }
+        self.playing = should_play;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ch8emu::chip8::{
+        assemble_opcodes, BootConfig, Chip8, Opcode, Options, DEFAULT_MIN_BEEP_TICKS,
+    };
+    use ch8emu::replay::FRAME_DURATION;
+
+    #[derive(Debug, Default)]
+    struct RecordingSink {
+        calls: Vec<&'static str>,
+    }
+
+    impl AudioSink for RecordingSink {
+        fn start(&mut self) {
+            self.calls.push("start");
+        }
+
+        fn stop(&mut self) {
+            self.calls.push("stop");
+        }
+    }
+
+    // Fake device API: each opened stream shares a flag that the test flips to simulate the device
+    // being unplugged.
+    #[derive(Debug, Default)]
+    struct FakeDevices {
+        available: std::rc::Rc<std::cell::Cell<bool>>,
+        started: std::rc::Rc<std::cell::Cell<u32>>,
+        opened: u32,
+    }
+
+    #[derive(Debug)]
+    struct FakeStream {
+        available: std::rc::Rc<std::cell::Cell<bool>>,
+        started: std::rc::Rc<std::cell::Cell<u32>>,
+    }
+
+    impl OutputStream for FakeStream {
+        fn start(&mut self) -> Result<(), AudioError> {
+            if !self.available.get() {
+                return Err(AudioError::DeviceLost("unplugged".to_string()));
+            }
+            self.started.set(self.started.get() + 1);
+            Ok(())
+        }
+
+        fn stop(&mut self) -> Result<(), AudioError> {
+            if !self.available.get() {
+                return Err(AudioError::DeviceLost("unplugged".to_string()));
+            }
+            Ok(())
+        }
+    }
+
+    impl OutputDevices for FakeDevices {
+        type Stream = FakeStream;
+
+        fn list(&self) -> Vec<String> {
+            vec!["headphones".to_string(), "speakers".to_string()]
+        }
+
+        fn open(&mut self, name_filter: Option<&str>) -> Result<FakeStream, AudioError> {
+            if !self.available.get() {
+                return Err(AudioError::NoDevice);
+            }
+            if let Some(filter) = name_filter {
+                if !self.list().iter().any(|name| name.contains(filter)) {
+                    return Err(AudioError::NoDevice);
+                }
+            }
+            self.opened += 1;
+            Ok(FakeStream {
+                available: self.available.clone(),
+                started: self.started.clone(),
+            })
+        }
+    }
+
+    fn fake_devices(available: bool) -> FakeDevices {
+        let devices = FakeDevices::default();
+        devices.available.set(available);
+        devices
+    }
+
+    #[test]
+    fn test_resilient_sink_opens_device() {
+        let sink = ResilientSink::new(fake_devices(true), None, Instant::now());
+
+        assert!(sink.stream.is_some());
+        assert_eq!(sink.devices.opened, 1);
+    }
+
+    #[test]
+    fn test_resilient_sink_name_filter() {
+        let now = Instant::now();
+
+        assert!(
+            ResilientSink::new(fake_devices(true), Some("phones".to_string()), now)
+                .stream
+                .is_some()
+        );
+        assert!(
+            ResilientSink::new(fake_devices(true), Some("hdmi".to_string()), now)
+                .stream
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_resilient_sink_falls_back_to_silence_on_device_loss() {
+        let now = Instant::now();
+        let devices = fake_devices(true);
+        let available = devices.available.clone();
+        let started = devices.started.clone();
+        let mut sink = ResilientSink::new(devices, None, now);
+
+        available.set(false);
+        sink.start();
+
+        assert!(!sink.is_available());
+        // Silent from then on: nothing plays and nothing reopens before the retry interval.
+        sink.stop();
+        sink.start();
+        sink.poll(now);
+        assert!(!sink.is_available());
+        assert_eq!(started.get(), 0);
+        assert_eq!(sink.devices.opened, 1);
+        assert!(sink.playing);
+    }
+
+    #[test]
+    fn test_resilient_sink_retries_after_interval() {
+        let now = Instant::now();
+        let devices = fake_devices(false);
+        let available = devices.available.clone();
+        let mut sink = ResilientSink::new(devices, None, now);
+        assert!(sink.stream.is_none());
+
+        available.set(true);
+        sink.poll(now + RETRY_INTERVAL / 2);
+        assert!(sink.stream.is_none());
+
+        sink.poll(now + RETRY_INTERVAL);
+        assert!(sink.stream.is_some());
+    }
+
+    #[test]
+    fn test_resilient_sink_resumes_tone_after_reconnect() {
+        let now = Instant::now();
+        let devices = fake_devices(true);
+        let available = devices.available.clone();
+        let started = devices.started.clone();
+        let mut sink = ResilientSink::new(devices, None, now);
+
+        available.set(false);
+        sink.start();
+        available.set(true);
+        sink.poll(now + RETRY_INTERVAL);
+
+        assert!(sink.stream.is_some());
+        assert_eq!(started.get(), 1);
+        assert_eq!(sink.devices.opened, 2);
+    }
+
+    // Runs a program one frame at a time, passing the buzzer to a beeper the way the frontend
+    // does, and returns the frame each call to the sink was made on.
+    fn beeps(program: &[Opcode], min_beep_ticks: u8, frames: usize) -> Vec<(usize, &'static str)> {
+        let mut system = Chip8::with_options(Options {
+            min_beep_ticks,
+            ..Options::default()
+        })
+        .unwrap();
+        system.boot(BootConfig::default()).unwrap();
+        system.load_rom(&assemble_opcodes(program)).unwrap();
+        let mut beeper = Beeper::new(RecordingSink::default());
+        let mut calls = Vec::new();
+        for frame in 0..frames {
+            system.run_for(FRAME_DURATION).unwrap();
+            let report = system.frame();
+            if report.buzzer_edge {
+                beeper.update(report.buzzer_on);
+            }
+            calls.extend(beeper.sink.calls.drain(..).map(|call| (frame, call)));
+        }
+        calls
+    }
+
+    // Sets the sound timer to `ticks` once, then spins.
+    fn beep_once(ticks: u8) -> Vec<Opcode> {
+        vec![
+            Opcode::LoadByte(0x0, ticks),
+            Opcode::SetSoundTimer(0x0),
+            Opcode::AddByte(0x1, 0x1),
+            Opcode::Jump(0x204),
+        ]
+    }
+
+    #[test]
+    fn test_sound_timer_of_zero_or_one_is_silent() {
+        assert_eq!(beeps(&beep_once(0), DEFAULT_MIN_BEEP_TICKS, 10), vec![]);
+        assert_eq!(beeps(&beep_once(1), DEFAULT_MIN_BEEP_TICKS, 10), vec![]);
+        assert_eq!(
+            beeps(&beep_once(2), DEFAULT_MIN_BEEP_TICKS, 10),
+            vec![(0, "start"), (2, "stop")]
+        );
+    }
+
+    #[test]
+    fn test_sound_timer_of_one_beeps_when_configured() {
+        assert_eq!(beeps(&beep_once(1), 1, 10), vec![(0, "start"), (1, "stop")]);
+        assert_eq!(beeps(&beep_once(0), 1, 10), vec![]);
+    }
+
+    #[test]
+    fn test_sound_timer_set_to_zero_stops_beep() {
+        let program = [
+            Opcode::LoadByte(0x0, 30),
+            Opcode::SetSoundTimer(0x0),
+            // Waits on the delay timer, well before the sound timer runs out.
+            Opcode::LoadByte(0x2, 5),
+            Opcode::SetDelayTimer(0x2),
+            Opcode::LoadDelayTimer(0x2),
+            Opcode::SkipIfEqual(0x2, 0),
+            Opcode::Jump(0x208),
+            Opcode::LoadByte(0x1, 0),
+            Opcode::SetSoundTimer(0x1),
+            Opcode::AddByte(0x3, 0x1),
+            Opcode::Jump(0x212),
+        ];
+
+        assert_eq!(
+            beeps(&program, DEFAULT_MIN_BEEP_TICKS, 40),
+            vec![(0, "start"), (6, "stop")]
+        );
+    }
+
+    #[test]
+    fn test_sound_timer_reloaded_every_frame_keeps_one_tone() {
+        // Reloads the sound timer, then waits for the next frame.
+        let program = |ticks| {
+            vec![
+                Opcode::LoadByte(0x0, ticks),
+                Opcode::SetSoundTimer(0x0),
+                Opcode::LoadByte(0x2, 1),
+                Opcode::SetDelayTimer(0x2),
+                Opcode::LoadDelayTimer(0x2),
+                Opcode::SkipIfEqual(0x2, 0),
+                Opcode::Jump(0x208),
+                Opcode::Jump(0x202),
+            ]
+        };
+
+        assert_eq!(
+            beeps(&program(2), DEFAULT_MIN_BEEP_TICKS, 60),
+            vec![(0, "start")]
+        );
+        // Too short to start a beep, so never one.
+        assert_eq!(beeps(&program(1), DEFAULT_MIN_BEEP_TICKS, 60), vec![]);
+    }
+
+    #[test]
+    fn test_beeper_forwards_transitions_only() {
+        let mut beeper = Beeper::new(RecordingSink::default());
+
+        beeper.update(true);
+        beeper.update(true);
+        beeper.update(false);
+        beeper.update(false);
+
+        assert_eq!(beeper.sink.calls, vec!["start", "stop"]);
+    }
+
+    #[test]
+    fn test_beeper_paused_stops_and_resumes() {
+        let mut beeper = Beeper::new(RecordingSink::default());
+        beeper.update(true);
+
+        beeper.set_paused(true);
+        beeper.update(true);
+        beeper.set_paused(false);
+
+        assert_eq!(beeper.sink.calls, vec!["start", "stop", "start"]);
+    }
+
+    #[test]
+    fn test_beeper_muted_gates_sink() {
+        let mut beeper = Beeper::new(RecordingSink::default());
+        beeper.set_muted(true);
+
+        beeper.update(true);
+        beeper.update(false);
+        beeper.update(true);
+        assert!(beeper.sink.calls.is_empty());
+
+        beeper.set_muted(false);
+        assert_eq!(beeper.sink.calls, vec!["start"]);
+    }
+
+    #[test]
+    fn test_beeper_unpause_while_muted_stays_silent() {
+        let mut beeper = Beeper::new(RecordingSink::default());
+        beeper.set_muted(true);
+        beeper.set_paused(true);
+        beeper.update(true);
+
+        beeper.set_paused(false);
+
+        assert!(beeper.sink.calls.is_empty());
+    }
+
+    #[test]
+    fn test_beeper_unpause_after_sound_ended_stays_silent() {
+        let mut beeper = Beeper::new(RecordingSink::default());
+        beeper.update(true);
+        beeper.set_paused(true);
+
+        beeper.update(false);
+        beeper.set_paused(false);
+
+        assert_eq!(beeper.sink.calls, vec!["start", "stop"]);
+    }
+}
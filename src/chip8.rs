@@ -1,24 +1,122 @@
-use opcodes::{Opcode, OpcodeError};
-use rand::random;
+use crate::numfmt::{format_addr, format_word};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::convert::From;
-
-mod display;
-mod input;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+mod compare;
+mod detect;
+mod diagnostics;
+mod disasm;
+pub mod display;
+mod fnv;
+pub mod input;
+mod inspect;
 mod memory;
 mod opcodes;
+mod overdraw;
+mod pacer;
+mod profile;
+mod quirks;
 mod registers;
 mod stack;
 mod timers;
-
-const PROGRAM_START_ADDRESS: usize = 0x200;
+mod violations;
+
+pub use compare::{Difference, Divergence, Lockstep};
+pub use detect::{detect_targets, extension_target, is_legacy_hires, Detection};
+pub use diagnostics::{Diagnostic, Diagnostics, Severity, DIAGNOSTICS_CAPACITY};
+pub use disasm::{disassemble, Line, Sweep};
+pub use fnv::Fnv1a;
+pub use inspect::{
+    CounterView, Inspector, KeypadView, MemoryView, RegisterView, StackView, TimerView,
+};
+pub(crate) use memory::MEMORY_SIZE;
+pub use memory::{MAX_MEMORY_SIZE, MIN_MEMORY_SIZE};
+pub use opcodes::{assemble_opcodes, Family, Opcode, OpcodeError};
+pub use pacer::TIMER_HZ;
+pub use profile::{profile_text, OpcodeProfile};
+pub use quirks::{LoadStoreIMode, Quirks, Target};
+pub use violations::{Violation, Violations};
+
+pub const PROGRAM_START_ADDRESS: usize = 0x200;
+pub const DEFAULT_OPS_PER_SECOND: u64 = 600;
+pub const DEFAULT_MIN_BEEP_TICKS: u8 = 2;
+// Columns of the original 64x32 display. A wider display is SUPER-CHIP's high resolution mode;
+// the VIP's 64x64 hi-res mode is only taller.
+const LOW_RES_WIDTH: usize = 64;
+const LOW_RES_HEIGHT: usize = 32;
+// Where the program proper starts in a ROM for the VIP's two-page 64x64 hi-res mode, past the
+// interpreter patch it carries at 0x200.
+pub const LEGACY_HIRES_START: usize = 0x2C0;
+const LEGACY_HIRES_WIDTH: usize = 64;
+const LEGACY_HIRES_HEIGHT: usize = 64;
+// The hi-res interpreter's clear screen routine, called with 0230 instead of 00E0.
+const LEGACY_HIRES_CLEAR: u16 = 0x230;
+// Calls the COSMAC VIP's interpreter had stack space for. Deeper ones work here, but are a
+// violation since they wouldn't on the original.
+pub const VIP_STACK_DEPTH: usize = 12;
+// Length of the built-in font that boot loads.
+pub const FONT_LEN: usize = display::BUILT_IN_SPRITES.len() * display::SPRITE_LEN;
+
+type FamilyHandler = fn(&mut Chip8, Opcode) -> Result<StepOutcome, Chip8Error>;
+// Stands in for the native routine a 0NNN instruction called on the original interpreter.
+pub type SysHandler = Box<dyn FnMut(&mut Chip8) -> Result<(), Chip8Error> + Send>;
+
+// The registered SysHandlers by NNN, listed by address when debugging.
+#[derive(Default)]
+struct SysHandlers(BTreeMap<u16, SysHandler>);
+
+impl std::fmt::Debug for SysHandlers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.0.keys()).finish()
+    }
+}
 
 #[derive(Debug, PartialEq)]
+#[allow(clippy::enum_variant_names)]
 pub enum Chip8Error {
     StackError(stack::StackError),
     MemoryError(memory::MemoryError),
     OpcodeError(OpcodeError),
     DisplayError(display::DisplayError),
     InputError(input::Error),
+    AlignmentError(Misalignment),
+    ViolationError(Violation),
+    SpriteOverrunError(SpriteOverrun),
+    FontOverwriteError(FontOverwrite),
+}
+
+// An instruction that left pc at an odd address.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Misalignment {
+    // Address and encoding of the instruction that moved pc.
+    pub address: u16,
+    pub opcode: u16,
+    pub pc: u16,
+}
+
+// A draw whose sprite data ran past the end of memory.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpriteOverrun {
+    pub pc: u16,
+    pub i: u16,
+}
+
+// A ROM that would have been loaded over the built-in font.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FontOverwrite {
+    pub address: usize,
+    pub len: usize,
+}
+
+// Checks that a machine can have `size` bytes of memory, as Chip8::with_options does.
+pub fn check_memory_size(size: usize) -> Result<(), Chip8Error> {
+    Ok(memory::check_size(size)?)
 }
 
 impl From<stack::StackError> for Chip8Error {
@@ -27,6 +125,12 @@ impl From<stack::StackError> for Chip8Error {
     }
 }
 
+impl From<input::Error> for Chip8Error {
+    fn from(err: input::Error) -> Chip8Error {
+        Chip8Error::InputError(err)
+    }
+}
+
 impl From<memory::MemoryError> for Chip8Error {
     fn from(err: memory::MemoryError) -> Chip8Error {
         Chip8Error::MemoryError(err)
@@ -45,439 +149,3912 @@ impl From<display::DisplayError> for Chip8Error {
     }
 }
 
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Chip8Error::StackError(stack::StackError::StackOverflow) => {
+                write!(f, "calls nested deeper than the stack")
+            }
+            Chip8Error::StackError(stack::StackError::StackUnderflow) => {
+                write!(f, "return with an empty stack")
+            }
+            Chip8Error::MemoryError(memory::MemoryError::AddressOutOfBounds) => {
+                write!(f, "doesn't fit in memory")
+            }
+            Chip8Error::MemoryError(memory::MemoryError::InvalidSize(size)) => write!(
+                f,
+                "a memory size of {} bytes is outside {} to {}",
+                size, MIN_MEMORY_SIZE, MAX_MEMORY_SIZE
+            ),
+            Chip8Error::OpcodeError(OpcodeError::InvalidAddress(address)) => {
+                write!(
+                    f,
+                    "address {} is past the end of memory",
+                    format_addr(*address)
+                )
+            }
+            Chip8Error::OpcodeError(OpcodeError::InvalidOpcode(opcode)) => {
+                write!(f, "{} is not an instruction", format_word(*opcode))
+            }
+            Chip8Error::DisplayError(display::DisplayError::InvalidSprite(sprite)) => {
+                write!(f, "no built-in sprite for {:X}", sprite)
+            }
+            Chip8Error::DisplayError(display::DisplayError::InvalidDrawPosition(x, y)) => {
+                write!(f, "can't draw at {},{}", x, y)
+            }
+            Chip8Error::DisplayError(display::DisplayError::UnsupportedMode(width, height)) => {
+                write!(f, "no {}x{} display mode", width, height)
+            }
+            Chip8Error::InputError(input::Error::InvalidKey(key)) => {
+                write!(f, "`{}` is not a keypad key", key)
+            }
+            Chip8Error::AlignmentError(misalignment) => write!(
+                f,
+                "{} at {} moved pc to the odd address {}",
+                format_word(misalignment.opcode),
+                format_addr(misalignment.address),
+                format_addr(misalignment.pc)
+            ),
+            Chip8Error::ViolationError(violation) => write!(f, "{}", violation),
+            Chip8Error::SpriteOverrunError(overrun) => write!(
+                f,
+                "sprite at I={} drawn from {} runs past the end of memory",
+                format_addr(overrun.i),
+                format_addr(overrun.pc)
+            ),
+            Chip8Error::FontOverwriteError(overwrite) => write!(
+                f,
+                "{} bytes at {} would overwrite the built-in font",
+                overwrite.len,
+                format_addr(overwrite.address as u16)
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Options {
+    // Instructions executed per second of emulated time by run_for.
+    pub ops_per_second: u64,
+    pub quirks: Quirks,
+    // Stop with an error on undefined opcodes instead of logging and skipping them.
+    pub strict_decode: bool,
+    // Stop with an error when pc becomes odd instead of warning about it.
+    pub strict_alignment: bool,
+    // Stop with an error on reads of uninitialized memory, writes below the program area, sprites
+    // that extend past the end of memory and sprites drawn from outside the ROM and font.
+    pub strict_memory: bool,
+    // Stop with an error when I overflows past the end of memory.
+    pub strict_index: bool,
+    // Stop with an error on calls nested deeper than the VIP's stack, VIP_STACK_DEPTH, instead of
+    // warning about them.
+    pub strict_stack: bool,
+    // Stop with an error on 0NNN calls that have no handler instead of skipping them.
+    pub strict_sys: bool,
+    // Seed for CXNN, so that runs can be reproduced. Seeded from the OS when unset.
+    pub rng_seed: Option<u64>,
+    // Load the registers that are in memory when FX65 reaches past its end, leaving the rest as
+    // they were, instead of failing without loading any. strict_memory still makes it an error.
+    pub partial_reg_load: bool,
+    // Shortest sound timer value FX18 starts a beep with. The original interpreter didn't sound
+    // the buzzer for a single tick, but some ROMs use 1 deliberately.
+    pub min_beep_ticks: u8,
+    // Hold each key press until EX9E, EXA1 or FX0A has seen it or a frame has ended, for ROMs
+    // that poll the keypad too rarely to catch a quick tap.
+    pub sticky_keys: bool,
+    // Run a ROM loaded at the program start in the VIP's 64x64 hi-res mode. It's never turned on
+    // from the ROM alone, since its marking jump is an ordinary instruction; see is_legacy_hires
+    // for suggesting it.
+    pub legacy_hires: bool,
+    // Most 60 Hz frames of work run_for catches up on after a stall, the rest being dropped and
+    // reported in RunSummary. Unset, it runs everything the elapsed time allows for.
+    pub max_catch_up_frames: Option<u64>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            ops_per_second: DEFAULT_OPS_PER_SECOND,
+            quirks: Quirks::default(),
+            strict_decode: false,
+            strict_alignment: false,
+            strict_memory: false,
+            strict_index: false,
+            strict_stack: false,
+            strict_sys: false,
+            rng_seed: None,
+            partial_reg_load: false,
+            min_beep_ticks: DEFAULT_MIN_BEEP_TICKS,
+            sticky_keys: false,
+            legacy_hires: false,
+            max_catch_up_frames: None,
+        }
+    }
+}
+
+impl Options {
+    // Every strict option enabled, for validating ROMs.
+    pub fn strict() -> Self {
+        Options {
+            strict_decode: true,
+            strict_alignment: true,
+            strict_memory: true,
+            strict_index: true,
+            strict_stack: true,
+            strict_sys: true,
+            ..Options::default()
+        }
+    }
+}
+
+// How a machine starts, which differs between variants: the ETI-660 runs programs from 0x600.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BootConfig {
+    // Where load_rom puts the program and where execution starts.
+    pub program_start: u16,
+    // Zero V0-VF and I. Off keeps whatever values they were given before booting.
+    pub clear_registers: bool,
+    // Where the built-in font goes and FX29 points I into: 0x000 by default, or
+    // display::CONVENTIONAL_FONT_START for ROMs that read the font from 0x050 themselves.
+    pub font_start: usize,
+}
+
+impl Default for BootConfig {
+    fn default() -> Self {
+        BootConfig {
+            program_start: PROGRAM_START_ADDRESS as u16,
+            clear_registers: true,
+            font_start: display::SPRITE_START_ADDRESS,
+        }
+    }
+}
+
+// Why run_for or run_steps returned before executing all the instructions it could.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StopReason {
+    // FX0A is waiting for a key press.
+    WaitingForKey,
+    // The program jumped to itself, the conventional way to end a CHIP-8 program.
+    Halted,
+    // pc reached a breakpoint, or one of StopConditions::addresses, the instruction at it hasn't
+    // been executed yet.
+    Breakpoint(u16),
+    // The last instruction changed a watched address: (address, old value, new value).
+    Watchpoint(u16, u8, u8),
+    // Only run_steps stops for the rest, when StopConditions asks it to.
+    // The last instruction drew to, cleared or scrolled the display.
+    DisplayUpdated,
+    // The last instruction started the beeper.
+    SoundStarted,
+    // pc entered StopConditions::pc_in at this address, the instruction at it hasn't been
+    // executed yet.
+    PcInRange(u16),
+}
+
+// What else ends a run_steps call early, besides breakpoints and watchpoints set on the machine.
+// Conditions on pc aren't checked before the first instruction, so a call made from an address
+// that stopped the last one runs on from it.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct StopConditions {
+    // Addresses to stop at, as if they were breakpoints.
+    pub addresses: BTreeSet<u16>,
+    pub pc_in: Option<Range<u16>>,
+    pub display_updated: bool,
+    pub sound_started: bool,
+    // Stop when the program halts or waits for a key.
+    pub halted: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct RunResult {
+    // Instructions executed, counting one that waited for a key.
+    pub instructions: u32,
+    // None if all the instructions allowed for ran.
+    pub stopped: Option<StopReason>,
+}
+
+// Why an instruction couldn't complete yet. It's left at pc, so stepping again retries it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BlockReason {
+    // FX0A found no key pressed. The key will go into the given register.
+    WaitingForKey(u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HaltReason {
+    // The program jumped to itself, the conventional way to end a CHIP-8 program.
+    JumpToSelf,
+}
+
+// What a single instruction did, beyond its effect on registers and memory.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StepOutcome {
+    // The instruction, which ran, and pc moved on to the next one.
+    Executed(Opcode),
+    // A conditional skip instruction skipped the next instruction.
+    Skipped,
+    // Control moved somewhere other than the next instruction: a jump, call or return.
+    Jumped { to: u16 },
+    // The instruction is waiting on something outside the program, so a frontend can show that
+    // rather than keep stepping.
+    Blocked(BlockReason),
+    Halted(HaltReason),
+    // pc is at a breakpoint. The instruction hasn't been executed, the next step executes it.
+    BreakpointHit(u16),
+    // The instruction changed a watched address: (address, old value, new value). Only the first
+    // change made by an instruction is reported.
+    WatchpointHit(u16, u8, u8),
+}
+
+// The outcome of the conditional skip `op`.
+fn skip_outcome(skipped: bool, op: Opcode) -> StepOutcome {
+    if skipped {
+        StepOutcome::Skipped
+    } else {
+        StepOutcome::Executed(op)
+    }
+}
+
+// The error for `op` reaching the handler of a family it isn't in, which would be a bug in
+// Opcode::family rather than in the ROM.
+fn wrong_family(op: Opcode) -> Chip8Error {
+    Chip8Error::OpcodeError(OpcodeError::InvalidOpcode(u16::from_be_bytes(
+        op.to_bytes(),
+    )))
+}
+
+// Where a DXYN drew, for debug overlays.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DrawRecord {
+    // Top-left corner of the sprite once wrapped onto the display.
+    pub x: usize,
+    pub y: usize,
+    pub height: u8,
+    pub i_addr: u16,
+    pub collided: bool,
+    pub pc: u16,
+}
+
+// A memory write made by an instruction, for the write log.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MemoryWrite {
+    // Number of instructions executed before the one that wrote.
+    pub cycle: u64,
+    pub address: u16,
+    pub old: u8,
+    pub new: u8,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct RunSummary {
+    pub instructions: u64,
+    // Number of 60 Hz timer ticks.
+    pub frames: u64,
+    // Whether any instruction drew to or cleared the display.
+    pub display_dirty: bool,
+    // Whether any instruction read the keypad.
+    pub keys_polled: bool,
+    pub sound_active: bool,
+    pub stopped: Option<StopReason>,
+    // Whether every instruction the elapsed time allowed for ran, with none left over to a stop
+    // or a display wait.
+    pub budget_used: bool,
+    // Instructions and 60 Hz frames the elapsed time allowed for beyond
+    // Options::max_catch_up_frames, which weren't run.
+    pub dropped_instructions: u64,
+    pub dropped_frames: u64,
+}
+
+// An instruction decoded without executing it, see Chip8::peek_at.
+#[derive(Debug, PartialEq)]
+pub struct PeekedInstruction {
+    pub address: u16,
+    // The opcode word.
+    pub word: u16,
+    pub op: Opcode,
+    // The word following F000, which is the address it loads into I.
+    pub operand: Option<u16>,
+    // Bytes the instruction spans, 4 with an operand and 2 otherwise.
+    pub len: u16,
+}
+
+// A flat copy of the machine's state for language bindings, see Chip8::export_state. Everything
+// is plain data so that a thin FFI layer can copy it field by field.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportedState {
+    pub v: [u8; 16],
+    pub i: u16,
+    pub pc: u16,
+    // Number of return addresses on the stack; the rest of `stack` is zero.
+    pub sp: u8,
+    pub stack: [u16; stack::STACK_SIZE],
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub display_width: u16,
+    pub display_height: u16,
+    // Packed as Display::pack_into lays it out: row by row, 8 pixels to a byte.
+    pub framebuffer: Vec<u8>,
+}
+
+// What running the machine changes, see Chip8::snapshot. Breakpoints, cheats, options and the
+// logs of what happened aren't part of it.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pacer: pacer::Pacer,
+    waiting_for_vblank: bool,
+    key_awaiting_release: Option<u8>,
+    display_generation: u64,
+    memory: memory::Memory,
+    registers: registers::Registers,
+    stack: stack::Stack,
+    timers: timers::Timers,
+    display: display::Display,
+    input: input::Input,
+    rng: StdRng,
+    cycles: u64,
+    silent_sound_timer: bool,
+    beep_started: bool,
+}
+
+// The buzzer at a frame boundary, see Chip8::frame.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct FrameReport {
+    pub buzzer_on: bool,
+    // Whether buzzer_on differs from the previous report.
+    pub buzzer_edge: bool,
+    // Pixels turned off and back on during the frame, see set_overdraw_counting. Always 0 while
+    // counting is off.
+    pub overdraw: u32,
+}
+
 #[derive(Debug)]
 pub struct Chip8 {
+    options: Options,
+    pacer: pacer::Pacer,
+    display_dirty: bool,
+    keys_polled: bool,
+    // Incremented on every change to the display, so frontends can tell whether to repaint.
+    display_generation: u64,
+    // Set by a draw under the display wait quirk, run_for executes nothing until the next tick.
+    waiting_for_vblank: bool,
+    // The key FX0A saw pressed under the wait_for_key_on_release quirk, until it's released.
+    key_awaiting_release: Option<u8>,
+    violations: Violations,
+    // The warnings and errors printed most recently.
+    diagnostics: Diagnostics,
+    // Address of the instruction being executed, violations are reported against it.
+    instruction_address: u16,
+    breakpoints: BTreeSet<u16>,
+    // Set when run_for stopped at a breakpoint, so the next call executes past it.
+    resume_from_breakpoint: bool,
+    watchpoints: HashSet<u16>,
+    watchpoint_hit: Option<(u16, u8, u8)>,
+    // Bytes pinned to a value by address, see add_cheat.
+    cheats: BTreeMap<u16, u8>,
+    // Handlers for 0NNN by NNN, see set_sys_handler.
+    sys_handlers: SysHandlers,
     memory: memory::Memory,
     registers: registers::Registers,
     stack: stack::Stack,
     timers: timers::Timers,
     display: display::Display,
     input: input::Input,
+    // Length of the loaded ROM.
+    rom_len: usize,
+    // Set when the loaded ROM runs in the VIP's 64x64 hi-res mode.
+    legacy_hires: bool,
+    // Each word of the ROM decoded as it was loaded, see decode_program.
+    decoded_program: Vec<Result<Opcode, OpcodeError>>,
+    // Set by boot.
+    program_start: usize,
+    font_start: usize,
+    rng: StdRng,
+    record_draws: bool,
+    draw_records: Vec<DrawRecord>,
+    // Instructions executed since the machine was created.
+    cycles: u64,
+    // Only allocated while write logging is on.
+    memory_writes: Option<Vec<MemoryWrite>>,
+    // Only allocated while profiling is on, see set_profiling.
+    profile: Option<profile::Profile>,
+    // Only allocated while overdraw counting is on, see set_overdraw_counting.
+    overdraw: Option<overdraw::Overdraw>,
+    // See set_silenced.
+    silenced: bool,
+    // The buzzer state in the last FrameReport.
+    buzzer_reported: bool,
+    // The sound timer was started with a value below min_beep_ticks, so it runs without a beep.
+    silent_sound_timer: bool,
+    // A beep started since the last FrameReport. It's reported even if the timer ran out before
+    // the end of the frame, so that a one tick beep is heard.
+    beep_started: bool,
+}
+
+impl Default for Chip8 {
+    fn default() -> Self {
+        Chip8::new()
+    }
 }
 
 impl Chip8 {
     pub fn new() -> Chip8 {
-        Chip8 {
-            memory: memory::Memory::new(),
+        Chip8::with_options(Options::default()).expect("the default memory size should be valid")
+    }
+
+    // Fails if the quirks ask for a memory size outside MIN_MEMORY_SIZE..=MAX_MEMORY_SIZE.
+    pub fn with_options(options: Options) -> Result<Chip8, Chip8Error> {
+        Ok(Chip8 {
+            options,
+            pacer: pacer::Pacer::new(options.ops_per_second, options.max_catch_up_frames),
+            display_dirty: false,
+            keys_polled: false,
+            display_generation: 0,
+            waiting_for_vblank: false,
+            key_awaiting_release: None,
+            violations: Violations::new(),
+            diagnostics: Diagnostics::new(DIAGNOSTICS_CAPACITY),
+            instruction_address: 0,
+            breakpoints: BTreeSet::new(),
+            resume_from_breakpoint: false,
+            watchpoints: HashSet::new(),
+            watchpoint_hit: None,
+            cheats: BTreeMap::new(),
+            sys_handlers: SysHandlers::default(),
+            memory: memory::Memory::with_size(options.quirks.memory_size)?,
             registers: registers::Registers::new(),
             stack: stack::Stack::new(),
             timers: timers::Timers::new(),
             display: display::Display::new(),
-            input: input::Input::new(),
+            input: input::Input::with_sticky_keys(options.sticky_keys),
+            rom_len: 0,
+            legacy_hires: false,
+            decoded_program: Vec::new(),
+            program_start: PROGRAM_START_ADDRESS,
+            font_start: display::SPRITE_START_ADDRESS,
+            rng: match options.rng_seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_entropy(),
+            },
+            record_draws: false,
+            draw_records: Vec::new(),
+            cycles: 0,
+            memory_writes: None,
+            profile: None,
+            overdraw: None,
+            silenced: false,
+            buzzer_reported: false,
+            silent_sound_timer: false,
+            beep_started: false,
+        })
+    }
+
+    // A machine that's ready to have a ROM loaded.
+    pub fn booted() -> Chip8 {
+        let mut chip8 = Chip8::new();
+        chip8
+            .boot(BootConfig::default())
+            .expect("built-in sprites should fit in memory");
+        chip8
+    }
+
+    // Memory is left untouched if the ROM doesn't fit.
+    pub fn load_rom(&mut self, rom: &[u8]) -> Result<(), Chip8Error> {
+        self.load_rom_at(rom, self.program_start, false)
+    }
+
+    // Loads the ROM at `address` and starts the program there. A ROM that would overlap the
+    // built-in font is refused unless `overwrite_font` is set. Memory is left untouched if it's
+    // refused or doesn't fit.
+    pub fn load_rom_at(
+        &mut self,
+        rom: &[u8],
+        address: usize,
+        overwrite_font: bool,
+    ) -> Result<(), Chip8Error> {
+        let overlaps_font = !rom.is_empty()
+            && address < self.font_start + FONT_LEN
+            && self.font_start < address + rom.len();
+        if overlaps_font && !overwrite_font {
+            return Err(Chip8Error::FontOverwriteError(FontOverwrite {
+                address,
+                len: rom.len(),
+            }));
         }
+        self.load_bytes(address, rom)?;
+        self.program_start = address;
+        self.registers.pc = address as u16;
+        self.rom_len = rom.len();
+        self.decoded_program = rom
+            .chunks_exact(2)
+            .map(|word| Opcode::from_bytes(word[0], word[1], self.memory.size()))
+            .collect();
+        let legacy_hires = address == PROGRAM_START_ADDRESS && self.options.legacy_hires;
+        if legacy_hires != self.legacy_hires {
+            self.set_legacy_hires(legacy_hires);
+        }
+        if legacy_hires {
+            self.registers.pc = LEGACY_HIRES_START as u16;
+        }
+        Ok(())
+    }
+
+    // Switches between the VIP's 64x64 hi-res mode and the usual display, which starts blank.
+    fn set_legacy_hires(&mut self, legacy_hires: bool) {
+        let (width, height) = if legacy_hires {
+            (LEGACY_HIRES_WIDTH, LEGACY_HIRES_HEIGHT)
+        } else {
+            (display::DISPLAY_WIDTH, display::DISPLAY_HEIGHT)
+        };
+        self.display = self
+            .display
+            .resized(width, height)
+            .expect("display modes are valid");
+        self.display.clear();
+        self.mark_display_changed();
+        if legacy_hires {
+            self.set_sys_handler(LEGACY_HIRES_CLEAR, |chip8| {
+                chip8.clear_display();
+                Ok(())
+            });
+        } else {
+            self.sys_handlers.0.remove(&LEGACY_HIRES_CLEAR);
+        }
+        self.legacy_hires = legacy_hires;
     }
 
-    pub fn load_rom(&mut self, rom: &[u8]) {
-        for (i, &byte) in rom.iter().enumerate() {
-            let addr = PROGRAM_START_ADDRESS + i;
-            self.memory.write_byte(addr, byte).unwrap();
+    // Whether the loaded ROM runs in the VIP's 64x64 hi-res mode, as asked for with
+    // Options::legacy_hires.
+    pub fn legacy_hires(&self) -> bool {
+        self.legacy_hires
+    }
+
+    // Every word of the loaded ROM decoded, the first at the program start and the rest 2 bytes
+    // apart. The words are decoded once, when the ROM is loaded, so changes the program makes to
+    // itself afterwards aren't seen here. A trailing odd byte is left out.
+    pub fn decode_program(&self) -> &[Result<Opcode, OpcodeError>] {
+        &self.decoded_program
+    }
+
+    // Copies bytes into memory from outside the program, so no violations or watchpoints apply.
+    // Memory is left untouched if they don't fit.
+    pub fn load_bytes(&mut self, address: usize, bytes: &[u8]) -> Result<(), Chip8Error> {
+        self.check_range(address, bytes.len())?;
+        for (i, &byte) in bytes.iter().enumerate() {
+            self.memory.write_byte(address + i, byte)?;
         }
+        Ok(())
+    }
+
+    pub fn memory_bytes(&self, address: usize, len: usize) -> Result<Vec<u8>, Chip8Error> {
+        self.check_range(address, len)?;
+        (address..address + len)
+            .map(|addr| Ok(self.memory.read_byte(addr)?))
+            .collect()
+    }
+
+    // Instructions in the loaded ROM that the emulator can't run, as (address, opcode). Every
+    // word is treated as an instruction, so data in the ROM shows up here too.
+    pub fn unsupported_opcodes_in_rom(&self) -> Vec<(u16, Opcode)> {
+        let end = self.program_start + self.rom_len - self.rom_len % 2;
+        (self.program_start..end)
+            .step_by(2)
+            .filter_map(|address| {
+                let [msb, lsb] = self.memory.read_word(address).ok()?.to_be_bytes();
+                let op = Opcode::from_bytes(msb, lsb, self.memory.size()).ok()?;
+                let handled = matches!(op, Opcode::SysAddr(address) if self.sys_handlers.0.contains_key(&address));
+                (!op.is_supported() && !handled).then_some((address as u16, op))
+            })
+            .collect()
     }
 
     pub fn load_rom_from_file(&mut self, path: &str) -> Result<(), std::io::Error> {
         let rom = std::fs::read(path)?;
-        self.load_rom(&rom);
-        Ok(())
+        self.load_rom(&rom)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))
     }
 
-    pub fn boot(&mut self) -> Result<(), Chip8Error> {
-        self.registers.pc = PROGRAM_START_ADDRESS as u16;
+    pub fn boot(&mut self, config: BootConfig) -> Result<(), Chip8Error> {
+        if config.clear_registers {
+            self.registers = registers::Registers::new();
+        }
+        self.program_start = config.program_start as usize;
+        self.registers.pc = config.program_start;
+        self.font_start = config.font_start;
         self.load_sprites()
     }
 
     fn load_sprites(&mut self) -> Result<(), Chip8Error> {
-        let sprite_size = display::BUILT_IN_SPRITES[0].len() as usize;
+        let sprite_size = display::BUILT_IN_SPRITES[0].len();
         for (sprite_idx, sprite) in display::BUILT_IN_SPRITES.iter().enumerate() {
             for (byte_idx, &byte) in sprite.iter().enumerate() {
-                let write_addr =
-                    display::SPRITE_START_ADDRESS + sprite_idx * sprite_size + byte_idx;
+                let write_addr = self.font_start + sprite_idx * sprite_size + byte_idx;
                 self.memory.write_byte(write_addr, byte)?;
             }
         }
         Ok(())
     }
 
-    pub fn press_key(&mut self, key: &str) {
-        self.input.set_key(key);
+    // Keys are 0x0 to 0xF; anything else is refused and leaves the pressed key as it was.
+    pub fn press_key(&mut self, key: u8) -> Result<(), Chip8Error> {
+        Ok(self.input.set_key(key)?)
+    }
+
+    // Presses a key named by its keypad label, "0" to "F" in either case.
+    pub fn press_key_str(&mut self, key: &str) -> Result<(), Chip8Error> {
+        self.press_key(input::parse_key(key)?)
     }
 
     pub fn release_key(&mut self) {
         self.input.clear_key();
     }
 
-    fn get_pressed_key(&self) -> Option<&str> {
+    pub fn get_pressed_key(&self) -> Option<u8> {
         self.input.get_key()
     }
 
-    fn get_pressed_key_u8(&self) -> Result<Option<u8>, Chip8Error> {
-        self.input
-            .get_key_u8()
-            .map_err(|err| Chip8Error::InputError(err))
-    }
+    // Runs the instructions and timer ticks owed for the elapsed wall-clock time at the configured
+    // speed, carrying fractions over to the next call. Stops executing instructions early if the
+    // program waits for a key or halts, and after a draw under the display wait quirk; timers keep
+    // ticking regardless. With Options::max_catch_up_frames set, time beyond that many frames is
+    // dropped so that a stalled caller doesn't get a burst of catch-up work.
+    pub fn run_for(&mut self, elapsed: Duration) -> Result<RunSummary, Chip8Error> {
+        let budget = self.pacer.budget(elapsed);
+        let mut summary = RunSummary {
+            dropped_instructions: budget.dropped_instructions,
+            dropped_frames: budget.dropped_timer_ticks,
+            ..RunSummary::default()
+        };
+        self.display_dirty = false;
+        self.keys_polled = false;
+        if !self.silenced {
+            self.draw_records.clear();
+        }
 
-    fn execute(&mut self, op: Opcode) -> Result<(), Chip8Error> {
-        match op {
-            Opcode::AddByte(vx, val) => self.add_vx_byte(vx, val),
-            Opcode::AddI(vx) => self.add_i_vx(vx),
-            Opcode::AddReg(vx, vy) => self.add_reg(vx, vy),
-            Opcode::And(vx, vy) => self.and(vx, vy),
-            Opcode::Call(addr) => self.call(addr)?,
-            Opcode::Jump(addr) => self.jump(addr),
-            Opcode::JumpV0(addr) => self.jump_v0(addr),
-            Opcode::LoadByte(vx, byte) => self.load_byte(vx, byte),
-            Opcode::LoadDelayTimer(vx) => self.load_delay_timer(vx),
-            Opcode::LoadReg(vx, vy) => self.load_register(vx, vy),
-            Opcode::Or(vx, vy) => self.or(vx, vy),
-            Opcode::Random(vx, byte) => self.random(vx, byte),
-            Opcode::RegDump(vx) => self.reg_dump(vx)?,
-            Opcode::RegLoad(vx) => self.reg_load(vx)?,
-            Opcode::Return => self.return_from()?,
-            Opcode::SetDelayTimer(vx) => self.set_delay_timer(vx),
-            Opcode::SetIndex(addr) => self.set_index(addr),
-            Opcode::SetSoundTimer(vx) => self.set_sound_timer(vx),
-            Opcode::ShiftLeft(vx) => self.shift_left(vx),
-            Opcode::ShiftRight(vx) => self.shift_right(vx),
-            Opcode::SkipIfEqual(vx, byte) => self.skip_if_equal(vx, byte),
-            Opcode::SkipIfNotEqual(vx, byte) => self.skip_if_not_equal(vx, byte),
-            Opcode::SkipIfRegEqual(vx, vy) => self.skip_if_reg_equal(vx, vy),
-            Opcode::SkipIfRegNotEqual(vx, vy) => self.skip_if_reg_not_equal(vx, vy),
-            Opcode::Sub(vx, vy) => self.sub(vx, vy),
-            Opcode::SubN(vx, vy) => self.subn(vx, vy),
-            Opcode::Xor(vx, vy) => self.xor(vx, vy),
-            Opcode::StoreBCD(vx) => self.store_bcd(vx),
-            Opcode::SysAddr(addr) => {}
-            Opcode::LoadSpriteAddr(vx) => self.load_sprite_addr(vx)?,
-            Opcode::Draw(vx, vy, n) => self.draw(vx, vy, n)?,
-            Opcode::SkipIfKeyNotPressed(vx) => self.skip_if_not_pressed(vx),
-            Opcode::SkipIfKeyPressed(vx) => self.skip_if_pressed(vx),
-            Opcode::ClearDisplay => self.clear_display(),
-            Opcode::WaitForKey(vx) => self.wait_for_key(vx)?,
-            Opcode::Undefined(opcode) => {
-                return Err(Chip8Error::OpcodeError(OpcodeError::InvalidOpcode(opcode)))
+        for _ in 0..budget.instructions {
+            if self.waiting_for_vblank {
+                break;
+            }
+            let stop = match self.step()? {
+                StepOutcome::BreakpointHit(address) => {
+                    summary.stopped = Some(StopReason::Breakpoint(address));
+                    break;
+                }
+                StepOutcome::Blocked(BlockReason::WaitingForKey(_)) => {
+                    Some(StopReason::WaitingForKey)
+                }
+                StepOutcome::Halted(_) => Some(StopReason::Halted),
+                StepOutcome::WatchpointHit(address, old, new) => {
+                    Some(StopReason::Watchpoint(address, old, new))
+                }
+                StepOutcome::Executed(_) | StepOutcome::Skipped | StepOutcome::Jumped { .. } => {
+                    None
+                }
+            };
+            summary.instructions += 1;
+            if stop.is_some() {
+                summary.stopped = stop;
+                break;
             }
         }
-        Ok(())
-    }
-
-    fn wait_for_key(&mut self, vx: u8) -> Result<(), Chip8Error> {
-        let key = self.get_pressed_key_u8()?;
-
-        match key {
-            Some(key) => self.registers.write_v(vx, key),
-            None => self.registers.pc -= 2,
+        for _ in 0..budget.timer_ticks {
+            self.tick_timers();
         }
-        Ok(())
+
+        summary.frames = budget.timer_ticks;
+        summary.display_dirty = self.display_dirty;
+        summary.keys_polled = self.keys_polled;
+        summary.budget_used =
+            summary.instructions == budget.instructions && summary.stopped.is_none();
+        summary.sound_active = self.sound_active();
+        Ok(summary)
     }
 
-    fn skip_if_pressed(&mut self, vx: u8) {
-        let key = self.get_pressed_key();
-        let expected_key = format!("{:x}", self.registers.read_v(vx));
-        if key.is_some() && key.unwrap() == expected_key {
-            self.registers.pc += 2;
+    // Executes up to `max` instructions, stopping early on a breakpoint, a watchpoint or any of
+    // `stop`. Timers aren't ticked; that's up to the caller.
+    pub fn run_steps(&mut self, max: u32, stop: StopConditions) -> Result<RunResult, Chip8Error> {
+        let mut result = RunResult::default();
+        while result.instructions < max {
+            let pc = self.registers.pc;
+            if result.instructions > 0 {
+                if stop.addresses.contains(&pc) {
+                    result.stopped = Some(StopReason::Breakpoint(pc));
+                    break;
+                }
+                if stop.pc_in.as_ref().is_some_and(|range| range.contains(&pc)) {
+                    result.stopped = Some(StopReason::PcInRange(pc));
+                    break;
+                }
+            }
+            let (generation, sounding) = (self.display_generation, self.sound_active());
+            let stopped = match self.step()? {
+                StepOutcome::BreakpointHit(address) => {
+                    result.stopped = Some(StopReason::Breakpoint(address));
+                    break;
+                }
+                StepOutcome::Blocked(BlockReason::WaitingForKey(_)) if stop.halted => {
+                    Some(StopReason::WaitingForKey)
+                }
+                StepOutcome::Halted(_) if stop.halted => Some(StopReason::Halted),
+                StepOutcome::WatchpointHit(address, old, new) => {
+                    Some(StopReason::Watchpoint(address, old, new))
+                }
+                _ if stop.display_updated && self.display_generation != generation => {
+                    Some(StopReason::DisplayUpdated)
+                }
+                _ if stop.sound_started && !sounding && self.sound_active() => {
+                    Some(StopReason::SoundStarted)
+                }
+                _ => None,
+            };
+            result.instructions += 1;
+            if stopped.is_some() {
+                result.stopped = stopped;
+                break;
+            }
         }
+        Ok(result)
     }
 
-    fn skip_if_not_pressed(&mut self, vx: u8) {
-        let key = self.get_pressed_key();
-        let expected_key = format!("{:x}", self.registers.read_v(vx));
-        if key.is_none() || key.unwrap() != expected_key {
-            self.registers.pc += 2;
-        }
+    // Stepping onto a breakpoint stops before executing the instruction there; the next step
+    // executes it.
+    pub fn add_breakpoint(&mut self, address: u16) {
+        self.breakpoints.insert(address);
     }
 
-    fn clear_display(&mut self) {
-        self.display.clear();
+    // Returns whether the breakpoint was set.
+    pub fn remove_breakpoint(&mut self, address: u16) -> bool {
+        self.breakpoints.remove(&address)
     }
 
-    fn draw(&mut self, vx: u8, vy: u8, n: u8) -> Result<(), Chip8Error> {
-        let col = self.registers.read_v(vx);
-        let row = self.registers.read_v(vy);
+    // Active breakpoint addresses in ascending order.
+    pub fn breakpoints(&self) -> Vec<u16> {
+        self.breakpoints.iter().copied().collect()
+    }
 
-        let sprite_addr = self.registers.i as usize;
-        let mut sprite: Vec<u8> = vec![];
-        for offset in 0..n {
-            let sprite_byte = self.memory.read_byte(sprite_addr + offset as usize)?;
-            sprite.push(sprite_byte);
+    // Pins the byte at `address` to `value`: frame writes it back at the end of every frame,
+    // whatever the program did to it. Replaces any cheat already on the address.
+    pub fn add_cheat(&mut self, address: u16, value: u8) -> Result<(), Chip8Error> {
+        if address as usize >= self.memory.size() {
+            return Err(Chip8Error::MemoryError(
+                memory::MemoryError::AddressOutOfBounds,
+            ));
         }
-
-        let erased = self
-            .display
-            .draw_sprite(row as usize, col as usize, &sprite);
-
-        self.registers.write_v(0xF, if erased { 1 } else { 0 });
+        self.cheats.insert(address, value);
         Ok(())
     }
 
-    fn load_sprite_addr(&mut self, vx: u8) -> Result<(), Chip8Error> {
-        let sprite = self.registers.read_v(vx);
-        let addr = display::Display::get_sprite_address(sprite)?;
-        self.registers.i = addr as u16;
-        Ok(())
+    // Returns whether a cheat was set. The byte keeps its value until the program changes it.
+    pub fn remove_cheat(&mut self, address: u16) -> bool {
+        self.cheats.remove(&address).is_some()
     }
 
-    fn subn(&mut self, vx: u8, vy: u8) {
-        let vx_val = self.registers.read_v(vx);
-        let vy_val = self.registers.read_v(vy);
+    // Active cheats as (address, value), in ascending order of address.
+    pub fn cheats(&self) -> Vec<(u16, u8)> {
+        self.cheats
+            .iter()
+            .map(|(&address, &value)| (address, value))
+            .collect()
+    }
 
-        let (result, borrow) = vy_val.overflowing_sub(vx_val);
+    // Watchpoints report instructions that change the value at an address.
+    pub fn add_watchpoint(&mut self, address: u16) {
+        self.watchpoints.insert(address);
+    }
 
-        self.registers.write_v(vx, result);
-        self.registers.write_v(0xF, if borrow { 0 } else { 1 });
+    // Returns whether the watchpoint was set.
+    pub fn remove_watchpoint(&mut self, address: u16) -> bool {
+        self.watchpoints.remove(&address)
     }
 
-    fn skip_if_reg_not_equal(&mut self, vx: u8, vy: u8) {
-        let vx_val = self.registers.read_v(vx);
-        let vy_val = self.registers.read_v(vy);
+    pub fn step(&mut self) -> Result<StepOutcome, Chip8Error> {
+        let result = self.fetch_and_execute();
+        self.diagnose_error(result)
+    }
+
+    fn fetch_and_execute(&mut self) -> Result<StepOutcome, Chip8Error> {
+        let address = self.registers.pc;
+        if self.breakpoints.contains(&address) && !self.resume_from_breakpoint {
+            self.resume_from_breakpoint = true;
+            return Ok(StepOutcome::BreakpointHit(address));
+        }
+        self.resume_from_breakpoint = false;
+        self.watchpoint_hit = None;
+        self.instruction_address = address;
+        self.check_initialized(address as usize, 2)?;
+        let opcode = self.memory.read_word(address as usize)?;
+        // Only wraps on a 64 KB machine, where the last word ends at the top of the address space.
+        self.registers.pc = address.wrapping_add(2);
+
+        let started = (self.profile.is_some() && !self.silenced).then(Instant::now);
+        let [msb, lsb] = opcode.to_be_bytes();
+        let op = Opcode::from_bytes(msb, lsb, self.memory.size())?;
+        let pattern = op.pattern();
+        let outcome = self.execute(op)?;
+        // FX0A waiting for a key runs again next step, so pc goes back to it. execute_opcode
+        // never moved pc past the instruction, so only the fetch path rewinds.
+        if let StepOutcome::Blocked(BlockReason::WaitingForKey(_)) = outcome {
+            self.registers.pc = address;
+        }
+        if let (Some(profile), Some(started)) = (&mut self.profile, started) {
+            profile.record(pattern, started.elapsed());
+        }
 
-        if vx_val != vy_val {
-            self.registers.pc += 2;
+        if !self.registers.pc.is_multiple_of(2) && address.is_multiple_of(2) {
+            self.check_alignment(address, opcode)?;
         }
+        Ok(outcome)
     }
 
-    fn xor(&mut self, vx: u8, vy: u8) {
-        let vx_val = self.registers.read_v(vx);
-        let vy_val = self.registers.read_v(vy);
+    // Executes a decoded instruction as if it had been fetched from pc. Nothing is fetched and pc
+    // isn't advanced past the instruction first, so callers manage pc themselves: skips and
+    // jumps act on pc as it is, and FX0A without a key is reported as Blocked with pc left
+    // where it was. Breakpoints don't apply, violations are reported against pc.
+    pub fn execute_opcode(&mut self, op: Opcode) -> Result<StepOutcome, Chip8Error> {
+        self.watchpoint_hit = None;
+        self.instruction_address = self.registers.pc;
+        let result = self.execute(op);
+        self.diagnose_error(result)
+    }
 
-        let result = vx_val ^ vy_val;
+    pub fn pc(&self) -> u16 {
+        self.registers.pc
+    }
 
-        self.registers.write_v(vx, result);
+    // Decodes the instruction at pc without executing it or recording violations.
+    pub fn next_opcode(&self) -> Result<Opcode, Chip8Error> {
+        Ok(self.peek_instruction()?.op)
     }
 
-    fn store_bcd(&mut self, vx: u8) {
-        let vx_val = self.registers.read_v(vx);
-        let i = self.registers.i as usize;
+    // The instruction step would execute next, decoded without changing anything.
+    pub fn peek_instruction(&self) -> Result<PeekedInstruction, Chip8Error> {
+        self.peek_at(self.registers.pc)
+    }
 
-        self.memory.write_byte(i, vx_val / 100).unwrap();
-        self.memory.write_byte(i + 1, (vx_val / 10) % 10).unwrap();
-        self.memory.write_byte(i + 2, vx_val % 10).unwrap();
+    // Decodes the instruction at `address` without changing anything, including the violations
+    // that fetching it for real would record. Fails if it extends past the end of memory.
+    pub fn peek_at(&self, address: u16) -> Result<PeekedInstruction, Chip8Error> {
+        let word = self.memory.read_word(address as usize)?;
+        let [msb, lsb] = word.to_be_bytes();
+        let op = Opcode::from_bytes(msb, lsb, self.memory.size())?;
+        let operand = match op {
+            Opcode::LoadLongI => Some(self.memory.read_word(address as usize + 2)?),
+            _ => None,
+        };
+        Ok(PeekedInstruction {
+            address,
+            word,
+            op,
+            operand,
+            len: if operand.is_some() { 4 } else { 2 },
+        })
     }
 
-    fn sub(&mut self, vx: u8, vy: u8) {
-        let vx_val = self.registers.read_v(vx);
-        let vy_val = self.registers.read_v(vy);
+    // Multi-byte accesses check the whole range up front so that they either complete or leave
+    // memory and registers untouched.
+    fn check_range(&self, address: usize, len: usize) -> Result<(), Chip8Error> {
+        if address + len > self.memory.size() {
+            return Err(Chip8Error::MemoryError(
+                memory::MemoryError::AddressOutOfBounds,
+            ));
+        }
+        Ok(())
+    }
 
-        let (result, borrow) = vx_val.overflowing_sub(vy_val);
+    // Memory writes made by instructions go through here so watchpoints see them.
+    fn write_memory(&mut self, address: usize, value: u8) -> Result<(), Chip8Error> {
+        let old = self.memory.read_byte(address)?;
+        self.memory.write_byte(address, value)?;
+        if let (Some(writes), false) = (&mut self.memory_writes, self.silenced) {
+            writes.push(MemoryWrite {
+                cycle: self.cycles,
+                address: address as u16,
+                old,
+                new: value,
+            });
+        }
+        let watched = self.watchpoints.contains(&(address as u16));
+        if watched && old != value && self.watchpoint_hit.is_none() {
+            self.watchpoint_hit = Some((address as u16, old, value));
+        }
+        Ok(())
+    }
 
-        self.registers.write_v(vx, result);
-        self.registers.write_v(0xF, if borrow { 0 } else { 1 });
+    // Logs every memory write an instruction makes, including ones that don't change the value,
+    // until turned off again. Turning it off drops the log.
+    pub fn set_memory_write_logging(&mut self, enabled: bool) {
+        self.memory_writes = enabled.then(Vec::new);
     }
 
-    fn skip_if_not_equal(&mut self, vx: u8, byte: u8) {
-        let vx_val = self.registers.read_v(vx);
-        if vx_val != byte {
-            self.registers.pc += 2;
-        }
+    // The writes logged since the last call, oldest first.
+    pub fn take_memory_writes(&mut self) -> Vec<MemoryWrite> {
+        self.memory_writes
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
     }
 
-    fn skip_if_reg_equal(&mut self, vx: u8, vy: u8) {
-        let vx_val = self.registers.read_v(vx);
-        let vy_val = self.registers.read_v(vy);
+    // Times every instruction step executes by its pattern, until turned off again. Turning it
+    // off drops what was recorded. Nothing is timed while it's off.
+    pub fn set_profiling(&mut self, enabled: bool) {
+        self.profile = enabled.then(profile::Profile::new);
+    }
 
-        if vx_val == vy_val {
-            self.registers.pc += 2;
-        }
+    // Counts pixels that draws and clears turn off and then back on within a frame, reported by
+    // frame. That's flicker: a sprite erased and redrawn in place counts every pixel of it.
+    pub fn set_overdraw_counting(&mut self, enabled: bool) {
+        self.overdraw = enabled.then(overdraw::Overdraw::new);
     }
 
-    fn skip_if_equal(&mut self, vx: u8, byte: u8) {
-        let vx_val = self.registers.read_v(vx);
-        if vx_val == byte {
-            self.registers.pc += 2;
-        }
+    // Time spent on each kind of instruction since profiling was turned on, most first.
+    pub fn profile_report(&self) -> Vec<OpcodeProfile> {
+        self.profile
+            .as_ref()
+            .map(|profile| profile.report())
+            .unwrap_or_default()
     }
 
-    fn shift_right(&mut self, vx: u8) {
-        let vx_val = self.registers.read_v(vx);
-        let lsb = vx_val & 0b00000001;
+    // While set, the machine runs as usual but keeps no record of it: no violations, diagnostics,
+    // memory writes, profile timings, overdraw or draw records. For runs whose results are thrown
+    // away, like trial runs and speculative frames.
+    pub fn set_silenced(&mut self, silenced: bool) {
+        self.silenced = silenced;
+    }
 
-        self.registers.write_v(vx, vx_val >> 1);
-        self.registers.write_v(0xF, lsb);
+    pub fn cycles(&self) -> u64 {
+        self.cycles
     }
 
-    fn shift_left(&mut self, vx: u8) {
-        let vx_val = self.registers.read_v(vx);
-        let overflow = vx_val & 0b10000000 != 0;
+    pub fn violations(&self) -> &Violations {
+        &self.violations
+    }
 
-        self.registers.write_v(vx, vx_val << 1);
-        self.registers.write_v(0xF, if overflow { 1 } else { 0 });
+    // The last DIAGNOSTICS_CAPACITY warnings and errors, oldest first, for showing in a UI after
+    // they've scrolled out of the terminal.
+    pub fn recent_diagnostics(&self) -> &Diagnostics {
+        &self.diagnostics
     }
 
-    fn set_sound_timer(&mut self, vx: u8) {
-        let vx_val = self.registers.read_v(vx);
-        self.timers.set_sound_timer(vx_val);
+    fn diagnose(&mut self, severity: Severity, pc: u16, message: String) {
+        if self.silenced {
+            return;
+        }
+        self.diagnostics.push(Diagnostic {
+            severity,
+            cycle: self.cycles,
+            pc,
+            message,
+        });
     }
 
-    fn set_index(&mut self, addr: u16) {
-        self.registers.i = addr;
+    // Logs the error an instruction failed with.
+    fn diagnose_error<T>(&mut self, result: Result<T, Chip8Error>) -> Result<T, Chip8Error> {
+        if let Err(err) = &result {
+            self.diagnose(
+                Severity::Error,
+                self.instruction_address,
+                format!("{:?}", err),
+            );
+        }
+        result
     }
 
-    fn set_delay_timer(&mut self, vx: u8) {
-        let vx_val = self.registers.read_v(vx);
-        self.timers.set_delay_timer(vx_val);
+    // Records a violation by the current instruction, logging a warning in the diagnostics the
+    // first time it happens there unless it's going to be turned into an error. Returns `strict`
+    // for the caller to act on.
+    fn violation(&mut self, violation: Violation, strict: bool) -> bool {
+        if self.silenced {
+            return strict;
+        }
+        let first = self.violations.record(self.instruction_address, violation);
+        if first && !strict {
+            self.diagnose(
+                Severity::Warning,
+                self.instruction_address,
+                violation.to_string(),
+            );
+        }
+        strict
     }
 
-    fn return_from(&mut self) -> Result<(), Chip8Error> {
-        self.registers.pc = self.stack.pop()?;
+    // Odd pc values usually come from a corrupted jump table, but a few ROMs use them on purpose,
+    // so outside of strict mode they're only reported and execution continues.
+    fn check_alignment(&mut self, address: u16, opcode: u16) -> Result<(), Chip8Error> {
+        let pc = self.registers.pc;
+        if self.violation(Violation::MisalignedPc(pc), self.options.strict_alignment) {
+            return Err(Chip8Error::AlignmentError(Misalignment {
+                address,
+                opcode,
+                pc,
+            }));
+        }
         Ok(())
     }
 
-    fn reg_load(&mut self, vx: u8) -> Result<(), Chip8Error> {
-        for reg in 0..=vx {
-            let reg_val = self
-                .memory
-                .read_byte(self.registers.i as usize + reg as usize)?;
-            self.registers.write_v(reg, reg_val);
+    fn check_initialized(&mut self, address: usize, len: usize) -> Result<(), Chip8Error> {
+        let uninitialized = (address..address + len)
+            .find(|&addr| addr < self.memory.size() && !self.memory.is_initialized(addr));
+        if let Some(addr) = uninitialized {
+            let violation = Violation::UninitializedRead(addr as u16);
+            if self.violation(violation, self.options.strict_memory) {
+                return Err(Chip8Error::ViolationError(violation));
+            }
         }
         Ok(())
     }
-    fn reg_dump(&mut self, vx: u8) -> Result<(), Chip8Error> {
-        for reg in 0..=vx {
-            let reg_val = self.registers.read_v(reg);
-            self.memory
-                .write_byte(self.registers.i as usize + reg as usize, reg_val)?;
+
+    fn check_write(&mut self, address: usize) -> Result<(), Chip8Error> {
+        if address < self.program_start {
+            let violation = Violation::WriteBelowProgram(address as u16);
+            if self.violation(violation, self.options.strict_memory) {
+                return Err(Chip8Error::ViolationError(violation));
+            }
         }
         Ok(())
     }
-    fn random(&mut self, vx: u8, byte: u8) {
-        let random_byte = random::<u8>();
-        self.registers.write_v(vx, random_byte & byte);
-    }
 
-    fn or(&mut self, vx: u8, vy: u8) {
-        let vx_val = self.registers.read_v(vx);
-        let vy_val = self.registers.read_v(vy);
+    pub fn tick_timers(&mut self) {
+        self.timers.decrement_timers();
+        self.waiting_for_vblank = false;
+        self.input.end_frame();
+    }
 
-        let result = vx_val | vy_val;
+    // Blanks the display and zeroes the timers, leaving registers, memory, the stack and pc as
+    // they are.
+    pub fn soft_reset(&mut self) {
+        self.clear_display();
+        self.timers = timers::Timers::new();
+        self.waiting_for_vblank = false;
+        self.key_awaiting_release = None;
+    }
 
-        self.registers.write_v(vx, result);
+    pub fn display(&self) -> &display::Display {
+        &self.display
     }
 
-    fn load_register(&mut self, vx: u8, vy: u8) {
-        let vy_val = self.registers.read_v(vy);
-        self.registers.write_v(vx, vy_val);
+    // Read-only views of registers, memory, the display, the stack, timers, the keypad and
+    // counters, for debuggers and exports.
+    pub fn inspect(&self) -> Inspector<'_> {
+        Inspector::new(self)
     }
 
-    fn load_delay_timer(&mut self, vx: u8) {
-        let delay_timer = self.timers.get_delay_timer();
-        self.registers.write_v(vx, delay_timer);
+    // The return addresses of the active subroutine calls, innermost first.
+    pub fn call_stack(&self) -> Vec<u16> {
+        self.stack.frames().collect()
     }
 
-    fn load_byte(&mut self, vx: u8, byte: u8) {
-        self.registers.write_v(vx, byte);
+    pub fn export_state(&self) -> ExportedState {
+        let inspector = self.inspect();
+        let (registers, timers, display) = (
+            inspector.registers(),
+            inspector.timers(),
+            inspector.display(),
+        );
+        let entries = inspector.stack().entries;
+        let mut stack = [0; stack::STACK_SIZE];
+        stack[..entries.len()].copy_from_slice(entries);
+        let mut framebuffer = vec![0; display.width * display.height / 8];
+        display.pack_into(&mut framebuffer);
+        ExportedState {
+            v: registers.v,
+            i: registers.i,
+            pc: registers.pc,
+            sp: entries.len() as u8,
+            stack,
+            delay_timer: timers.delay,
+            sound_timer: timers.sound,
+            display_width: display.width as u16,
+            display_height: display.height as u16,
+            framebuffer,
+        }
     }
 
-    fn jump_v0(&mut self, addr: u16) {
-        self.jump(self.registers.read_v(0) as u16 + addr);
+    // A copy of the machine's state to go back to with restore, for running ahead speculatively.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            pacer: self.pacer.clone(),
+            waiting_for_vblank: self.waiting_for_vblank,
+            key_awaiting_release: self.key_awaiting_release,
+            display_generation: self.display_generation,
+            memory: self.memory.clone(),
+            registers: self.registers.clone(),
+            stack: self.stack.clone(),
+            timers: self.timers.clone(),
+            display: self.display.clone(),
+            input: self.input.clone(),
+            rng: self.rng.clone(),
+            cycles: self.cycles,
+            silent_sound_timer: self.silent_sound_timer,
+            beep_started: self.beep_started,
+        }
     }
 
-    fn jump(&mut self, addr: u16) {
-        self.registers.pc = addr;
+    // Puts the machine back as it was when `snapshot` was taken. The display generation never
+    // goes back: if the display changed since, it moves on once more, so that a frontend showing
+    // the display from before the restore repaints.
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        let display_changed = self.display_generation != snapshot.display_generation;
+        self.pacer = snapshot.pacer.clone();
+        self.waiting_for_vblank = snapshot.waiting_for_vblank;
+        self.key_awaiting_release = snapshot.key_awaiting_release;
+        self.memory = snapshot.memory.clone();
+        self.registers = snapshot.registers.clone();
+        self.stack = snapshot.stack.clone();
+        self.timers = snapshot.timers.clone();
+        self.display = snapshot.display.clone();
+        self.input = snapshot.input.clone();
+        self.rng = snapshot.rng.clone();
+        self.cycles = snapshot.cycles;
+        self.silent_sound_timer = snapshot.silent_sound_timer;
+        self.beep_started = snapshot.beep_started;
+        if display_changed {
+            self.display_generation += 1;
+        }
     }
 
-    fn call(&mut self, addr: u16) -> Result<(), Chip8Error> {
-        self.stack.push(self.registers.pc)?;
-        self.registers.pc = addr;
+    // Draw recording is off by default. While it's on, every draw is recorded, and run_for
+    // clears the records when it starts, so after a call they cover the frame it ran.
+    pub fn set_draw_recording(&mut self, enabled: bool) {
+        self.record_draws = enabled;
+        self.draw_records.clear();
+    }
+
+    pub fn draw_records(&self) -> &[DrawRecord] {
+        &self.draw_records
+    }
+
+    // How many bytes from I the draw would read as its sprite, or None if `op` isn't a draw. This
+    // follows draw, which reads N bytes for DXYN even when N is 0: 16x16 DXY0 sprites aren't
+    // drawn yet.
+    pub fn draw_sprite_len(&self, op: &Opcode) -> Option<usize> {
+        match *op {
+            Opcode::Draw(_, _, n) => Some(n as usize),
+            _ => None,
+        }
+    }
+
+    // Blanks a rectangle of the display, for frontends compositing onto it. The parts past the
+    // edges wrap or are cut off as sprites are under the clip_sprites quirk.
+    pub fn clear_rect(
+        &mut self,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+    ) -> Result<(), Chip8Error> {
+        if self.options.quirks.clip_sprites {
+            self.display.clear_rect_clipped(x, y, width, height)?;
+        } else {
+            self.display.clear_rect(x, y, width, height)?;
+        }
+        self.mark_display_changed();
         Ok(())
     }
 
-    fn and(&mut self, vx: u8, vy: u8) {
+    // The colors the display is shown in, for each combination of planes a pixel is set in.
+    pub fn set_palette(&mut self, palette: [u32; 4]) {
+        self.display.set_palette(palette);
+    }
+
+    pub fn display_generation(&self) -> u64 {
+        self.display_generation
+    }
+
+    // The display as text, one line per row with `#` for set pixels and `.` for cleared ones.
+    pub fn render_ascii(&self) -> String {
+        self.inspect().display().to_string()
+    }
+
+    // render_ascii followed by a status line with pc, I and the stack, as the inspector shows
+    // them.
+    pub fn render_ascii_with_status(&self) -> String {
+        let inspector = self.inspect();
+        let registers = inspector.registers();
+        format!(
+            "{}\npc={} I={} stack={}",
+            inspector.display(),
+            format_addr(registers.pc),
+            format_addr(registers.i),
+            inspector.stack()
+        )
+    }
+
+    // Hash of everything a program can observe: registers, the stack, timers, memory, the
+    // display and a key FX0A is waiting to see released. Two runs that end with the same hash
+    // ended in the same state.
+    pub fn state_hash(&self) -> u64 {
+        let mut hasher = Fnv1a::default();
+        self.registers.hash(&mut hasher);
+        self.stack.hash(&mut hasher);
+        self.timers.hash(&mut hasher);
+        self.memory.hash(&mut hasher);
+        self.display.hash(&mut hasher);
+        // Only hashed while there's one, so the hash of every other state stays as it was.
+        if let Some(key) = self.key_awaiting_release {
+            key.hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    // How this machine's state differs from `other`'s: everything state_hash covers, except
+    // memory past the end of the smaller one.
+    pub fn diff(&self, other: &Chip8) -> Vec<Difference> {
+        let (a, b) = (self.export_state(), other.export_state());
+        let mut differences: Vec<Difference> = (0..16)
+            .filter(|&x| a.v[x] != b.v[x])
+            .map(|x| Difference::V(x as u8, a.v[x], b.v[x]))
+            .collect();
+        if a.i != b.i {
+            differences.push(Difference::I(a.i, b.i));
+        }
+        if a.pc != b.pc {
+            differences.push(Difference::Pc(a.pc, b.pc));
+        }
+        let (a_stack, b_stack) = (self.inspect().stack(), other.inspect().stack());
+        if a_stack != b_stack {
+            differences.push(Difference::Stack(
+                a_stack.entries.to_vec(),
+                b_stack.entries.to_vec(),
+            ));
+        }
+        if a.delay_timer != b.delay_timer {
+            differences.push(Difference::DelayTimer(a.delay_timer, b.delay_timer));
+        }
+        if a.sound_timer != b.sound_timer {
+            differences.push(Difference::SoundTimer(a.sound_timer, b.sound_timer));
+        }
+        let shared = self.memory.size().min(other.memory.size());
+        if let (Ok(ours), Ok(theirs)) = (
+            self.memory.read_slice(0, shared),
+            other.memory.read_slice(0, shared),
+        ) {
+            let bytes = ours.iter().zip(theirs).enumerate();
+            differences.extend(
+                bytes
+                    .filter(|(_, (a, b))| a != b)
+                    .map(|(address, (&a, &b))| Difference::Memory(address as u16, a, b)),
+            );
+        }
+        let (a_size, b_size) = (
+            (a.display_width, a.display_height),
+            (b.display_width, b.display_height),
+        );
+        if a_size != b_size {
+            differences.push(Difference::Resolution(a_size, b_size));
+        } else {
+            let pixels: u32 = a
+                .framebuffer
+                .iter()
+                .zip(&b.framebuffer)
+                .map(|(a, b)| (a ^ b).count_ones())
+                .sum();
+            if pixels > 0 {
+                differences.push(Difference::Pixels(pixels as usize));
+            }
+        }
+        differences
+    }
+
+    pub fn sound_active(&self) -> bool {
+        self.timers.get_sound_timer() > 0 && !self.silent_sound_timer
+    }
+
+    // Whether either timer is still counting down.
+    pub fn timers_running(&self) -> bool {
+        self.timers.get_delay_timer() > 0 || self.timers.get_sound_timer() > 0
+    }
+
+    // Call once at the end of each frame. Cheats are applied, and the buzzer reported. Its edge is
+    // only set on the first frame after the buzzer starts or stops, so a one-shot sound can be
+    // started or stopped exactly once.
+    pub fn frame(&mut self) -> FrameReport {
+        for (&address, &value) in &self.cheats {
+            // add_cheat checked the address.
+            let _ = self.memory.write_byte(address as usize, value);
+        }
+        let buzzer_on = self.sound_active() || self.beep_started;
+        self.beep_started = false;
+        let buzzer_edge = buzzer_on != self.buzzer_reported;
+        self.buzzer_reported = buzzer_on;
+        FrameReport {
+            buzzer_on,
+            buzzer_edge,
+            overdraw: self
+                .overdraw
+                .as_mut()
+                .map_or(0, |overdraw| overdraw.end_frame()),
+        }
+    }
+
+    // Every instruction goes through here, so anything that should apply to all of them belongs
+    // in this function. Each family of instructions has its own handler; a new family gets a new
+    // handler rather than more arms in an existing one. A watched write is reported ahead of
+    // whatever else the instruction did.
+    fn execute(&mut self, op: Opcode) -> Result<StepOutcome, Chip8Error> {
+        let handler: FamilyHandler = match op.family() {
+            Family::Flow => Chip8::execute_flow,
+            Family::Register => Chip8::execute_register,
+            Family::Memory => Chip8::execute_memory,
+            Family::Display => Chip8::execute_display,
+            Family::Input => Chip8::execute_input,
+            Family::Timer => Chip8::execute_timer,
+            Family::System => Chip8::execute_system,
+        };
+        let result = handler(self, op);
+        self.cycles += 1;
+        let outcome = result?;
+        Ok(match self.watchpoint_hit {
+            Some((address, old, new)) => StepOutcome::WatchpointHit(address, old, new),
+            None => outcome,
+        })
+    }
+
+    fn execute_flow(&mut self, op: Opcode) -> Result<StepOutcome, Chip8Error> {
+        let skipped = match op {
+            Opcode::Call(addr) => return self.call(addr),
+            Opcode::Jump(addr) => return Ok(self.jump(addr)),
+            Opcode::JumpV0(addr) => return Ok(self.jump_v0(addr)),
+            Opcode::Return => return self.return_from(),
+            Opcode::SkipIfEqual(vx, byte) => self.skip_if_equal(vx, byte),
+            Opcode::SkipIfNotEqual(vx, byte) => self.skip_if_not_equal(vx, byte),
+            Opcode::SkipIfRegEqual(vx, vy) => self.skip_if_reg_equal(vx, vy),
+            Opcode::SkipIfRegNotEqual(vx, vy) => self.skip_if_reg_not_equal(vx, vy),
+            op => return Err(wrong_family(op)),
+        };
+        Ok(skip_outcome(skipped, op))
+    }
+
+    fn execute_register(&mut self, op: Opcode) -> Result<StepOutcome, Chip8Error> {
+        match op {
+            Opcode::LoadByte(vx, byte) => self.load_byte(vx, byte),
+            Opcode::AddByte(vx, val) => self.add_vx_byte(vx, val),
+            Opcode::LoadReg(vx, vy) => self.load_register(vx, vy),
+            Opcode::Or(vx, vy) => self.or(vx, vy),
+            Opcode::And(vx, vy) => self.and(vx, vy),
+            Opcode::Xor(vx, vy) => self.xor(vx, vy),
+            Opcode::AddReg(vx, vy) => self.add_reg(vx, vy),
+            Opcode::Sub(vx, vy) => self.sub(vx, vy),
+            Opcode::ShiftRight(vx, vy) => self.shift_right(vx, vy),
+            Opcode::SubN(vx, vy) => self.subn(vx, vy),
+            Opcode::ShiftLeft(vx, vy) => self.shift_left(vx, vy),
+            Opcode::Random(vx, byte) => self.random(vx, byte),
+            op => return Err(wrong_family(op)),
+        }
+        Ok(StepOutcome::Executed(op))
+    }
+
+    fn execute_memory(&mut self, op: Opcode) -> Result<StepOutcome, Chip8Error> {
+        match op {
+            Opcode::SetIndex(addr) => self.set_index(addr),
+            Opcode::LoadLongI => self.load_long_i()?,
+            Opcode::AddI(vx) => self.add_i_vx(vx)?,
+            Opcode::LoadSpriteAddr(vx) => self.load_sprite_addr(vx)?,
+            Opcode::StoreBCD(vx) => self.store_bcd(vx)?,
+            Opcode::RegDump(vx) => self.reg_dump(vx)?,
+            Opcode::RegLoad(vx) => self.reg_load(vx)?,
+            op => return Err(wrong_family(op)),
+        }
+        Ok(StepOutcome::Executed(op))
+    }
+
+    fn execute_display(&mut self, op: Opcode) -> Result<StepOutcome, Chip8Error> {
+        match op {
+            Opcode::ClearDisplay => self.clear_display(),
+            Opcode::Draw(vx, vy, n) => self.draw(vx, vy, n)?,
+            Opcode::ScrollUp(n) => self.scroll_up(n),
+            Opcode::LowRes => self.switch_resolution(LOW_RES_WIDTH, LOW_RES_HEIGHT)?,
+            Opcode::HighRes => {
+                self.switch_resolution(display::DISPLAY_WIDTH, display::DISPLAY_HEIGHT)?
+            }
+            op => return Err(wrong_family(op)),
+        }
+        Ok(StepOutcome::Executed(op))
+    }
+
+    fn execute_input(&mut self, op: Opcode) -> Result<StepOutcome, Chip8Error> {
+        self.keys_polled = true;
+        let skipped = match op {
+            Opcode::SkipIfKeyPressed(vx) => self.skip_if_pressed(vx),
+            Opcode::SkipIfKeyNotPressed(vx) => self.skip_if_not_pressed(vx),
+            Opcode::WaitForKey(vx) => return self.wait_for_key(vx),
+            op => return Err(wrong_family(op)),
+        };
+        Ok(skip_outcome(skipped, op))
+    }
+
+    fn execute_timer(&mut self, op: Opcode) -> Result<StepOutcome, Chip8Error> {
+        match op {
+            Opcode::LoadDelayTimer(vx) => self.load_delay_timer(vx),
+            Opcode::SetDelayTimer(vx) => self.set_delay_timer(vx),
+            Opcode::SetSoundTimer(vx) => self.set_sound_timer(vx),
+            op => return Err(wrong_family(op)),
+        }
+        Ok(StepOutcome::Executed(op))
+    }
+
+    // 0NNN runs the handler registered for NNN with set_sys_handler, or is skipped since machine
+    // code can't be run.
+    fn execute_system(&mut self, op: Opcode) -> Result<StepOutcome, Chip8Error> {
+        match op {
+            Opcode::SysAddr(address) => self.sys(address)?,
+            Opcode::Undefined(opcode) => self.undefined(opcode)?,
+            op => return Err(wrong_family(op)),
+        }
+        Ok(StepOutcome::Executed(op))
+    }
+
+    // Runs `handler` for 0NNN instead of skipping it, replacing any handler already registered
+    // for `address`. ROMs for some interpreters relied on particular routines, like 0230 clearing
+    // the screen on the VIP's hi-res interpreters.
+    pub fn set_sys_handler<F>(&mut self, address: u16, handler: F)
+    where
+        F: FnMut(&mut Chip8) -> Result<(), Chip8Error> + Send + 'static,
+    {
+        self.sys_handlers.0.insert(address, Box::new(handler));
+    }
+
+    // Machine code can't be run, so 0NNN without a handler is skipped with a warning the first
+    // time each address is called, or an error in strict mode.
+    fn sys(&mut self, address: u16) -> Result<(), Chip8Error> {
+        // The handler is taken out while it runs, since it gets the whole machine. One it
+        // registered for its own address in the meantime replaces it.
+        if let Some(mut handler) = self.sys_handlers.0.remove(&address) {
+            let result = handler(self);
+            self.sys_handlers.0.entry(address).or_insert(handler);
+            return result;
+        }
+        let violation = Violation::MachineCall(address);
+        if self.violation(violation, self.options.strict_sys) {
+            return Err(Chip8Error::ViolationError(violation));
+        }
+        // pc has already moved past the word, so in lenient mode skipping it is a no-op.
+        Ok(())
+    }
+
+    fn undefined(&mut self, opcode: u16) -> Result<(), Chip8Error> {
+        if self.violation(
+            Violation::UndefinedOpcode(opcode),
+            self.options.strict_decode,
+        ) {
+            return Err(Chip8Error::OpcodeError(OpcodeError::InvalidOpcode(opcode)));
+        }
+        Ok(())
+    }
+
+    fn wait_for_key(&mut self, vx: u8) -> Result<StepOutcome, Chip8Error> {
+        let pressed = self.input.query_key();
+        let key = if self.options.quirks.wait_for_key_on_release {
+            match (self.key_awaiting_release, pressed) {
+                (Some(held), Some(key)) if key == held => None,
+                // Pressing another key counts as letting go of the first.
+                (Some(held), _) => {
+                    self.key_awaiting_release = None;
+                    Some(held)
+                }
+                (None, pressed) => {
+                    self.key_awaiting_release = pressed;
+                    None
+                }
+            }
+        } else {
+            pressed
+        };
+        match key {
+            Some(key) => {
+                self.registers.write_v(vx, key);
+                Ok(StepOutcome::Executed(Opcode::WaitForKey(vx)))
+            }
+            None => Ok(StepOutcome::Blocked(BlockReason::WaitingForKey(vx))),
+        }
+    }
+
+    // Skips the next instruction if `condition` holds, returning whether it did.
+    fn skip_next_if(&mut self, condition: bool) -> bool {
+        if condition {
+            // Wraps like the fetch does, on a 64 KB machine skipping from the last word.
+            self.registers.pc = self.registers.pc.wrapping_add(2);
+        }
+        condition
+    }
+
+    fn skip_if_pressed(&mut self, vx: u8) -> bool {
+        let pressed = self.input.query_key() == Some(self.registers.read_v(vx));
+        self.skip_next_if(pressed)
+    }
+
+    fn skip_if_not_pressed(&mut self, vx: u8) -> bool {
+        let pressed = self.input.query_key() == Some(self.registers.read_v(vx));
+        self.skip_next_if(!pressed)
+    }
+
+    fn mark_display_changed(&mut self) {
+        self.display_dirty = true;
+        self.display_generation += 1;
+    }
+
+    fn clear_display(&mut self) {
+        let before = self.display_for_overdraw();
+        self.display.clear();
+        self.count_overdraw(before);
+        self.mark_display_changed();
+    }
+
+    // The packed display while overdraw counting is on, to pass to count_overdraw after changing
+    // it.
+    fn display_for_overdraw(&self) -> Option<Vec<u8>> {
+        if self.silenced {
+            return None;
+        }
+        self.overdraw.as_ref()?;
+        let mut packed = vec![0; self.display.width * self.display.height / 8];
+        self.display.pack_into(&mut packed);
+        Some(packed)
+    }
+
+    fn count_overdraw(&mut self, before: Option<Vec<u8>>) {
+        if let (Some(before), Some(after)) = (before, self.display_for_overdraw()) {
+            if let Some(overdraw) = self.overdraw.as_mut() {
+                overdraw.record(&before, &after);
+            }
+        }
+    }
+
+    // Pixels that fit in the new resolution stay put unless the quirk clears the display.
+    fn switch_resolution(&mut self, width: usize, height: usize) -> Result<(), Chip8Error> {
+        self.display = self.display.resized(width, height)?;
+        if self.options.quirks.clear_on_resolution_switch {
+            self.display.clear();
+        }
+        self.mark_display_changed();
+        Ok(())
+    }
+
+    fn scroll_up(&mut self, n: u8) {
+        self.display.scroll_up(n as usize);
+        self.mark_display_changed();
+    }
+
+    // The starting position wraps around the display whatever the clipping quirk says.
+    fn draw(&mut self, vx: u8, vy: u8, n: u8) -> Result<(), Chip8Error> {
+        let col = self.registers.read_v(vx) as usize % self.display.width;
+        let row = self.registers.read_v(vy) as usize % self.display.height;
+
+        let sprite = self.read_sprite(n as usize)?;
+
+        let before = self.display_for_overdraw();
+        let erased = if self.options.quirks.clip_sprites {
+            self.display.draw_sprite_clipped(row, col, &sprite)?
+        } else {
+            self.display.draw_sprite(row, col, &sprite)?
+        };
+        self.count_overdraw(before);
+        self.mark_display_changed();
+        if self.options.quirks.display_wait {
+            self.waiting_for_vblank = true;
+        }
+        if self.record_draws && !self.silenced {
+            self.draw_records.push(DrawRecord {
+                x: col,
+                y: row,
+                height: n,
+                i_addr: self.registers.i,
+                collided: erased,
+                pc: self.instruction_address,
+            });
+        }
+
+        self.registers.write_v(0xF, if erased { 1 } else { 0 });
+        Ok(())
+    }
+
+    // Rows past the end of memory wrap to the start of memory under the sprite_reads_wrap quirk.
+    // Otherwise they're a violation and blank, or an error in strict mode.
+    fn read_sprite(&mut self, n: usize) -> Result<Vec<u8>, Chip8Error> {
+        let sprite_addr = self.registers.i as usize;
+        let size = self.memory.size();
+        let in_memory = size.saturating_sub(sprite_addr).min(n);
+        self.check_initialized(sprite_addr, in_memory)?;
+        let mut sprite = self
+            .memory
+            .read_slice(sprite_addr.min(size), in_memory)?
+            .to_vec();
+        // A sprite past the end of memory is a violation of its own.
+        if in_memory == n {
+            self.check_sprite_source(sprite_addr, n)?;
+            return Ok(sprite);
+        }
+
+        if self.options.quirks.sprite_reads_wrap {
+            for offset in in_memory..n {
+                let addr = (sprite_addr + offset) % size;
+                self.check_initialized(addr, 1)?;
+                sprite.push(self.memory.read_byte(addr)?);
+            }
+        } else {
+            let violation = Violation::SpritePastMemory(sprite_addr as u16);
+            if self.violation(violation, self.options.strict_memory) {
+                return Err(Chip8Error::SpriteOverrunError(SpriteOverrun {
+                    pc: self.instruction_address,
+                    i: sprite_addr as u16,
+                }));
+            }
+            sprite.resize(n, 0x00);
+        }
+        Ok(sprite)
+    }
+
+    // Sprites come from the ROM or the font. Anything else is drawn from data the program wrote
+    // itself or from stray memory, which is usually a bad I.
+    fn check_sprite_source(&mut self, address: usize, len: usize) -> Result<(), Chip8Error> {
+        let end = address + len;
+        let font = self.font_start..self.font_start + FONT_LEN;
+        let rom = self.program_start..self.program_start + self.rom_len;
+        let within = |range: &Range<usize>| range.start <= address && end <= range.end;
+        if len == 0 || within(&font) || within(&rom) {
+            return Ok(());
+        }
+        let violation = Violation::SpriteOutsideProgram(address as u16);
+        if self.violation(violation, self.options.strict_memory) {
+            return Err(Chip8Error::ViolationError(violation));
+        }
+        Ok(())
+    }
+
+    fn load_sprite_addr(&mut self, vx: u8) -> Result<(), Chip8Error> {
+        let sprite = self.registers.read_v(vx);
+        let addr = display::Display::get_sprite_address(self.font_start, sprite)?;
+        self.registers.i = addr as u16;
+        Ok(())
+    }
+
+    fn subn(&mut self, vx: u8, vy: u8) {
         let vx_val = self.registers.read_v(vx);
         let vy_val = self.registers.read_v(vy);
 
-        let result = vx_val & vy_val;
+        let (result, borrow) = vy_val.overflowing_sub(vx_val);
+
+        self.write_result_and_flag(vx, result, if borrow { 0 } else { 1 });
+    }
+
+    fn skip_if_reg_not_equal(&mut self, vx: u8, vy: u8) -> bool {
+        let vx_val = self.registers.read_v(vx);
+        let vy_val = self.registers.read_v(vy);
+
+        self.skip_next_if(vx_val != vy_val)
+    }
+
+    fn xor(&mut self, vx: u8, vy: u8) {
+        let vx_val = self.registers.read_v(vx);
+        let vy_val = self.registers.read_v(vy);
+
+        let result = vx_val ^ vy_val;
 
         self.registers.write_v(vx, result);
+        self.reset_vf_after_logic();
     }
 
-    fn add_reg(&mut self, vx: u8, vy: u8) {
+    fn store_bcd(&mut self, vx: u8) -> Result<(), Chip8Error> {
+        let vx_val = self.registers.read_v(vx);
+        let i = self.registers.i as usize;
+        self.check_write(i)?;
+        self.check_range(i, 3)?;
+
+        self.write_memory(i, vx_val / 100)?;
+        self.write_memory(i + 1, (vx_val / 10) % 10)?;
+        self.write_memory(i + 2, vx_val % 10)?;
+        Ok(())
+    }
+
+    fn sub(&mut self, vx: u8, vy: u8) {
+        let vx_val = self.registers.read_v(vx);
+        let vy_val = self.registers.read_v(vy);
+
+        let (result, borrow) = vx_val.overflowing_sub(vy_val);
+
+        self.write_result_and_flag(vx, result, if borrow { 0 } else { 1 });
+    }
+
+    fn skip_if_not_equal(&mut self, vx: u8, byte: u8) -> bool {
+        let vx_val = self.registers.read_v(vx);
+        self.skip_next_if(vx_val != byte)
+    }
+
+    fn skip_if_reg_equal(&mut self, vx: u8, vy: u8) -> bool {
         let vx_val = self.registers.read_v(vx);
         let vy_val = self.registers.read_v(vy);
 
-        let (result, overflow) = vx_val.overflowing_add(vy_val);
+        self.skip_next_if(vx_val == vy_val)
+    }
+
+    fn skip_if_equal(&mut self, vx: u8, byte: u8) -> bool {
+        let vx_val = self.registers.read_v(vx);
+        self.skip_next_if(vx_val == byte)
+    }
+
+    // The register the shift instructions read from, depending on the shift quirk.
+    fn shift_source(&self, vx: u8, vy: u8) -> u8 {
+        let source = if self.options.quirks.shift_uses_vy {
+            vy
+        } else {
+            vx
+        };
+        self.registers.read_v(source)
+    }
+
+    fn shift_right(&mut self, vx: u8, vy: u8) {
+        let value = self.shift_source(vx, vy);
+        let lsb = value & 0b00000001;
+
+        self.write_result_and_flag(vx, value >> 1, lsb);
+    }
+
+    fn shift_left(&mut self, vx: u8, vy: u8) {
+        let value = self.shift_source(vx, vy);
+        let overflow = value & 0b10000000 != 0;
+
+        self.write_result_and_flag(vx, value << 1, if overflow { 1 } else { 0 });
+    }
+
+    // Arithmetic results and their VF flag are written in the order the vf quirk asks for, which
+    // matters when X is F.
+    fn write_result_and_flag(&mut self, vx: u8, result: u8, flag: u8) {
+        if self.options.quirks.vf_before_result {
+            self.registers.write_v(0xF, flag);
+            self.registers.write_v(vx, result);
+        } else {
+            self.registers.write_v(vx, result);
+            self.registers.write_v(0xF, flag);
+        }
+    }
+
+    fn reset_vf_after_logic(&mut self) {
+        if self.options.quirks.logic_resets_vf {
+            self.registers.write_v(0xF, 0);
+        }
+    }
+
+    // A beep only starts for a value of at least min_beep_ticks. Reloading the timer while it's
+    // beeping carries on with the same beep whatever the value, and 0 stops it.
+    fn set_sound_timer(&mut self, vx: u8) {
+        let vx_val = self.registers.read_v(vx);
+        let was_beeping = self.sound_active();
+        self.silent_sound_timer = !was_beeping && vx_val < self.options.min_beep_ticks;
+        self.timers.set_sound_timer(vx_val);
+        self.beep_started |= !was_beeping && self.sound_active();
+    }
+
+    fn set_index(&mut self, addr: u16) {
+        self.registers.i = addr;
+    }
+
+    // The address is the word following the opcode, which pc already points at. It's fetched
+    // like the opcode, so it's checked the same way.
+    fn load_long_i(&mut self) -> Result<(), Chip8Error> {
+        self.check_initialized(self.registers.pc as usize, 2)?;
+        self.registers.i = self.memory.read_word(self.registers.pc as usize)?;
+        self.registers.pc = self.registers.pc.wrapping_add(2);
+        Ok(())
+    }
+
+    fn set_delay_timer(&mut self, vx: u8) {
+        let vx_val = self.registers.read_v(vx);
+        self.timers.set_delay_timer(vx_val);
+    }
+
+    fn return_from(&mut self) -> Result<StepOutcome, Chip8Error> {
+        self.registers.pc = self.stack.pop()?;
+        Ok(StepOutcome::Jumped {
+            to: self.registers.pc,
+        })
+    }
+
+    // A load reaching past the end of memory fails before any register is written, unless
+    // partial_reg_load is set: then the registers whose bytes are in memory are loaded and the
+    // overrun is a violation.
+    fn reg_load(&mut self, vx: u8) -> Result<(), Chip8Error> {
+        let address = self.registers.i as usize;
+        let len = vx as usize + 1;
+        let in_memory = self.memory.size().saturating_sub(address).min(len);
+        if in_memory < len {
+            let violation = Violation::LoadPastMemory(self.registers.i);
+            if !self.options.partial_reg_load
+                || self.violation(violation, self.options.strict_memory)
+            {
+                return Err(Chip8Error::MemoryError(
+                    memory::MemoryError::AddressOutOfBounds,
+                ));
+            }
+        }
+        self.check_initialized(address, in_memory)?;
+        for offset in 0..in_memory {
+            let reg_val = self.memory.read_byte(address + offset)?;
+            self.registers.write_v(offset as u8, reg_val);
+        }
+        self.increment_i_after_load_store(vx);
+        Ok(())
+    }
+    fn reg_dump(&mut self, vx: u8) -> Result<(), Chip8Error> {
+        self.check_write(self.registers.i as usize)?;
+        self.check_range(self.registers.i as usize, vx as usize + 1)?;
+        for reg in 0..=vx {
+            let reg_val = self.registers.read_v(reg);
+            self.write_memory(self.registers.i as usize + reg as usize, reg_val)?;
+        }
+        self.increment_i_after_load_store(vx);
+        Ok(())
+    }
+
+    // The range was checked before the access, so I stays within memory, except on a 64 KB
+    // machine where storing the last byte moves I to the top of the address space and it wraps,
+    // and after a partial load, which moves I as if every register had been loaded.
+    fn increment_i_after_load_store(&mut self, vx: u8) {
+        let increment = match self.options.quirks.load_store_i {
+            LoadStoreIMode::Unchanged => return,
+            LoadStoreIMode::IncX => vx as u16,
+            LoadStoreIMode::IncXPlus1 => vx as u16 + 1,
+        };
+        self.registers.i = self.registers.i.wrapping_add(increment);
+    }
+    fn random(&mut self, vx: u8, byte: u8) {
+        let random_byte = self.rng.gen::<u8>();
+        self.registers.write_v(vx, random_byte & byte);
+    }
+
+    fn or(&mut self, vx: u8, vy: u8) {
+        let vx_val = self.registers.read_v(vx);
+        let vy_val = self.registers.read_v(vy);
+
+        let result = vx_val | vy_val;
+
+        self.registers.write_v(vx, result);
+        self.reset_vf_after_logic();
+    }
+
+    fn load_register(&mut self, vx: u8, vy: u8) {
+        let vy_val = self.registers.read_v(vy);
+        self.registers.write_v(vx, vy_val);
+    }
+
+    fn load_delay_timer(&mut self, vx: u8) {
+        let delay_timer = self.timers.get_delay_timer();
+        self.registers.write_v(vx, delay_timer);
+    }
+
+    fn load_byte(&mut self, vx: u8, byte: u8) {
+        self.registers.write_v(vx, byte);
+    }
+
+    // SUPER-CHIP reads the offset from the register named by the address' top nibble.
+    fn jump_v0(&mut self, addr: u16) -> StepOutcome {
+        let register = if self.options.quirks.jump_uses_vx {
+            (addr >> 8) as u8
+        } else {
+            0
+        };
+        self.jump(self.registers.read_v(register) as u16 + addr)
+    }
+
+    fn jump(&mut self, addr: u16) -> StepOutcome {
+        let halted = self.registers.pc.checked_sub(2) == Some(addr);
+        self.registers.pc = addr;
+        if halted {
+            StepOutcome::Halted(HaltReason::JumpToSelf)
+        } else {
+            StepOutcome::Jumped { to: addr }
+        }
+    }
+
+    fn call(&mut self, addr: u16) -> Result<StepOutcome, Chip8Error> {
+        let depth = self.stack.entries().len() + 1;
+        if depth > VIP_STACK_DEPTH && depth <= stack::STACK_SIZE {
+            let violation = Violation::DeepCall(depth as u8);
+            if self.violation(violation, self.options.strict_stack) {
+                return Err(Chip8Error::ViolationError(violation));
+            }
+        }
+        self.stack.push(self.registers.pc)?;
+        self.registers.pc = addr;
+        Ok(StepOutcome::Jumped { to: addr })
+    }
+
+    fn and(&mut self, vx: u8, vy: u8) {
+        let vx_val = self.registers.read_v(vx);
+        let vy_val = self.registers.read_v(vy);
+
+        let result = vx_val & vy_val;
+
+        self.registers.write_v(vx, result);
+        self.reset_vf_after_logic();
+    }
+
+    fn add_reg(&mut self, vx: u8, vy: u8) {
+        let vx_val = self.registers.read_v(vx);
+        let vy_val = self.registers.read_v(vy);
+
+        let (result, overflow) = vx_val.overflowing_add(vy_val);
+
+        self.write_result_and_flag(vx, result, if overflow { 1 } else { 0 });
+    }
+
+    fn add_vx_byte(&mut self, vx: u8, val: u8) {
+        let result = self.registers.read_v(vx).wrapping_add(val);
+        self.registers.write_v(vx, result);
+    }
+
+    // I wraps around within memory so that later accesses fail where they're made rather than
+    // far from the cause.
+    fn add_i_vx(&mut self, vx: u8) -> Result<(), Chip8Error> {
+        let size = self.memory.size();
+        let sum = self.registers.i as usize + self.registers.read_v(vx) as usize;
+        let overflow = sum >= size;
+        if overflow && self.violation(Violation::IndexOverflow, self.options.strict_index) {
+            return Err(Chip8Error::ViolationError(Violation::IndexOverflow));
+        }
+
+        self.registers.i = (sum % size) as u16;
+        if self.options.quirks.addi_sets_vf {
+            self.registers.write_v(0xF, overflow as u8);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chip8_new() {
+        Chip8::new();
+    }
+
+    #[test]
+    fn test_chip8_load_rom() {
+        let mut chip8 = Chip8::new();
+        let rom = [0x12, 0x34, 0x56, 0x78];
+        chip8.load_rom(&rom).unwrap();
+
+        // Verify that the ROM is loaded correctly into memory
+        assert_eq!(chip8.memory.read_byte(PROGRAM_START_ADDRESS), Ok(0x12));
+        assert_eq!(chip8.memory.read_byte(PROGRAM_START_ADDRESS + 1), Ok(0x34));
+        assert_eq!(chip8.memory.read_byte(PROGRAM_START_ADDRESS + 2), Ok(0x56));
+        assert_eq!(chip8.memory.read_byte(PROGRAM_START_ADDRESS + 3), Ok(0x78));
+    }
+
+    #[test]
+    fn test_chip8_next_opcode() {
+        let mut chip8 = Chip8::booted();
+        chip8.load_rom(&[0x60, 0x2A, 0x12, 0x00]).unwrap();
+
+        assert_eq!(chip8.next_opcode(), Ok(Opcode::LoadByte(0x0, 0x2A)));
+        chip8.step().unwrap();
+        assert_eq!(chip8.pc(), 0x202);
+        assert_eq!(chip8.next_opcode(), Ok(Opcode::Jump(0x200)));
+        assert_eq!(chip8.registers.read_v(0x0), 0x2A);
+    }
+
+    #[test]
+    fn test_chip8_peek_instruction() {
+        let mut chip8 = Chip8::booted();
+        chip8
+            .load_rom(&[0xD1, 0x25, 0xF0, 0x00, 0x12, 0x34, 0xFA, 0xBC])
+            .unwrap();
+        let before = format!("{:?}", chip8);
+
+        assert_eq!(
+            chip8.peek_instruction(),
+            Ok(PeekedInstruction {
+                address: 0x200,
+                word: 0xD125,
+                op: Opcode::Draw(0x1, 0x2, 0x5),
+                operand: None,
+                len: 2,
+            })
+        );
+        assert_eq!(
+            chip8.peek_at(0x202),
+            Ok(PeekedInstruction {
+                address: 0x202,
+                word: 0xF000,
+                op: Opcode::LoadLongI,
+                operand: Some(0x1234),
+                len: 4,
+            })
+        );
+        assert_eq!(
+            chip8.peek_at(0x206).map(|peeked| peeked.op),
+            Ok(Opcode::Undefined(0xFABC))
+        );
+        // Uninitialized memory is decoded without recording a violation.
+        assert_eq!(chip8.peek_at(0x300).map(|peeked| peeked.word), Ok(0x0000));
+        assert_eq!(format!("{:?}", chip8), before);
+        assert!(chip8.violations().is_empty());
+    }
+
+    #[test]
+    fn test_chip8_peek_at_end_of_memory() {
+        let mut chip8 = Chip8::booted();
+        chip8.load_bytes(0xFFE, &[0x12, 0x00]).unwrap();
+
+        assert_eq!(
+            chip8.peek_at(0xFFE).map(|peeked| peeked.op),
+            Ok(Opcode::Jump(0x200))
+        );
+        assert!(chip8.peek_at(0xFFF).is_err());
+        chip8.load_bytes(0xFFE, &[0xF0, 0x00]).unwrap();
+        // The operand would be past the end.
+        assert!(chip8.peek_at(0xFFE).is_err());
+    }
+
+    #[test]
+    fn test_chip8_load_assembled_rom() {
+        let mut chip8 = Chip8::booted();
+        let rom = assemble_opcodes(&[Opcode::LoadByte(0x0, 0x2A), Opcode::Jump(0x200)]);
+
+        chip8.load_rom(&rom).unwrap();
+
+        assert_eq!(
+            chip8.memory_bytes(0x200, 4),
+            Ok(vec![0x60, 0x2A, 0x12, 0x00])
+        );
+        assert_eq!(chip8.next_opcode(), Ok(Opcode::LoadByte(0x0, 0x2A)));
+    }
+
+    #[test]
+    fn test_chip8_load_and_read_bytes() {
+        let mut chip8 = Chip8::booted();
+
+        chip8.load_bytes(0xFFD, &[0x01, 0x02, 0x03]).unwrap();
+
+        assert_eq!(
+            chip8.memory_bytes(0xFFC, 4),
+            Ok(vec![0x00, 0x01, 0x02, 0x03])
+        );
+        assert_eq!(
+            chip8.load_bytes(0xFFE, &[0x04, 0x05, 0x06]),
+            Err(Chip8Error::MemoryError(
+                memory::MemoryError::AddressOutOfBounds
+            ))
+        );
+        assert_eq!(chip8.memory_bytes(0xFFE, 2), Ok(vec![0x02, 0x03]));
+        assert!(chip8.memory_bytes(0xFFE, 3).is_err());
+    }
+
+    #[test]
+    fn test_chip8_unsupported_opcodes_in_rom() {
+        let mut chip8 = Chip8::booted();
+        assert_eq!(chip8.unsupported_opcodes_in_rom(), vec![]);
+
+        chip8
+            .load_rom(&[
+                0x00, 0xE0, 0x03, 0x00, 0xD0, 0x15, 0xFA, 0xBC, 0x12, 0x08, 0x5A,
+            ])
+            .unwrap();
+
+        assert_eq!(
+            chip8.unsupported_opcodes_in_rom(),
+            vec![
+                (0x202, Opcode::SysAddr(0x300)),
+                (0x206, Opcode::Undefined(0xFABC)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_chip8_load_rom_too_large() {
+        let mut chip8 = Chip8::new();
+        let rom = vec![0xAB; memory::MEMORY_SIZE - PROGRAM_START_ADDRESS + 1];
+
+        assert_eq!(
+            chip8.load_rom(&rom),
+            Err(Chip8Error::MemoryError(
+                memory::MemoryError::AddressOutOfBounds
+            ))
+        );
+        assert!(!chip8.memory.is_initialized(PROGRAM_START_ADDRESS));
+    }
+
+    #[test]
+    fn test_chip8_error_messages() {
+        let mut chip8 = Chip8::booted();
+
+        assert_eq!(
+            chip8
+                .load_rom_at(&[0xAB; 4], FONT_LEN - 2, false)
+                .unwrap_err()
+                .to_string(),
+            format!(
+                "4 bytes at {} would overwrite the built-in font",
+                format_addr(FONT_LEN as u16 - 2)
+            )
+        );
+        assert_eq!(
+            chip8.load_rom(&[0xAB; 0x1000]).unwrap_err().to_string(),
+            "doesn't fit in memory"
+        );
+    }
+
+    #[test]
+    fn test_chip8_load_rom_at() {
+        let mut chip8 = Chip8::booted();
+
+        chip8.load_rom_at(&[0x12, 0x00], 0x100, false).unwrap();
+
+        assert_eq!(chip8.registers.pc, 0x100);
+        assert_eq!(chip8.memory_bytes(0x100, 2), Ok(vec![0x12, 0x00]));
+        assert_eq!(chip8.unsupported_opcodes_in_rom(), vec![]);
+    }
+
+    #[test]
+    fn test_chip8_decode_program_matches_stepping() {
+        let ops = [
+            Opcode::LoadByte(0x0, 0x05),
+            Opcode::AddByte(0x0, 0x01),
+            Opcode::SetIndex(0x300),
+            Opcode::LoadReg(0x1, 0x0),
+            Opcode::Xor(0x1, 0x0),
+            Opcode::SetDelayTimer(0x1),
+        ];
+        let mut chip8 = Chip8::booted();
+        chip8.load_rom(&assemble_opcodes(&ops)).unwrap();
+
+        assert_eq!(chip8.decode_program().len(), ops.len());
+        let mut stepped = Vec::new();
+        for _ in 0..ops.len() {
+            stepped.push(chip8.peek_at(chip8.pc()).unwrap().op);
+            chip8.step().unwrap();
+        }
+        let decoded: Vec<&Opcode> = chip8.decode_program().iter().flatten().collect();
+        assert_eq!(decoded, stepped.iter().collect::<Vec<_>>());
+
+        chip8.load_rom(&[0x00, 0xE0, 0x12]).unwrap();
+        assert_eq!(chip8.decode_program(), &[Ok(Opcode::ClearDisplay)]);
+    }
+
+    // A ROM for the VIP's 64x64 hi-res mode: the marking jump, the interpreter patch left blank,
+    // then `program` at 0x2C0.
+    fn legacy_hires_rom(program: &[Opcode]) -> Vec<u8> {
+        let mut rom = vec![0x12, 0x60];
+        rom.resize(LEGACY_HIRES_START - PROGRAM_START_ADDRESS, 0);
+        rom.extend(assemble_opcodes(program));
+        rom
+    }
+
+    fn legacy_hires_machine() -> Chip8 {
+        let mut chip8 = Chip8::with_options(Options {
+            legacy_hires: true,
+            ..Options::default()
+        })
+        .unwrap();
+        chip8.boot(BootConfig::default()).unwrap();
+        chip8
+    }
+
+    #[test]
+    fn test_chip8_runs_legacy_hires() {
+        let rom = legacy_hires_rom(&[
+            Opcode::LoadSpriteAddr(0x0),
+            Opcode::LoadByte(0x1, 63),
+            Opcode::Draw(0x0, 0x1, 2),
+            Opcode::SysAddr(LEGACY_HIRES_CLEAR),
+        ]);
+        assert!(is_legacy_hires(&rom));
+        let mut chip8 = legacy_hires_machine();
+        chip8.load_rom(&rom).unwrap();
+
+        assert!(chip8.legacy_hires());
+        assert_eq!((chip8.display.width, chip8.display.height), (64, 64));
+        assert_eq!(chip8.pc(), LEGACY_HIRES_START as u16);
+        assert_eq!(chip8.draw_sprite_len(&Opcode::Draw(0x0, 0x1, 0)), Some(0));
+
+        for _ in 0..3 {
+            chip8.step().unwrap();
+        }
+
+        // The glyph for 0 starts F0 90: the second row wraps from y = 63 to the top.
+        assert!(chip8.display.is_pixel_set(3, 63));
+        assert!(!chip8.display.is_pixel_set(0, 1));
+        assert!(chip8.display.is_pixel_set(0, 0));
+        assert!(!chip8.display.is_pixel_set(1, 0));
+
+        chip8.step().unwrap();
+        assert!(!chip8.display.is_pixel_set(0, 0));
+    }
+
+    #[test]
+    fn test_chip8_legacy_hires_is_opt_in() {
+        let mut chip8 = legacy_hires_machine();
+        chip8.load_rom(&[0x00, 0xE0]).unwrap();
+
+        assert!(chip8.legacy_hires());
+        assert_eq!(chip8.display.height, 64);
+
+        // A ROM that merely opens with JP 0x260 runs as an ordinary one.
+        let rom = legacy_hires_rom(&[Opcode::ClearDisplay]);
+        let mut chip8 = Chip8::booted();
+        chip8.load_rom(&rom).unwrap();
+
+        assert!(!chip8.legacy_hires());
+        assert_eq!(chip8.display.width, display::DISPLAY_WIDTH);
+        assert_eq!(chip8.pc(), PROGRAM_START_ADDRESS as u16);
+    }
+
+    #[test]
+    fn test_chip8_load_rom_at_refuses_the_font() {
+        let mut chip8 = Chip8::booted();
+        let font = chip8.memory_bytes(0, FONT_LEN).unwrap();
+
+        assert_eq!(
+            chip8.load_rom_at(&[0xAB; 4], FONT_LEN - 2, false),
+            Err(Chip8Error::FontOverwriteError(FontOverwrite {
+                address: FONT_LEN - 2,
+                len: 4
+            }))
+        );
+        assert_eq!(chip8.memory_bytes(0, FONT_LEN), Ok(font));
+        assert_eq!(chip8.registers.pc, PROGRAM_START_ADDRESS as u16);
+
+        chip8.load_rom_at(&[0xAB; 4], 0x000, true).unwrap();
+
+        assert_eq!(chip8.memory_bytes(0, 4), Ok(vec![0xAB; 4]));
+        assert_eq!(chip8.registers.pc, 0x000);
+    }
+
+    #[test]
+    fn test_chip8_load_rom_from_non_existing_file() {
+        let mut chip8 = Chip8::new();
+        let result = chip8.load_rom_from_file("nonexistent_file.txt");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_chip8_load_rom_from_file() {
+        let mut chip8 = Chip8::new();
+
+        // Create a temporary file with some bytes
+        let temp_file_path = "./test_rom.ch8";
+        std::fs::write(temp_file_path, [0xAB, 0xCD, 0xEF]).unwrap();
+
+        // Load the ROM from the temporary file
+        let result = chip8.load_rom_from_file(temp_file_path);
+        assert!(result.is_ok());
+
+        // Verify that the ROM is loaded correctly into memory
+        assert_eq!(chip8.memory.read_byte(PROGRAM_START_ADDRESS), Ok(0xAB));
+        assert_eq!(chip8.memory.read_byte(PROGRAM_START_ADDRESS + 1), Ok(0xCD));
+        assert_eq!(chip8.memory.read_byte(PROGRAM_START_ADDRESS + 2), Ok(0xEF));
+
+        // Delete the temporary file
+        std::fs::remove_file(temp_file_path).unwrap();
+    }
+
+    #[test]
+    fn test_chip8_step() {
+        let mut chip8 = Chip8::booted();
+        chip8.load_rom(&[0x60, 0x42, 0x12, 0x00]).unwrap();
+
+        chip8.step().unwrap();
+        assert_eq!(chip8.registers.read_v(0x0), 0x42);
+        assert_eq!(chip8.registers.pc, 0x202);
+
+        chip8.step().unwrap();
+        assert_eq!(chip8.registers.pc, 0x200);
+    }
+
+    #[test]
+    fn test_chip8_step_blocked_waiting_for_key() {
+        let mut chip8 = Chip8::booted();
+        chip8.load_rom(&[0xF3, 0x0A]).unwrap();
+
+        for _ in 0..2 {
+            assert_eq!(
+                chip8.step(),
+                Ok(StepOutcome::Blocked(BlockReason::WaitingForKey(0x3)))
+            );
+            assert_eq!(chip8.pc(), 0x200);
+        }
+
+        chip8.press_key(0xB).unwrap();
+        assert_eq!(
+            chip8.step(),
+            Ok(StepOutcome::Executed(Opcode::WaitForKey(0x3)))
+        );
+        assert_eq!(chip8.registers.read_v(0x3), 0xB);
+        assert_eq!(chip8.pc(), 0x202);
+    }
+
+    #[test]
+    fn test_chip8_step_undefined_opcode_lenient() {
+        let mut chip8 = Chip8::booted();
+        chip8.load_rom(&[0xFA, 0xBC, 0x60, 0x42]).unwrap();
+
+        chip8.step().unwrap();
+        assert_eq!(chip8.registers.pc, 0x202);
+
+        chip8.step().unwrap();
+        assert_eq!(chip8.registers.read_v(0x0), 0x42);
+    }
+
+    #[test]
+    fn test_chip8_recent_diagnostics() {
+        let mut chip8 = Chip8::booted();
+        chip8
+            .load_rom(&[0xFA, 0xBC, 0x12, 0x04, 0x00, 0xEE])
+            .unwrap();
+
+        chip8.step().unwrap();
+        chip8.step().unwrap();
+        assert!(chip8.step().is_err());
+
+        let entries: Vec<&Diagnostic> = chip8.recent_diagnostics().iter().collect();
+        assert_eq!(
+            entries,
+            vec![
+                &Diagnostic {
+                    severity: Severity::Warning,
+                    cycle: 0,
+                    pc: 0x200,
+                    message: "undefined opcode 0xfabc".to_string(),
+                },
+                &Diagnostic {
+                    severity: Severity::Error,
+                    cycle: 3,
+                    pc: 0x204,
+                    message: "StackError(StackUnderflow)".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_chip8_silenced_records_nothing() {
+        let mut chip8 = Chip8::booted();
+        chip8
+            .load_rom(&[0xFA, 0xBC, 0xA1, 0x00, 0xF0, 0x55, 0x00, 0xEE])
+            .unwrap();
+        chip8.set_memory_write_logging(true);
+        chip8.set_profiling(true);
+        chip8.set_silenced(true);
+
+        chip8.step().unwrap();
+        chip8.step().unwrap();
+        chip8.step().unwrap();
+        assert!(chip8.step().is_err());
+
+        assert!(chip8.violations().is_empty());
+        assert_eq!(chip8.recent_diagnostics().iter().count(), 0);
+        assert_eq!(chip8.take_memory_writes(), vec![]);
+        assert!(chip8.profile_report().is_empty());
+
+        chip8.set_silenced(false);
+        chip8.load_rom(&[0xFA, 0xBC]).unwrap();
+        chip8.step().unwrap();
+        assert_eq!(chip8.violations().entries().len(), 1);
+    }
+
+    #[test]
+    fn test_chip8_handler_rejects_other_families() {
+        let mut chip8 = Chip8::booted();
+
+        assert_eq!(
+            chip8.execute_register(Opcode::Jump(0x300)),
+            Err(Chip8Error::OpcodeError(OpcodeError::InvalidOpcode(0x1300)))
+        );
+        assert_eq!(
+            chip8.execute_flow(Opcode::LoadByte(0x1, 0x42)),
+            Err(Chip8Error::OpcodeError(OpcodeError::InvalidOpcode(0x6142)))
+        );
+        assert_eq!(chip8.registers.pc, 0x200);
+    }
+
+    #[test]
+    fn test_chip8_step_undefined_opcode_strict() {
+        let mut chip8 = Chip8::with_options(Options {
+            strict_decode: true,
+            ..Options::default()
+        })
+        .unwrap();
+        chip8.boot(BootConfig::default()).unwrap();
+        chip8.load_rom(&[0xFA, 0xBC]).unwrap();
+
+        assert_eq!(
+            chip8.step(),
+            Err(Chip8Error::OpcodeError(OpcodeError::InvalidOpcode(0xFABC)))
+        );
+    }
+
+    // Stands in for the 0230 routine of the VIP's hi-res interpreters, marking VF to show it ran.
+    fn fake_clear_screen(chip8: &mut Chip8) -> Result<(), Chip8Error> {
+        chip8.clear_display();
+        chip8.registers.write_v(0xF, 0x1);
+        Ok(())
+    }
+
+    fn failing_sys_routine(_: &mut Chip8) -> Result<(), Chip8Error> {
+        Err(Chip8Error::StackError(stack::StackError::StackOverflow))
+    }
+
+    #[test]
+    fn test_chip8_sys_call_skipped() {
+        let mut chip8 = Chip8::booted();
+        chip8.load_rom(&[0x02, 0x30, 0x12, 0x00]).unwrap();
+
+        for _ in 0..4 {
+            chip8.step().unwrap();
+        }
+
+        assert_eq!(chip8.registers.pc, 0x200);
+        assert_eq!(
+            chip8.violations().entries(),
+            vec![(0x200, Violation::MachineCall(0x230), 2)]
+        );
+    }
+
+    #[test]
+    fn test_chip8_sys_call_strict() {
+        let mut chip8 = Chip8::with_options(Options {
+            strict_sys: true,
+            ..Options::default()
+        })
+        .unwrap();
+        chip8.boot(BootConfig::default()).unwrap();
+        chip8.load_rom(&[0x02, 0x30]).unwrap();
+
+        assert_eq!(
+            chip8.step(),
+            Err(Chip8Error::ViolationError(Violation::MachineCall(0x230)))
+        );
+    }
+
+    #[test]
+    fn test_chip8_sys_handler() {
+        let mut chip8 = Chip8::with_options(Options::strict()).unwrap();
+        chip8.boot(BootConfig::default()).unwrap();
+        chip8.load_rom(&[0x02, 0x30, 0x03, 0x00]).unwrap();
+        chip8.display.draw_sprite(0, 0, &vec![0xFF]).unwrap();
+        chip8.set_sys_handler(0x230, fake_clear_screen);
+
+        chip8.step().unwrap();
+
+        assert_eq!(chip8.registers.read_v(0xF), 0x1);
+        assert!(!chip8.display.is_pixel_set(0, 0));
+        assert_eq!(chip8.registers.pc, 0x202);
+        assert!(chip8.violations().is_empty());
+        // Handled calls aren't unsupported, the others still are.
+        assert_eq!(
+            chip8.unsupported_opcodes_in_rom(),
+            vec![(0x202, Opcode::SysAddr(0x300))]
+        );
+
+        chip8.set_sys_handler(0x300, failing_sys_routine);
+        assert_eq!(
+            chip8.step(),
+            Err(Chip8Error::StackError(stack::StackError::StackOverflow))
+        );
+    }
+
+    #[test]
+    fn test_chip8_sys_handler_keeps_state() {
+        let mut chip8 = Chip8::booted();
+        chip8
+            .load_rom(&assemble_opcodes(&[
+                Opcode::SysAddr(0x240),
+                Opcode::SysAddr(0x240),
+                Opcode::SysAddr(0x240),
+            ]))
+            .unwrap();
+        let mut calls = 0;
+        chip8.set_sys_handler(0x240, move |chip8| {
+            calls += 1;
+            chip8.registers.write_v(0x0, calls);
+            Ok(())
+        });
+
+        for _ in 0..3 {
+            chip8.step().unwrap();
+        }
+
+        assert_eq!(chip8.registers.read_v(0x0), 3);
+        assert!(chip8.violations().is_empty());
+    }
+
+    // Counts up in V0 forever: 7001 1200.
+    const COUNTER_ROM: [u8; 4] = [0x70, 0x01, 0x12, 0x00];
+
+    #[test]
+    fn test_chip8_run_for_exact_over_odd_durations() {
+        let mut chip8 = Chip8::booted();
+        chip8.load_rom(&COUNTER_ROM).unwrap();
+
+        let (mut instructions, mut frames) = (0, 0);
+        for elapsed_ms in [3, 47].repeat(100) {
+            let summary = chip8.run_for(Duration::from_millis(elapsed_ms)).unwrap();
+            assert_eq!(summary.stopped, None);
+            instructions += summary.instructions;
+            frames += summary.frames;
+        }
+
+        // 5 seconds in total.
+        assert_eq!(instructions, 5 * DEFAULT_OPS_PER_SECOND);
+        assert_eq!(frames, 5 * TIMER_HZ);
+        assert_eq!(
+            chip8.registers.read_v(0x0),
+            (5 * DEFAULT_OPS_PER_SECOND / 2 % 256) as u8
+        );
+    }
+
+    #[test]
+    fn test_chip8_run_for_configured_speed() {
+        let mut chip8 = Chip8::with_options(Options {
+            ops_per_second: 1000,
+            ..Options::default()
+        })
+        .unwrap();
+        chip8.boot(BootConfig::default()).unwrap();
+        chip8.load_rom(&COUNTER_ROM).unwrap();
+
+        let summary = chip8.run_for(Duration::from_millis(50)).unwrap();
+
+        assert_eq!(summary.instructions, 50);
+        assert_eq!(summary.frames, 3);
+    }
+
+    #[test]
+    fn test_chip8_run_for_reports_dropped_catch_up() {
+        let options = Options {
+            ops_per_second: 600,
+            ..Options::default()
+        };
+        let mut unlimited = Chip8::with_options(options).unwrap();
+        let mut limited = Chip8::with_options(Options {
+            max_catch_up_frames: Some(4),
+            ..options
+        })
+        .unwrap();
+        for chip8 in [&mut unlimited, &mut limited] {
+            chip8.boot(BootConfig::default()).unwrap();
+            chip8.load_rom(&COUNTER_ROM).unwrap();
+        }
+
+        let summary = unlimited.run_for(Duration::from_secs(1)).unwrap();
+        assert_eq!((summary.instructions, summary.frames), (600, 60));
+        assert_eq!(
+            (summary.dropped_instructions, summary.dropped_frames),
+            (0, 0)
+        );
+
+        let summary = limited.run_for(Duration::from_secs(1)).unwrap();
+        assert_eq!((summary.instructions, summary.frames), (40, 4));
+        assert_eq!(
+            (summary.dropped_instructions, summary.dropped_frames),
+            (560, 56)
+        );
+    }
+
+    #[test]
+    fn test_chip8_run_for_stops_when_waiting_for_key() {
+        let mut chip8 = Chip8::booted();
+        chip8.load_rom(&[0xF0, 0x0A, 0x12, 0x00]).unwrap();
+        chip8.timers.set_delay_timer(0x2);
+
+        let summary = chip8.run_for(Duration::from_millis(50)).unwrap();
+
+        assert_eq!(summary.instructions, 1);
+        assert_eq!(summary.frames, 3);
+        assert_eq!(summary.stopped, Some(StopReason::WaitingForKey));
+        assert_eq!(chip8.timers.get_delay_timer(), 0);
+
+        chip8.press_key(0x7).unwrap();
+        let summary = chip8.run_for(Duration::from_millis(50)).unwrap();
+        assert_eq!(summary.stopped, None);
+        assert_eq!(chip8.registers.read_v(0x0), 0x7);
+    }
+
+    #[test]
+    fn test_chip8_run_for_stops_when_halted() {
+        let mut chip8 = Chip8::booted();
+        chip8.load_rom(&[0x00, 0xE0, 0x12, 0x02]).unwrap();
+
+        let summary = chip8.run_for(Duration::from_millis(50)).unwrap();
+
+        assert_eq!(summary.instructions, 2);
+        assert_eq!(summary.stopped, Some(StopReason::Halted));
+        assert!(summary.display_dirty);
+
+        let summary = chip8.run_for(Duration::from_millis(50)).unwrap();
+        assert_eq!(summary.instructions, 1);
+        assert!(!summary.display_dirty);
+        assert!(!summary.budget_used);
+    }
+
+    #[test]
+    fn test_chip8_run_for_reports_a_busy_loop() {
+        let mut chip8 = Chip8::booted();
+        // V0 += 1 forever, then the same with a key check in the loop.
+        chip8.load_rom(&[0x70, 0x01, 0x12, 0x00]).unwrap();
+
+        let summary = chip8.run_for(Duration::from_millis(50)).unwrap();
+
+        assert!(summary.budget_used);
+        assert!(!summary.keys_polled);
+        assert!(!summary.display_dirty);
+
+        chip8
+            .load_rom(&[0x70, 0x01, 0xE0, 0x9E, 0x12, 0x00])
+            .unwrap();
+        let summary = chip8.run_for(Duration::from_millis(50)).unwrap();
+        assert!(summary.budget_used);
+        assert!(summary.keys_polled);
+    }
+
+    #[test]
+    fn test_chip8_run_for_reports_sound() {
+        let mut chip8 = Chip8::booted();
+        chip8.load_rom(&COUNTER_ROM).unwrap();
+        chip8.timers.set_sound_timer(0x2);
+
+        assert!(
+            chip8
+                .run_for(Duration::from_millis(17))
+                .unwrap()
+                .sound_active
+        );
+        assert!(
+            !chip8
+                .run_for(Duration::from_millis(17))
+                .unwrap()
+                .sound_active
+        );
+    }
+
+    #[test]
+    fn test_chip8_frame_reports_buzzer_edges() {
+        let mut chip8 = Chip8::booted();
+        chip8
+            .load_rom(&assemble_opcodes(&[
+                Opcode::LoadByte(0x0, 0x3),
+                Opcode::SetSoundTimer(0x0),
+                Opcode::Jump(0x204),
+            ]))
+            .unwrap();
+        let report = |buzzer_on, buzzer_edge| FrameReport {
+            buzzer_on,
+            buzzer_edge,
+            overdraw: 0,
+        };
+
+        assert_eq!(chip8.frame(), report(false, false));
+        let reports: Vec<FrameReport> = (0..6)
+            .map(|_| {
+                chip8.run_for(Duration::from_millis(17)).unwrap();
+                chip8.frame()
+            })
+            .collect();
+
+        assert_eq!(reports[0], report(true, true));
+        assert_eq!(reports[1], report(true, false));
+        let edges: Vec<&FrameReport> = reports.iter().filter(|r| r.buzzer_edge).collect();
+        assert_eq!(edges, vec![&report(true, true), &report(false, true)]);
+        assert_eq!(reports[5], report(false, false));
+    }
+
+    #[test]
+    fn test_chip8_clear_rect_follows_the_clip_quirk() {
+        for (clip_sprites, wraps) in [(false, true), (true, false)] {
+            let mut chip8 = with_quirks(Quirks {
+                clip_sprites,
+                ..Quirks::default()
+            });
+            chip8.display.draw_sprite(0, 0, &vec![0x80]).unwrap();
+            let width = chip8.display.width;
+            let generation = chip8.display_generation();
+
+            chip8.clear_rect(width - 1, 0, 2, 1).unwrap();
+
+            assert_eq!(chip8.display.is_pixel_set(0, 0), !wraps);
+            assert!(chip8.display_generation() > generation);
+        }
+    }
+
+    #[test]
+    fn test_chip8_frame_reports_overdraw() {
+        let mut chip8 = Chip8::booted();
+        chip8.memory.write_byte(0x300, 0b1111_0000).unwrap();
+        chip8.registers.i = 0x300;
+        chip8.execute_opcode(Opcode::Draw(0x0, 0x0, 1)).unwrap();
+        chip8.set_overdraw_counting(true);
+
+        // Erased and redrawn in place, as a moving sprite that hasn't moved is.
+        chip8.execute_opcode(Opcode::Draw(0x0, 0x0, 1)).unwrap();
+        chip8.execute_opcode(Opcode::Draw(0x0, 0x0, 1)).unwrap();
+        assert_eq!(chip8.frame().overdraw, 4);
+
+        chip8.execute_opcode(Opcode::ClearDisplay).unwrap();
+        assert_eq!(chip8.frame().overdraw, 0);
+        chip8.execute_opcode(Opcode::Draw(0x0, 0x0, 1)).unwrap();
+        assert_eq!(chip8.frame().overdraw, 0);
+        chip8.set_overdraw_counting(false);
+        chip8.execute_opcode(Opcode::Draw(0x0, 0x0, 1)).unwrap();
+        chip8.execute_opcode(Opcode::Draw(0x0, 0x0, 1)).unwrap();
+        assert_eq!(chip8.frame().overdraw, 0);
+    }
+
+    #[test]
+    fn test_chip8_display_wait_removes_overdraw() {
+        // Erases the sprite and draws it again, over and over.
+        let rom = assemble_opcodes(&[
+            Opcode::LoadByte(0x0, 0x0),
+            Opcode::LoadSpriteAddr(0x0),
+            Opcode::Draw(0x0, 0x0, 5),
+            Opcode::Jump(0x204),
+        ]);
+        let overdraw = |display_wait| {
+            let mut chip8 = with_quirks(Quirks {
+                display_wait,
+                ..Quirks::default()
+            });
+            chip8.boot(BootConfig::default()).unwrap();
+            chip8.load_rom(&rom).unwrap();
+            chip8.set_overdraw_counting(true);
+            (0..10)
+                .map(|_| {
+                    chip8.run_for(Duration::from_micros(16_667)).unwrap();
+                    chip8.frame().overdraw
+                })
+                .sum::<u32>()
+        };
+
+        assert!(overdraw(false) > 0);
+        assert_eq!(overdraw(true), 0);
+    }
+
+    #[test]
+    fn test_chip8_frame_reports_beep_that_ended_within_frame() {
+        let mut chip8 = Chip8::with_options(Options {
+            min_beep_ticks: 1,
+            ..Options::default()
+        })
+        .unwrap();
+        chip8.registers.write_v(0x0, 1);
+
+        chip8.execute_opcode(Opcode::SetSoundTimer(0x0)).unwrap();
+        chip8.tick_timers();
+
+        assert!(!chip8.sound_active());
+        assert!(chip8.frame().buzzer_on);
+        assert!(!chip8.frame().buzzer_on);
+    }
+
+    #[test]
+    fn test_chip8_short_sound_timer_reload_keeps_beep() {
+        let mut chip8 = Chip8::new();
+        chip8.registers.write_v(0x0, 1);
+        chip8.registers.write_v(0x1, 3);
+
+        chip8.execute_opcode(Opcode::SetSoundTimer(0x0)).unwrap();
+        assert!(!chip8.sound_active());
+        chip8.execute_opcode(Opcode::SetSoundTimer(0x1)).unwrap();
+        chip8.execute_opcode(Opcode::SetSoundTimer(0x0)).unwrap();
+        assert!(chip8.sound_active());
+    }
+
+    #[test]
+    fn test_chip8_step_load_long_i() {
+        let mut chip8 = Chip8::booted();
+        chip8
+            .load_rom(&[0xF0, 0x00, 0xAB, 0xCD, 0x60, 0x42])
+            .unwrap();
+
+        chip8.step().unwrap();
+        assert_eq!(chip8.registers.i, 0xABCD);
+        assert_eq!(chip8.registers.pc, 0x204);
+
+        chip8.step().unwrap();
+        assert_eq!(chip8.registers.read_v(0x0), 0x42);
+    }
+
+    #[test]
+    fn test_chip8_step_load_long_i_above_4k() {
+        let mut chip8 = Chip8::with_options(Options {
+            quirks: Quirks::preset(Target::XoChip),
+            ..Options::default()
+        })
+        .unwrap();
+        chip8.boot(BootConfig::default()).unwrap();
+        chip8
+            .load_rom(&[0x60, 0x42, 0xF0, 0x00, 0xFF, 0xF0, 0xF0, 0x55])
+            .unwrap();
+
+        chip8.step().unwrap();
+        chip8.step().unwrap();
+        assert_eq!(chip8.registers.i, 0xFFF0);
+        assert_eq!(chip8.registers.pc, 0x206);
+
+        chip8.step().unwrap();
+        assert_eq!(chip8.memory.read_byte(0xFFF0), Ok(0x42));
+    }
+
+    #[test]
+    fn test_chip8_step_load_long_i_missing_address() {
+        let mut chip8 = Chip8::booted();
+        chip8.load_rom(&[0xF0, 0x00]).unwrap();
+
+        chip8.step().unwrap();
+        assert_eq!(
+            chip8.violations().entries(),
+            vec![(0x200, Violation::UninitializedRead(0x202), 1)]
+        );
+    }
+
+    #[test]
+    fn test_chip8_step_odd_jump_lenient() {
+        let mut chip8 = Chip8::booted();
+        chip8
+            .load_rom(&[0x12, 0x03, 0x00, 0x60, 0x42, 0x12, 0x03])
+            .unwrap();
+
+        chip8.step().unwrap();
+        assert_eq!(chip8.registers.pc, 0x203);
+        assert_eq!(
+            chip8.violations().entries(),
+            vec![(0x200, Violation::MisalignedPc(0x203), 1)]
+        );
+
+        chip8.step().unwrap();
+        assert_eq!(chip8.registers.read_v(0x0), 0x42);
+    }
+
+    #[test]
+    fn test_chip8_step_odd_jump_strict() {
+        let mut chip8 = Chip8::with_options(Options {
+            strict_alignment: true,
+            ..Options::default()
+        })
+        .unwrap();
+        chip8.boot(BootConfig::default()).unwrap();
+        chip8.load_rom(&[0x12, 0x03]).unwrap();
+
+        assert_eq!(
+            chip8.step(),
+            Err(Chip8Error::AlignmentError(Misalignment {
+                address: 0x200,
+                opcode: 0x1203,
+                pc: 0x203,
+            }))
+        );
+    }
+
+    // Loses a life stored at 0x300 on every pass of its loop.
+    const LIVES_ROM: [u8; 10] = [
+        0xA3, 0x00, // 0x200: I = 0x300
+        0xF0, 0x65, // 0x202: V0 = lives
+        0x70, 0xFF, // 0x204: V0 -= 1
+        0xF0, 0x55, // 0x206: lives = V0
+        0x12, 0x00, // 0x208: again
+    ];
+
+    #[test]
+    fn test_chip8_cheat_pins_memory_across_frames() {
+        let mut chip8 = Chip8::booted();
+        chip8.load_rom(&LIVES_ROM).unwrap();
+        chip8.load_bytes(0x300, &[3]).unwrap();
+        chip8.add_cheat(0x300, 3).unwrap();
+
+        for _ in 0..5 {
+            chip8.run_for(Duration::from_millis(17)).unwrap();
+            assert_ne!(chip8.memory_bytes(0x300, 1), Ok(vec![3]));
+            chip8.frame();
+            assert_eq!(chip8.memory_bytes(0x300, 1), Ok(vec![3]));
+        }
+
+        assert!(chip8.remove_cheat(0x300));
+        assert!(!chip8.remove_cheat(0x300));
+        chip8.run_for(Duration::from_millis(17)).unwrap();
+        chip8.frame();
+        assert_ne!(chip8.memory_bytes(0x300, 1), Ok(vec![3]));
+    }
+
+    #[test]
+    fn test_chip8_cheats() {
+        let mut chip8 = Chip8::new();
+
+        chip8.add_cheat(0x400, 9).unwrap();
+        chip8.add_cheat(0x300, 3).unwrap();
+        chip8.add_cheat(0x400, 5).unwrap();
+
+        assert_eq!(chip8.cheats(), vec![(0x300, 3), (0x400, 5)]);
+        assert_eq!(
+            chip8.add_cheat(0x1000, 1),
+            Err(Chip8Error::MemoryError(
+                memory::MemoryError::AddressOutOfBounds
+            ))
+        );
+        assert_eq!(chip8.cheats().len(), 2);
+    }
+
+    #[test]
+    fn test_chip8_breakpoints_sorted() {
+        let mut chip8 = Chip8::new();
+        chip8.add_breakpoint(0x300);
+        chip8.add_breakpoint(0x200);
+        chip8.add_breakpoint(0x2A0);
+        chip8.add_breakpoint(0x200);
+
+        assert_eq!(chip8.breakpoints(), vec![0x200, 0x2A0, 0x300]);
+
+        assert!(chip8.remove_breakpoint(0x2A0));
+        assert!(!chip8.remove_breakpoint(0x2A0));
+        assert_eq!(chip8.breakpoints(), vec![0x200, 0x300]);
+    }
+
+    #[test]
+    fn test_chip8_step_reports_watchpoint_hit() {
+        let mut chip8 = Chip8::booted();
+        // V0 = 1, V1 = 2, I = 0x300, store V0..V1 at I, then store them again.
+        chip8
+            .load_rom(&[0x60, 0x01, 0x61, 0x02, 0xA3, 0x00, 0xF1, 0x55, 0xF1, 0x55])
+            .unwrap();
+        chip8.add_watchpoint(0x301);
+        chip8.add_watchpoint(0x400);
+        assert!(chip8.remove_watchpoint(0x400));
+
+        for op in [
+            Opcode::LoadByte(0x0, 0x01),
+            Opcode::LoadByte(0x1, 0x02),
+            Opcode::SetIndex(0x300),
+        ] {
+            assert_eq!(chip8.step(), Ok(StepOutcome::Executed(op)));
+        }
+        assert_eq!(chip8.step(), Ok(StepOutcome::WatchpointHit(0x301, 0, 2)));
+        // Writing the same value again isn't a change.
+        assert_eq!(
+            chip8.step(),
+            Ok(StepOutcome::Executed(Opcode::RegDump(0x1)))
+        );
+    }
+
+    #[test]
+    fn test_chip8_run_for_stops_at_watchpoint() {
+        let mut chip8 = Chip8::booted();
+        chip8
+            .load_rom(&[0x60, 0x07, 0xA3, 0x00, 0xF0, 0x55, 0x12, 0x06])
+            .unwrap();
+        chip8.add_watchpoint(0x300);
+
+        let summary = chip8.run_for(Duration::from_millis(50)).unwrap();
+
+        assert_eq!(summary.instructions, 3);
+        assert_eq!(summary.stopped, Some(StopReason::Watchpoint(0x300, 0, 7)));
+    }
+
+    #[test]
+    fn test_chip8_step_stops_at_breakpoint_once() {
+        let mut chip8 = Chip8::booted();
+        chip8.load_rom(&COUNTER_ROM).unwrap();
+        chip8.add_breakpoint(0x200);
+
+        assert_eq!(chip8.step(), Ok(StepOutcome::BreakpointHit(0x200)));
+        assert_eq!(chip8.registers.read_v(0x0), 0);
+        assert_eq!(
+            chip8.step(),
+            Ok(StepOutcome::Executed(Opcode::AddByte(0x0, 0x01)))
+        );
+        assert_eq!(chip8.registers.read_v(0x0), 1);
+        // Executing an instruction directly doesn't check breakpoints.
+        chip8.registers.pc = 0x200;
+        assert_eq!(
+            chip8.execute_opcode(Opcode::AddByte(0x0, 0x01)),
+            Ok(StepOutcome::Executed(Opcode::AddByte(0x0, 0x01)))
+        );
+    }
+
+    #[test]
+    fn test_chip8_run_for_stops_at_breakpoint() {
+        let mut chip8 = Chip8::booted();
+        chip8.load_rom(&COUNTER_ROM).unwrap();
+        chip8.add_breakpoint(0x202);
+
+        let summary = chip8.run_for(Duration::from_millis(50)).unwrap();
+        assert_eq!(summary.instructions, 1);
+        assert_eq!(summary.stopped, Some(StopReason::Breakpoint(0x202)));
+        assert_eq!(chip8.registers.pc, 0x202);
+
+        // Resuming executes the instruction at the breakpoint and stops the next time around.
+        let summary = chip8.run_for(Duration::from_millis(50)).unwrap();
+        assert_eq!(summary.instructions, 2);
+        assert_eq!(summary.stopped, Some(StopReason::Breakpoint(0x202)));
+        assert_eq!(chip8.registers.read_v(0x0), 2);
+    }
+
+    // Beeps, draws, counts V0 to 3 in a loop at 0x208, then halts at 0x20E.
+    fn run_steps_rom() -> Chip8 {
+        let mut chip8 = Chip8::booted();
+        chip8
+            .load_rom(&assemble_opcodes(&[
+                Opcode::LoadByte(0x1, 0x5),
+                Opcode::SetSoundTimer(0x1),
+                Opcode::AddByte(0x2, 0x1),
+                Opcode::Draw(0x0, 0x0, 1),
+                Opcode::AddByte(0x0, 0x1),
+                Opcode::SkipIfEqual(0x0, 0x3),
+                Opcode::Jump(0x208),
+                Opcode::Jump(0x20E),
+            ]))
+            .unwrap();
+        chip8
+    }
+
+    #[test]
+    fn test_chip8_run_steps_stop_conditions() {
+        let run = |stop: StopConditions| {
+            let mut chip8 = run_steps_rom();
+            let result = chip8.run_steps(100, stop).unwrap();
+            (result.instructions, result.stopped, chip8.pc())
+        };
+
+        assert_eq!(run(StopConditions::default()), (100, None, 0x20E));
+        assert_eq!(
+            run(StopConditions {
+                sound_started: true,
+                ..StopConditions::default()
+            }),
+            (2, Some(StopReason::SoundStarted), 0x204)
+        );
+        assert_eq!(
+            run(StopConditions {
+                display_updated: true,
+                ..StopConditions::default()
+            }),
+            (4, Some(StopReason::DisplayUpdated), 0x208)
+        );
+        assert_eq!(
+            run(StopConditions {
+                addresses: BTreeSet::from([0x206, 0x208]),
+                ..StopConditions::default()
+            }),
+            (3, Some(StopReason::Breakpoint(0x206)), 0x206)
+        );
+        assert_eq!(
+            run(StopConditions {
+                pc_in: Some(0x208..0x20C),
+                ..StopConditions::default()
+            }),
+            (4, Some(StopReason::PcInRange(0x208)), 0x208)
+        );
+        assert_eq!(
+            run(StopConditions {
+                halted: true,
+                ..StopConditions::default()
+            }),
+            (13, Some(StopReason::Halted), 0x20E)
+        );
+    }
+
+    #[test]
+    fn test_chip8_run_steps_runs_on_from_where_it_stopped() {
+        let mut chip8 = run_steps_rom();
+        let stop = StopConditions {
+            addresses: BTreeSet::from([0x208]),
+            ..StopConditions::default()
+        };
+
+        assert_eq!(chip8.run_steps(10, stop.clone()).unwrap().instructions, 4);
+        // Starting at the address doesn't stop again, it stops the next time round the loop.
+        let result = chip8.run_steps(10, stop).unwrap();
+        assert_eq!(result.instructions, 3);
+        assert_eq!(result.stopped, Some(StopReason::Breakpoint(0x208)));
+
+        chip8.add_breakpoint(0x208);
+        let result = chip8.run_steps(10, StopConditions::default()).unwrap();
+        assert_eq!(result.instructions, 0);
+        assert_eq!(result.stopped, Some(StopReason::Breakpoint(0x208)));
+        assert_eq!(
+            chip8.run_steps(0, StopConditions::default()).unwrap(),
+            RunResult::default()
+        );
+    }
+
+    #[test]
+    fn test_chip8_run_steps_stops_waiting_for_a_key() {
+        let mut chip8 = Chip8::booted();
+        chip8
+            .load_rom(&assemble_opcodes(&[Opcode::WaitForKey(0x0)]))
+            .unwrap();
+        let stop = StopConditions {
+            halted: true,
+            ..StopConditions::default()
+        };
+
+        let result = chip8.run_steps(10, stop).unwrap();
+
+        assert_eq!(result.instructions, 1);
+        assert_eq!(result.stopped, Some(StopReason::WaitingForKey));
+    }
+
+    fn with_memory_size(memory_size: usize) -> Chip8 {
+        let mut chip8 = Chip8::with_options(Options {
+            quirks: Quirks {
+                memory_size,
+                ..Quirks::default()
+            },
+            ..Options::default()
+        })
+        .unwrap();
+        chip8.boot(BootConfig::default()).unwrap();
+        chip8
+    }
+
+    #[test]
+    fn test_chip8_64k_memory_accesses_high_address() {
+        let mut chip8 = with_memory_size(0x10000);
+        chip8
+            .load_rom(&[0xF0, 0x00, 0x50, 0x00, 0x60, 0x42, 0xF0, 0x55, 0xF0, 0x65])
+            .unwrap();
+
+        for _ in 0..4 {
+            chip8.step().unwrap();
+        }
+
+        assert_eq!(chip8.registers.i, 0x5000);
+        assert_eq!(chip8.memory.read_byte(0x5000), Ok(0x42));
+        assert_eq!(chip8.registers.read_v(0x0), 0x42);
+    }
+
+    #[test]
+    fn test_chip8_with_options_rejects_invalid_memory_size() {
+        for memory_size in [MAX_MEMORY_SIZE + 1, MIN_MEMORY_SIZE - 2] {
+            let options = Options {
+                quirks: Quirks {
+                    memory_size,
+                    ..Quirks::default()
+                },
+                ..Options::default()
+            };
+
+            assert_eq!(
+                Chip8::with_options(options).err(),
+                Some(Chip8Error::MemoryError(memory::MemoryError::InvalidSize(
+                    memory_size
+                )))
+            );
+        }
+    }
+
+    #[test]
+    fn test_chip8_64k_memory_skip_wraps_pc() {
+        let mut chip8 = with_memory_size(0x10000);
+        chip8
+            .load_rom_at(
+                &assemble_opcodes(&[Opcode::SkipIfEqual(0x0, 0x00)]),
+                0xFFFC,
+                false,
+            )
+            .unwrap();
+
+        assert_eq!(chip8.step(), Ok(StepOutcome::Skipped));
+        assert_eq!(chip8.pc(), 0x0000);
+    }
+
+    #[test]
+    fn test_chip8_2k_memory_rejects_address_past_end() {
+        let mut chip8 = with_memory_size(0x800);
+        chip8.load_rom(&[0xA9, 0x00]).unwrap();
+
+        assert_eq!(
+            chip8.step(),
+            Err(Chip8Error::OpcodeError(OpcodeError::InvalidAddress(0x900)))
+        );
+        assert_eq!(
+            chip8.load_rom(&[0xAB; 0x601]),
+            Err(Chip8Error::MemoryError(
+                memory::MemoryError::AddressOutOfBounds
+            ))
+        );
+    }
+
+    #[test]
+    fn test_chip8_execute_opcode_leaves_pc_alone() {
+        let mut chip8 = Chip8::booted();
+        chip8.add_watchpoint(0x300);
+        chip8.registers.i = 0x300;
+        chip8.registers.write_v(0x0, 0x42);
+
+        assert_eq!(
+            chip8.execute_opcode(Opcode::RegDump(0x0)),
+            Ok(StepOutcome::WatchpointHit(0x300, 0x00, 0x42))
+        );
+        assert_eq!(chip8.registers.pc, 0x200);
+        assert_eq!(
+            chip8.execute_opcode(Opcode::LoadByte(0x1, 0x01)),
+            Ok(StepOutcome::Executed(Opcode::LoadByte(0x1, 0x01)))
+        );
+    }
+
+    #[test]
+    fn test_chip8_add_i_wraps_at_memory_size() {
+        let mut chip8 = with_memory_size(0x800);
+        chip8.registers.write_v(0x0, 0x2);
+        chip8.registers.i = 0x7FF;
+
+        chip8.execute_opcode(Opcode::AddI(0x0)).unwrap();
+
+        assert_eq!(chip8.registers.i, 0x001);
+        assert_eq!(
+            chip8.violations().entries(),
+            vec![(0x200, Violation::IndexOverflow, 1)]
+        );
+    }
+
+    #[test]
+    fn test_chip8_execute_store_bcd_past_end_of_memory() {
+        let mut chip8 = Chip8::booted();
+        chip8.registers.i = 0xFFE;
+        chip8.registers.write_v(0x0, 123);
+
+        assert_eq!(
+            chip8.execute_opcode(Opcode::StoreBCD(0x0)),
+            Err(Chip8Error::MemoryError(
+                memory::MemoryError::AddressOutOfBounds
+            ))
+        );
+        assert!(!chip8.memory.is_initialized(0xFFE));
+    }
+
+    #[test]
+    fn test_chip8_execute_reg_dump_past_end_of_memory() {
+        let mut chip8 = Chip8::booted();
+        chip8.registers.i = 0xFFE;
+
+        assert_eq!(
+            chip8.execute_opcode(Opcode::RegDump(0x2)),
+            Err(Chip8Error::MemoryError(
+                memory::MemoryError::AddressOutOfBounds
+            ))
+        );
+        assert!(!chip8.memory.is_initialized(0xFFE));
+    }
+
+    #[test]
+    fn test_chip8_execute_reg_load_past_end_of_memory() {
+        let mut chip8 = Chip8::booted();
+        chip8.registers.i = 0xFFE;
+
+        assert_eq!(
+            chip8.execute_opcode(Opcode::RegLoad(0x2)),
+            Err(Chip8Error::MemoryError(
+                memory::MemoryError::AddressOutOfBounds
+            ))
+        );
+    }
+
+    #[test]
+    fn test_chip8_memory_write_log() {
+        let mut chip8 = Chip8::booted();
+        chip8.registers.i = 0x300;
+        chip8.registers.write_v(0x0, 0x11);
+        chip8.registers.write_v(0x1, 0x22);
+        chip8.execute_opcode(Opcode::RegDump(0x1)).unwrap();
+        assert_eq!(chip8.take_memory_writes(), vec![]);
+
+        chip8.set_memory_write_logging(true);
+        chip8.registers.write_v(0x1, 0x33);
+        chip8.execute_opcode(Opcode::RegDump(0x1)).unwrap();
+        chip8.execute_opcode(Opcode::LoadByte(0x0, 0x44)).unwrap();
+        chip8.execute_opcode(Opcode::RegDump(0x0)).unwrap();
+
+        let write = |cycle, address, old, new| MemoryWrite {
+            cycle,
+            address,
+            old,
+            new,
+        };
+        assert_eq!(
+            chip8.take_memory_writes(),
+            vec![
+                write(1, 0x300, 0x11, 0x11),
+                write(1, 0x301, 0x22, 0x33),
+                write(3, 0x300, 0x11, 0x44),
+            ]
+        );
+        assert_eq!(chip8.take_memory_writes(), vec![]);
+        assert_eq!(chip8.cycles(), 4);
+    }
+
+    #[test]
+    fn test_chip8_profile_report() {
+        let mut chip8 = Chip8::booted();
+        chip8
+            .load_rom(&assemble_opcodes(&[
+                Opcode::LoadByte(0x0, 0x00),
+                Opcode::Draw(0x0, 0x0, 5),
+                Opcode::AddByte(0x0, 0x08),
+                Opcode::Jump(0x202),
+            ]))
+            .unwrap();
+        chip8.step().unwrap();
+        assert_eq!(chip8.profile_report(), vec![]);
+
+        chip8.set_profiling(true);
+        for _ in 0..30 {
+            chip8.step().unwrap();
+        }
+
+        let report = chip8.profile_report();
+        let count = |pattern| {
+            report
+                .iter()
+                .find(|entry| entry.pattern == pattern)
+                .map(|entry| entry.count)
+        };
+        assert_eq!(count("DXYN"), Some(10));
+        assert_eq!(count("7XNN"), Some(10));
+        assert_eq!(count("1NNN"), Some(10));
+        assert_eq!(count("6XNN"), None);
+        assert!(report.windows(2).all(|pair| pair[0].time >= pair[1].time));
+        chip8.set_profiling(false);
+        assert_eq!(chip8.profile_report(), vec![]);
+    }
+
+    #[test]
+    fn test_chip8_execute_reg_dump_and_load_all_registers() {
+        let mut chip8 = Chip8::booted();
+        chip8.registers.i = 0xFF0;
+        for reg in 0x0..=0xF {
+            chip8.registers.write_v(reg, reg * 3);
+        }
+
+        chip8.execute_opcode(Opcode::RegDump(0xF)).unwrap();
+        for reg in 0x0..=0xF {
+            assert_eq!(chip8.memory.read_byte(0xFF0 + reg as usize), Ok(reg * 3));
+            chip8.registers.write_v(reg, 0);
+        }
+
+        chip8.execute_opcode(Opcode::RegLoad(0xF)).unwrap();
+        for reg in 0x0..=0xF {
+            assert_eq!(chip8.registers.read_v(reg), reg * 3);
+        }
+        assert!(chip8.violations().is_empty());
+    }
+
+    #[test]
+    fn test_chip8_execute_reg_dump_and_load_all_registers_past_end_of_memory() {
+        let mut chip8 = Chip8::booted();
+        chip8.registers.i = 0xFF1;
+        chip8.registers.write_v(0x0, 0x42);
+
+        assert_eq!(
+            chip8.execute_opcode(Opcode::RegDump(0xF)),
+            Err(Chip8Error::MemoryError(
+                memory::MemoryError::AddressOutOfBounds
+            ))
+        );
+        assert!(!chip8.memory.is_initialized(0xFF1));
+        assert_eq!(
+            chip8.execute_opcode(Opcode::RegLoad(0xF)),
+            Err(Chip8Error::MemoryError(
+                memory::MemoryError::AddressOutOfBounds
+            ))
+        );
+        assert_eq!(chip8.registers.read_v(0x0), 0x42);
+    }
+
+    #[test]
+    fn test_chip8_execute_reg_dump_to_top_of_64k_memory() {
+        let mut chip8 = Chip8::with_options(Options {
+            quirks: Quirks::preset(Target::XoChip),
+            ..Options::default()
+        })
+        .unwrap();
+        chip8.registers.i = 0xFFF0;
+        chip8.registers.write_v(0xF, 0x42);
+
+        chip8.execute_opcode(Opcode::RegDump(0xF)).unwrap();
+
+        assert_eq!(chip8.memory.read_byte(0xFFFF), Ok(0x42));
+        assert_eq!(chip8.registers.i, 0x0000);
+    }
+
+    // I = 0xFFD with the last three bytes of memory set, so a 5 row sprite has 2 rows past the end.
+    fn with_sprite_at_end_of_memory(options: Options) -> Chip8 {
+        let mut chip8 = Chip8::with_options(options).unwrap();
+        chip8.boot(BootConfig::default()).unwrap();
+        chip8.load_bytes(0xFFD, &[0xFF, 0xFF, 0xFF]).unwrap();
+        chip8.registers.i = 0xFFD;
+        chip8
+    }
+
+    fn sprite_column(chip8: &Chip8) -> Vec<bool> {
+        (0..5).map(|y| chip8.display.is_pixel_set(0, y)).collect()
+    }
+
+    #[test]
+    fn test_chip8_execute_draw_past_end_of_memory() {
+        let mut chip8 = with_sprite_at_end_of_memory(Options::default());
+
+        chip8.execute_opcode(Opcode::Draw(0x0, 0x0, 5)).unwrap();
+
+        assert_eq!(sprite_column(&chip8), vec![true, true, true, false, false]);
+        assert_eq!(
+            chip8.violations().entries(),
+            vec![(0x200, Violation::SpritePastMemory(0xFFD), 1)]
+        );
+    }
+
+    #[test]
+    fn test_chip8_execute_draw_past_end_of_memory_wraps() {
+        let mut chip8 = with_sprite_at_end_of_memory(Options {
+            quirks: Quirks {
+                sprite_reads_wrap: true,
+                ..Quirks::default()
+            },
+            ..Options::default()
+        });
+
+        chip8.execute_opcode(Opcode::Draw(0x0, 0x0, 5)).unwrap();
+
+        // The last two rows are the first two of the built-in 0, 0xF0 and 0x90.
+        assert_eq!(sprite_column(&chip8), vec![true, true, true, true, true]);
+        assert!(chip8.display.is_pixel_set(1, 3));
+        assert!(!chip8.display.is_pixel_set(1, 4));
+        assert!(chip8.violations().is_empty());
+    }
+
+    #[test]
+    fn test_chip8_execute_draw_past_end_of_memory_strict() {
+        let mut chip8 = with_sprite_at_end_of_memory(Options {
+            strict_memory: true,
+            ..Options::default()
+        });
+
+        assert_eq!(
+            chip8.execute_opcode(Opcode::Draw(0x0, 0x0, 5)),
+            Err(Chip8Error::SpriteOverrunError(SpriteOverrun {
+                pc: 0x200,
+                i: 0xFFD
+            }))
+        );
+        assert_eq!(sprite_column(&chip8), vec![false; 5]);
+    }
+
+    #[test]
+    fn test_chip8_draw_from_outside_the_rom_and_font() {
+        let draw_from = |i, options| {
+            let mut chip8 = Chip8::with_options(options).unwrap();
+            chip8.boot(BootConfig::default()).unwrap();
+            chip8.load_rom(&[0xFF; 8]).unwrap();
+            chip8.load_bytes(0x208, &[0xFF; 2]).unwrap();
+            chip8.load_bytes(0x300, &[0xFF; 4]).unwrap();
+            chip8.registers.i = i;
+            let result = chip8.execute_opcode(Opcode::Draw(0x0, 0x0, 4));
+            (chip8, result)
+        };
+
+        for i in [0x000, 0x04C, 0x200, 0x204] {
+            let (chip8, result) = draw_from(i, Options::strict());
+            assert!(result.is_ok(), "{:#05X}", i);
+            assert!(chip8.violations().is_empty());
+        }
+        // Straddling the end of the ROM, and in data the ROM didn't bring.
+        for i in [0x206, 0x300] {
+            let (chip8, result) = draw_from(i, Options::default());
+            assert!(result.is_ok());
+            assert_eq!(
+                chip8.violations().entries(),
+                vec![(0x200, Violation::SpriteOutsideProgram(i), 1)]
+            );
+        }
+        let (_, result) = draw_from(0x300, Options::strict());
+        assert_eq!(
+            result,
+            Err(Chip8Error::ViolationError(Violation::SpriteOutsideProgram(
+                0x300
+            )))
+        );
+    }
+
+    #[test]
+    fn test_chip8_execute_draw_entirely_past_end_of_memory() {
+        let mut chip8 = Chip8::booted();
+        chip8.registers.i = 0xABCD;
+
+        chip8.execute_opcode(Opcode::Draw(0x0, 0x0, 2)).unwrap();
+
+        assert_eq!(
+            chip8.violations().entries(),
+            vec![(0x200, Violation::SpritePastMemory(0xABCD), 1)]
+        );
+    }
+
+    #[test]
+    fn test_chip8_draw_sprite_len() {
+        let mut chip8 = Chip8::booted();
+        chip8.display = display::Display::with_mode(64, 32).unwrap();
+
+        assert_eq!(chip8.draw_sprite_len(&Opcode::Draw(0x0, 0x1, 5)), Some(5));
+        assert_eq!(chip8.draw_sprite_len(&Opcode::Draw(0x0, 0x1, 0)), Some(0));
+        assert_eq!(chip8.draw_sprite_len(&Opcode::ClearDisplay), None);
+    }
+
+    #[test]
+    fn test_chip8_draw_sprite_len_matches_draw() {
+        // A draw that reads past the initialized bytes at I is caught reading uninitialized
+        // memory, so with exactly draw_sprite_len of them it must not be.
+        let draw_after = |initialized: usize, n: u8| {
+            let mut chip8 = Chip8::booted();
+            chip8.load_bytes(0x300, &vec![0xFF; initialized]).unwrap();
+            chip8.registers.i = 0x300;
+            chip8.execute_opcode(Opcode::Draw(0x0, 0x1, n)).unwrap();
+            chip8.violations().entries()
+        };
+
+        for n in [0, 1, 5, 15] {
+            let len = Chip8::booted()
+                .draw_sprite_len(&Opcode::Draw(0x0, 0x1, n))
+                .unwrap();
+            let read_past = |(_, violation, _): &(u16, Violation, u64)| {
+                matches!(violation, Violation::UninitializedRead(_))
+            };
+            assert!(!draw_after(len, n).iter().any(read_past), "DXY{:X}", n);
+            if len > 0 {
+                assert!(draw_after(len - 1, n).iter().any(read_past), "DXY{:X}", n);
+            }
+        }
+    }
+
+    #[test]
+    fn test_chip8_display_generation() {
+        let mut chip8 = Chip8::booted();
+        chip8
+            .load_rom(&[0x00, 0xE0, 0x60, 0x01, 0xD0, 0x05])
+            .unwrap();
+
+        chip8.step().unwrap();
+        assert_eq!(chip8.display_generation(), 1);
+        chip8.step().unwrap();
+        assert_eq!(chip8.display_generation(), 1);
+        chip8.step().unwrap();
+        assert_eq!(chip8.display_generation(), 2);
+    }
+
+    #[test]
+    fn test_chip8_render_ascii() {
+        let mut chip8 = Chip8::booted();
+        chip8.display = display::Display::with_mode(8, 2).unwrap();
+        chip8.display.draw_sprite(1, 0, &vec![0b1010_0001]).unwrap();
+
+        assert_eq!(chip8.render_ascii(), "........\n#.#....#");
+    }
+
+    #[test]
+    fn test_chip8_render_ascii_with_status() {
+        let mut chip8 = Chip8::booted();
+        chip8
+            .load_rom(&[0x22, 0x04, 0x00, 0x00, 0xA2, 0x34])
+            .unwrap();
+        chip8.step().unwrap();
+        chip8.step().unwrap();
+
+        let rendered = chip8.render_ascii_with_status();
+        let status = rendered.lines().last().unwrap();
+
+        assert!(rendered.starts_with(&chip8.render_ascii()));
+        assert_eq!(status, "pc=0x206 I=0x234 stack=0x202");
+    }
+
+    #[test]
+    fn test_chip8_soft_reset() {
+        let mut chip8 = Chip8::booted();
+        chip8
+            .load_rom(&[0x60, 0x05, 0xF0, 0x15, 0xF0, 0x18, 0xD1, 0x15])
+            .unwrap();
+        for _ in 0..4 {
+            chip8.step().unwrap();
+        }
+        assert!(chip8.display.is_pixel_set(0, 0));
+
+        chip8.soft_reset();
+
+        assert_eq!(chip8.registers.pc, 0x208);
+        assert_eq!(chip8.registers.read_v(0x0), 0x05);
+        assert_eq!(chip8.registers.i, 0x000);
+        assert_eq!(chip8.memory.read_byte(0x200), Ok(0x60));
+        assert_eq!(chip8.timers.get_delay_timer(), 0);
+        assert_eq!(chip8.timers.get_sound_timer(), 0);
+        assert!(!chip8.sound_active());
+        assert_eq!(chip8.render_ascii(), Chip8::booted().render_ascii());
+    }
+
+    #[test]
+    fn test_chip8_export_state() {
+        let mut chip8 = Chip8::booted();
+        chip8.registers.write_v(0x3, 0x42);
+        chip8.registers.write_v(0xF, 0x1);
+        chip8.registers.i = 0x345;
+        chip8.registers.pc = 0x204;
+        chip8.execute_opcode(Opcode::Call(0x300)).unwrap();
+        chip8.timers.set_delay_timer(0x10);
+        chip8.timers.set_sound_timer(0x05);
+        chip8.display.draw_sprite(2, 8, &vec![0b1100_0000]).unwrap();
+
+        let state = chip8.export_state();
+
+        for register in 0..16 {
+            assert_eq!(state.v[register], chip8.registers.read_v(register as u8));
+        }
+        assert_eq!(state.v[0x3], 0x42);
+        assert_eq!(state.i, 0x345);
+        assert_eq!(state.pc, chip8.pc());
+        assert_eq!(state.pc, 0x300);
+        assert_eq!(state.sp, 1);
+        assert_eq!(state.stack[..2], [0x204, 0x000]);
+        assert_eq!(state.delay_timer, 0x10);
+        assert_eq!(state.sound_timer, 0x05);
+        assert_eq!(
+            (state.display_width, state.display_height),
+            (chip8.display.width as u16, chip8.display.height as u16)
+        );
+        let row_len = chip8.display.width / 8;
+        assert_eq!(state.framebuffer.len(), row_len * chip8.display.height);
+        assert_eq!(state.framebuffer[2 * row_len + 1], 0b1100_0000);
+        assert_eq!(
+            state.framebuffer.iter().filter(|&&byte| byte != 0).count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_chip8_tick_timers() {
+        let mut chip8 = Chip8::new();
+        chip8.timers.set_delay_timer(0x2);
+
+        chip8.tick_timers();
+
+        assert_eq!(chip8.timers.get_delay_timer(), 0x1);
+        assert!(chip8.timers_running());
+        chip8.tick_timers();
+        assert!(!chip8.timers_running());
+    }
+
+    #[test]
+    fn test_chip8_execute_add_byte() {
+        let mut chip8 = Chip8::new();
+        chip8.registers.write_v(0x0, 0x10);
+
+        chip8.execute_opcode(Opcode::AddByte(0x0, 0x20)).unwrap();
+
+        assert_eq!(chip8.registers.read_v(0x0), 0x30);
+    }
+
+    #[test]
+    fn test_chip8_execute_add_i() {
+        let mut chip8 = Chip8::new();
+        chip8.registers.write_v(0x0, 0x10);
+        chip8.registers.i = 0x100;
+
+        chip8.execute_opcode(Opcode::AddI(0x0)).unwrap();
+
+        assert_eq!(chip8.registers.i, 0x110);
+    }
+
+    #[test]
+    fn test_chip8_execute_add_i_overflow_masks_i() {
+        let mut chip8 = Chip8::new();
+        chip8.registers.write_v(0x0, 0x2);
+        chip8.registers.write_v(0xF, 0x7);
+        chip8.registers.i = 0xFFF;
+
+        chip8.execute_opcode(Opcode::AddI(0x0)).unwrap();
+
+        assert_eq!(chip8.registers.i, 0x001);
+        assert_eq!(chip8.registers.read_v(0xF), 0x7);
+    }
+
+    #[test]
+    fn test_chip8_execute_add_i_overflow_sets_vf_quirk() {
+        let mut chip8 = Chip8::with_options(Options {
+            quirks: Quirks {
+                addi_sets_vf: true,
+                ..Quirks::default()
+            },
+            ..Options::default()
+        })
+        .unwrap();
+        chip8.registers.write_v(0x0, 0x2);
+        chip8.registers.i = 0xFFF;
+
+        chip8.execute_opcode(Opcode::AddI(0x0)).unwrap();
+        assert_eq!(chip8.registers.i, 0x001);
+        assert_eq!(chip8.registers.read_v(0xF), 1);
+
+        chip8.execute_opcode(Opcode::AddI(0x0)).unwrap();
+        assert_eq!(chip8.registers.i, 0x003);
+        assert_eq!(chip8.registers.read_v(0xF), 0);
+    }
+
+    #[test]
+    fn test_chip8_execute_add_i_overflow_strict() {
+        let mut chip8 = Chip8::with_options(Options::strict()).unwrap();
+        chip8.registers.write_v(0x0, 0x2);
+        chip8.registers.i = 0xFFF;
+
+        assert_eq!(
+            chip8.execute_opcode(Opcode::AddI(0x0)),
+            Err(Chip8Error::ViolationError(Violation::IndexOverflow))
+        );
+        assert_eq!(chip8.registers.i, 0xFFF);
+        assert_eq!(
+            chip8.violations().entries(),
+            vec![(0x000, Violation::IndexOverflow, 1)]
+        );
+    }
+
+    fn with_quirks(quirks: Quirks) -> Chip8 {
+        Chip8::with_options(Options {
+            quirks,
+            ..Options::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_chip8_shift_uses_vy_quirk() {
+        let mut chip8 = with_quirks(Quirks::preset(Target::Chip8));
+        chip8.registers.write_v(0x0, 0xFF);
+        chip8.registers.write_v(0x1, 0b0000_0110);
+
+        chip8.execute_opcode(Opcode::ShiftRight(0x0, 0x1)).unwrap();
+        assert_eq!(chip8.registers.read_v(0x0), 0b0000_0011);
+        assert_eq!(chip8.registers.read_v(0xF), 0);
 
-        self.registers.write_v(vx, result);
-        self.registers.write_v(0xF, if overflow { 1 } else { 0 });
+        chip8.execute_opcode(Opcode::ShiftLeft(0x0, 0x1)).unwrap();
+        assert_eq!(chip8.registers.read_v(0x0), 0b0000_1100);
+        assert_eq!(chip8.registers.read_v(0x1), 0b0000_0110);
     }
 
-    fn add_vx_byte(&mut self, vx: u8, val: u8) {
-        let result = self.registers.read_v(vx).wrapping_add(val);
-        self.registers.write_v(vx, result);
+    #[test]
+    fn test_chip8_load_store_i_quirk() {
+        for (mode, after_dump, after_load) in [
+            (LoadStoreIMode::Unchanged, 0x300, 0x300),
+            (LoadStoreIMode::IncX, 0x302, 0x301),
+            (LoadStoreIMode::IncXPlus1, 0x303, 0x302),
+        ] {
+            let mut chip8 = with_quirks(Quirks {
+                load_store_i: mode,
+                ..Quirks::default()
+            });
+            chip8.registers.i = 0x300;
+
+            chip8.execute_opcode(Opcode::RegDump(0x2)).unwrap();
+            assert_eq!(chip8.registers.i, after_dump, "{:?}", mode);
+
+            chip8.registers.i = 0x300;
+            chip8.execute_opcode(Opcode::RegLoad(0x1)).unwrap();
+            assert_eq!(chip8.registers.i, after_load, "{:?}", mode);
+        }
     }
 
-    fn add_i_vx(&mut self, vx: u8) {
-        let vx_val = self.registers.read_v(vx) as u16;
-        self.registers.i = vx_val.wrapping_add(self.registers.i)
-    }
-}
+    #[test]
+    fn test_chip8_jump_uses_vx_quirk() {
+        let mut chip8 = with_quirks(Quirks::preset(Target::SuperChipModern));
+        chip8.registers.write_v(0x0, 0x10);
+        chip8.registers.write_v(0x3, 0x04);
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        chip8.execute_opcode(Opcode::JumpV0(0x320)).unwrap();
+
+        assert_eq!(chip8.registers.pc, 0x324);
+    }
 
     #[test]
-    fn test_chip8_new() {
-        Chip8::new();
+    fn test_chip8_logic_resets_vf_quirk() {
+        let mut chip8 = with_quirks(Quirks::preset(Target::Chip8));
+        for op in [
+            Opcode::Or(0x0, 0x1),
+            Opcode::And(0x0, 0x1),
+            Opcode::Xor(0x0, 0x1),
+        ] {
+            chip8.registers.write_v(0xF, 0x7);
+
+            chip8.execute_opcode(op).unwrap();
+
+            assert_eq!(chip8.registers.read_v(0xF), 0);
+        }
     }
 
     #[test]
-    fn test_chip8_load_rom() {
-        let mut chip8 = Chip8::new();
-        let rom = [0x12, 0x34, 0x56, 0x78];
-        chip8.load_rom(&rom);
+    fn test_chip8_vf_before_result_quirk() {
+        let mut chip8 = with_quirks(Quirks {
+            vf_before_result: true,
+            ..Quirks::default()
+        });
+        chip8.registers.write_v(0xF, 0xFF);
+        chip8.registers.write_v(0x1, 0x02);
 
-        // Verify that the ROM is loaded correctly into memory
-        assert_eq!(chip8.memory.read_byte(PROGRAM_START_ADDRESS), Ok(0x12));
-        assert_eq!(chip8.memory.read_byte(PROGRAM_START_ADDRESS + 1), Ok(0x34));
-        assert_eq!(chip8.memory.read_byte(PROGRAM_START_ADDRESS + 2), Ok(0x56));
-        assert_eq!(chip8.memory.read_byte(PROGRAM_START_ADDRESS + 3), Ok(0x78));
+        chip8.execute_opcode(Opcode::AddReg(0xF, 0x1)).unwrap();
+
+        assert_eq!(chip8.registers.read_v(0xF), 0x01);
     }
 
-    #[test]
-    fn test_chip8_load_rom_from_non_existing_file() {
-        let mut chip8 = Chip8::new();
-        let result = chip8.load_rom_from_file("nonexistent_file.txt");
-        assert!(result.is_err());
+    // Draws a pixel in the top left corner, then switches to low resolution and back.
+    fn switch_resolution_after_drawing(chip8: &mut Chip8) {
+        chip8.memory.write_byte(0x300, 0x80).unwrap();
+        chip8.registers.i = 0x300;
+        chip8.execute_opcode(Opcode::Draw(0x0, 0x0, 1)).unwrap();
+
+        chip8.execute_opcode(Opcode::LowRes).unwrap();
+
+        assert_eq!((chip8.display.width, chip8.display.height), (64, 32));
     }
 
     #[test]
-    fn test_chip8_load_rom_from_file() {
-        let mut chip8 = Chip8::new();
+    fn test_chip8_resolution_switch_clears_by_default() {
+        let mut chip8 = with_quirks(Quirks::default());
 
-        // Create a temporary file with some bytes
-        let temp_file_path = "./test_rom.ch8";
-        std::fs::write(temp_file_path, &[0xAB, 0xCD, 0xEF]).unwrap();
+        switch_resolution_after_drawing(&mut chip8);
 
-        // Load the ROM from the temporary file
-        let result = chip8.load_rom_from_file(temp_file_path);
-        assert!(result.is_ok());
+        assert!(!chip8.display.is_pixel_set(0, 0));
+        chip8.execute_opcode(Opcode::HighRes).unwrap();
+        assert_eq!(chip8.display.width, display::DISPLAY_WIDTH);
+    }
 
-        // Verify that the ROM is loaded correctly into memory
-        assert_eq!(chip8.memory.read_byte(PROGRAM_START_ADDRESS), Ok(0xAB));
-        assert_eq!(chip8.memory.read_byte(PROGRAM_START_ADDRESS + 1), Ok(0xCD));
-        assert_eq!(chip8.memory.read_byte(PROGRAM_START_ADDRESS + 2), Ok(0xEF));
+    #[test]
+    fn test_chip8_resolution_switch_can_keep_the_display() {
+        let mut chip8 = with_quirks(Quirks {
+            clear_on_resolution_switch: false,
+            ..Quirks::default()
+        });
 
-        // Delete the temporary file
-        std::fs::remove_file(temp_file_path).unwrap();
+        switch_resolution_after_drawing(&mut chip8);
+
+        assert!(chip8.display.is_pixel_set(0, 0));
+        chip8.execute_opcode(Opcode::HighRes).unwrap();
+        assert!(chip8.display.is_pixel_set(0, 0));
     }
 
     #[test]
-    fn test_chip8_execute_add_byte() {
-        let mut chip8 = Chip8::new();
-        chip8.registers.write_v(0x0, 0x10);
+    fn test_chip8_clip_sprites_quirk() {
+        let mut chip8 = with_quirks(Quirks::preset(Target::SuperChipModern));
+        chip8.display = display::Display::with_mode(64, 32).unwrap();
+        chip8.memory.write_byte(0x300, 0xFF).unwrap();
+        chip8.registers.i = 0x300;
+        chip8.registers.write_v(0x0, 60);
 
-        chip8.execute(Opcode::AddByte(0x0, 0x20)).unwrap();
+        chip8.execute_opcode(Opcode::Draw(0x0, 0x1, 1)).unwrap();
 
-        assert_eq!(chip8.registers.read_v(0x0), 0x30);
+        assert!(chip8.display.is_pixel_set(63, 0));
+        assert!(!chip8.display.is_pixel_set(0, 0));
     }
 
     #[test]
-    fn test_chip8_execute_add_i() {
-        let mut chip8 = Chip8::new();
-        chip8.registers.write_v(0x0, 0x10);
-        chip8.registers.i = 0x100;
+    fn test_chip8_draw_wraps_start_position() {
+        for quirks in [Quirks::default(), Quirks::preset(Target::SuperChipModern)] {
+            let mut chip8 = with_quirks(quirks);
+            chip8.display = display::Display::with_mode(64, 32).unwrap();
+            chip8.memory.write_byte(0x300, 0b1000_0000).unwrap();
+            chip8.registers.i = 0x300;
+            chip8.registers.write_v(0x0, 66);
+            chip8.registers.write_v(0x1, 255);
+
+            chip8.execute_opcode(Opcode::Draw(0x0, 0x1, 1)).unwrap();
+
+            assert!(chip8.display.is_pixel_set(2, 31), "{:?}", quirks);
+        }
+    }
+
+    #[test]
+    fn test_chip8_display_wait_quirk() {
+        let mut chip8 = with_quirks(Quirks::preset(Target::Chip8));
+        chip8.boot(BootConfig::default()).unwrap();
+        // Draws and counts in V1 forever.
+        chip8
+            .load_rom(&[0xD0, 0x01, 0x71, 0x01, 0x12, 0x00])
+            .unwrap();
 
-        chip8.execute(Opcode::AddI(0x0)).unwrap();
+        let summary = chip8.run_for(Duration::from_millis(50)).unwrap();
 
-        assert_eq!(chip8.registers.i, 0x110);
+        assert_eq!(summary.frames, 3);
+        assert_eq!(summary.instructions, 1);
+        let summary = chip8.run_for(Duration::from_millis(50)).unwrap();
+        assert_eq!(summary.instructions, 3);
+        assert_eq!(chip8.registers.read_v(0x1), 1);
     }
 
     #[test]
@@ -486,7 +4063,7 @@ mod tests {
         chip8.registers.write_v(0x0, 0x10);
         chip8.registers.write_v(0x1, 0x20);
 
-        chip8.execute(Opcode::AddReg(0x0, 0x1)).unwrap();
+        chip8.execute_opcode(Opcode::AddReg(0x0, 0x1)).unwrap();
 
         assert_eq!(chip8.registers.read_v(0x0), 0x30);
         assert_eq!(chip8.registers.read_v(0xF), 0x0);
@@ -498,7 +4075,7 @@ mod tests {
         chip8.registers.write_v(0x0, 0xFF);
         chip8.registers.write_v(0x1, 0x01);
 
-        chip8.execute(Opcode::AddReg(0x0, 0x1)).unwrap();
+        chip8.execute_opcode(Opcode::AddReg(0x0, 0x1)).unwrap();
 
         assert_eq!(chip8.registers.read_v(0x0), 0x00);
         assert_eq!(chip8.registers.read_v(0xF), 0x1);
@@ -510,7 +4087,7 @@ mod tests {
         chip8.registers.write_v(0x0, 0b10101010);
         chip8.registers.write_v(0x1, 0b11001100);
 
-        chip8.execute(Opcode::And(0x0, 0x1)).unwrap();
+        chip8.execute_opcode(Opcode::And(0x0, 0x1)).unwrap();
 
         assert_eq!(chip8.registers.read_v(0x0), 0b10001000);
     }
@@ -519,12 +4096,60 @@ mod tests {
         let mut chip8 = Chip8::new();
         chip8.registers.pc = 0x200;
 
-        chip8.execute(Opcode::Call(0x300)).unwrap();
-
+        assert_eq!(
+            chip8.execute_opcode(Opcode::Call(0x300)),
+            Ok(StepOutcome::Jumped { to: 0x300 })
+        );
         assert_eq!(chip8.stack.pop(), Ok(0x200));
         assert_eq!(chip8.registers.pc, 0x300);
     }
 
+    #[test]
+    fn test_chip8_call_stack() {
+        let mut chip8 = Chip8::new();
+        chip8.registers.pc = 0x202;
+        assert!(chip8.call_stack().is_empty());
+
+        chip8.execute_opcode(Opcode::Call(0x300)).unwrap();
+        chip8.registers.pc = 0x302;
+        chip8.execute_opcode(Opcode::Call(0x400)).unwrap();
+
+        assert_eq!(chip8.call_stack(), vec![0x302, 0x202]);
+        chip8.execute_opcode(Opcode::Return).unwrap();
+        assert_eq!(chip8.call_stack(), vec![0x202]);
+    }
+
+    #[test]
+    fn test_chip8_execute_call_past_vip_depth() {
+        let call_deep = |options| {
+            let mut chip8 = Chip8::with_options(options).unwrap();
+            chip8.registers.pc = 0x200;
+            for _ in 0..VIP_STACK_DEPTH {
+                chip8.execute_opcode(Opcode::Call(0x200)).unwrap();
+            }
+            assert!(chip8.violations().is_empty());
+            let result = chip8.execute_opcode(Opcode::Call(0x200));
+            (chip8, result)
+        };
+
+        let (chip8, result) = call_deep(Options::default());
+        assert!(result.is_ok());
+        assert_eq!(
+            chip8.violations().entries(),
+            vec![(0x200, Violation::DeepCall(13), 1)]
+        );
+
+        let (chip8, result) = call_deep(Options {
+            strict_stack: true,
+            ..Options::default()
+        });
+        assert_eq!(
+            result,
+            Err(Chip8Error::ViolationError(Violation::DeepCall(13)))
+        );
+        assert_eq!(chip8.call_stack().len(), VIP_STACK_DEPTH);
+    }
+
     #[test]
     fn test_chip8_execute_call_stack_overflow() {
         let mut chip8 = Chip8::new();
@@ -533,7 +4158,7 @@ mod tests {
             chip8.stack.push(0x200).unwrap();
         }
 
-        let result = chip8.execute(Opcode::Call(0x300));
+        let result = chip8.execute_opcode(Opcode::Call(0x300));
 
         assert_eq!(
             result.unwrap_err(),
@@ -545,18 +4170,34 @@ mod tests {
     fn test_chip8_execute_jump() {
         let mut chip8 = Chip8::new();
 
-        chip8.execute(Opcode::Jump(0x300)).unwrap();
-
+        assert_eq!(
+            chip8.execute_opcode(Opcode::Jump(0x300)),
+            Ok(StepOutcome::Jumped { to: 0x300 })
+        );
         assert_eq!(chip8.registers.pc, 0x300);
     }
 
+    #[test]
+    fn test_chip8_execute_jump_to_self_halts() {
+        let mut chip8 = Chip8::booted();
+        chip8.load_rom(&[0x12, 0x00]).unwrap();
+
+        assert_eq!(
+            chip8.step(),
+            Ok(StepOutcome::Halted(HaltReason::JumpToSelf))
+        );
+        assert_eq!(chip8.registers.pc, 0x200);
+    }
+
     #[test]
     fn test_chip8_execute_jump_v0() {
         let mut chip8 = Chip8::new();
         chip8.registers.write_v(0x0, 0x01);
 
-        chip8.execute(Opcode::JumpV0(0x300)).unwrap();
-
+        assert_eq!(
+            chip8.execute_opcode(Opcode::JumpV0(0x300)),
+            Ok(StepOutcome::Jumped { to: 0x301 })
+        );
         assert_eq!(chip8.registers.pc, 0x301);
     }
 
@@ -564,7 +4205,7 @@ mod tests {
     fn test_chip8_execute_load_byte() {
         let mut chip8 = Chip8::new();
 
-        chip8.execute(Opcode::LoadByte(0x0, 0xFF)).unwrap();
+        chip8.execute_opcode(Opcode::LoadByte(0x0, 0xFF)).unwrap();
 
         assert_eq!(chip8.registers.read_v(0x0), 0xFF);
     }
@@ -573,7 +4214,7 @@ mod tests {
         let mut chip8 = Chip8::new();
         chip8.timers.set_delay_timer(0x10);
 
-        chip8.execute(Opcode::LoadDelayTimer(0x0)).unwrap();
+        chip8.execute_opcode(Opcode::LoadDelayTimer(0x0)).unwrap();
 
         assert_eq!(chip8.registers.read_v(0x0), 0x10);
     }
@@ -582,7 +4223,7 @@ mod tests {
         let mut chip8 = Chip8::new();
         chip8.registers.write_v(0x1, 0x42);
 
-        chip8.execute(Opcode::LoadReg(0x0, 0x1)).unwrap();
+        chip8.execute_opcode(Opcode::LoadReg(0x0, 0x1)).unwrap();
 
         assert_eq!(chip8.registers.read_v(0x0), 0x42);
     }
@@ -592,7 +4233,7 @@ mod tests {
         chip8.registers.write_v(0x0, 0b10101010);
         chip8.registers.write_v(0x1, 0b11001100);
 
-        chip8.execute(Opcode::Or(0x0, 0x1)).unwrap();
+        chip8.execute_opcode(Opcode::Or(0x0, 0x1)).unwrap();
 
         assert_eq!(chip8.registers.read_v(0x0), 0b11101110);
     }
@@ -600,12 +4241,74 @@ mod tests {
     fn test_chip8_execute_random() {
         let mut chip8 = Chip8::new();
 
-        chip8.execute(Opcode::Random(0x0, 0b11001100)).unwrap();
+        chip8
+            .execute_opcode(Opcode::Random(0x0, 0b11001100))
+            .unwrap();
 
         let result = chip8.registers.read_v(0x0);
         assert_eq!(result & 0b11001100, result);
     }
     #[test]
+    fn test_chip8_random_is_reproducible_with_a_seed() {
+        let options = Options {
+            rng_seed: Some(42),
+            ..Options::default()
+        };
+        let mut first = Chip8::with_options(options).unwrap();
+        let mut second = Chip8::with_options(options).unwrap();
+
+        for vx in 0x0..=0xE {
+            first.execute_opcode(Opcode::Random(vx, 0xFF)).unwrap();
+            second.execute_opcode(Opcode::Random(vx, 0xFF)).unwrap();
+        }
+
+        assert_eq!(first.state_hash(), second.state_hash());
+    }
+    #[test]
+    fn test_chip8_state_hash() {
+        let rom = [0x60, 0x01, 0x12, 0x00];
+        let mut chip8 = Chip8::booted();
+        chip8.load_rom(&rom).unwrap();
+        let mut other = Chip8::booted();
+        other.load_rom(&rom).unwrap();
+        let loaded = chip8.state_hash();
+        assert_eq!(other.state_hash(), loaded);
+
+        // Back at 0x200 after the jump, but with V0 set.
+        chip8.step().unwrap();
+        chip8.step().unwrap();
+        assert_eq!(chip8.registers.pc, 0x200);
+        assert_ne!(chip8.state_hash(), loaded);
+
+        other.registers.write_v(0x0, 0x01);
+        assert_eq!(other.state_hash(), chip8.state_hash());
+    }
+
+    #[test]
+    fn test_chip8_restore_snapshot() {
+        let rom = assemble_opcodes(&[
+            Opcode::Random(0x0, 0xFF),
+            Opcode::LoadSpriteAddr(0x1),
+            Opcode::Draw(0x0, 0x0, 5),
+            Opcode::Jump(0x200),
+        ]);
+        let mut chip8 = Chip8::booted();
+        chip8.load_rom(&rom).unwrap();
+        chip8.run_for(Duration::from_millis(20)).unwrap();
+        let snapshot = chip8.snapshot();
+        let (hash, generation) = (chip8.state_hash(), chip8.display_generation());
+
+        chip8.run_for(Duration::from_millis(20)).unwrap();
+        let ahead = chip8.state_hash();
+        chip8.restore(&snapshot);
+
+        assert_eq!(chip8.state_hash(), hash);
+        assert!(chip8.display_generation() > generation);
+        // Random numbers come out the same the second time round.
+        chip8.run_for(Duration::from_millis(20)).unwrap();
+        assert_eq!(chip8.state_hash(), ahead);
+    }
+    #[test]
     fn test_chip8_execute_reg_dump() {
         let mut chip8 = Chip8::new();
         chip8.registers.write_v(0x0, 0x01);
@@ -614,7 +4317,7 @@ mod tests {
         chip8.registers.write_v(0x3, 0x04);
         chip8.registers.i = 0x100;
 
-        chip8.execute(Opcode::RegDump(0x3)).unwrap();
+        chip8.execute_opcode(Opcode::RegDump(0x3)).unwrap();
 
         assert_eq!(chip8.memory.read_byte(0x100), Ok(0x01));
         assert_eq!(chip8.memory.read_byte(0x101), Ok(0x02));
@@ -631,7 +4334,7 @@ mod tests {
         chip8.registers.write_v(0x3, 0x04);
         chip8.registers.i = 0xFFFF;
 
-        let result = chip8.execute(Opcode::RegDump(0x3));
+        let result = chip8.execute_opcode(Opcode::RegDump(0x3));
 
         assert_eq!(
             result.unwrap_err(),
@@ -645,7 +4348,7 @@ mod tests {
         chip8.memory.write_byte(0x100, 0x42).unwrap();
         chip8.memory.write_byte(0x101, 0x43).unwrap();
 
-        chip8.execute(Opcode::RegLoad(0x01)).unwrap();
+        chip8.execute_opcode(Opcode::RegLoad(0x01)).unwrap();
 
         assert_eq!(chip8.registers.read_v(0x0), 0x42);
         assert_eq!(chip8.registers.read_v(0x1), 0x43);
@@ -656,20 +4359,71 @@ mod tests {
         let mut chip8 = Chip8::new();
         chip8.registers.i = 0xFFFF;
 
-        let result = chip8.execute(Opcode::RegLoad(0x0));
+        let result = chip8.execute_opcode(Opcode::RegLoad(0x0));
 
         assert_eq!(
             result.unwrap_err(),
             Chip8Error::MemoryError(memory::MemoryError::AddressOutOfBounds)
         );
     }
+    #[test]
+    fn test_chip8_execute_reg_load_past_end_writes_no_registers() {
+        let mut chip8 = Chip8::booted();
+        chip8.registers.i = 0xFFE;
+        chip8.memory.write_byte(0xFFE, 0x11).unwrap();
+        chip8.memory.write_byte(0xFFF, 0x22).unwrap();
+
+        assert!(chip8.execute_opcode(Opcode::RegLoad(0x3)).is_err());
+
+        for reg in 0..4 {
+            assert_eq!(chip8.registers.read_v(reg), 0);
+        }
+        assert_eq!(chip8.registers.i, 0xFFE);
+    }
+
+    #[test]
+    fn test_chip8_execute_reg_load_partial() {
+        let mut chip8 = Chip8::with_options(Options {
+            partial_reg_load: true,
+            ..Options::default()
+        })
+        .unwrap();
+        chip8.boot(BootConfig::default()).unwrap();
+        chip8.registers.i = 0xFFE;
+        chip8.memory.write_byte(0xFFE, 0x11).unwrap();
+        chip8.memory.write_byte(0xFFF, 0x22).unwrap();
+        chip8.registers.write_v(0x2, 0xAA);
+
+        chip8.execute_opcode(Opcode::RegLoad(0x3)).unwrap();
+
+        assert_eq!(chip8.registers.read_v(0x0), 0x11);
+        assert_eq!(chip8.registers.read_v(0x1), 0x22);
+        assert_eq!(chip8.registers.read_v(0x2), 0xAA);
+        assert_eq!(chip8.registers.read_v(0x3), 0x00);
+        assert_eq!(
+            chip8.violations().entries(),
+            vec![(0x200, Violation::LoadPastMemory(0xFFE), 1)]
+        );
+
+        let mut strict = Chip8::with_options(Options {
+            partial_reg_load: true,
+            ..Options::strict()
+        })
+        .unwrap();
+        strict.registers.i = 0xFFF;
+        assert!(strict.execute_opcode(Opcode::RegLoad(0x1)).is_err());
+        assert_eq!(strict.registers.read_v(0x0), 0);
+    }
+
     #[test]
     fn test_chip8_execute_return() {
         let mut chip8 = Chip8::new();
         chip8.stack.push(0x300).unwrap();
 
-        chip8.execute(Opcode::Return).unwrap();
-
+        assert_eq!(
+            chip8.execute_opcode(Opcode::Return),
+            Ok(StepOutcome::Jumped { to: 0x300 })
+        );
         assert_eq!(chip8.registers.pc, 0x300);
     }
 
@@ -678,7 +4432,7 @@ mod tests {
         let mut chip8 = Chip8::new();
         chip8.registers.pc = 0x200;
 
-        let result = chip8.execute(Opcode::Return);
+        let result = chip8.execute_opcode(Opcode::Return);
 
         assert_eq!(
             result.unwrap_err(),
@@ -691,7 +4445,7 @@ mod tests {
         let mut chip8 = Chip8::new();
         chip8.registers.write_v(0x0, 0x10);
 
-        chip8.execute(Opcode::SetDelayTimer(0x0)).unwrap();
+        chip8.execute_opcode(Opcode::SetDelayTimer(0x0)).unwrap();
 
         assert_eq!(chip8.timers.get_delay_timer(), 0x10);
     }
@@ -700,7 +4454,7 @@ mod tests {
     fn test_chip8_execute_set_index() {
         let mut chip8 = Chip8::new();
 
-        chip8.execute(Opcode::SetIndex(0x300)).unwrap();
+        chip8.execute_opcode(Opcode::SetIndex(0x300)).unwrap();
 
         assert_eq!(chip8.registers.i, 0x300);
     }
@@ -710,7 +4464,7 @@ mod tests {
         let mut chip8 = Chip8::new();
         chip8.registers.write_v(0x0, 0x10);
 
-        chip8.execute(Opcode::SetSoundTimer(0x0)).unwrap();
+        chip8.execute_opcode(Opcode::SetSoundTimer(0x0)).unwrap();
 
         assert_eq!(chip8.timers.get_sound_timer(), 0x10);
     }
@@ -719,7 +4473,7 @@ mod tests {
         let mut chip8 = Chip8::new();
         chip8.registers.write_v(0x0, 0b00101010);
 
-        chip8.execute(Opcode::ShiftLeft(0x0)).unwrap();
+        chip8.execute_opcode(Opcode::ShiftLeft(0x0, 0x0)).unwrap();
 
         assert_eq!(chip8.registers.read_v(0x0), 0b01010100);
         assert_eq!(chip8.registers.read_v(0xF), 0x0);
@@ -730,7 +4484,7 @@ mod tests {
         let mut chip8 = Chip8::new();
         chip8.registers.write_v(0x0, 0b10000000);
 
-        chip8.execute(Opcode::ShiftLeft(0x0)).unwrap();
+        chip8.execute_opcode(Opcode::ShiftLeft(0x0, 0x0)).unwrap();
 
         assert_eq!(chip8.registers.read_v(0x0), 0b00000000);
         assert_eq!(chip8.registers.read_v(0xF), 0x1);
@@ -740,7 +4494,7 @@ mod tests {
         let mut chip8 = Chip8::new();
         chip8.registers.write_v(0x0, 0b10101010);
 
-        chip8.execute(Opcode::ShiftRight(0x0)).unwrap();
+        chip8.execute_opcode(Opcode::ShiftRight(0x0, 0x0)).unwrap();
 
         assert_eq!(chip8.registers.read_v(0x0), 0b01010101);
         assert_eq!(chip8.registers.read_v(0xF), 0x0);
@@ -751,7 +4505,7 @@ mod tests {
         let mut chip8 = Chip8::new();
         chip8.registers.write_v(0x0, 0b00000001);
 
-        chip8.execute(Opcode::ShiftRight(0x0)).unwrap();
+        chip8.execute_opcode(Opcode::ShiftRight(0x0, 0x0)).unwrap();
 
         assert_eq!(chip8.registers.read_v(0x0), 0b00000000);
         assert_eq!(chip8.registers.read_v(0xF), 0x1);
@@ -762,9 +4516,12 @@ mod tests {
         chip8.registers.write_v(0x0, 0x10);
         chip8.registers.pc = 0x200;
 
-        chip8.execute(Opcode::SkipIfEqual(0x0, 0x10)).unwrap();
+        let outcome = chip8
+            .execute_opcode(Opcode::SkipIfEqual(0x0, 0x10))
+            .unwrap();
 
         assert_eq!(chip8.registers.pc, 0x202);
+        assert_eq!(outcome, StepOutcome::Skipped);
     }
 
     #[test]
@@ -773,9 +4530,13 @@ mod tests {
         chip8.registers.write_v(0x0, 0x10);
         chip8.registers.pc = 0x200;
 
-        chip8.execute(Opcode::SkipIfEqual(0x0, 0x1)).unwrap();
+        let outcome = chip8.execute_opcode(Opcode::SkipIfEqual(0x0, 0x1)).unwrap();
 
         assert_eq!(chip8.registers.pc, 0x200);
+        assert_eq!(
+            outcome,
+            StepOutcome::Executed(Opcode::SkipIfEqual(0x0, 0x1))
+        );
     }
     #[test]
     fn test_chip8_execute_skip_if_reg_equal_skips() {
@@ -784,9 +4545,12 @@ mod tests {
         chip8.registers.write_v(0x1, 0x10);
         chip8.registers.pc = 0x200;
 
-        chip8.execute(Opcode::SkipIfRegEqual(0x0, 0x1)).unwrap();
+        let outcome = chip8
+            .execute_opcode(Opcode::SkipIfRegEqual(0x0, 0x1))
+            .unwrap();
 
         assert_eq!(chip8.registers.pc, 0x202);
+        assert_eq!(outcome, StepOutcome::Skipped);
     }
 
     #[test]
@@ -796,9 +4560,15 @@ mod tests {
         chip8.registers.write_v(0x1, 0x20);
         chip8.registers.pc = 0x200;
 
-        chip8.execute(Opcode::SkipIfRegEqual(0x0, 0x1)).unwrap();
+        let outcome = chip8
+            .execute_opcode(Opcode::SkipIfRegEqual(0x0, 0x1))
+            .unwrap();
 
         assert_eq!(chip8.registers.pc, 0x200);
+        assert_eq!(
+            outcome,
+            StepOutcome::Executed(Opcode::SkipIfRegEqual(0x0, 0x1))
+        );
     }
     #[test]
     fn test_chip8_execute_skip_if_not_equal_skips() {
@@ -806,9 +4576,12 @@ mod tests {
         chip8.registers.write_v(0x0, 0x10);
         chip8.registers.pc = 0x200;
 
-        chip8.execute(Opcode::SkipIfNotEqual(0x0, 0x1)).unwrap();
+        let outcome = chip8
+            .execute_opcode(Opcode::SkipIfNotEqual(0x0, 0x1))
+            .unwrap();
 
         assert_eq!(chip8.registers.pc, 0x202);
+        assert_eq!(outcome, StepOutcome::Skipped);
     }
 
     #[test]
@@ -817,9 +4590,15 @@ mod tests {
         chip8.registers.write_v(0x0, 0x10);
         chip8.registers.pc = 0x200;
 
-        chip8.execute(Opcode::SkipIfNotEqual(0x0, 0x10)).unwrap();
+        let outcome = chip8
+            .execute_opcode(Opcode::SkipIfNotEqual(0x0, 0x10))
+            .unwrap();
 
         assert_eq!(chip8.registers.pc, 0x200);
+        assert_eq!(
+            outcome,
+            StepOutcome::Executed(Opcode::SkipIfNotEqual(0x0, 0x10))
+        );
     }
 
     #[test]
@@ -828,7 +4607,7 @@ mod tests {
         chip8.registers.write_v(0x0, 0x10);
         chip8.registers.write_v(0x1, 0x05);
 
-        chip8.execute(Opcode::Sub(0x0, 0x1)).unwrap();
+        chip8.execute_opcode(Opcode::Sub(0x0, 0x1)).unwrap();
 
         assert_eq!(chip8.registers.read_v(0x0), 0x0B);
         assert_eq!(chip8.registers.read_v(0xF), 0x1);
@@ -840,7 +4619,7 @@ mod tests {
         chip8.registers.write_v(0x0, 0x10);
         chip8.registers.write_v(0x1, 0x0F);
 
-        chip8.execute(Opcode::Sub(0x0, 0x1)).unwrap();
+        chip8.execute_opcode(Opcode::Sub(0x0, 0x1)).unwrap();
 
         assert_eq!(chip8.registers.read_v(0x0), 0x01);
         assert_eq!(chip8.registers.read_v(0xF), 0x1);
@@ -851,7 +4630,7 @@ mod tests {
         let mut chip8 = Chip8::new();
         chip8.registers.write_v(0x0, 0x0F);
         chip8.registers.write_v(0x1, 0x10);
-        chip8.execute(Opcode::Sub(0x0, 0x1)).unwrap();
+        chip8.execute_opcode(Opcode::Sub(0x0, 0x1)).unwrap();
         assert_eq!(chip8.registers.read_v(0x0), 0xFF);
         assert_eq!(chip8.registers.read_v(0xF), 0x0);
     }
@@ -861,7 +4640,7 @@ mod tests {
         chip8.registers.write_v(0x0, 123);
         chip8.registers.i = 0x200;
 
-        chip8.execute(Opcode::StoreBCD(0x0)).unwrap();
+        chip8.execute_opcode(Opcode::StoreBCD(0x0)).unwrap();
 
         assert_eq!(chip8.memory.read_byte(0x200), Ok(1));
         assert_eq!(chip8.memory.read_byte(0x201), Ok(2));
@@ -873,7 +4652,7 @@ mod tests {
         chip8.registers.write_v(0x0, 0b10101010);
         chip8.registers.write_v(0x1, 0b11001100);
 
-        chip8.execute(Opcode::Xor(0x0, 0x1)).unwrap();
+        chip8.execute_opcode(Opcode::Xor(0x0, 0x1)).unwrap();
 
         assert_eq!(chip8.registers.read_v(0x0), 0b01100110);
     }
@@ -885,9 +4664,12 @@ mod tests {
         chip8.registers.write_v(0x1, 0x20);
         chip8.registers.pc = 0x200;
 
-        chip8.execute(Opcode::SkipIfRegNotEqual(0x0, 0x1)).unwrap();
+        let outcome = chip8
+            .execute_opcode(Opcode::SkipIfRegNotEqual(0x0, 0x1))
+            .unwrap();
 
         assert_eq!(chip8.registers.pc, 0x202);
+        assert_eq!(outcome, StepOutcome::Skipped);
     }
 
     #[test]
@@ -897,9 +4679,15 @@ mod tests {
         chip8.registers.write_v(0x1, 0x10);
         chip8.registers.pc = 0x200;
 
-        chip8.execute(Opcode::SkipIfRegNotEqual(0x0, 0x1)).unwrap();
+        let outcome = chip8
+            .execute_opcode(Opcode::SkipIfRegNotEqual(0x0, 0x1))
+            .unwrap();
 
         assert_eq!(chip8.registers.pc, 0x200);
+        assert_eq!(
+            outcome,
+            StepOutcome::Executed(Opcode::SkipIfRegNotEqual(0x0, 0x1))
+        );
     }
     #[test]
     fn test_chip8_execute_subn() {
@@ -907,7 +4695,7 @@ mod tests {
         chip8.registers.write_v(0x0, 0x5);
         chip8.registers.write_v(0x1, 0x10);
 
-        chip8.execute(Opcode::SubN(0x0, 0x1)).unwrap();
+        chip8.execute_opcode(Opcode::SubN(0x0, 0x1)).unwrap();
 
         assert_eq!(chip8.registers.read_v(0x0), 0xB);
         assert_eq!(chip8.registers.read_v(0xF), 0x1);
@@ -919,7 +4707,7 @@ mod tests {
         chip8.registers.write_v(0x0, 0x0F);
         chip8.registers.write_v(0x1, 0x10);
 
-        chip8.execute(Opcode::SubN(0x0, 0x1)).unwrap();
+        chip8.execute_opcode(Opcode::SubN(0x0, 0x1)).unwrap();
 
         assert_eq!(chip8.registers.read_v(0x0), 0x01);
         assert_eq!(chip8.registers.read_v(0xF), 0x1);
@@ -931,16 +4719,63 @@ mod tests {
         chip8.registers.write_v(0x0, 0x10);
         chip8.registers.write_v(0x1, 0x0F);
 
-        chip8.execute(Opcode::SubN(0x0, 0x1)).unwrap();
+        chip8.execute_opcode(Opcode::SubN(0x0, 0x1)).unwrap();
 
         assert_eq!(chip8.registers.read_v(0x0), 0xFF);
         assert_eq!(chip8.registers.read_v(0xF), 0x0);
     }
 
+    #[test]
+    fn test_chip8_booted() {
+        let chip8 = Chip8::booted();
+
+        assert_eq!(chip8.registers.pc, PROGRAM_START_ADDRESS as u16);
+        let font_len = display::BUILT_IN_SPRITES.len() * display::SPRITE_LEN;
+        for (offset, &byte) in display::BUILT_IN_SPRITES.concat().iter().enumerate() {
+            assert_eq!(
+                chip8
+                    .memory
+                    .read_byte(display::SPRITE_START_ADDRESS + offset),
+                Ok(byte)
+            );
+        }
+        assert!(display::SPRITE_START_ADDRESS + font_len <= PROGRAM_START_ADDRESS);
+    }
+
+    #[test]
+    fn test_chip8_boot_with_program_start() {
+        let mut chip8 = Chip8::new();
+        chip8.registers.write_v(0x3, 0x42);
+        chip8
+            .boot(BootConfig {
+                program_start: 0x600,
+                clear_registers: false,
+                ..BootConfig::default()
+            })
+            .unwrap();
+        chip8
+            .load_rom(&assemble_opcodes(&[
+                Opcode::LoadByte(0x0, 0x7),
+                Opcode::Jump(0x602),
+            ]))
+            .unwrap();
+
+        assert_eq!(chip8.pc(), 0x600);
+        assert_eq!(chip8.registers.read_v(0x3), 0x42);
+        assert_eq!(chip8.memory_bytes(0x200, 2), Ok(vec![0, 0]));
+        chip8.step().unwrap();
+        assert_eq!(chip8.registers.read_v(0x0), 0x7);
+        assert_eq!(chip8.pc(), 0x602);
+
+        chip8.boot(BootConfig::default()).unwrap();
+        assert_eq!(chip8.pc(), 0x200);
+        assert_eq!(chip8.registers.read_v(0x3), 0x0);
+    }
+
     #[test]
     fn test_chip8_load_sprites() {
         let mut chip8 = Chip8::new();
-        chip8.load_sprites();
+        chip8.load_sprites().unwrap();
         for (sprite_idx, sprite) in display::BUILT_IN_SPRITES.iter().enumerate() {
             for (byte_idx, &byte) in sprite.iter().enumerate() {
                 let read_addr =
@@ -955,11 +4790,68 @@ mod tests {
         let mut chip8 = Chip8::new();
         chip8.registers.write_v(0xF, 0x0F);
 
-        chip8.execute(Opcode::LoadSpriteAddr(0xF)).unwrap();
+        chip8.execute_opcode(Opcode::LoadSpriteAddr(0xF)).unwrap();
 
         assert_eq!(chip8.registers.i, 0x4B);
     }
 
+    #[test]
+    fn test_chip8_boot_with_font_start() {
+        let draw_digit = |font_start| {
+            let mut chip8 = Chip8::new();
+            chip8
+                .boot(BootConfig {
+                    font_start,
+                    ..BootConfig::default()
+                })
+                .unwrap();
+            chip8
+                .load_rom(&assemble_opcodes(&[
+                    Opcode::LoadByte(0x0, 0xE),
+                    Opcode::LoadSpriteAddr(0x0),
+                    Opcode::Draw(0x1, 0x1, 5),
+                ]))
+                .unwrap();
+            for _ in 0..3 {
+                chip8.step().unwrap();
+            }
+            chip8
+        };
+
+        let default = draw_digit(display::SPRITE_START_ADDRESS);
+        let conventional = draw_digit(display::CONVENTIONAL_FONT_START);
+
+        assert_eq!(default.registers.i, 0x46);
+        assert_eq!(conventional.registers.i, 0x096);
+        assert_eq!(
+            conventional.memory_bytes(0x050, FONT_LEN),
+            Ok(display::BUILT_IN_SPRITES.concat())
+        );
+        assert_eq!(conventional.memory_bytes(0x000, 5), Ok(vec![0; 5]));
+        assert_eq!(
+            conventional.display().to_string(),
+            default.display().to_string()
+        );
+    }
+
+    #[test]
+    fn test_chip8_load_rom_at_refuses_a_moved_font() {
+        let mut chip8 = Chip8::new();
+        chip8
+            .boot(BootConfig {
+                font_start: display::CONVENTIONAL_FONT_START,
+                ..BootConfig::default()
+            })
+            .unwrap();
+
+        assert!(matches!(
+            chip8.load_rom_at(&[0xAB; 4], 0x09E, false),
+            Err(Chip8Error::FontOverwriteError(_))
+        ));
+        assert!(chip8.load_rom_at(&[0xAB; 4], 0x000, false).is_ok());
+        assert!(chip8.load_rom_at(&[0xAB; 4], 0x0A0, false).is_ok());
+    }
+
     #[test]
     fn test_chip8_load_sprite_address_invalid_sprite() {
         let mut chip8 = Chip8::new();
@@ -967,18 +4859,75 @@ mod tests {
         chip8.registers.write_v(register, 0x10);
 
         assert_eq!(
-            chip8.execute(Opcode::LoadSpriteAddr(register)).unwrap_err(),
+            chip8
+                .execute_opcode(Opcode::LoadSpriteAddr(register))
+                .unwrap_err(),
             Chip8Error::DisplayError(display::DisplayError::InvalidSprite(0x10))
         );
     }
 
+    // Draws a bar, then another overlapping it, then halts.
+    const OVERLAPPING_DRAWS_ROM: [u8; 19] = [
+        0x60, 0x05, // 0x200: V0 = 5
+        0x61, 0x03, // 0x202: V1 = 3
+        0xA2, 0x0E, // 0x204: I = 0x20E
+        0xD0, 0x15, // 0x206: draw at (5, 3)
+        0x70, 0x02, // 0x208: V0 += 2
+        0xD0, 0x15, // 0x20A: draw at (7, 3)
+        0x12, 0x0C, // 0x20C: halt
+        0xF0, 0xF0, 0xF0, 0xF0, 0xF0, // 0x20E: sprite
+    ];
+
+    #[test]
+    fn test_chip8_draw_records() {
+        let mut chip8 = Chip8::booted();
+        chip8.load_rom(&OVERLAPPING_DRAWS_ROM).unwrap();
+        chip8.set_draw_recording(true);
+
+        chip8.run_for(Duration::from_millis(17)).unwrap();
+
+        let record = DrawRecord {
+            x: 5,
+            y: 3,
+            height: 5,
+            i_addr: 0x20E,
+            collided: false,
+            pc: 0x206,
+        };
+        assert_eq!(
+            chip8.draw_records(),
+            &[
+                record,
+                DrawRecord {
+                    x: 7,
+                    collided: true,
+                    pc: 0x20A,
+                    ..record
+                }
+            ]
+        );
+
+        chip8.run_for(Duration::from_millis(17)).unwrap();
+        assert_eq!(chip8.draw_records(), &[]);
+    }
+
+    #[test]
+    fn test_chip8_draw_records_off_by_default() {
+        let mut chip8 = Chip8::booted();
+        chip8.load_rom(&OVERLAPPING_DRAWS_ROM).unwrap();
+
+        chip8.run_for(Duration::from_millis(17)).unwrap();
+
+        assert_eq!(chip8.draw_records(), &[]);
+        assert_eq!(chip8.registers.read_v(0xF), 1);
+    }
+
     #[test]
     fn test_chip8_execute_draw() {
-        let mut chip8 = Chip8::new();
-        chip8.boot().unwrap();
+        let mut chip8 = Chip8::booted();
         chip8.registers.i = display::SPRITE_START_ADDRESS as u16;
 
-        let res = chip8.execute(Opcode::Draw(0, 0, 5));
+        let res = chip8.execute_opcode(Opcode::Draw(0, 0, 5));
         assert!(res.is_ok());
 
         assert_eq!(chip8.registers.read_v(0xF), 0); // No pixels were erased.
@@ -986,41 +4935,81 @@ mod tests {
 
     #[test]
     fn test_chip8_execute_draw_erased_pixels_reported() {
-        let mut chip8 = Chip8::new();
-        chip8.boot().unwrap();
+        let mut chip8 = Chip8::booted();
         chip8.registers.i = display::SPRITE_START_ADDRESS as u16;
 
-        let res = chip8.execute(Opcode::Draw(0, 0, 5));
+        let res = chip8.execute_opcode(Opcode::Draw(0, 0, 5));
         assert!(res.is_ok());
         // The following draw will erase a single pixel.
-        let res = chip8.execute(Opcode::Draw(4, 0, 5));
+        let res = chip8.execute_opcode(Opcode::Draw(4, 0, 5));
         assert!(res.is_ok());
 
         assert_eq!(chip8.registers.read_v(0xF), 1);
     }
 
+    #[test]
+    fn test_chip8_execute_draw_wrapped_collision_sets_vf() {
+        let mut chip8 = Chip8::booted();
+        chip8.memory.write_byte(0x300, 0b1111_1111).unwrap();
+        chip8.registers.i = 0x300;
+        chip8
+            .registers
+            .write_v(0x0, (display::DISPLAY_WIDTH - 4) as u8);
+        // Only the wrapped half lands on a lit pixel.
+        chip8.display.draw_sprite(0, 3, &vec![0b1000_0000]).unwrap();
+
+        chip8.execute_opcode(Opcode::Draw(0x0, 0x1, 1)).unwrap();
+
+        assert_eq!(chip8.registers.read_v(0xF), 1);
+        assert!(!chip8.display.is_pixel_set(3, 0));
+        assert!(chip8.display.is_pixel_set(2, 0));
+    }
+
     #[test]
     fn test_chip8_execute_clear_display() {
-        let mut chip8 = Chip8::new();
-        chip8.boot().unwrap();
+        let mut chip8 = Chip8::booted();
+        chip8
+            .display
+            .draw_sprite(0, 0, &display::BUILT_IN_SPRITES[0].to_vec())
+            .unwrap();
+
+        let res = chip8.execute_opcode(Opcode::ClearDisplay);
+        assert_eq!(res, Ok(StepOutcome::Executed(Opcode::ClearDisplay)));
+    }
+
+    #[test]
+    fn test_chip8_step_scroll_up() {
+        let mut chip8 = Chip8::booted();
+        chip8.load_rom(&[0x00, 0xD2]).unwrap();
         chip8
             .display
-            .draw_sprite(0, 0, &display::BUILT_IN_SPRITES[0].to_vec());
+            .draw_sprite(2, 0, &display::BUILT_IN_SPRITES[0].to_vec())
+            .unwrap();
+        let generation = chip8.display_generation();
+
+        assert_eq!(
+            chip8.step(),
+            Ok(StepOutcome::Executed(Opcode::ScrollUp(0x2)))
+        );
 
-        let res = chip8.execute(Opcode::ClearDisplay);
-        assert_eq!(res, Ok(()));
+        assert!(chip8.display.is_pixel_set(0, 0));
+        assert!(!chip8.display.is_pixel_set(0, 5));
+        assert!(chip8.display_generation() > generation);
     }
 
     #[test]
     fn test_chip8_execute_skip_if_not_pressed_skips_on_wrong_key_press() {
         let mut chip8 = Chip8::new();
-        chip8.press_key("1");
+        chip8.press_key(0x1).unwrap();
         chip8.registers.write_v(0x0, 0xf);
         chip8.registers.pc = 0x200;
 
-        chip8.execute(Opcode::SkipIfKeyNotPressed(0x0)).unwrap();
+        let outcome = chip8
+            .execute_opcode(Opcode::SkipIfKeyNotPressed(0x0))
+            .unwrap();
 
         assert_eq!(chip8.registers.pc, 0x202);
+        assert_eq!(outcome, StepOutcome::Skipped);
     }
 
     #[test]
@@ -1030,45 +5019,59 @@ mod tests {
         chip8.registers.write_v(0x0, 0xf);
         chip8.registers.pc = 0x200;
 
-        chip8.execute(Opcode::SkipIfKeyNotPressed(0x0)).unwrap();
+        let outcome = chip8
+            .execute_opcode(Opcode::SkipIfKeyNotPressed(0x0))
+            .unwrap();
 
         assert_eq!(chip8.registers.pc, 0x202);
+        assert_eq!(outcome, StepOutcome::Skipped);
     }
 
     #[test]
     fn test_chip8_execute_skip_if_not_pressed_not_skips() {
         let mut chip8 = Chip8::new();
-        chip8.press_key("f");
+        chip8.press_key(0xF).unwrap();
         chip8.registers.write_v(0x0, 0xf);
         chip8.registers.pc = 0x200;
 
-        chip8.execute(Opcode::SkipIfKeyNotPressed(0x0)).unwrap();
+        let outcome = chip8
+            .execute_opcode(Opcode::SkipIfKeyNotPressed(0x0))
+            .unwrap();
 
         assert_eq!(chip8.registers.pc, 0x200);
+        assert_eq!(
+            outcome,
+            StepOutcome::Executed(Opcode::SkipIfKeyNotPressed(0x0))
+        );
     }
 
     #[test]
     fn test_chip8_execute_skip_if_pressed_does_not_skip_on_wrong_key_press() {
         let mut chip8 = Chip8::new();
-        chip8.press_key("1");
+        chip8.press_key(0x1).unwrap();
         chip8.registers.write_v(0x0, 0xf);
         chip8.registers.pc = 0x200;
 
-        chip8.execute(Opcode::SkipIfKeyPressed(0x0)).unwrap();
+        let outcome = chip8.execute_opcode(Opcode::SkipIfKeyPressed(0x0)).unwrap();
 
         assert_eq!(chip8.registers.pc, 0x200);
+        assert_eq!(
+            outcome,
+            StepOutcome::Executed(Opcode::SkipIfKeyPressed(0x0))
+        );
     }
 
     #[test]
     fn test_chip8_execute_skip_if_pressed() {
         let mut chip8 = Chip8::new();
-        chip8.press_key("f");
+        chip8.press_key(0xF).unwrap();
         chip8.registers.write_v(0x0, 0xf);
         chip8.registers.pc = 0x200;
 
-        chip8.execute(Opcode::SkipIfKeyPressed(0x0)).unwrap();
+        let outcome = chip8.execute_opcode(Opcode::SkipIfKeyPressed(0x0)).unwrap();
 
         assert_eq!(chip8.registers.pc, 0x202);
+        assert_eq!(outcome, StepOutcome::Skipped);
     }
 
     #[test]
@@ -1078,19 +5081,101 @@ mod tests {
         chip8.registers.write_v(0x0, 0xf);
         chip8.registers.pc = 0x200;
 
-        chip8.execute(Opcode::SkipIfKeyPressed(0x0)).unwrap();
+        let outcome = chip8.execute_opcode(Opcode::SkipIfKeyPressed(0x0)).unwrap();
 
         assert_eq!(chip8.registers.pc, 0x200);
+        assert_eq!(
+            outcome,
+            StepOutcome::Executed(Opcode::SkipIfKeyPressed(0x0))
+        );
+    }
+
+    // Taps key 5 between two EX9E polls and returns whether each poll saw it.
+    fn tap_between_polls(sticky_keys: bool) -> (bool, bool) {
+        let mut chip8 = Chip8::with_options(Options {
+            sticky_keys,
+            ..Options::default()
+        })
+        .unwrap();
+        chip8.registers.write_v(0x0, 0x5);
+        let poll = |chip8: &mut Chip8| {
+            chip8.registers.pc = 0x200;
+            chip8.execute_opcode(Opcode::SkipIfKeyPressed(0x0)).unwrap() == StepOutcome::Skipped
+        };
+
+        assert!(!poll(&mut chip8));
+        chip8.press_key(0x5).unwrap();
+        chip8.release_key();
+        (poll(&mut chip8), poll(&mut chip8))
+    }
+
+    #[test]
+    fn test_chip8_sticky_keys_catch_tap_between_polls() {
+        assert_eq!(tap_between_polls(false), (false, false));
+        assert_eq!(tap_between_polls(true), (true, false));
+    }
+
+    #[test]
+    fn test_chip8_sticky_key_released_at_end_of_frame() {
+        let mut chip8 = Chip8::with_options(Options {
+            sticky_keys: true,
+            ..Options::default()
+        })
+        .unwrap();
+        chip8.press_key(0x5).unwrap();
+        chip8.release_key();
+        assert_eq!(chip8.get_pressed_key(), Some(0x5));
+
+        chip8.tick_timers();
+
+        assert_eq!(chip8.get_pressed_key(), None);
+    }
+
+    #[test]
+    fn test_chip8_press_key_rejects_invalid_keys() {
+        let mut chip8 = Chip8::booted();
+        chip8
+            .load_rom(&assemble_opcodes(&[
+                Opcode::LoadByte(0x0, 0xA),
+                Opcode::SkipIfKeyPressed(0x0),
+                Opcode::Jump(0x202),
+                Opcode::Jump(0x206),
+            ]))
+            .unwrap();
+
+        assert_eq!(
+            chip8.press_key_str("g"),
+            Err(Chip8Error::InputError(input::Error::InvalidKey(
+                "g".to_string()
+            )))
+        );
+        assert_eq!(
+            chip8.press_key(0x10),
+            Err(Chip8Error::InputError(input::Error::InvalidKey(
+                "0x10".to_string()
+            )))
+        );
+        assert_eq!(chip8.get_pressed_key(), None);
+        chip8.run_for(Duration::from_millis(17)).unwrap();
+        assert_ne!(chip8.pc(), 0x206);
+
+        chip8.press_key_str("A").unwrap();
+        chip8.run_for(Duration::from_millis(17)).unwrap();
+        assert_eq!(chip8.get_pressed_key(), Some(0xA));
+        assert_eq!(chip8.pc(), 0x206);
     }
 
     #[test]
     fn test_chip8_wait_for_key() {
         let mut chip8 = Chip8::new();
-        chip8.press_key("1");
+        chip8.press_key(0x1).unwrap();
         chip8.registers.write_v(0x0, 0x0);
         chip8.registers.pc = 0x200;
 
-        chip8.wait_for_key(0x0).unwrap();
+        assert_eq!(
+            chip8.wait_for_key(0x0),
+            Ok(StepOutcome::Executed(Opcode::WaitForKey(0x0)))
+        );
 
         assert_eq!(chip8.registers.read_v(0x0), 0x1);
         assert_eq!(chip8.registers.pc, 0x200);
@@ -1103,9 +5188,100 @@ mod tests {
         chip8.registers.write_v(0x0, 0x0);
         chip8.registers.pc = 0x200;
 
-        chip8.wait_for_key(0x0).unwrap();
+        assert_eq!(
+            chip8.wait_for_key(0x0),
+            Ok(StepOutcome::Blocked(BlockReason::WaitingForKey(0x0)))
+        );
 
         assert_eq!(chip8.registers.read_v(0x0), 0x0);
-        assert_eq!(chip8.registers.pc, 0x1fe);
+        assert_eq!(chip8.registers.pc, 0x200);
+    }
+
+    #[test]
+    fn test_chip8_execute_opcode_wait_for_key_leaves_pc_alone() {
+        let mut chip8 = Chip8::booted();
+        chip8.execute_opcode(Opcode::Jump(0x000)).unwrap();
+
+        assert_eq!(
+            chip8.execute_opcode(Opcode::WaitForKey(0x0)),
+            Ok(StepOutcome::Blocked(BlockReason::WaitingForKey(0x0)))
+        );
+        assert_eq!(chip8.pc(), 0x000);
+    }
+
+    // Runs FX0A with each of `keys` held in turn, returning the frames it was still waiting after
+    // and V0.
+    fn wait_for_key_with(on_release: bool, keys: &[Option<u8>]) -> (Vec<bool>, u8) {
+        let mut chip8 = with_quirks(Quirks {
+            wait_for_key_on_release: on_release,
+            ..Quirks::default()
+        });
+        chip8.boot(BootConfig::default()).unwrap();
+        chip8
+            .load_rom(&assemble_opcodes(&[
+                Opcode::WaitForKey(0x0),
+                Opcode::Jump(0x202),
+            ]))
+            .unwrap();
+        let waiting = keys
+            .iter()
+            .map(|&key| {
+                match key {
+                    Some(key) => chip8.press_key(key).unwrap(),
+                    None => chip8.release_key(),
+                }
+                chip8.step().unwrap();
+                chip8.pc() == 0x200
+            })
+            .collect();
+        (waiting, chip8.registers.read_v(0x0))
+    }
+
+    #[test]
+    fn test_chip8_wait_for_key_on_press() {
+        assert_eq!(
+            wait_for_key_with(false, &[None, Some(0x7)]),
+            (vec![true, false], 0x7)
+        );
+    }
+
+    #[test]
+    fn test_chip8_wait_for_key_on_release() {
+        // Holding the key isn't enough.
+        assert_eq!(
+            wait_for_key_with(true, &[None, Some(0x7), Some(0x7), Some(0x7)]),
+            (vec![true, true, true, true], 0x0)
+        );
+        assert_eq!(
+            wait_for_key_with(true, &[Some(0x7), Some(0x7), None]),
+            (vec![true, true, false], 0x7)
+        );
+        // Moving straight to another key releases the first.
+        assert_eq!(
+            wait_for_key_with(true, &[Some(0x7), Some(0x3)]),
+            (vec![true, false], 0x7)
+        );
+    }
+
+    #[test]
+    fn test_chip8_state_hash_covers_key_awaiting_release() {
+        let mut chip8 = with_quirks(Quirks {
+            wait_for_key_on_release: true,
+            ..Quirks::default()
+        });
+        chip8.boot(BootConfig::default()).unwrap();
+        chip8
+            .load_rom(&assemble_opcodes(&[Opcode::WaitForKey(0x0)]))
+            .unwrap();
+        chip8.step().unwrap();
+        let idle = chip8.state_hash();
+
+        chip8.press_key(0x7).unwrap();
+        chip8.step().unwrap();
+        chip8.release_key();
+
+        // Only the key FX0A saw differs, and the next step acts on it.
+        assert_eq!(chip8.key_awaiting_release, Some(0x7));
+        assert_ne!(chip8.state_hash(), idle);
     }
 }
@@ -0,0 +1,262 @@
+use super::{Chip8, Chip8Error, PeekedInstruction};
+use std::fmt;
+
+// One way two machines' states differ, with the first machine's value before the second's.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Difference {
+    // Register VX.
+    V(u8, u8, u8),
+    I(u16, u16),
+    Pc(u16, u16),
+    // The return addresses on the stack, bottom first.
+    Stack(Vec<u16>, Vec<u16>),
+    DelayTimer(u8, u8),
+    SoundTimer(u8, u8),
+    // A byte of memory both machines have.
+    Memory(u16, u8, u8),
+    // The display resolutions, as (width, height).
+    Resolution((u16, u16), (u16, u16)),
+    // Number of pixels set on one display and not the other, at the same resolution.
+    Pixels(usize),
+    // Only one of the machines failed its last instruction, with the given error.
+    Failed(Option<String>, Option<String>),
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Difference::V(x, a, b) => write!(f, "V{:X}: {:#04X} vs {:#04X}", x, a, b),
+            Difference::I(a, b) => write!(f, "I: {:#05X} vs {:#05X}", a, b),
+            Difference::Pc(a, b) => write!(f, "pc: {:#05X} vs {:#05X}", a, b),
+            Difference::Stack(a, b) => write!(f, "stack: {:X?} vs {:X?}", a, b),
+            Difference::DelayTimer(a, b) => write!(f, "delay timer: {} vs {}", a, b),
+            Difference::SoundTimer(a, b) => write!(f, "sound timer: {} vs {}", a, b),
+            Difference::Memory(address, a, b) => {
+                write!(f, "memory at {:#05X}: {:#04X} vs {:#04X}", address, a, b)
+            }
+            Difference::Resolution((aw, ah), (bw, bh)) => {
+                write!(f, "resolution: {}x{} vs {}x{}", aw, ah, bw, bh)
+            }
+            Difference::Pixels(count) => write!(f, "display: {} pixels differ", count),
+            Difference::Failed(a, b) => write!(
+                f,
+                "failed: {} vs {}",
+                a.as_deref().unwrap_or("no"),
+                b.as_deref().unwrap_or("no")
+            ),
+        }
+    }
+}
+
+// Where two machines run in lockstep first stopped agreeing.
+#[derive(Debug, PartialEq)]
+pub struct Divergence {
+    // Instructions both machines had executed, counting the one that diverged.
+    pub cycle: u64,
+    // The instruction each machine executed last, if it could be decoded.
+    pub last: [Option<PeekedInstruction>; 2],
+    pub differences: Vec<Difference>,
+}
+
+impl Divergence {
+    pub fn report(&self) -> String {
+        let mut lines = vec![format!("Diverged after {} instructions", self.cycle)];
+        for (name, last) in ["first", "second"].iter().zip(&self.last) {
+            let instruction = match last {
+                Some(last) => format!("{:#05X}: {:04X} {:?}", last.address, last.word, last.op),
+                None => "unknown".to_string(),
+            };
+            lines.push(format!("  {} ran {}", name, instruction));
+        }
+        lines.extend(self.differences.iter().map(|diff| format!("  {}", diff)));
+        lines.join("\n")
+    }
+}
+
+// Runs two machines one instruction at a time each, for watching the same ROM behave differently
+// under two sets of quirks. Both stop for good at the first instruction after which their states
+// differ. The machines are the caller's, so that it can also draw them and press their keys.
+#[derive(Debug, Default)]
+pub struct Lockstep {
+    divergence: Option<Divergence>,
+}
+
+impl Lockstep {
+    pub fn new() -> Self {
+        Lockstep::default()
+    }
+
+    pub fn divergence(&self) -> Option<&Divergence> {
+        self.divergence.as_ref()
+    }
+
+    // Executes an instruction on each machine and compares them. Returns whether they still
+    // agree; once they don't, nothing is executed anymore. A machine held by the display wait
+    // quirk holds the other one too until the next timer tick, so that the quirk changes how many
+    // instructions run in a frame but not which instruction each machine is at. Fails if both
+    // machines fail the same way.
+    pub fn step(&mut self, left: &mut Chip8, right: &mut Chip8) -> Result<bool, Chip8Error> {
+        if self.divergence.is_some() {
+            return Ok(false);
+        }
+        if left.waiting_for_vblank || right.waiting_for_vblank {
+            return Ok(true);
+        }
+        let last = [left.peek_instruction().ok(), right.peek_instruction().ok()];
+        let differences = match (left.step(), right.step()) {
+            (Err(a), Err(b)) if a == b => return Err(a),
+            (Ok(_), Ok(_)) => left.diff(right),
+            (a, b) => vec![Difference::Failed(
+                a.err().map(|err| format!("{:?}", err)),
+                b.err().map(|err| format!("{:?}", err)),
+            )],
+        };
+        if differences.is_empty() {
+            return Ok(true);
+        }
+        self.divergence = Some(Divergence {
+            cycle: left.cycles().max(right.cycles()),
+            last,
+            differences,
+        });
+        Ok(false)
+    }
+
+    // Steps both machines up to `instructions` times, stopping early if they diverge. Returns
+    // whether they still agree.
+    pub fn run(
+        &mut self,
+        left: &mut Chip8,
+        right: &mut Chip8,
+        instructions: u64,
+    ) -> Result<bool, Chip8Error> {
+        for _ in 0..instructions {
+            if !self.step(left, right)? {
+                return Ok(false);
+            }
+        }
+        Ok(self.divergence.is_none())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::{assemble_opcodes, BootConfig, Opcode, Options, Quirks, Target};
+
+    fn machine(quirks: Quirks, rom: &[u8]) -> Chip8 {
+        let mut system = Chip8::with_options(Options {
+            quirks,
+            rng_seed: Some(7),
+            ..Options::default()
+        })
+        .unwrap();
+        system.boot(BootConfig::default()).unwrap();
+        system.load_rom(rom).unwrap();
+        system
+    }
+
+    #[test]
+    fn test_diff() {
+        let mut left = Chip8::new();
+        let mut right = Chip8::new();
+        assert_eq!(left.diff(&right), vec![]);
+
+        left.execute_opcode(Opcode::LoadByte(0x3, 0x05)).unwrap();
+        right.execute_opcode(Opcode::LoadByte(0x3, 0x07)).unwrap();
+        right.execute_opcode(Opcode::SetIndex(0x300)).unwrap();
+        right.load_bytes(0x400, &[0xAB]).unwrap();
+
+        assert_eq!(
+            left.diff(&right),
+            vec![
+                Difference::V(0x3, 0x05, 0x07),
+                Difference::I(0x000, 0x300),
+                Difference::Memory(0x400, 0x00, 0xAB),
+            ]
+        );
+        assert_eq!(
+            Difference::V(0x3, 0x05, 0x07).to_string(),
+            "V3: 0x05 vs 0x07"
+        );
+    }
+
+    #[test]
+    fn test_lockstep_stops_at_first_divergence() {
+        // V0 = 0x81, V1 = 0x02, V0 >>= 1: only the shift depends on the quirks.
+        let rom = assemble_opcodes(&[
+            Opcode::LoadByte(0x0, 0x81),
+            Opcode::LoadByte(0x1, 0x02),
+            Opcode::ShiftRight(0x0, 0x1),
+            Opcode::LoadByte(0x2, 0x01),
+        ]);
+        let mut left = machine(Quirks::default(), &rom);
+        let mut right = machine(
+            Quirks {
+                shift_uses_vy: true,
+                ..Quirks::default()
+            },
+            &rom,
+        );
+        let mut lockstep = Lockstep::new();
+
+        assert!(!lockstep.run(&mut left, &mut right, 10).unwrap());
+
+        let divergence = lockstep.divergence().unwrap();
+        assert_eq!(divergence.cycle, 3);
+        assert_eq!(divergence.last[0].as_ref().unwrap().address, 0x204);
+        assert_eq!(
+            divergence.differences,
+            vec![
+                Difference::V(0x0, 0x40, 0x01),
+                Difference::V(0xF, 0x01, 0x00)
+            ]
+        );
+        assert_eq!(
+            divergence.report(),
+            "Diverged after 3 instructions\n  first ran 0x204: 8016 ShiftRight(0, 1)\n  \
+             second ran 0x204: 8016 ShiftRight(0, 1)\n  V0: 0x40 vs 0x01\n  VF: 0x01 vs 0x00"
+        );
+        // Neither machine moves on once they've diverged.
+        assert!(!lockstep.step(&mut left, &mut right).unwrap());
+        assert_eq!((left.pc(), right.pc()), (0x206, 0x206));
+    }
+
+    #[test]
+    fn test_lockstep_holds_both_for_display_wait() {
+        let rom = assemble_opcodes(&[
+            Opcode::Draw(0x0, 0x0, 1),
+            Opcode::LoadByte(0x0, 0x01),
+            Opcode::Jump(0x204),
+        ]);
+        let mut left = machine(Quirks::preset(Target::Chip8), &rom);
+        let mut right = machine(Quirks::preset(Target::SuperChipModern), &rom);
+        let mut lockstep = Lockstep::new();
+
+        assert!(lockstep.run(&mut left, &mut right, 5).unwrap());
+        assert_eq!((left.pc(), right.pc()), (0x202, 0x202));
+        left.tick_timers();
+        right.tick_timers();
+        assert!(lockstep.run(&mut left, &mut right, 5).unwrap());
+        assert_eq!((left.pc(), right.pc()), (0x204, 0x204));
+        assert_eq!(lockstep.divergence(), None);
+    }
+
+    #[test]
+    fn test_lockstep_failure_of_one_machine_is_a_divergence() {
+        // I ends up past the end of the smaller memory, and FX65 reads from it.
+        let rom = assemble_opcodes(&[Opcode::SetIndex(0xFFF), Opcode::RegLoad(0x3)]);
+        let mut left = machine(Quirks::preset(Target::Chip8), &rom);
+        let mut right = machine(Quirks::preset(Target::XoChip), &rom);
+        let mut lockstep = Lockstep::new();
+
+        assert!(!lockstep.run(&mut left, &mut right, 2).unwrap());
+
+        let differences = &lockstep.divergence().unwrap().differences;
+        assert!(
+            matches!(&differences[..], [Difference::Failed(Some(_), None)]),
+            "{:?}",
+            differences
+        );
+    }
+}
@@ -0,0 +1,267 @@
+use super::quirks::{Quirks, Target};
+use super::{BootConfig, Chip8, Options, StepOutcome, LEGACY_HIRES_START, PROGRAM_START_ADDRESS};
+
+// In the order ties are broken: plain CHIP-8 wins when nothing points elsewhere.
+const CANDIDATES: [Target; 5] = [
+    Target::Chip8,
+    Target::Chip48,
+    Target::SuperChipLegacy,
+    Target::SuperChipModern,
+    Target::XoChip,
+];
+const SUPER_CHIP: [Target; 3] = [
+    Target::SuperChipLegacy,
+    Target::SuperChipModern,
+    Target::XoChip,
+];
+const JUMP_USES_VX: [Target; 3] = [
+    Target::Chip48,
+    Target::SuperChipLegacy,
+    Target::SuperChipModern,
+];
+const LOAD_STORE_MOVES_I: [Target; 3] = [Target::Chip8, Target::Chip48, Target::XoChip];
+// Instructions each candidate is run for to see whether it faults early.
+const TRIAL_INSTRUCTIONS: usize = 2000;
+const FAULT_PENALTY: i32 = 10;
+
+// How well a target fits a ROM, with the signals that led to the score.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Detection {
+    pub target: Target,
+    pub score: i32,
+    pub evidence: Vec<String>,
+}
+
+// An instruction pattern that points to some targets.
+struct Signal {
+    targets: &'static [Target],
+    weight: i32,
+    description: String,
+}
+
+// Ranks the targets a ROM of unknown origin was most likely written for, best first. This is a
+// guess from the instructions the ROM contains and from running it briefly under each target;
+// a ROM without any telling instructions comes out as plain CHIP-8 with a score of 0.
+pub fn detect_targets(rom: &[u8]) -> Vec<Detection> {
+    let signals = scan(rom);
+    let mut detections: Vec<Detection> = CANDIDATES
+        .iter()
+        .map(|&target| {
+            let mut detection = Detection {
+                target,
+                score: 0,
+                evidence: Vec::new(),
+            };
+            for signal in signals.iter().filter(|s| s.targets.contains(&target)) {
+                detection.score += signal.weight;
+                detection.evidence.push(signal.description.clone());
+            }
+            if let Some(fault) = trial_run(rom, target) {
+                detection.score -= FAULT_PENALTY;
+                detection.evidence.push(fault);
+            }
+            detection
+        })
+        .collect();
+    // Stable, so equal scores keep the candidate order.
+    detections.sort_by_key(|detection| -detection.score);
+    detections
+}
+
+// Looks at every instruction-aligned word of the ROM. Data is scanned too, so each kind of signal
+// only counts once however often it appears.
+fn scan(rom: &[u8]) -> Vec<Signal> {
+    let words: Vec<u16> = rom
+        .chunks_exact(2)
+        .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+        .collect();
+    let mut signals: Vec<Signal> = Vec::new();
+    let mut add = |kind: &str, targets: &'static [Target], weight: i32, index: usize| {
+        if signals
+            .iter()
+            .any(|signal| signal.description.starts_with(kind))
+        {
+            return;
+        }
+        signals.push(Signal {
+            targets,
+            weight,
+            description: format!("{} at {:#05X}", kind, 0x200 + index * 2),
+        });
+    };
+
+    for (index, &word) in words.iter().enumerate() {
+        match extension_target(word) {
+            Some(Target::XoChip) => add("XO-CHIP instruction", &[Target::XoChip], 5, index),
+            Some(_) => add("SUPER-CHIP instruction", &SUPER_CHIP, 3, index),
+            None => {}
+        }
+        let x = (word >> 8) & 0xF;
+        if word >> 12 == 0xB && x != 0 {
+            add("BXNN with X other than 0", &JUMP_USES_VX, 1, index);
+        }
+        // A store or load straight after another one carries on where I was left, which only
+        // works if I moved past the registers.
+        if is_load_store(word)
+            && words
+                .get(index + 1)
+                .is_some_and(|&next| is_load_store(next))
+        {
+            add("FX55/FX65 reusing I", &LOAD_STORE_MOVES_I, 1, index);
+        }
+    }
+    signals
+}
+
+// Whether the ROM looks like one for the VIP's two-page hi-res interpreter: it starts by jumping
+// into the interpreter's own setup at 0x260, with the program proper at 0x2C0. Plenty of ordinary
+// ROMs open with the same jump, so this is only good for suggesting Options::legacy_hires.
+pub fn is_legacy_hires(rom: &[u8]) -> bool {
+    rom.starts_with(&[0x12, 0x60]) && rom.len() > LEGACY_HIRES_START - PROGRAM_START_ADDRESS
+}
+
+// The earliest platform that has the instruction `word`, for instructions plain CHIP-8 doesn't
+// have: SUPER-CHIP for its own, XO-CHIP for the ones only it added.
+pub fn extension_target(word: u16) -> Option<Target> {
+    let low = word & 0xFF;
+    let xo_chip_only = match word >> 12 {
+        0x0 => word & 0xFFF0 == 0x00D0,
+        0x5 => word & 0xF == 0x2 || word & 0xF == 0x3,
+        0xF => word == 0xF000 || word == 0xF002 || low == 0x01 || low == 0x3A,
+        _ => false,
+    };
+    let super_chip = match word >> 12 {
+        0x0 => (word & 0xFFF0 == 0x00C0 && word != 0x00C0) || (0x00FB..=0x00FF).contains(&word),
+        0xD => word & 0xF == 0,
+        0xF => low == 0x30 || low == 0x75 || low == 0x85,
+        _ => false,
+    };
+    if xo_chip_only {
+        Some(Target::XoChip)
+    } else if super_chip {
+        Some(Target::SuperChipLegacy)
+    } else {
+        None
+    }
+}
+
+// FX55 or FX65.
+fn is_load_store(word: u16) -> bool {
+    word >> 12 == 0xF && (word & 0xFF == 0x55 || word & 0xFF == 0x65)
+}
+
+// Runs the ROM under the target's quirks, describing the fault if it hits one early.
+fn trial_run(rom: &[u8], target: Target) -> Option<String> {
+    let mut system = Chip8::with_options(Options {
+        quirks: Quirks::preset(target),
+        strict_decode: true,
+        rng_seed: Some(0),
+        ..Options::default()
+    })
+    .expect("presets should have valid memory sizes");
+    // Its warnings are about targets the ROM may not be for, and go with it.
+    system.set_silenced(true);
+    system.boot(BootConfig::default()).ok()?;
+    if system.load_rom(rom).is_err() {
+        return Some("doesn't fit in memory".to_string());
+    }
+    for executed in 0..TRIAL_INSTRUCTIONS {
+        match system.step() {
+            Ok(StepOutcome::Halted(_) | StepOutcome::Blocked(_)) => break,
+            Ok(_) => {}
+            Err(err) => return Some(format!("faults after {} instructions: {:?}", executed, err)),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::{assemble_opcodes, Opcode};
+
+    // A ROM that loops forever after `body`.
+    fn rom(body: &[u16]) -> Vec<u8> {
+        let mut rom: Vec<u8> = body.iter().flat_map(|word| word.to_be_bytes()).collect();
+        let end = 0x200 + rom.len() as u16;
+        rom.extend(assemble_opcodes(&[Opcode::Jump(end)]));
+        rom
+    }
+
+    fn ranking(rom: &[u8]) -> Vec<(Target, i32)> {
+        detect_targets(rom)
+            .iter()
+            .map(|detection| (detection.target, detection.score))
+            .collect()
+    }
+
+    #[test]
+    fn test_plain_rom_is_chip8() {
+        let detections = detect_targets(&rom(&[0x6005, 0xA300, 0xF055]));
+
+        assert_eq!(detections[0].target, Target::Chip8);
+        assert!(detections
+            .iter()
+            .all(|detection| detection.score == 0 && detection.evidence.is_empty()));
+    }
+
+    #[test]
+    fn test_super_chip_instructions() {
+        // 00FF switches to high resolution, FX75 saves flags.
+        let detections = detect_targets(&rom(&[0x6005, 0x00FF, 0xF275, 0x00FF]));
+
+        assert_eq!(detections[0].target, Target::SuperChipLegacy);
+        assert_eq!(detections[0].evidence[0], "SUPER-CHIP instruction at 0x202");
+        assert_eq!(detections[0].score, 3 - FAULT_PENALTY);
+        // FX75 isn't implemented, so every target faults on it.
+        let scores = ranking(&rom(&[0x00FF]));
+        assert_eq!(
+            &scores[..4],
+            &[
+                (Target::SuperChipLegacy, 3),
+                (Target::SuperChipModern, 3),
+                (Target::XoChip, 3),
+                (Target::Chip8, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_xo_chip_instructions() {
+        let detections = detect_targets(&rom(&[0xF000, 0x0300, 0x00D2]));
+
+        assert_eq!(detections[0].target, Target::XoChip);
+        assert_eq!(detections[0].score, 5);
+        assert_eq!(detections[0].evidence, vec!["XO-CHIP instruction at 0x200"]);
+        assert!(detections[1..].iter().all(|detection| detection.score <= 0));
+    }
+
+    #[test]
+    fn test_jump_with_vx() {
+        // V2 is 0, so the jump lands on the loop at 0x204 either way.
+        let scores = ranking(&rom(&[0x6200, 0xB204]));
+
+        assert_eq!(scores[0], (Target::Chip48, 1));
+        assert_eq!(scores[3], (Target::Chip8, 0));
+    }
+
+    #[test]
+    fn test_load_store_reusing_i() {
+        let scores = ranking(&rom(&[0xA300, 0xF155, 0xF155]));
+
+        assert_eq!(scores[0], (Target::Chip8, 1));
+        assert_eq!(scores[1], (Target::Chip48, 1));
+        assert_eq!(scores[2], (Target::XoChip, 1));
+    }
+
+    #[test]
+    fn test_rom_too_large_for_4k() {
+        let mut large = rom(&[0x6005]);
+        large.resize(0x1000, 0);
+
+        let detections = detect_targets(&large);
+
+        assert_eq!(detections[0].target, Target::XoChip);
+        assert_eq!(detections[1].evidence, vec!["doesn't fit in memory"]);
+    }
+}
@@ -0,0 +1,152 @@
+use std::collections::VecDeque;
+
+// Entries kept by the machine's diagnostics log.
+pub const DIAGNOSTICS_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+// One warning or error, as printed to the terminal when it happened.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    // Instructions executed before it happened.
+    pub cycle: u64,
+    pub pc: u16,
+    pub message: String,
+}
+
+// The most recent diagnostics, oldest first. Once full, each new entry replaces the oldest.
+#[derive(Debug)]
+pub struct Diagnostics {
+    entries: VecDeque<Diagnostic>,
+    capacity: usize,
+    // Entries ever pushed, including the ones since dropped.
+    pushed: u64,
+}
+
+impl Diagnostics {
+    pub fn new(capacity: usize) -> Self {
+        Diagnostics {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+            pushed: 0,
+        }
+    }
+
+    pub fn push(&mut self, diagnostic: Diagnostic) {
+        self.pushed += 1;
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(diagnostic);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.entries.iter()
+    }
+
+    // A count that goes up by one with every push, for since.
+    pub fn pushed(&self) -> u64 {
+        self.pushed
+    }
+
+    // The entries pushed after pushed() returned `pushed`, or as many of them as are still kept.
+    pub fn since(&self, pushed: u64) -> impl Iterator<Item = &Diagnostic> {
+        let new = self
+            .pushed
+            .saturating_sub(pushed)
+            .min(self.entries.len() as u64);
+        self.entries.iter().skip(self.entries.len() - new as usize)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn warning(cycle: u64) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Warning,
+            cycle,
+            pc: 0x200,
+            message: format!("warning {}", cycle),
+        }
+    }
+
+    #[test]
+    fn test_keeps_entries_in_order() {
+        let mut diagnostics = Diagnostics::new(4);
+        assert!(diagnostics.is_empty());
+
+        diagnostics.push(warning(1));
+        diagnostics.push(warning(2));
+
+        let cycles: Vec<u64> = diagnostics.iter().map(|entry| entry.cycle).collect();
+        assert_eq!(cycles, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_overwrites_oldest_when_full() {
+        let mut diagnostics = Diagnostics::new(DIAGNOSTICS_CAPACITY);
+
+        for cycle in 0..DIAGNOSTICS_CAPACITY as u64 + 10 {
+            diagnostics.push(warning(cycle));
+        }
+
+        assert_eq!(diagnostics.len(), DIAGNOSTICS_CAPACITY);
+        let cycles: Vec<u64> = diagnostics.iter().map(|entry| entry.cycle).collect();
+        let expected: Vec<u64> = (10..DIAGNOSTICS_CAPACITY as u64 + 10).collect();
+        assert_eq!(cycles, expected);
+        assert_eq!(
+            diagnostics
+                .iter()
+                .last()
+                .map(|entry| entry.message.as_str()),
+            Some("warning 265")
+        );
+    }
+
+    #[test]
+    fn test_since() {
+        let mut diagnostics = Diagnostics::new(4);
+        diagnostics.push(warning(1));
+        let seen = diagnostics.pushed();
+
+        assert_eq!(diagnostics.since(seen).count(), 0);
+        for cycle in 2..5 {
+            diagnostics.push(warning(cycle));
+        }
+        let cycles: Vec<u64> = diagnostics.since(seen).map(|entry| entry.cycle).collect();
+        assert_eq!(cycles, vec![2, 3, 4]);
+        // Entries already dropped are skipped.
+        for cycle in 5..10 {
+            diagnostics.push(warning(cycle));
+        }
+        let cycles: Vec<u64> = diagnostics.since(seen).map(|entry| entry.cycle).collect();
+        assert_eq!(cycles, vec![6, 7, 8, 9]);
+    }
+
+    #[test]
+    fn test_zero_capacity_keeps_nothing() {
+        let mut diagnostics = Diagnostics::new(0);
+
+        diagnostics.push(warning(1));
+
+        assert!(diagnostics.is_empty());
+    }
+}
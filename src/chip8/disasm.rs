@@ -0,0 +1,241 @@
+use super::opcodes::Opcode;
+use super::PeekedInstruction;
+use std::collections::BTreeSet;
+use std::fmt;
+
+// Addresses are decoded as on the largest machine, so that jumps anywhere in a 64 KB XO-CHIP
+// program can be followed.
+const ADDRESS_SPACE: usize = 0x10000;
+
+// How disassemble tells code from data.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sweep {
+    // Every word that decodes is listed as an instruction, including sprites and tables that
+    // happen to look like one.
+    Linear,
+    // Only instructions that can be reached from the start by following the program's jumps,
+    // calls and skips are listed as instructions; every other byte is data.
+    Reachable,
+}
+
+// A line of a disassembly.
+#[derive(Debug, PartialEq)]
+pub enum Line {
+    Code(PeekedInstruction),
+    Data { address: u16, byte: u8 },
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Line::Code(instruction) => {
+                let encoding = match instruction.operand {
+                    Some(operand) => format!("{:04X} {:04X}", instruction.word, operand),
+                    None => format!("{:04X}", instruction.word),
+                };
+                write!(
+                    f,
+                    "{:#05X}: {:<9} {:?}",
+                    instruction.address, encoding, instruction.op
+                )
+            }
+            Line::Data { address, byte } => {
+                write!(
+                    f,
+                    "{:#05X}: {:<9} DB {:#04X}",
+                    address,
+                    format!("{:02X}", byte),
+                    byte
+                )
+            }
+        }
+    }
+}
+
+// Disassembles a ROM loaded at `start`, which is also where a reachable sweep starts following
+// the program from.
+pub fn disassemble(rom: &[u8], start: u16, sweep: Sweep) -> Vec<Line> {
+    let code = match sweep {
+        Sweep::Linear => linear_code(rom, start),
+        Sweep::Reachable => reachable_code(rom, start),
+    };
+    let mut lines = Vec::new();
+    let mut offset = 0;
+    while offset < rom.len() {
+        let address = start + offset as u16;
+        match decode(rom, start, address).filter(|_| code.contains(&address)) {
+            Some(instruction) => {
+                offset += instruction.len as usize;
+                lines.push(Line::Code(instruction));
+            }
+            None => {
+                lines.push(Line::Data {
+                    address,
+                    byte: rom[offset],
+                });
+                offset += 1;
+            }
+        }
+    }
+    lines
+}
+
+// The instruction at `address`, if all of it is in the ROM and it's a defined opcode.
+fn decode(rom: &[u8], start: u16, address: u16) -> Option<PeekedInstruction> {
+    let word_at = |address: u16| {
+        let offset = usize::from(address.checked_sub(start)?);
+        let bytes = rom.get(offset..offset + 2)?;
+        Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+    };
+    let word = word_at(address)?;
+    let [msb, lsb] = word.to_be_bytes();
+    let op = match Opcode::from_bytes(msb, lsb, ADDRESS_SPACE) {
+        Ok(Opcode::Undefined(_)) | Err(_) => return None,
+        Ok(op) => op,
+    };
+    let operand = match op {
+        Opcode::LoadLongI => Some(word_at(address.checked_add(2)?)?),
+        _ => None,
+    };
+    Some(PeekedInstruction {
+        address,
+        word,
+        op,
+        operand,
+        len: if operand.is_some() { 4 } else { 2 },
+    })
+}
+
+// Every word, one after the other.
+fn linear_code(rom: &[u8], start: u16) -> BTreeSet<u16> {
+    let mut code = BTreeSet::new();
+    let mut offset = 0;
+    while offset < rom.len() {
+        let address = start + offset as u16;
+        match decode(rom, start, address) {
+            Some(instruction) => {
+                code.insert(address);
+                offset += instruction.len as usize;
+            }
+            None => offset += 2,
+        }
+    }
+    code
+}
+
+// The instructions reachable from `start`. A path ends at a return, at a jump to somewhere
+// already visited and at anything that doesn't decode. BNNN's target depends on V0, so only NNN
+// itself is followed, which is where a jump table starts.
+fn reachable_code(rom: &[u8], start: u16) -> BTreeSet<u16> {
+    let mut code = BTreeSet::new();
+    let mut pending = vec![start];
+    while let Some(address) = pending.pop() {
+        if code.contains(&address) {
+            continue;
+        }
+        let Some(instruction) = decode(rom, start, address) else {
+            continue;
+        };
+        let next = address.wrapping_add(instruction.len);
+        match instruction.op {
+            Opcode::Return => {}
+            Opcode::Jump(target) | Opcode::JumpV0(target) => pending.push(target),
+            Opcode::Call(target) => pending.extend([target, next]),
+            Opcode::SkipIfEqual(..)
+            | Opcode::SkipIfNotEqual(..)
+            | Opcode::SkipIfRegEqual(..)
+            | Opcode::SkipIfRegNotEqual(..)
+            | Opcode::SkipIfKeyPressed(_)
+            | Opcode::SkipIfKeyNotPressed(_) => {
+                // A skip steps over all of a long instruction.
+                let skipped_len = decode(rom, start, next).map_or(2, |skipped| skipped.len);
+                pending.extend([next, next.wrapping_add(skipped_len)]);
+            }
+            _ => pending.push(next),
+        }
+        code.insert(address);
+    }
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::assemble_opcodes;
+
+    // Draws a circle from the sprite table after the program, then halts.
+    fn circle_rom() -> Vec<u8> {
+        let mut rom = assemble_opcodes(&[
+            Opcode::SetIndex(0x208),
+            Opcode::LoadByte(0x0, 0x00),
+            Opcode::Draw(0x0, 0x0, 6),
+            Opcode::Jump(0x206),
+        ]);
+        rom.extend([0x3C, 0x42, 0x81, 0x81, 0x42, 0x3C]);
+        rom
+    }
+
+    fn text(lines: &[Line]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn test_reachable_sweep_renders_sprites_as_data() {
+        let lines = disassemble(&circle_rom(), 0x200, Sweep::Reachable);
+
+        assert_eq!(
+            text(&lines),
+            vec![
+                "0x200: A208      SetIndex(520)",
+                "0x202: 6000      LoadByte(0, 0)",
+                "0x204: D006      Draw(0, 0, 6)",
+                "0x206: 1206      Jump(518)",
+                "0x208: 3C        DB 0x3C",
+                "0x209: 42        DB 0x42",
+                "0x20A: 81        DB 0x81",
+                "0x20B: 81        DB 0x81",
+                "0x20C: 42        DB 0x42",
+                "0x20D: 3C        DB 0x3C",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_linear_sweep_decodes_sprites_as_code() {
+        let lines = disassemble(&circle_rom(), 0x200, Sweep::Linear);
+
+        assert_eq!(
+            text(&lines[4..]),
+            vec![
+                "0x208: 3C42      SkipIfEqual(12, 66)",
+                "0x20A: 8181      Or(1, 8)",
+                "0x20C: 423C      SkipIfNotEqual(2, 60)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reachable_sweep_follows_calls_and_skips() {
+        let rom = assemble_opcodes(&[
+            Opcode::Call(0x20A),
+            Opcode::SkipIfKeyPressed(0x1),
+            Opcode::LoadLongI,
+            Opcode::SetIndex(0x300),
+            Opcode::Jump(0x208),
+            Opcode::Return,
+            // Never reached: the subroutine returns before it.
+            Opcode::ClearDisplay,
+        ]);
+
+        let code: Vec<u16> = disassemble(&rom, 0x200, Sweep::Reachable)
+            .iter()
+            .filter_map(|line| match line {
+                Line::Code(instruction) => Some(instruction.address),
+                Line::Data { .. } => None,
+            })
+            .collect();
+
+        // The skip steps over the whole F000 NNNN to 0x208.
+        assert_eq!(code, vec![0x200, 0x202, 0x204, 0x208, 0x20A]);
+    }
+}
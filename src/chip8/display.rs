@@ -1,9 +1,12 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
 pub const SPRITE_LEN: usize = 5;
 
 pub type SpriteValue = Vec<u8>;
 
 // Constants with predefined sprites for digits 0x0-0xF that will be loaded into RAM during the system boot.
-pub const BUILT_IN_SPRITES: [[u8; 5]; 0xF] = [
+pub const BUILT_IN_SPRITES: [[u8; 5]; 0x10] = [
     [0xF0, 0x90, 0x90, 0x90, 0xF0],
     [0x20, 0x60, 0x20, 0x20, 0x70],
     [0xF0, 0x10, 0xF0, 0x80, 0xF0],
@@ -14,6 +17,7 @@ pub const BUILT_IN_SPRITES: [[u8; 5]; 0xF] = [
     [0xF0, 0x10, 0x20, 0x40, 0x40],
     [0xF0, 0x90, 0xF0, 0x90, 0xF0],
     [0xF0, 0x90, 0xF0, 0x10, 0xF0],
+    [0xF0, 0x90, 0xF0, 0x90, 0x90],
     [0xE0, 0x90, 0xE0, 0x90, 0xE0],
     [0xF0, 0x80, 0x80, 0x80, 0xF0],
     [0xE0, 0x90, 0x90, 0x90, 0xE0],
@@ -21,43 +25,171 @@ pub const BUILT_IN_SPRITES: [[u8; 5]; 0xF] = [
     [0xF0, 0x80, 0xF0, 0x80, 0x80],
 ];
 
+// Where boot puts the built-in font unless BootConfig says otherwise.
 pub const SPRITE_START_ADDRESS: usize = 0x0;
+// Where many interpreters put the font instead, and where the few ROMs that read it directly
+// expect to find it.
+pub const CONVENTIONAL_FONT_START: usize = 0x050;
 pub const DISPLAY_WIDTH: usize = 128;
 pub const DISPLAY_HEIGHT: usize = 64;
-const BUFFER_WIDTH: usize = DISPLAY_WIDTH / 8;
-const BUFFER_HEIGHT: usize = DISPLAY_HEIGHT;
+
+// Colors as 0x00RRGGBB for each combination of XO-CHIP planes a pixel is set in: none, the
+// first, the second and both. Plain CHIP-8 only draws to the first, so it's black and white.
+pub const DEFAULT_PALETTE: [u32; 4] = [0x0000_0000, 0x00FF_FFFF, 0x00FF_6600, 0x0066_2200];
+
+// What a draw did to the pixels already on the display.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DrawResult {
+    // Whether any pixel was erased, which is what VF reports.
+    pub collided: bool,
+    pub erased: u32,
+}
+
+impl DrawResult {
+    fn new(erased: u32) -> Self {
+        DrawResult {
+            collided: erased > 0,
+            erased,
+        }
+    }
+}
 
 #[derive(Debug, PartialEq)]
 pub enum DisplayError {
     InvalidSprite(u8),
     InvalidDrawPosition(usize, usize),
+    UnsupportedMode(usize, usize),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Display {
     pub width: usize,
     pub height: usize,
 
-    buffer: [[u8; BUFFER_WIDTH]; BUFFER_HEIGHT],
+    // Each row packs 8 horizontal pixels per byte, most significant bit first.
+    buffer: Vec<Vec<u8>>,
+    // How the pixels are shown; programs can't see it.
+    palette: [u32; 4],
+}
+
+// The palette is left out so that it doesn't change state hashes.
+impl Hash for Display {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.width.hash(state);
+        self.height.hash(state);
+        self.buffer.hash(state);
+    }
+}
+
+// One line per row, `#` for set pixels and `.` for cleared ones.
+impl fmt::Display for Display {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for y in 0..self.height {
+            if y > 0 {
+                writeln!(f)?;
+            }
+            for x in 0..self.width {
+                write!(f, "{}", if self.is_pixel_set(x, y) { '#' } else { '.' })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for Display {
+    fn default() -> Self {
+        Display::new()
+    }
 }
 
 impl Display {
     pub fn new() -> Self {
-        Display {
-            width: DISPLAY_WIDTH,
-            height: DISPLAY_HEIGHT,
-            buffer: [[0; BUFFER_WIDTH]; BUFFER_HEIGHT],
+        Display::with_mode(DISPLAY_WIDTH, DISPLAY_HEIGHT).expect("default display mode is valid")
+    }
+
+    // Creates a display with the given resolution. The width must be a non-zero multiple of 8 so
+    // that rows can be packed into whole bytes.
+    pub fn with_mode(width: usize, height: usize) -> Result<Self, DisplayError> {
+        if width == 0 || height == 0 || !width.is_multiple_of(8) {
+            return Err(DisplayError::UnsupportedMode(width, height));
         }
+        Ok(Display {
+            width,
+            height,
+            buffer: vec![vec![0; width / 8]; height],
+            palette: DEFAULT_PALETTE,
+        })
     }
 
-    pub fn get_sprite_address(sprite: u8) -> Result<usize, DisplayError> {
-        if sprite as usize > BUILT_IN_SPRITES.len() {
+    // A display of the given resolution with the same palette, keeping the pixels that fit in it
+    // where they were.
+    pub fn resized(&self, width: usize, height: usize) -> Result<Self, DisplayError> {
+        let mut display = Display::with_mode(width, height)?;
+        display.palette = self.palette;
+        let row_bytes = width.min(self.width) / 8;
+        for (row, old_row) in display.buffer.iter_mut().zip(&self.buffer) {
+            row[..row_bytes].copy_from_slice(&old_row[..row_bytes]);
+        }
+        Ok(display)
+    }
+
+    // The address of a digit's glyph in a font loaded at `font_start`.
+    pub fn get_sprite_address(font_start: usize, sprite: u8) -> Result<usize, DisplayError> {
+        if sprite as usize >= BUILT_IN_SPRITES.len() {
             Err(DisplayError::InvalidSprite(sprite))
         } else {
-            Ok(SPRITE_START_ADDRESS + (sprite as usize * 5))
+            Ok(font_start + (sprite as usize * SPRITE_LEN))
         }
     }
 
+    // The bytes of the built-in glyph for a hex digit, or None if it isn't one.
+    pub fn font_glyph(digit: u8) -> Option<&'static [u8]> {
+        BUILT_IN_SPRITES.get(digit as usize).map(|glyph| &glyph[..])
+    }
+
+    // Copies the pixels into `out` row by row, 8 to a byte with the leftmost in the most
+    // significant bit. Bytes past the end of the display are left alone.
+    pub fn pack_into(&self, out: &mut [u8]) {
+        for (out, &byte) in out.iter_mut().zip(self.buffer.iter().flatten()) {
+            *out = byte;
+        }
+    }
+
+    pub fn is_pixel_set(&self, x: usize, y: usize) -> bool {
+        let byte = self.buffer[y % self.height][(x % self.width) / 8];
+        byte & (0b1000_0000 >> (x % 8)) != 0
+    }
+
+    pub fn palette(&self) -> [u32; 4] {
+        self.palette
+    }
+
+    pub fn set_palette(&mut self, palette: [u32; 4]) {
+        self.palette = palette;
+    }
+
+    // The planes the pixel is set in, as an index into the palette: bit 0 for the first plane and
+    // bit 1 for the second. Only the first plane is emulated so far.
+    pub fn planes_at(&self, x: usize, y: usize) -> usize {
+        usize::from(self.is_pixel_set(x, y))
+    }
+
+    pub fn color_at(&self, x: usize, y: usize) -> u32 {
+        self.palette[self.planes_at(x, y)]
+    }
+
+    // The pixels row by row in their palette colors, 4 bytes each in RGBA order.
+    pub fn to_rgba(&self) -> Vec<u8> {
+        let mut rgba = Vec::with_capacity(self.width * self.height * 4);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let [_, r, g, b] = self.color_at(x, y).to_be_bytes();
+                rgba.extend([r, g, b, 0xFF]);
+            }
+        }
+        rgba
+    }
+
     pub fn clear(&mut self) {
         for row in self.buffer.iter_mut() {
             for pixel in row.iter_mut() {
@@ -66,53 +198,155 @@ impl Display {
         }
     }
 
-    pub fn draw_sprite(&mut self, row: usize, col: usize, sprite_value: &SpriteValue) -> bool {
-        let mut erased = false;
+    // Blanks the `width` by `height` pixels from (`x`, `y`), for compositing overlays without
+    // clearing everything. Like draw_sprite, the parts past the right and bottom edges wrap
+    // around to the other side.
+    pub fn clear_rect(
+        &mut self,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+    ) -> Result<(), DisplayError> {
+        self.check_position(y, x)?;
+        for row in y..y + height.min(self.height) {
+            for col in x..x + width.min(self.width) {
+                self.clear_pixel(col % self.width, row % self.height);
+            }
+        }
+        Ok(())
+    }
+
+    // Like clear_rect, but the parts past the right and bottom edges are cut off instead of
+    // wrapping around.
+    pub fn clear_rect_clipped(
+        &mut self,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+    ) -> Result<(), DisplayError> {
+        self.check_position(y, x)?;
+        for row in y..(y + height).min(self.height) {
+            for col in x..(x + width).min(self.width) {
+                self.clear_pixel(col, row);
+            }
+        }
+        Ok(())
+    }
+
+    fn clear_pixel(&mut self, x: usize, y: usize) {
+        self.buffer[y][x / 8] &= !(0b1000_0000 >> (x % 8));
+    }
+
+    // Moves the contents up by `rows`, blanking the rows that enter at the bottom.
+    pub fn scroll_up(&mut self, rows: usize) {
+        let rows = rows.min(self.height);
+        self.buffer.rotate_left(rows);
+        for row in self.buffer[self.height - rows..].iter_mut() {
+            row.fill(0);
+        }
+    }
+
+    // The drawing functions take a position within the display; wrapping the coordinates a program
+    // asked for is up to the caller. The parts of the sprite past the right and bottom edges wrap
+    // around to the other side. Returns whether any pixel was erased.
+    pub fn draw_sprite(
+        &mut self,
+        row: usize,
+        col: usize,
+        sprite_value: &SpriteValue,
+    ) -> Result<bool, DisplayError> {
+        Ok(self.draw_sprite_counted(row, col, sprite_value)?.collided)
+    }
+
+    // Like draw_sprite, but also counts the pixels erased.
+    pub fn draw_sprite_counted(
+        &mut self,
+        row: usize,
+        col: usize,
+        sprite_value: &SpriteValue,
+    ) -> Result<DrawResult, DisplayError> {
+        self.check_position(row, col)?;
+        let mut erased = 0;
         for (row_delta, &sprite_row) in sprite_value.iter().enumerate() {
-            erased |= self.draw_sprite_row((row + row_delta) % BUFFER_HEIGHT, col, sprite_row);
+            erased += self.draw_sprite_row((row + row_delta) % self.height, col, sprite_row);
         }
-        erased
+        Ok(DrawResult::new(erased))
+    }
+
+    // Like draw_sprite, but the parts of the sprite past the right and bottom edges are cut off
+    // instead of wrapping around.
+    pub fn draw_sprite_clipped(
+        &mut self,
+        row: usize,
+        col: usize,
+        sprite_value: &SpriteValue,
+    ) -> Result<bool, DisplayError> {
+        Ok(self
+            .draw_sprite_clipped_counted(row, col, sprite_value)?
+            .collided)
+    }
+
+    // Like draw_sprite_clipped, but also counts the pixels erased.
+    pub fn draw_sprite_clipped_counted(
+        &mut self,
+        row: usize,
+        col: usize,
+        sprite_value: &SpriteValue,
+    ) -> Result<DrawResult, DisplayError> {
+        self.check_position(row, col)?;
+        let visible_bits = self.width - col;
+        let mask = if visible_bits >= 8 {
+            0b1111_1111
+        } else {
+            !(0b1111_1111_u8 >> visible_bits)
+        };
+        let mut erased = 0;
+        for (row_delta, &sprite_row) in sprite_value.iter().take(self.height - row).enumerate() {
+            erased += self.draw_sprite_row(row + row_delta, col, sprite_row & mask);
+        }
+        Ok(DrawResult::new(erased))
     }
 
-    fn draw_sprite_row(&mut self, row: usize, col: usize, value: u8) -> bool {
-        let row_idx = row % BUFFER_HEIGHT;
+    fn check_position(&self, row: usize, col: usize) -> Result<(), DisplayError> {
+        if row >= self.height || col >= self.width {
+            return Err(DisplayError::InvalidDrawPosition(row, col));
+        }
+        Ok(())
+    }
+
+    // The position must be within the display; rows past the bottom are wrapped by the callers.
+    // Returns the number of pixels erased.
+    fn draw_sprite_row(&mut self, row: usize, col: usize, value: u8) -> u32 {
+        let row_idx = row;
         let col_idx = col / 8;
+        let next_col_idx = (col_idx + 1) % (self.width / 8);
         let start_bit_idx = col % 8;
 
-        let (mask, _) = (0b1111_1111 as u8).overflowing_shl(start_bit_idx as u32);
+        let (mask, _) = 0b1111_1111_u8.overflowing_shl(start_bit_idx as u32);
         let (masked_value, _) = (value & mask).overflowing_shr(start_bit_idx as u32);
 
         let original_value = self.buffer[row_idx][col_idx];
         self.buffer[row_idx][col_idx] ^= masked_value;
 
-        let mut erased = bit_erased(original_value, self.buffer[row_idx][col_idx]);
+        let mut erased = erased_bits(original_value, self.buffer[row_idx][col_idx]);
 
+        // The low bits that didn't fit into the first byte spill into the next one.
         if start_bit_idx != 0 {
-            let (mask, _) = (0b1111_1111 as u8).overflowing_shr(start_bit_idx as u32);
-            let (masked_value, _) = (value & mask).overflowing_shl((8 - start_bit_idx) as u32);
-            let original_value = self.buffer[row_idx][(col_idx + 1) % BUFFER_WIDTH];
-
-            self.buffer[row_idx][(col_idx + 1) % BUFFER_WIDTH] ^= masked_value;
-            if !erased {
-                erased = bit_erased(
-                    original_value,
-                    self.buffer[row_idx][(col_idx + 1) % BUFFER_WIDTH],
-                );
-            }
+            let masked_value = value << (8 - start_bit_idx);
+            let original_value = self.buffer[row_idx][next_col_idx];
+
+            self.buffer[row_idx][next_col_idx] ^= masked_value;
+            erased += erased_bits(original_value, self.buffer[row_idx][next_col_idx]);
         }
         erased
     }
 }
 
-fn bit_erased(original: u8, current: u8) -> bool {
-    for bit_idx in 0..8 {
-        let was = original & 0x1 << bit_idx != 0;
-        let now = current & 0x1 << bit_idx != 0;
-        if was && !now {
-            return true;
-        }
-    }
-    false
+// How many bits set in `original` are cleared in `current`.
+fn erased_bits(original: u8, current: u8) -> u32 {
+    (original & !current).count_ones()
 }
 
 #[cfg(test)]
@@ -127,6 +361,73 @@ mod tests {
         assert_eq!(display.height, DISPLAY_HEIGHT);
     }
 
+    #[test]
+    fn test_with_mode() {
+        let display = Display::with_mode(64, 32).unwrap();
+
+        assert_eq!(display.width, 64);
+        assert_eq!(display.height, 32);
+        assert_eq!(display.buffer.len(), 32);
+        assert_eq!(display.buffer[0].len(), 8);
+    }
+
+    #[test]
+    fn test_with_mode_rejects_zero_dimensions() {
+        assert_eq!(
+            Display::with_mode(0, 32).unwrap_err(),
+            DisplayError::UnsupportedMode(0, 32)
+        );
+        assert_eq!(
+            Display::with_mode(64, 0).unwrap_err(),
+            DisplayError::UnsupportedMode(64, 0)
+        );
+    }
+
+    #[test]
+    fn test_with_mode_rejects_width_not_multiple_of_8() {
+        assert_eq!(
+            Display::with_mode(60, 32).unwrap_err(),
+            DisplayError::UnsupportedMode(60, 32)
+        );
+    }
+
+    #[test]
+    fn test_resized_keeps_what_fits() {
+        let mut display = Display::new();
+        display.set_palette([1, 2, 3, 4]);
+        display.draw_sprite(0, 0, &vec![0b1000_0000]).unwrap();
+        display.draw_sprite(40, 100, &vec![0b1000_0000]).unwrap();
+
+        let resized = display.resized(64, 32).unwrap();
+
+        assert_eq!((resized.width, resized.height), (64, 32));
+        assert_eq!(resized.palette(), [1, 2, 3, 4]);
+        assert!(resized.is_pixel_set(0, 0));
+        assert_eq!(
+            resized
+                .buffer
+                .iter()
+                .flatten()
+                .filter(|&&byte| byte != 0)
+                .count(),
+            1
+        );
+
+        let back = resized.resized(128, 64).unwrap();
+        assert!(back.is_pixel_set(0, 0));
+        assert!(!back.is_pixel_set(100, 40));
+    }
+
+    #[test]
+    fn test_with_mode_wraps_at_its_own_width() {
+        let mut display = Display::with_mode(64, 32).unwrap();
+
+        display.draw_sprite(31, 60, &vec![0b1111_1111]).unwrap();
+
+        assert_eq!(display.buffer[31][7], 0b0000_1111);
+        assert_eq!(display.buffer[31][0], 0b1111_0000);
+    }
+
     #[test]
     fn test_buffer_initialized_with_zeroes() {
         let display = Display::new();
@@ -143,7 +444,14 @@ mod tests {
 
         let expected_address = SPRITE_START_ADDRESS + (sprite as usize * 5);
 
-        assert_eq!(Display::get_sprite_address(sprite), Ok(expected_address));
+        assert_eq!(
+            Display::get_sprite_address(SPRITE_START_ADDRESS, sprite),
+            Ok(expected_address)
+        );
+        assert_eq!(
+            Display::get_sprite_address(CONVENTIONAL_FONT_START, sprite),
+            Ok(0x050 + 0x0A * 5)
+        );
     }
 
     #[test]
@@ -151,11 +459,28 @@ mod tests {
         let sprite = 0x10;
 
         assert_eq!(
-            Display::get_sprite_address(sprite),
+            Display::get_sprite_address(SPRITE_START_ADDRESS, sprite),
             Err(DisplayError::InvalidSprite(sprite))
         );
     }
 
+    #[test]
+    fn test_font_glyph() {
+        assert_eq!(
+            Display::font_glyph(0x0),
+            Some(&[0xF0, 0x90, 0x90, 0x90, 0xF0][..])
+        );
+        assert_eq!(
+            Display::font_glyph(0xA),
+            Some(&[0xF0, 0x90, 0xF0, 0x90, 0x90][..])
+        );
+        assert_eq!(
+            Display::font_glyph(0xF),
+            Some(&[0xF0, 0x80, 0xF0, 0x80, 0x80][..])
+        );
+        assert_eq!(Display::font_glyph(0x10), None);
+    }
+
     #[test]
     fn test_render_sprite_row() {
         let mut display = Display::new();
@@ -165,7 +490,7 @@ mod tests {
 
         let erased = display.draw_sprite_row(row, col, value);
 
-        assert!(!erased);
+        assert_eq!(erased, 0);
         assert_eq!(display.buffer[row][col], value);
     }
 
@@ -180,7 +505,7 @@ mod tests {
 
         let erased = display.draw_sprite_row(row, col, value);
 
-        assert!(!erased);
+        assert_eq!(erased, 0);
         assert_eq!(display.buffer[row][col / 8], expected_byte_1);
         assert_eq!(display.buffer[row][col / 8 + 1], expected_byte_2);
     }
@@ -196,11 +521,37 @@ mod tests {
 
         let erased = display.draw_sprite_row(row, col, value);
 
-        assert!(!erased);
+        assert_eq!(erased, 0);
         assert_eq!(display.buffer[row][col / 8], expected_byte_1);
         assert_eq!(display.buffer[row][0], expected_byte_2);
     }
 
+    #[test]
+    fn test_render_sprite_row_wrapped_collision_on_either_side() {
+        // The sprite straddles the right edge: 4 pixels in the last column, 4 wrapped to the
+        // first. A collision in either half must be reported.
+        for (existing_col, existing) in [(DISPLAY_WIDTH - 8, 0b0000_0001), (0, 0b1000_0000)] {
+            let mut display = Display::new();
+            display.draw_sprite_row(0, existing_col, existing);
+
+            let erased = display.draw_sprite_row(0, DISPLAY_WIDTH - 4, 0b1111_1111);
+
+            assert_eq!(erased, 1, "collision at column {}", existing_col);
+        }
+    }
+
+    #[test]
+    fn test_render_sprite_row_wrapped_collision_on_both_sides() {
+        let mut display = Display::new();
+        display.draw_sprite_row(0, DISPLAY_WIDTH - 1, 0b1100_0000);
+
+        let erased = display.draw_sprite_row(0, DISPLAY_WIDTH - 4, 0b0001_1000);
+
+        assert_eq!(erased, 2);
+        assert!(!display.is_pixel_set(DISPLAY_WIDTH - 1, 0));
+        assert!(!display.is_pixel_set(0, 0));
+    }
+
     #[test]
     fn draw_sprite() {
         let mut display = Display::new();
@@ -214,7 +565,7 @@ mod tests {
         let row = 0;
         let col = 0;
 
-        let erased = display.draw_sprite(row, col, &sprite);
+        let erased = display.draw_sprite(row, col, &sprite).unwrap();
 
         assert!(!erased);
         for (row_idx, &sprite_row) in sprite.iter().enumerate() {
@@ -241,9 +592,9 @@ mod tests {
         ];
         let row = 0;
         let col = 0;
-        display.draw_sprite(row, col, &initial_sprite);
+        display.draw_sprite(row, col, &initial_sprite).unwrap();
 
-        let erased = display.draw_sprite(row, col, &new_sprite);
+        let erased = display.draw_sprite(row, col, &new_sprite).unwrap();
 
         assert!(erased);
         assert_eq!(display.buffer[row][col], 0b1000_0000);
@@ -253,6 +604,89 @@ mod tests {
         assert_eq!(display.buffer[row + 4][col], 0b1000_0000);
     }
 
+    #[test]
+    fn draw_sprite_reports_erased_bit_on_last_row_only() {
+        let mut display = Display::new();
+        let (row, col) = (10, 12);
+        display.draw_sprite_row(row + 3, col, 0b0000_0001);
+
+        let erased = display
+            .draw_sprite(row, col, &vec![0xF0, 0x0F, 0xF0, 0x01])
+            .unwrap();
+
+        assert!(erased);
+        assert!(!display.is_pixel_set(col + 7, row + 3));
+        assert!(display.is_pixel_set(col, row));
+    }
+
+    #[test]
+    fn draw_sprite_reports_erased_bit_on_wrapped_last_row() {
+        let mut display = Display::new();
+        display.draw_sprite_row(0, 0, 0b1000_0000);
+
+        let erased = display
+            .draw_sprite(DISPLAY_HEIGHT - 2, 0, &vec![0x0F, 0x0F, 0x80])
+            .unwrap();
+
+        assert!(erased);
+        assert!(!display.is_pixel_set(0, 0));
+        assert!(display.is_pixel_set(4, DISPLAY_HEIGHT - 1));
+    }
+
+    #[test]
+    fn draw_sprite_clipped_cuts_off_at_edges() {
+        let mut display = Display::with_mode(64, 32).unwrap();
+
+        let erased = display
+            .draw_sprite_clipped(30, 60, &vec![0xFF, 0xFF, 0xFF])
+            .unwrap();
+
+        assert!(!erased);
+        for col in 60..64 {
+            assert!(display.is_pixel_set(col, 30));
+            assert!(display.is_pixel_set(col, 31));
+        }
+        for row in [0, 30, 31] {
+            for col in 0..4 {
+                assert!(!display.is_pixel_set(col, row), "({}, {})", col, row);
+            }
+        }
+        assert!(!display.is_pixel_set(60, 0));
+    }
+
+    #[test]
+    fn draw_sprite_at_last_row_and_column() {
+        let mut display = Display::with_mode(64, 32).unwrap();
+
+        display.draw_sprite(31, 63, &vec![0b1100_0000]).unwrap();
+        display
+            .draw_sprite_clipped(31, 63, &vec![0b1000_0000])
+            .unwrap();
+
+        assert!(!display.is_pixel_set(63, 31));
+        assert!(display.is_pixel_set(0, 31));
+    }
+
+    #[test]
+    fn draw_sprite_rejects_position_outside_display() {
+        let mut display = Display::with_mode(64, 32).unwrap();
+        let sprite: SpriteValue = vec![0xFF];
+
+        assert_eq!(
+            display.draw_sprite(32, 0, &sprite),
+            Err(DisplayError::InvalidDrawPosition(32, 0))
+        );
+        assert_eq!(
+            display.draw_sprite(0, 64, &sprite),
+            Err(DisplayError::InvalidDrawPosition(0, 64))
+        );
+        assert_eq!(
+            display.draw_sprite_clipped(33, 66, &sprite),
+            Err(DisplayError::InvalidDrawPosition(33, 66))
+        );
+        assert_eq!(display.buffer, Display::with_mode(64, 32).unwrap().buffer);
+    }
+
     #[test]
     fn draw_sprite_wraps_around_bottom_edge() {
         let mut display = Display::new();
@@ -263,12 +697,12 @@ mod tests {
             0b1000_0000,
             0b1000_0000,
         ];
-        let row = BUFFER_HEIGHT - 3;
+        let row = DISPLAY_HEIGHT - 3;
         let col = 0;
 
-        let erased = display.draw_sprite(row, col, &sprite);
+        let erased = display.draw_sprite(row, col, &sprite).unwrap();
 
-        assert_eq!(erased, false);
+        assert!(!erased);
         assert_eq!(display.buffer[row][0], sprite[0]);
         assert_eq!(display.buffer[row + 1][0], sprite[1]);
         assert_eq!(display.buffer[row + 2][0], sprite[2]);
@@ -277,30 +711,124 @@ mod tests {
     }
 
     #[test]
-    fn test_bit_erased_no_erased_bits() {
+    fn test_erased_bits_no_erased_bits() {
         let original = 0b1010_1010;
         let current = 0b1010_1010;
-        assert_eq!(bit_erased(original, current), false);
+        assert_eq!(erased_bits(original, current), 0);
     }
 
     #[test]
-    fn test_bit_erased_some_erased_bits() {
+    fn test_erased_bits_some_erased_bits() {
         let original = 0b1010_1010;
         let current = 0b1010_0010;
-        assert_eq!(bit_erased(original, current), true);
+        assert_eq!(erased_bits(original, current), 1);
     }
 
     #[test]
-    fn test_bit_erased_all_erased_bits() {
+    fn test_erased_bits_all_erased_bits() {
         let original = 0b1010_1010;
         let current = 0b0000_0000;
-        assert_eq!(bit_erased(original, current), true);
+        assert_eq!(erased_bits(original, current), 4);
+    }
+
+    #[test]
+    fn test_draw_sprite_counted() {
+        let mut display = Display::new();
+        display
+            .draw_sprite(0, 0, &vec![0b1111_0000, 0b1010_1010])
+            .unwrap();
+
+        let result = display
+            .draw_sprite_counted(0, 2, &vec![0b1111_0000, 0b1111_1111])
+            .unwrap();
+
+        // Columns 2 and 3 of the first row, and 2, 4 and 6 of the second.
+        assert_eq!(
+            result,
+            DrawResult {
+                collided: true,
+                erased: 5
+            }
+        );
+        let result = display
+            .draw_sprite_clipped_counted(5, 0, &vec![0xFF])
+            .unwrap();
+        assert_eq!(
+            result,
+            DrawResult {
+                collided: false,
+                erased: 0
+            }
+        );
+    }
+
+    #[test]
+    fn test_is_pixel_set() {
+        let mut display = Display::new();
+        display.draw_sprite(0, 3, &vec![0b1000_0000]).unwrap();
+
+        assert!(display.is_pixel_set(3, 0));
+        assert!(!display.is_pixel_set(2, 0));
+        assert!(!display.is_pixel_set(3, 1));
+    }
+
+    #[test]
+    fn test_palette_colors_pixels_by_plane() {
+        let mut display = Display::with_mode(8, 1).unwrap();
+        display.draw_sprite(0, 1, &vec![0b1000_0000]).unwrap();
+
+        assert_eq!(display.palette(), DEFAULT_PALETTE);
+        assert_eq!((display.planes_at(0, 0), display.planes_at(1, 0)), (0, 1));
+
+        display.set_palette([0x0011_2233, 0x00AA_BBCC, 0x00DD_0000, 0x0000_DD00]);
+
+        assert_eq!(display.color_at(0, 0), 0x0011_2233);
+        assert_eq!(display.color_at(1, 0), 0x00AA_BBCC);
+        assert_eq!(
+            display.to_rgba()[..12],
+            [0x11, 0x22, 0x33, 0xFF, 0xAA, 0xBB, 0xCC, 0xFF, 0x11, 0x22, 0x33, 0xFF]
+        );
+    }
+
+    #[test]
+    fn test_scroll_up() {
+        let mut display = Display::new();
+        display.draw_sprite(0, 0, &vec![0xFF, 0x81, 0xFF]).unwrap();
+        display
+            .draw_sprite(display.height - 1, 8, &vec![0x3C])
+            .unwrap();
+
+        display.scroll_up(1);
+
+        assert_eq!(display.buffer[0][0], 0x81);
+        assert_eq!(display.buffer[1][0], 0xFF);
+        assert_eq!(display.buffer[2][0], 0x00);
+        assert_eq!(display.buffer[display.height - 2][1], 0x3C);
+        assert!(display.buffer[display.height - 1]
+            .iter()
+            .all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn test_scroll_up_by_display_height() {
+        let mut display = Display::with_mode(64, 32).unwrap();
+        display.draw_sprite(0, 0, &vec![0xFF; 15]).unwrap();
+        display.draw_sprite(31, 56, &vec![0xFF]).unwrap();
+
+        display.scroll_up(display.height);
+        assert_eq!(display.buffer, Display::with_mode(64, 32).unwrap().buffer);
+
+        display.draw_sprite(0, 0, &vec![0xFF]).unwrap();
+        display.scroll_up(display.height + 1);
+        assert_eq!(display.buffer, Display::with_mode(64, 32).unwrap().buffer);
     }
 
     #[test]
     fn test_clear() {
         let mut display = Display::new();
-        display.draw_sprite(0, 0, &BUILT_IN_SPRITES[0].to_vec());
+        display
+            .draw_sprite(0, 0, &BUILT_IN_SPRITES[0].to_vec())
+            .unwrap();
 
         display.clear();
 
@@ -310,4 +838,59 @@ mod tests {
             }
         }
     }
+
+    fn filled(width: usize, height: usize) -> Display {
+        let mut display = Display::with_mode(width, height).unwrap();
+        for row in display.buffer.iter_mut() {
+            row.fill(0xFF);
+        }
+        display
+    }
+
+    fn cleared_pixels(display: &Display) -> Vec<(usize, usize)> {
+        (0..display.height)
+            .flat_map(|y| (0..display.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| !display.is_pixel_set(x, y))
+            .collect()
+    }
+
+    #[test]
+    fn test_clear_rect() {
+        let mut display = filled(64, 32);
+
+        display.clear_rect(6, 2, 4, 2).unwrap();
+
+        assert_eq!(
+            cleared_pixels(&display),
+            vec![
+                (6, 2),
+                (7, 2),
+                (8, 2),
+                (9, 2),
+                (6, 3),
+                (7, 3),
+                (8, 3),
+                (9, 3)
+            ]
+        );
+        assert_eq!(
+            display.clear_rect(64, 0, 1, 1),
+            Err(DisplayError::InvalidDrawPosition(0, 64))
+        );
+    }
+
+    #[test]
+    fn test_clear_rect_wraps_or_clips_at_the_edges() {
+        let mut wrapped = filled(64, 32);
+        let mut clipped = filled(64, 32);
+
+        wrapped.clear_rect(63, 31, 2, 2).unwrap();
+        clipped.clear_rect_clipped(63, 31, 2, 2).unwrap();
+
+        assert_eq!(
+            cleared_pixels(&wrapped),
+            vec![(0, 0), (63, 0), (0, 31), (63, 31)]
+        );
+        assert_eq!(cleared_pixels(&clipped), vec![(63, 31)]);
+    }
 }
@@ -1,46 +1,88 @@
-use std::str::FromStr;
-
 #[derive(Debug, PartialEq)]
 pub enum Error {
     InvalidKey(String),
 }
-#[derive(Debug)]
+
+// The keypad has keys 0 to F.
+pub const KEY_COUNT: u8 = 16;
+
+// Parses a keypad key named by its label, a single hex digit in either case.
+pub fn parse_key(key: &str) -> Result<u8, Error> {
+    match u8::from_str_radix(key, 16) {
+        Ok(key_u8) if key.len() == 1 => Ok(key_u8),
+        _ => Err(Error::InvalidKey(key.to_string())),
+    }
+}
+
+// The keypad as the ROM sees it. With sticky keys, a press is held until a key instruction has
+// seen it or a frame has ended, so that a tap between two polls isn't lost; a release that comes
+// before then is applied once it has.
+#[derive(Debug, Default, Clone)]
 pub struct Input {
-    key: Option<String>,
+    key: Option<u8>,
+    sticky: bool,
+    // Whether a key instruction has seen the pressed key.
+    observed: bool,
+    // The key was released before it was observed.
+    release_deferred: bool,
 }
 
 impl Input {
     pub fn new() -> Self {
-        Input { key: None }
+        Input::default()
     }
 
-    pub fn set_key(&mut self, key: &str) {
-        self.key = Some(key.to_string());
+    pub fn with_sticky_keys(sticky: bool) -> Self {
+        Input {
+            sticky,
+            ..Input::default()
+        }
     }
 
-    pub fn get_key(&self) -> Option<&str> {
-        if self.key.is_some() {
-            Some(self.key.as_ref().unwrap())
-        } else {
-            None
+    pub fn set_key(&mut self, key: u8) -> Result<(), Error> {
+        if key >= KEY_COUNT {
+            return Err(Error::InvalidKey(format!("{:#X}", key)));
         }
+        self.key = Some(key);
+        self.observed = false;
+        self.release_deferred = false;
+        Ok(())
     }
 
-    pub fn clear_key(&mut self) {
-        self.key = None;
+    // The pressed key, without counting as the ROM seeing it.
+    pub fn get_key(&self) -> Option<u8> {
+        self.key
     }
 
-    pub fn get_key_u8(&self) -> Result<Option<u8>, Error> {
-        if self.key.is_none() {
-            return Ok(None);
+    // The pressed key as seen by a key instruction. A deferred release is applied afterwards.
+    pub fn query_key(&mut self) -> Option<u8> {
+        let key = self.key;
+        self.observed = true;
+        if self.release_deferred {
+            self.clear_latch();
         }
+        key
+    }
 
-        let key_str = self.key.as_ref().unwrap();
-        match u8::from_str_radix(key_str.as_str(), 16) {
-            Ok(key_u8) => Ok(Some(key_u8)),
-            _ => Err(Error::InvalidKey(key_str.to_string())),
+    pub fn clear_key(&mut self) {
+        if self.sticky && self.key.is_some() && !self.observed {
+            self.release_deferred = true;
+        } else {
+            self.clear_latch();
         }
     }
+
+    // Call at the end of each frame. A deferred release is applied even if no key instruction ran.
+    pub fn end_frame(&mut self) {
+        if self.release_deferred {
+            self.clear_latch();
+        }
+    }
+
+    fn clear_latch(&mut self) {
+        self.key = None;
+        self.release_deferred = false;
+    }
 }
 
 #[cfg(test)]
@@ -56,34 +98,93 @@ mod tests {
     #[test]
     fn test_set_key() {
         let mut input = Input::new();
-        input.set_key("A");
-        assert_eq!(input.get_key(), Some("A"));
+        input.set_key(0xA).unwrap();
+        assert_eq!(input.get_key(), Some(0xA));
+    }
+
+    #[test]
+    fn test_set_key_out_of_range() {
+        let mut input = Input::new();
+        input.set_key(0x1).unwrap();
+        assert_eq!(
+            input.set_key(0x10),
+            Err(Error::InvalidKey("0x10".to_string()))
+        );
+        assert_eq!(input.get_key(), Some(0x1));
     }
 
     #[test]
     fn test_clear_key() {
         let mut input = Input::new();
-        input.set_key("A");
+        input.set_key(0xA).unwrap();
         input.clear_key();
         assert_eq!(input.get_key(), None);
     }
+
     #[test]
-    fn test_get_key_u8_valid_key() {
-        let mut input = Input::new();
-        input.set_key("1");
-        assert_eq!(input.get_key_u8(), Ok(Some(1)));
+    fn test_sticky_release_waits_for_query() {
+        let mut input = Input::with_sticky_keys(true);
+        input.set_key(0x5).unwrap();
+        input.clear_key();
+
+        assert_eq!(input.get_key(), Some(0x5));
+        assert_eq!(input.query_key(), Some(0x5));
+        assert_eq!(input.query_key(), None);
     }
 
     #[test]
-    fn test_get_key_u8_invalid_key() {
-        let mut input = Input::new();
-        input.set_key("G");
-        assert_eq!(input.get_key_u8(), Err(Error::InvalidKey("G".to_string())));
+    fn test_sticky_observed_key_releases_immediately() {
+        let mut input = Input::with_sticky_keys(true);
+        input.set_key(0x5).unwrap();
+        assert_eq!(input.query_key(), Some(0x5));
+
+        input.clear_key();
+
+        assert_eq!(input.get_key(), None);
     }
 
     #[test]
-    fn test_get_key_u8_no_key() {
-        let input = Input::new();
-        assert_eq!(input.get_key_u8(), Ok(None));
+    fn test_sticky_release_applied_at_end_of_frame() {
+        let mut input = Input::with_sticky_keys(true);
+        input.set_key(0x5).unwrap();
+        input.clear_key();
+
+        input.end_frame();
+
+        assert_eq!(input.query_key(), None);
+    }
+
+    #[test]
+    fn test_new_press_cancels_deferred_release() {
+        let mut input = Input::with_sticky_keys(true);
+        input.set_key(0x5).unwrap();
+        input.clear_key();
+        input.set_key(0x6).unwrap();
+
+        input.end_frame();
+
+        assert_eq!(input.query_key(), Some(0x6));
+    }
+
+    #[test]
+    fn test_parse_key_valid_key() {
+        assert_eq!(parse_key("1"), Ok(0x1));
+        assert_eq!(parse_key("a"), Ok(0xA));
+        assert_eq!(parse_key("F"), Ok(0xF));
+    }
+
+    #[test]
+    fn test_parse_key_either_case() {
+        for (value, lower) in (10..16).zip(["a", "b", "c", "d", "e", "f"]) {
+            assert_eq!(parse_key(lower), Ok(value));
+            assert_eq!(parse_key(&lower.to_uppercase()), Ok(value));
+        }
+    }
+
+    #[test]
+    fn test_parse_key_invalid_key() {
+        for key in ["G", "", "10", "+1", " 1"] {
+            assert_eq!(parse_key(key), Err(Error::InvalidKey(key.to_string())));
+        }
     }
 }
@@ -0,0 +1,252 @@
+use super::display::Display;
+use super::{Chip8, Chip8Error};
+use crate::numfmt::format_addr;
+use std::fmt;
+use std::ops::Range;
+
+// Read-only views of a machine's state, see Chip8::inspect. An inspector borrows the machine, so
+// nothing can run or change it while one, or any view taken from one, is still around.
+#[derive(Debug, Clone, Copy)]
+pub struct Inspector<'a> {
+    chip8: &'a Chip8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RegisterView {
+    pub v: [u8; 16],
+    pub i: u16,
+    pub pc: u16,
+}
+
+// Bytes of memory from `start`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MemoryView<'a> {
+    pub start: u16,
+    pub bytes: &'a [u8],
+}
+
+// Return addresses, oldest first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StackView<'a> {
+    pub entries: &'a [u16],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimerView {
+    pub delay: u8,
+    pub sound: u8,
+    // Whether the sound timer is beeping, which a timer started below min_beep_ticks isn't.
+    pub sound_active: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeypadView {
+    pub pressed: Option<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CounterView {
+    // Instructions executed since the machine was created.
+    pub cycles: u64,
+    // Changes to the display, see Chip8::display_generation.
+    pub display_generation: u64,
+}
+
+impl<'a> Inspector<'a> {
+    pub(super) fn new(chip8: &'a Chip8) -> Self {
+        Inspector { chip8 }
+    }
+
+    pub fn registers(&self) -> RegisterView {
+        let registers = &self.chip8.registers;
+        RegisterView {
+            v: std::array::from_fn(|register| registers.read_v(register as u8)),
+            i: registers.i,
+            pc: registers.pc,
+        }
+    }
+
+    pub fn memory(&self, range: Range<usize>) -> Result<MemoryView<'a>, Chip8Error> {
+        let len = range.end.saturating_sub(range.start);
+        self.chip8.check_range(range.start, len)?;
+        Ok(MemoryView {
+            start: range.start as u16,
+            bytes: self.chip8.memory.read_slice(range.start, len)?,
+        })
+    }
+
+    pub fn display(&self) -> &'a Display {
+        &self.chip8.display
+    }
+
+    pub fn stack(&self) -> StackView<'a> {
+        StackView {
+            entries: self.chip8.stack.entries(),
+        }
+    }
+
+    pub fn timers(&self) -> TimerView {
+        TimerView {
+            delay: self.chip8.timers.get_delay_timer(),
+            sound: self.chip8.timers.get_sound_timer(),
+            sound_active: self.chip8.sound_active(),
+        }
+    }
+
+    // The key held down, without counting as the ROM seeing it.
+    pub fn keypad(&self) -> KeypadView {
+        KeypadView {
+            pressed: self.chip8.input.get_key(),
+        }
+    }
+
+    pub fn counters(&self) -> CounterView {
+        CounterView {
+            cycles: self.chip8.cycles,
+            display_generation: self.chip8.display_generation,
+        }
+    }
+}
+
+impl fmt::Display for RegisterView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (register, value) in self.v.iter().enumerate() {
+            write!(f, "V{:X}={:02X} ", register, value)?;
+        }
+        write!(f, "I={} pc={}", format_addr(self.i), format_addr(self.pc))
+    }
+}
+
+// Sixteen bytes to a line, each line starting with its address.
+impl fmt::Display for MemoryView<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lines: Vec<String> = self
+            .bytes
+            .chunks(16)
+            .enumerate()
+            .map(|(line, bytes)| {
+                let bytes: Vec<String> = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+                let address = usize::from(self.start) + line * 16;
+                format!("{}: {}", format_addr(address as u16), bytes.join(" "))
+            })
+            .collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+impl fmt::Display for StackView<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.entries.is_empty() {
+            return write!(f, "empty");
+        }
+        let entries: Vec<String> = self
+            .entries
+            .iter()
+            .map(|&address| format_addr(address))
+            .collect();
+        write!(f, "{}", entries.join(" "))
+    }
+}
+
+impl fmt::Display for TimerView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "delay={} sound={}", self.delay, self.sound)?;
+        if self.sound_active {
+            write!(f, " beeping")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for KeypadView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.pressed {
+            Some(key) => write!(f, "key={:X}", key),
+            None => write!(f, "key=none"),
+        }
+    }
+}
+
+impl fmt::Display for CounterView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "cycles={} display generation={}",
+            self.cycles, self.display_generation
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::{assemble_opcodes, Opcode};
+    use std::time::Duration;
+
+    #[test]
+    fn test_views_agree_with_the_machine() {
+        let mut chip8 = Chip8::booted();
+        let rom = assemble_opcodes(&[
+            Opcode::LoadByte(0x3, 0x42),
+            Opcode::SetIndex(0x345),
+            Opcode::SetDelayTimer(0x3),
+            Opcode::Call(0x20A),
+            Opcode::Jump(0x208),
+            Opcode::Draw(0x0, 0x0, 1),
+            Opcode::Jump(0x20A),
+        ]);
+        chip8.load_rom(&rom).unwrap();
+        chip8.press_key(0x5).unwrap();
+        chip8.run_for(Duration::from_millis(20)).unwrap();
+
+        let inspector = chip8.inspect();
+        let state = chip8.export_state();
+
+        let registers = inspector.registers();
+        assert_eq!(
+            (registers.v, registers.i, registers.pc),
+            (state.v, state.i, state.pc)
+        );
+        assert_eq!(registers.v[0x3], 0x42);
+        assert_eq!(inspector.stack().entries, &[0x208]);
+        assert_eq!(inspector.stack().to_string(), "0x208");
+        let timers = inspector.timers();
+        assert_eq!(
+            (timers.delay, timers.sound),
+            (state.delay_timer, state.sound_timer)
+        );
+        assert_eq!(inspector.keypad().to_string(), "key=5");
+        assert_eq!(inspector.counters().cycles, chip8.cycles());
+        assert_eq!(
+            inspector.counters().display_generation,
+            chip8.display_generation()
+        );
+        assert_eq!(inspector.display().to_string(), chip8.render_ascii());
+        assert_eq!(
+            inspector.memory(0x200..0x204).unwrap().bytes,
+            &chip8.memory_bytes(0x200, 4).unwrap()[..]
+        );
+    }
+
+    #[test]
+    fn test_view_text() {
+        let mut chip8 = Chip8::booted();
+        chip8.load_rom(&(0..20).collect::<Vec<u8>>()).unwrap();
+        chip8.timers.set_sound_timer(4);
+
+        let inspector = chip8.inspect();
+
+        assert_eq!(
+            inspector.memory(0x200..0x214).unwrap().to_string(),
+            "0x200: 00 01 02 03 04 05 06 07 08 09 0A 0B 0C 0D 0E 0F\n0x210: 10 11 12 13"
+        );
+        assert_eq!(inspector.stack().to_string(), "empty");
+        assert_eq!(inspector.timers().to_string(), "delay=0 sound=4 beeping");
+        assert_eq!(inspector.keypad().to_string(), "key=none");
+        assert!(inspector
+            .registers()
+            .to_string()
+            .ends_with("VF=00 I=0x000 pc=0x200"));
+        assert!(inspector.memory(0xFFE..0x1002).is_err());
+    }
+}
@@ -1,35 +1,79 @@
-const MEMORY_SIZE: usize = 4096;
+use super::PROGRAM_START_ADDRESS;
+
+// The original 4 KB. XO-CHIP machines have 64 KB and some early systems only had 2 KB.
+pub const MEMORY_SIZE: usize = 4096;
+// The smallest memory with the font and an instruction at the program start, and the largest a
+// 16-bit pc can address.
+pub const MIN_MEMORY_SIZE: usize = PROGRAM_START_ADDRESS + 2;
+pub const MAX_MEMORY_SIZE: usize = 0x10000;
 
 #[derive(Debug, PartialEq)]
 pub enum MemoryError {
     AddressOutOfBounds,
+    // A size outside MIN_MEMORY_SIZE..=MAX_MEMORY_SIZE.
+    InvalidSize(usize),
+}
+
+pub fn check_size(size: usize) -> Result<(), MemoryError> {
+    if (MIN_MEMORY_SIZE..=MAX_MEMORY_SIZE).contains(&size) {
+        Ok(())
+    } else {
+        Err(MemoryError::InvalidSize(size))
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Hash)]
 pub struct Memory {
-    ram: [u8; MEMORY_SIZE],
+    ram: Vec<u8>,
+    // Whether each byte was ever written, to catch reads of garbage.
+    initialized: Vec<bool>,
 }
 
 impl Memory {
-    pub fn new() -> Memory {
-        Memory {
-            ram: [0; MEMORY_SIZE],
-        }
+    pub fn with_size(size: usize) -> Result<Memory, MemoryError> {
+        check_size(size)?;
+        Ok(Memory {
+            ram: vec![0; size],
+            initialized: vec![false; size],
+        })
+    }
+
+    pub fn size(&self) -> usize {
+        self.ram.len()
     }
 
     pub fn read_byte(&self, address: usize) -> Result<u8, MemoryError> {
-        if address >= MEMORY_SIZE {
+        if address >= self.size() {
             Err(MemoryError::AddressOutOfBounds)
         } else {
             Ok(self.ram[address])
         }
     }
 
+    // Out of bounds addresses are never initialized.
+    pub fn is_initialized(&self, address: usize) -> bool {
+        self.initialized.get(address).copied().unwrap_or(false)
+    }
+
+    // CHIP-8 is big-endian: the byte at `address` is the most significant one.
+    pub fn read_word(&self, address: usize) -> Result<u16, MemoryError> {
+        let msb = self.read_byte(address)?;
+        let lsb = self.read_byte(address + 1)?;
+        Ok(u16::from_be_bytes([msb, lsb]))
+    }
+
+    pub fn read_slice(&self, address: usize, len: usize) -> Result<&[u8], MemoryError> {
+        self.ram
+            .get(address..address + len)
+            .ok_or(MemoryError::AddressOutOfBounds)
+    }
+
     pub fn write_byte(&mut self, address: usize, value: u8) -> Result<(), MemoryError> {
-        if address >= MEMORY_SIZE {
+        if address >= self.size() {
             Err(MemoryError::AddressOutOfBounds)
         } else {
             self.ram[address] = value;
+            self.initialized[address] = true;
             Ok(())
         }
     }
@@ -43,7 +87,7 @@ mod tests {
     fn test_read_byte() {
         let value = 0xAB;
         let address = 0x200;
-        let mut memory = Memory::new();
+        let mut memory = Memory::with_size(MEMORY_SIZE).unwrap();
 
         let res = memory.write_byte(address, value);
 
@@ -51,11 +95,44 @@ mod tests {
         assert_eq!(memory.read_byte(address).unwrap(), value);
     }
 
+    #[test]
+    fn test_read_word_big_endian() {
+        let mut memory = Memory::with_size(MEMORY_SIZE).unwrap();
+        memory.write_byte(0x200, 0x12).unwrap();
+        memory.write_byte(0x201, 0x34).unwrap();
+
+        assert_eq!(memory.read_word(0x200), Ok(0x1234));
+    }
+
+    #[test]
+    fn test_read_slice() {
+        let mut memory = Memory::with_size(MEMORY_SIZE).unwrap();
+        memory.write_byte(MEMORY_SIZE - 2, 0xAB).unwrap();
+        memory.write_byte(MEMORY_SIZE - 1, 0xCD).unwrap();
+
+        assert_eq!(memory.read_slice(MEMORY_SIZE - 2, 2), Ok(&[0xAB, 0xCD][..]));
+        assert_eq!(memory.read_slice(MEMORY_SIZE, 0), Ok(&[][..]));
+        assert_eq!(
+            memory.read_slice(MEMORY_SIZE - 2, 3),
+            Err(MemoryError::AddressOutOfBounds)
+        );
+    }
+
+    #[test]
+    fn test_read_word_out_of_bounds() {
+        let memory = Memory::with_size(MEMORY_SIZE).unwrap();
+
+        assert_eq!(
+            memory.read_word(MEMORY_SIZE - 1),
+            Err(MemoryError::AddressOutOfBounds)
+        );
+    }
+
     #[test]
     fn test_write_byte() {
         let address = 0x300;
         let value = 0xCD;
-        let mut memory = Memory::new();
+        let mut memory = Memory::with_size(MEMORY_SIZE).unwrap();
 
         let res = memory.write_byte(address, value);
 
@@ -63,9 +140,21 @@ mod tests {
         assert_eq!(memory.read_byte(address), Ok(value));
     }
 
+    #[test]
+    fn test_is_initialized() {
+        let mut memory = Memory::with_size(MEMORY_SIZE).unwrap();
+        assert!(!memory.is_initialized(0x200));
+
+        memory.write_byte(0x200, 0x00).unwrap();
+
+        assert!(memory.is_initialized(0x200));
+        assert!(!memory.is_initialized(0x201));
+        assert!(!memory.is_initialized(0x5000));
+    }
+
     #[test]
     fn test_read_byte_out_of_bounds() {
-        let memory = Memory::new();
+        let memory = Memory::with_size(MEMORY_SIZE).unwrap();
         let address = 0x5000;
 
         assert_eq!(
@@ -74,11 +163,37 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_with_size() {
+        let mut memory = Memory::with_size(0x10000).unwrap();
+
+        assert_eq!(memory.size(), 0x10000);
+        assert_eq!(memory.write_byte(0x5000, 0x42), Ok(()));
+        assert_eq!(memory.read_byte(0x5000), Ok(0x42));
+        assert_eq!(
+            Memory::with_size(0x800).unwrap().read_byte(0x900),
+            Err(MemoryError::AddressOutOfBounds)
+        );
+    }
+
+    #[test]
+    fn test_with_size_rejects_sizes_out_of_range() {
+        assert_eq!(
+            Memory::with_size(70000).unwrap_err(),
+            MemoryError::InvalidSize(70000)
+        );
+        assert_eq!(
+            Memory::with_size(0x100).unwrap_err(),
+            MemoryError::InvalidSize(0x100)
+        );
+        assert!(Memory::with_size(MIN_MEMORY_SIZE).is_ok());
+    }
+
     #[test]
     fn test_write_byte_out_of_bounds() {
         let address = 0x5000;
         let value = 0xEF;
-        let mut memory = Memory::new();
+        let mut memory = Memory::with_size(MEMORY_SIZE).unwrap();
 
         let result = memory.write_byte(address, value);
 
@@ -6,7 +6,7 @@ pub enum OpcodeError {
     InvalidOpcode(u16),
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Opcode {
     // System Commands
     ClearDisplay, // 00E0
@@ -24,17 +24,17 @@ pub enum Opcode {
     SkipIfRegNotEqual(u8, u8), // 9XY0
 
     // Register Commands
-    LoadByte(u8, u8), // 6XNN
-    AddByte(u8, u8),  // 7XNN
-    LoadReg(u8, u8),  // 8XY0
-    Or(u8, u8),       // 8XY1
-    And(u8, u8),      // 8XY2
-    Xor(u8, u8),      // 8XY3
-    AddReg(u8, u8),   // 8XY4
-    Sub(u8, u8),      // 8XY5
-    ShiftRight(u8),   // 8XY6
-    SubN(u8, u8),     // 8XY7
-    ShiftLeft(u8),    // 8XYE
+    LoadByte(u8, u8),   // 6XNN
+    AddByte(u8, u8),    // 7XNN
+    LoadReg(u8, u8),    // 8XY0
+    Or(u8, u8),         // 8XY1
+    And(u8, u8),        // 8XY2
+    Xor(u8, u8),        // 8XY3
+    AddReg(u8, u8),     // 8XY4
+    Sub(u8, u8),        // 8XY5
+    ShiftRight(u8, u8), // 8XY6
+    SubN(u8, u8),       // 8XY7
+    ShiftLeft(u8, u8),  // 8XYE
 
     // Memory Commands
     SetIndex(u16),  // ANNN
@@ -43,6 +43,9 @@ pub enum Opcode {
 
     // Display Commands
     Draw(u8, u8, u8), // DXYN
+    ScrollUp(u8),     // 00DN (XO-CHIP)
+    LowRes,           // 00FE (SUPER-CHIP)
+    HighRes,          // 00FF (SUPER-CHIP)
 
     // KeyOp Commands
     SkipIfKeyPressed(u8),    // EX9E
@@ -55,6 +58,7 @@ pub enum Opcode {
     SetSoundTimer(u8),  // FX18
 
     // I Register Commands
+    LoadLongI,          // F000 NNNN (XO-CHIP), NNNN is the word following the opcode
     AddI(u8),           // FX1E
     LoadSpriteAddr(u8), // FX29
     StoreBCD(u8),       // FX33
@@ -67,6 +71,23 @@ pub enum Opcode {
     Undefined(u16), // For any opcode that doesn't match the above
 }
 
+// Instructions grouped by what they act on. Each family is executed by its own handler.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Family {
+    // Jumps, calls, returns and conditional skips on register values.
+    Flow,
+    // Arithmetic and logic on V registers.
+    Register,
+    // I and memory.
+    Memory,
+    Display,
+    // The keypad.
+    Input,
+    Timer,
+    // 0NNN and undefined opcodes.
+    System,
+}
+
 struct Instruction {
     msb: u8,
     lsb: u8,
@@ -81,10 +102,10 @@ impl From<(u8, u8)> for Instruction {
     }
 }
 impl Instruction {
-    fn get_address(&self) -> Result<u16, OpcodeError> {
+    // Machines with less than 4 KB of memory can't reach every 12-bit address.
+    fn get_address(&self, memory_size: usize) -> Result<u16, OpcodeError> {
         let addr = ((self.msb as u16 & 0x0F) << 8) | self.lsb as u16;
-        // Return Err if the address is out of bounds (only first 12 bits are used for address space).
-        if 0xC00 & addr != 0 {
+        if addr as usize >= memory_size {
             Err(OpcodeError::InvalidAddress(addr))
         } else {
             Ok(addr)
@@ -116,8 +137,13 @@ impl Instruction {
     }
 }
 
+// Encodes the opcodes back to back, for building ROMs in tests.
+pub fn assemble_opcodes(ops: &[Opcode]) -> Vec<u8> {
+    ops.iter().flat_map(Opcode::to_bytes).collect()
+}
+
 impl Opcode {
-    pub fn from_bytes(msb: u8, lsb: u8) -> Result<Opcode, OpcodeError> {
+    pub fn from_bytes(msb: u8, lsb: u8, memory_size: usize) -> Result<Opcode, OpcodeError> {
         let instruction = Instruction::from((msb, lsb));
         let (code, x, y, n) = (
             instruction.get_code(),
@@ -129,9 +155,12 @@ impl Opcode {
         match (code, x, y, n) {
             (0, 0, 0xE, 0) => Ok(Opcode::ClearDisplay),
             (0, 0, 0xE, 0xE) => Ok(Opcode::Return),
-            (0, _, _, _) => Ok(Opcode::SysAddr(instruction.get_address()?)),
-            (0x1, _, _, _) => Ok(Opcode::Jump(instruction.get_address()?)),
-            (0x2, _, _, _) => Ok(Opcode::Call(instruction.get_address()?)),
+            (0, 0, 0xD, _) => Ok(Opcode::ScrollUp(instruction.get_n())),
+            (0, 0, 0xF, 0xE) => Ok(Opcode::LowRes),
+            (0, 0, 0xF, 0xF) => Ok(Opcode::HighRes),
+            (0, _, _, _) => Ok(Opcode::SysAddr(instruction.get_address(memory_size)?)),
+            (0x1, _, _, _) => Ok(Opcode::Jump(instruction.get_address(memory_size)?)),
+            (0x2, _, _, _) => Ok(Opcode::Call(instruction.get_address(memory_size)?)),
             (0x3, _, _, _) => Ok(Opcode::SkipIfEqual(
                 instruction.get_x(),
                 instruction.get_kk(),
@@ -155,15 +184,15 @@ impl Opcode {
             (0x8, _, _, 0x3) => Ok(Opcode::Xor(instruction.get_x(), instruction.get_y())),
             (0x8, _, _, 0x4) => Ok(Opcode::AddReg(instruction.get_x(), instruction.get_y())),
             (0x8, _, _, 0x5) => Ok(Opcode::Sub(instruction.get_x(), instruction.get_y())),
-            (0x8, _, _, 0x6) => Ok(Opcode::ShiftRight(instruction.get_x())),
+            (0x8, _, _, 0x6) => Ok(Opcode::ShiftRight(instruction.get_x(), instruction.get_y())),
             (0x8, _, _, 0x7) => Ok(Opcode::SubN(instruction.get_x(), instruction.get_y())),
-            (0x8, _, _, 0xE) => Ok(Opcode::ShiftLeft(instruction.get_x())),
+            (0x8, _, _, 0xE) => Ok(Opcode::ShiftLeft(instruction.get_x(), instruction.get_y())),
             (0x9, _, _, 0x0) => Ok(Opcode::SkipIfRegNotEqual(
                 instruction.get_x(),
                 instruction.get_y(),
             )),
-            (0xA, _, _, _) => Ok(Opcode::SetIndex(instruction.get_address()?)),
-            (0xB, _, _, _) => Ok(Opcode::JumpV0(instruction.get_address()?)),
+            (0xA, _, _, _) => Ok(Opcode::SetIndex(instruction.get_address(memory_size)?)),
+            (0xB, _, _, _) => Ok(Opcode::JumpV0(instruction.get_address(memory_size)?)),
             (0xC, _, _, _) => Ok(Opcode::Random(instruction.get_x(), instruction.get_byte())),
             (0xD, _, _, _) => Ok(Opcode::Draw(
                 instruction.get_x(),
@@ -172,6 +201,7 @@ impl Opcode {
             )),
             (0xE, _, 0x9, 0xE) => Ok(Opcode::SkipIfKeyPressed(instruction.get_x())),
             (0xE, _, 0xA, 0x1) => Ok(Opcode::SkipIfKeyNotPressed(instruction.get_x())),
+            (0xF, 0x0, 0x0, 0x0) => Ok(Opcode::LoadLongI),
             (0xF, _, 0x0, 0x7) => Ok(Opcode::LoadDelayTimer(instruction.get_x())),
             (0xF, _, 0x0, 0xA) => Ok(Opcode::WaitForKey(instruction.get_x())),
             (0xF, _, 0x1, 0x5) => Ok(Opcode::SetDelayTimer(instruction.get_x())),
@@ -182,18 +212,168 @@ impl Opcode {
             (0xF, _, 0x5, 0x5) => Ok(Opcode::RegDump(instruction.get_x())),
             (0xF, _, 0x6, 0x5) => Ok(Opcode::RegLoad(instruction.get_x())),
 
-            _ => Err(OpcodeError::InvalidOpcode((msb as u16) << 8 | lsb as u16)),
+            _ => Ok(Opcode::Undefined((msb as u16) << 8 | lsb as u16)),
+        }
+    }
+
+    // The encoding from_bytes decodes. F000 NNNN encodes to F000 alone, since the address word
+    // isn't part of the opcode.
+    pub fn to_bytes(&self) -> [u8; 2] {
+        let xyn = |code: u16, x: u8, y: u8, n: u8| {
+            code << 12 | (x as u16) << 8 | (y as u16) << 4 | n as u16
+        };
+        let xkk = |code: u16, x: u8, kk: u8| code << 12 | (x as u16) << 8 | kk as u16;
+        let word = match *self {
+            Opcode::ClearDisplay => 0x00E0,
+            Opcode::Return => 0x00EE,
+            Opcode::ScrollUp(n) => 0x00D0 | n as u16,
+            Opcode::LowRes => 0x00FE,
+            Opcode::HighRes => 0x00FF,
+            Opcode::SysAddr(addr) => addr,
+            Opcode::Jump(addr) => 0x1000 | addr,
+            Opcode::Call(addr) => 0x2000 | addr,
+            Opcode::SkipIfEqual(x, kk) => xkk(0x3, x, kk),
+            Opcode::SkipIfNotEqual(x, kk) => xkk(0x4, x, kk),
+            Opcode::SkipIfRegEqual(x, y) => xyn(0x5, x, y, 0x0),
+            Opcode::LoadByte(x, kk) => xkk(0x6, x, kk),
+            Opcode::AddByte(x, kk) => xkk(0x7, x, kk),
+            Opcode::LoadReg(x, y) => xyn(0x8, x, y, 0x0),
+            Opcode::Or(x, y) => xyn(0x8, x, y, 0x1),
+            Opcode::And(x, y) => xyn(0x8, x, y, 0x2),
+            Opcode::Xor(x, y) => xyn(0x8, x, y, 0x3),
+            Opcode::AddReg(x, y) => xyn(0x8, x, y, 0x4),
+            Opcode::Sub(x, y) => xyn(0x8, x, y, 0x5),
+            Opcode::ShiftRight(x, y) => xyn(0x8, x, y, 0x6),
+            Opcode::SubN(x, y) => xyn(0x8, x, y, 0x7),
+            Opcode::ShiftLeft(x, y) => xyn(0x8, x, y, 0xE),
+            Opcode::SkipIfRegNotEqual(x, y) => xyn(0x9, x, y, 0x0),
+            Opcode::SetIndex(addr) => 0xA000 | addr,
+            Opcode::JumpV0(addr) => 0xB000 | addr,
+            Opcode::Random(x, kk) => xkk(0xC, x, kk),
+            Opcode::Draw(x, y, n) => xyn(0xD, x, y, n),
+            Opcode::SkipIfKeyPressed(x) => xkk(0xE, x, 0x9E),
+            Opcode::SkipIfKeyNotPressed(x) => xkk(0xE, x, 0xA1),
+            Opcode::LoadDelayTimer(x) => xkk(0xF, x, 0x07),
+            Opcode::WaitForKey(x) => xkk(0xF, x, 0x0A),
+            Opcode::SetDelayTimer(x) => xkk(0xF, x, 0x15),
+            Opcode::SetSoundTimer(x) => xkk(0xF, x, 0x18),
+            Opcode::LoadLongI => 0xF000,
+            Opcode::AddI(x) => xkk(0xF, x, 0x1E),
+            Opcode::LoadSpriteAddr(x) => xkk(0xF, x, 0x29),
+            Opcode::StoreBCD(x) => xkk(0xF, x, 0x33),
+            Opcode::RegDump(x) => xkk(0xF, x, 0x55),
+            Opcode::RegLoad(x) => xkk(0xF, x, 0x65),
+            Opcode::Undefined(opcode) => opcode,
+        };
+        word.to_be_bytes()
+    }
+
+    pub fn family(&self) -> Family {
+        match self {
+            Opcode::Jump(_)
+            | Opcode::Call(_)
+            | Opcode::Return
+            | Opcode::JumpV0(_)
+            | Opcode::SkipIfEqual(..)
+            | Opcode::SkipIfNotEqual(..)
+            | Opcode::SkipIfRegEqual(..)
+            | Opcode::SkipIfRegNotEqual(..) => Family::Flow,
+            Opcode::LoadByte(..)
+            | Opcode::AddByte(..)
+            | Opcode::LoadReg(..)
+            | Opcode::Or(..)
+            | Opcode::And(..)
+            | Opcode::Xor(..)
+            | Opcode::AddReg(..)
+            | Opcode::Sub(..)
+            | Opcode::ShiftRight(..)
+            | Opcode::SubN(..)
+            | Opcode::ShiftLeft(..)
+            | Opcode::Random(..) => Family::Register,
+            Opcode::SetIndex(_)
+            | Opcode::LoadLongI
+            | Opcode::AddI(_)
+            | Opcode::LoadSpriteAddr(_)
+            | Opcode::StoreBCD(_)
+            | Opcode::RegDump(_)
+            | Opcode::RegLoad(_) => Family::Memory,
+            Opcode::ClearDisplay
+            | Opcode::Draw(..)
+            | Opcode::ScrollUp(_)
+            | Opcode::LowRes
+            | Opcode::HighRes => Family::Display,
+            Opcode::SkipIfKeyPressed(_)
+            | Opcode::SkipIfKeyNotPressed(_)
+            | Opcode::WaitForKey(_) => Family::Input,
+            Opcode::LoadDelayTimer(_) | Opcode::SetDelayTimer(_) | Opcode::SetSoundTimer(_) => {
+                Family::Timer
+            }
+            Opcode::SysAddr(_) | Opcode::Undefined(_) => Family::System,
+        }
+    }
+
+    // The instruction's encoding with its operands as letters, such as DXYN, which names the
+    // instruction regardless of operands.
+    pub fn pattern(&self) -> &'static str {
+        match self {
+            Opcode::ClearDisplay => "00E0",
+            Opcode::Return => "00EE",
+            Opcode::ScrollUp(_) => "00DN",
+            Opcode::LowRes => "00FE",
+            Opcode::HighRes => "00FF",
+            Opcode::SysAddr(_) => "0NNN",
+            Opcode::Jump(_) => "1NNN",
+            Opcode::Call(_) => "2NNN",
+            Opcode::SkipIfEqual(..) => "3XNN",
+            Opcode::SkipIfNotEqual(..) => "4XNN",
+            Opcode::SkipIfRegEqual(..) => "5XY0",
+            Opcode::LoadByte(..) => "6XNN",
+            Opcode::AddByte(..) => "7XNN",
+            Opcode::LoadReg(..) => "8XY0",
+            Opcode::Or(..) => "8XY1",
+            Opcode::And(..) => "8XY2",
+            Opcode::Xor(..) => "8XY3",
+            Opcode::AddReg(..) => "8XY4",
+            Opcode::Sub(..) => "8XY5",
+            Opcode::ShiftRight(..) => "8XY6",
+            Opcode::SubN(..) => "8XY7",
+            Opcode::ShiftLeft(..) => "8XYE",
+            Opcode::SkipIfRegNotEqual(..) => "9XY0",
+            Opcode::SetIndex(_) => "ANNN",
+            Opcode::JumpV0(_) => "BNNN",
+            Opcode::Random(..) => "CXNN",
+            Opcode::Draw(..) => "DXYN",
+            Opcode::SkipIfKeyPressed(_) => "EX9E",
+            Opcode::SkipIfKeyNotPressed(_) => "EXA1",
+            Opcode::LoadDelayTimer(_) => "FX07",
+            Opcode::WaitForKey(_) => "FX0A",
+            Opcode::SetDelayTimer(_) => "FX15",
+            Opcode::SetSoundTimer(_) => "FX18",
+            Opcode::LoadLongI => "F000",
+            Opcode::AddI(_) => "FX1E",
+            Opcode::LoadSpriteAddr(_) => "FX29",
+            Opcode::StoreBCD(_) => "FX33",
+            Opcode::RegDump(_) => "FX55",
+            Opcode::RegLoad(_) => "FX65",
+            Opcode::Undefined(_) => "????",
         }
     }
+
+    // Whether the emulator can run the instruction. Machine code routines called through 0NNN
+    // are ignored, and undefined opcodes are skipped or rejected.
+    pub fn is_supported(&self) -> bool {
+        !matches!(self, Opcode::SysAddr(_) | Opcode::Undefined(_))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::chip8::memory::MEMORY_SIZE;
 
     #[test]
     fn test_opcode_from_bytes_clear_display() {
-        let opcode = Opcode::from_bytes(0x00, 0xE0);
+        let opcode = Opcode::from_bytes(0x00, 0xE0, MEMORY_SIZE);
 
         assert!(opcode.is_ok(), "{:?}", opcode);
         assert_eq!(opcode.unwrap(), Opcode::ClearDisplay);
@@ -201,7 +381,7 @@ mod tests {
 
     #[test]
     fn test_opcode_from_bytes_return() {
-        let opcode = Opcode::from_bytes(0x00, 0xEE);
+        let opcode = Opcode::from_bytes(0x00, 0xEE, MEMORY_SIZE);
 
         assert!(opcode.is_ok(), "{:?}", opcode);
         assert_eq!(opcode.unwrap(), Opcode::Return);
@@ -209,15 +389,44 @@ mod tests {
 
     #[test]
     fn test_opcode_from_bytes_sys_addr() {
-        let opcode = Opcode::from_bytes(0x01, 0x23);
+        let opcode = Opcode::from_bytes(0x01, 0x23, MEMORY_SIZE);
 
         assert!(opcode.is_ok(), "{:?}", opcode);
         assert_eq!(opcode.unwrap(), Opcode::SysAddr(0x123));
     }
 
+    #[test]
+    fn test_opcode_from_bytes_resolution() {
+        assert_eq!(
+            Opcode::from_bytes(0x00, 0xFE, MEMORY_SIZE),
+            Ok(Opcode::LowRes)
+        );
+        assert_eq!(
+            Opcode::from_bytes(0x00, 0xFF, MEMORY_SIZE),
+            Ok(Opcode::HighRes)
+        );
+        assert_eq!(
+            Opcode::from_bytes(0x00, 0xFD, MEMORY_SIZE),
+            Ok(Opcode::SysAddr(0x0FD))
+        );
+    }
+
+    #[test]
+    fn test_opcode_from_bytes_scroll_up() {
+        assert_eq!(
+            Opcode::from_bytes(0x00, 0xD3, MEMORY_SIZE),
+            Ok(Opcode::ScrollUp(0x3))
+        );
+        // Only 00DN scrolls; the rest of 0NNN is still a machine code call.
+        assert_eq!(
+            Opcode::from_bytes(0x01, 0xD3, MEMORY_SIZE),
+            Ok(Opcode::SysAddr(0x1D3))
+        );
+    }
+
     #[test]
     fn test_opcode_from_bytes_jump() {
-        let opcode = Opcode::from_bytes(0x12, 0x34);
+        let opcode = Opcode::from_bytes(0x12, 0x34, MEMORY_SIZE);
 
         assert!(opcode.is_ok(), "{:?}", opcode);
         assert_eq!(opcode.unwrap(), Opcode::Jump(0x234));
@@ -225,14 +434,14 @@ mod tests {
 
     #[test]
     fn test_opcode_from_bytes_call() {
-        let opcode = Opcode::from_bytes(0x23, 0x45);
+        let opcode = Opcode::from_bytes(0x23, 0x45, MEMORY_SIZE);
         assert!(opcode.is_ok(), "{:?}", opcode);
         assert_eq!(opcode.unwrap(), Opcode::Call(0x345));
     }
 
     #[test]
     fn test_opcode_from_bytes_skip_if_equal() {
-        let opcode = Opcode::from_bytes(0x30, 0x12);
+        let opcode = Opcode::from_bytes(0x30, 0x12, MEMORY_SIZE);
 
         assert!(opcode.is_ok(), "{:?}", opcode);
         assert_eq!(opcode.unwrap(), Opcode::SkipIfEqual(0x0, 0x12));
@@ -240,7 +449,7 @@ mod tests {
 
     #[test]
     fn test_opcode_from_bytes_skip_if_not_equal() {
-        let opcode = Opcode::from_bytes(0x40, 0x34);
+        let opcode = Opcode::from_bytes(0x40, 0x34, MEMORY_SIZE);
 
         assert!(opcode.is_ok(), "{:?}", opcode);
         assert_eq!(opcode.unwrap(), Opcode::SkipIfNotEqual(0x0, 0x34));
@@ -248,7 +457,7 @@ mod tests {
 
     #[test]
     fn test_opcode_from_bytes_skip_if_reg_equal() {
-        let opcode = Opcode::from_bytes(0x50, 0x40);
+        let opcode = Opcode::from_bytes(0x50, 0x40, MEMORY_SIZE);
 
         assert!(opcode.is_ok(), "{:?}", opcode);
         assert_eq!(opcode.unwrap(), Opcode::SkipIfRegEqual(0x0, 0x4));
@@ -256,7 +465,7 @@ mod tests {
 
     #[test]
     fn test_opcode_from_bytes_load_byte() {
-        let opcode = Opcode::from_bytes(0x60, 0x23);
+        let opcode = Opcode::from_bytes(0x60, 0x23, MEMORY_SIZE);
 
         assert!(opcode.is_ok(), "{:?}", opcode);
         assert_eq!(opcode.unwrap(), Opcode::LoadByte(0x0, 0x23));
@@ -264,7 +473,7 @@ mod tests {
 
     #[test]
     fn test_opcode_from_bytes_add_byte() {
-        let opcode = Opcode::from_bytes(0x70, 0x45);
+        let opcode = Opcode::from_bytes(0x70, 0x45, MEMORY_SIZE);
 
         assert!(opcode.is_ok(), "{:?}", opcode);
         assert_eq!(opcode.unwrap(), Opcode::AddByte(0x0, 0x45));
@@ -272,7 +481,7 @@ mod tests {
 
     #[test]
     fn test_opcode_from_bytes_load_reg() {
-        let opcode = Opcode::from_bytes(0x80, 0x10);
+        let opcode = Opcode::from_bytes(0x80, 0x10, MEMORY_SIZE);
 
         assert!(opcode.is_ok(), "{:?}", opcode);
         assert_eq!(opcode.unwrap(), Opcode::LoadReg(0x0, 0x1));
@@ -280,7 +489,7 @@ mod tests {
 
     #[test]
     fn test_opcode_from_bytes_or() {
-        let opcode = Opcode::from_bytes(0x80, 0x21);
+        let opcode = Opcode::from_bytes(0x80, 0x21, MEMORY_SIZE);
 
         assert!(opcode.is_ok(), "{:?}", opcode);
         assert_eq!(opcode.unwrap(), Opcode::Or(0x0, 0x2));
@@ -288,7 +497,7 @@ mod tests {
 
     #[test]
     fn test_opcode_from_str_and() {
-        let opcode = Opcode::from_bytes(0x81, 0x32);
+        let opcode = Opcode::from_bytes(0x81, 0x32, MEMORY_SIZE);
 
         assert!(opcode.is_ok(), "{:?}", opcode);
         assert_eq!(opcode.unwrap(), Opcode::And(0x1, 0x3));
@@ -296,7 +505,7 @@ mod tests {
 
     #[test]
     fn test_opcode_from_bytes_xor() {
-        let opcode = Opcode::from_bytes(0x80, 0x23);
+        let opcode = Opcode::from_bytes(0x80, 0x23, MEMORY_SIZE);
 
         assert!(opcode.is_ok(), "{:?}", opcode);
         assert_eq!(opcode.unwrap(), Opcode::Xor(0x0, 0x2));
@@ -304,7 +513,7 @@ mod tests {
 
     #[test]
     fn test_opcode_from_bytes_add_reg() {
-        let opcode = Opcode::from_bytes(0x80, 0x34);
+        let opcode = Opcode::from_bytes(0x80, 0x34, MEMORY_SIZE);
 
         assert!(opcode.is_ok(), "{:?}", opcode);
         assert_eq!(opcode.unwrap(), Opcode::AddReg(0x0, 0x3));
@@ -312,7 +521,7 @@ mod tests {
 
     #[test]
     fn test_opcode_from_bytes_add_sub() {
-        let opcode = Opcode::from_bytes(0x80, 0x45);
+        let opcode = Opcode::from_bytes(0x80, 0x45, MEMORY_SIZE);
 
         assert!(opcode.is_ok(), "{:?}", opcode);
         assert_eq!(opcode.unwrap(), Opcode::Sub(0x0, 0x4));
@@ -320,15 +529,15 @@ mod tests {
 
     #[test]
     fn test_opcode_from_bytes_shr() {
-        let opcode = Opcode::from_bytes(0x80, 0x56);
+        let opcode = Opcode::from_bytes(0x80, 0x56, MEMORY_SIZE);
 
         assert!(opcode.is_ok(), "{:?}", opcode);
-        assert_eq!(opcode.unwrap(), Opcode::ShiftRight(0x0));
+        assert_eq!(opcode.unwrap(), Opcode::ShiftRight(0x0, 0x5));
     }
 
     #[test]
     fn test_opcode_from_bytes_subn() {
-        let opcode = Opcode::from_bytes(0x80, 0x67);
+        let opcode = Opcode::from_bytes(0x80, 0x67, MEMORY_SIZE);
 
         assert!(opcode.is_ok(), "{:?}", opcode);
         assert_eq!(opcode.unwrap(), Opcode::SubN(0x0, 0x6));
@@ -336,15 +545,15 @@ mod tests {
 
     #[test]
     fn test_opcode_from_bytes_shift_left() {
-        let opcode = Opcode::from_bytes(0x80, 0x8E);
+        let opcode = Opcode::from_bytes(0x80, 0x8E, MEMORY_SIZE);
 
         assert!(opcode.is_ok(), "{:?}", opcode);
-        assert_eq!(opcode.unwrap(), Opcode::ShiftLeft(0x0));
+        assert_eq!(opcode.unwrap(), Opcode::ShiftLeft(0x0, 0x8));
     }
 
     #[test]
     fn test_opcode_from_bytes_skip_if_reg_not_equal() {
-        let opcode = Opcode::from_bytes(0x90, 0x50);
+        let opcode = Opcode::from_bytes(0x90, 0x50, MEMORY_SIZE);
 
         assert!(opcode.is_ok(), "{:?}", opcode);
         assert_eq!(opcode.unwrap(), Opcode::SkipIfRegNotEqual(0x0, 0x5));
@@ -352,7 +561,7 @@ mod tests {
 
     #[test]
     fn test_opcode_from_bytes_set_index() {
-        let opcode = Opcode::from_bytes(0xA0, 0x12);
+        let opcode = Opcode::from_bytes(0xA0, 0x12, MEMORY_SIZE);
 
         assert!(opcode.is_ok(), "{:?}", opcode);
         assert_eq!(opcode.unwrap(), Opcode::SetIndex(0x012));
@@ -360,7 +569,7 @@ mod tests {
 
     #[test]
     fn test_opcode_from_bytes_jump_v0() {
-        let opcode = Opcode::from_bytes(0xB0, 0x12);
+        let opcode = Opcode::from_bytes(0xB0, 0x12, MEMORY_SIZE);
 
         assert!(opcode.is_ok(), "{:?}", opcode);
         assert_eq!(opcode.unwrap(), Opcode::JumpV0(0x012));
@@ -368,7 +577,7 @@ mod tests {
 
     #[test]
     fn test_opcode_from_bytes_random() {
-        let opcode = Opcode::from_bytes(0xC0, 0x12);
+        let opcode = Opcode::from_bytes(0xC0, 0x12, MEMORY_SIZE);
 
         assert!(opcode.is_ok(), "{:?}", opcode);
         assert_eq!(opcode.unwrap(), Opcode::Random(0x0, 0x12));
@@ -376,7 +585,7 @@ mod tests {
 
     #[test]
     fn test_opcode_from_bytes_draw() {
-        let opcode = Opcode::from_bytes(0xD0, 0x12);
+        let opcode = Opcode::from_bytes(0xD0, 0x12, MEMORY_SIZE);
 
         assert!(opcode.is_ok(), "{:?}", opcode);
         assert_eq!(opcode.unwrap(), Opcode::Draw(0x0, 0x1, 0x2));
@@ -384,7 +593,7 @@ mod tests {
 
     #[test]
     fn test_opcode_from_bytes_skip_if_key_pressed() {
-        let opcode = Opcode::from_bytes(0xE0, 0x9E);
+        let opcode = Opcode::from_bytes(0xE0, 0x9E, MEMORY_SIZE);
 
         assert!(opcode.is_ok(), "{:?}", opcode);
         assert_eq!(opcode.unwrap(), Opcode::SkipIfKeyPressed(0x0));
@@ -392,7 +601,7 @@ mod tests {
 
     #[test]
     fn test_opcode_from_bytes_skip_if_key_not_pressed() {
-        let opcode = Opcode::from_bytes(0xE0, 0xA1);
+        let opcode = Opcode::from_bytes(0xE0, 0xA1, MEMORY_SIZE);
 
         assert!(opcode.is_ok(), "{:?}", opcode);
         assert_eq!(opcode.unwrap(), Opcode::SkipIfKeyNotPressed(0x0));
@@ -400,7 +609,7 @@ mod tests {
 
     #[test]
     fn test_opcode_from_bytes_load_delay_timer() {
-        let opcode = Opcode::from_bytes(0xF1, 0x07);
+        let opcode = Opcode::from_bytes(0xF1, 0x07, MEMORY_SIZE);
 
         assert!(opcode.is_ok(), "{:?}", opcode);
         assert_eq!(opcode.unwrap(), Opcode::LoadDelayTimer(0x1));
@@ -408,7 +617,7 @@ mod tests {
 
     #[test]
     fn test_opcode_from_bytes_wait_for_key() {
-        let opcode = Opcode::from_bytes(0xF1, 0x0A);
+        let opcode = Opcode::from_bytes(0xF1, 0x0A, MEMORY_SIZE);
 
         assert!(opcode.is_ok(), "{:?}", opcode);
         assert_eq!(opcode.unwrap(), Opcode::WaitForKey(0x1));
@@ -416,7 +625,7 @@ mod tests {
 
     #[test]
     fn test_opcode_from_bytes_set_delay_timer() {
-        let opcode = Opcode::from_bytes(0xF0, 0x15);
+        let opcode = Opcode::from_bytes(0xF0, 0x15, MEMORY_SIZE);
 
         assert!(opcode.is_ok(), "{:?}", opcode);
         assert_eq!(opcode.unwrap(), Opcode::SetDelayTimer(0x0));
@@ -424,7 +633,7 @@ mod tests {
 
     #[test]
     fn test_opcode_from_bytes_set_sound_timer() {
-        let opcode = Opcode::from_bytes(0xF0, 0x18);
+        let opcode = Opcode::from_bytes(0xF0, 0x18, MEMORY_SIZE);
 
         assert!(opcode.is_ok(), "{:?}", opcode);
         assert_eq!(opcode.unwrap(), Opcode::SetSoundTimer(0x0));
@@ -432,7 +641,7 @@ mod tests {
 
     #[test]
     fn test_opcode_from_bytes_add_i() {
-        let opcode = Opcode::from_bytes(0xF0, 0x1E);
+        let opcode = Opcode::from_bytes(0xF0, 0x1E, MEMORY_SIZE);
 
         assert!(opcode.is_ok(), "{:?}", opcode);
         assert_eq!(opcode.unwrap(), Opcode::AddI(0x0));
@@ -440,7 +649,7 @@ mod tests {
 
     #[test]
     fn test_opcode_from_bytes_load_sprite_addr() {
-        let opcode = Opcode::from_bytes(0xF0, 0x29);
+        let opcode = Opcode::from_bytes(0xF0, 0x29, MEMORY_SIZE);
 
         assert!(opcode.is_ok(), "{:?}", opcode);
         assert_eq!(opcode.unwrap(), Opcode::LoadSpriteAddr(0x0));
@@ -448,7 +657,7 @@ mod tests {
 
     #[test]
     fn test_opcode_from_bytes_store_bcd() {
-        let opcode = Opcode::from_bytes(0xF0, 0x33);
+        let opcode = Opcode::from_bytes(0xF0, 0x33, MEMORY_SIZE);
 
         assert!(opcode.is_ok(), "{:?}", opcode);
         assert_eq!(opcode.unwrap(), Opcode::StoreBCD(0x0));
@@ -456,7 +665,7 @@ mod tests {
 
     #[test]
     fn test_opcode_from_bytes_reg_dump() {
-        let opcode = Opcode::from_bytes(0xF0, 0x55);
+        let opcode = Opcode::from_bytes(0xF0, 0x55, MEMORY_SIZE);
 
         assert!(opcode.is_ok(), "{:?}", opcode);
         assert_eq!(opcode.unwrap(), Opcode::RegDump(0x0));
@@ -464,25 +673,129 @@ mod tests {
 
     #[test]
     fn test_opcode_from_bytes_reg_load() {
-        let opcode = Opcode::from_bytes(0xF0, 0x65);
+        let opcode = Opcode::from_bytes(0xF0, 0x65, MEMORY_SIZE);
 
         assert!(opcode.is_ok(), "{:?}", opcode);
         assert_eq!(opcode.unwrap(), Opcode::RegLoad(0x0));
     }
 
     #[test]
-    fn test_opcode_from_bytes_with_invalid_opcode() {
-        let opcode = Opcode::from_bytes(0xFA, 0xBC);
+    fn test_opcode_from_bytes_load_long_i() {
+        let opcode = Opcode::from_bytes(0xF0, 0x00, MEMORY_SIZE);
+
+        assert!(opcode.is_ok(), "{:?}", opcode);
+        assert_eq!(opcode.unwrap(), Opcode::LoadLongI);
+    }
+
+    #[test]
+    fn test_opcode_from_bytes_with_undefined_opcode() {
+        let opcode = Opcode::from_bytes(0xFA, 0xBC, MEMORY_SIZE);
+
+        assert!(opcode.is_ok(), "{:?}", opcode);
+        assert_eq!(opcode.unwrap(), Opcode::Undefined(0xFABC));
+    }
+
+    #[test]
+    fn test_opcode_to_bytes_round_trips() {
+        let ops = [
+            Opcode::ClearDisplay,
+            Opcode::Return,
+            Opcode::ScrollUp(0x4),
+            Opcode::LowRes,
+            Opcode::HighRes,
+            Opcode::SysAddr(0x123),
+            Opcode::Jump(0x234),
+            Opcode::Call(0x345),
+            Opcode::SkipIfEqual(0x1, 0x23),
+            Opcode::SkipIfNotEqual(0x2, 0x34),
+            Opcode::SkipIfRegEqual(0x3, 0x4),
+            Opcode::LoadByte(0x4, 0x56),
+            Opcode::AddByte(0x5, 0x67),
+            Opcode::LoadReg(0x6, 0x7),
+            Opcode::Or(0x7, 0x8),
+            Opcode::And(0x8, 0x9),
+            Opcode::Xor(0x9, 0xA),
+            Opcode::AddReg(0xA, 0xB),
+            Opcode::Sub(0xB, 0xC),
+            Opcode::ShiftRight(0xC, 0xD),
+            Opcode::SubN(0xD, 0xE),
+            Opcode::ShiftLeft(0xE, 0xF),
+            Opcode::SkipIfRegNotEqual(0xF, 0x0),
+            Opcode::SetIndex(0x456),
+            Opcode::JumpV0(0x567),
+            Opcode::Random(0x1, 0x0F),
+            Opcode::Draw(0x2, 0x3, 0x5),
+            Opcode::SkipIfKeyPressed(0x3),
+            Opcode::SkipIfKeyNotPressed(0x4),
+            Opcode::LoadDelayTimer(0x5),
+            Opcode::WaitForKey(0x6),
+            Opcode::SetDelayTimer(0x7),
+            Opcode::SetSoundTimer(0x8),
+            Opcode::LoadLongI,
+            Opcode::AddI(0x9),
+            Opcode::LoadSpriteAddr(0xA),
+            Opcode::StoreBCD(0xB),
+            Opcode::RegDump(0xC),
+            Opcode::RegLoad(0xD),
+            Opcode::Undefined(0xFABC),
+        ];
+
+        for op in ops {
+            let [msb, lsb] = op.to_bytes();
+            assert_eq!(Opcode::from_bytes(msb, lsb, MEMORY_SIZE), Ok(op));
+        }
+    }
 
-        assert!(opcode.is_err(), "{:?}", opcode);
-        assert_eq!(opcode.unwrap_err(), OpcodeError::InvalidOpcode(0xFABC));
+    #[test]
+    fn test_assemble_opcodes() {
+        assert_eq!(
+            assemble_opcodes(&[
+                Opcode::LoadByte(0x0, 0x2A),
+                Opcode::LoadLongI,
+                Opcode::Jump(0x200)
+            ]),
+            vec![0x60, 0x2A, 0xF0, 0x00, 0x12, 0x00]
+        );
+        assert_eq!(assemble_opcodes(&[]), vec![]);
+    }
+
+    #[test]
+    fn test_opcode_pattern() {
+        assert_eq!(Opcode::Draw(0x1, 0x2, 0x5).pattern(), "DXYN");
+        assert_eq!(Opcode::ShiftLeft(0x0, 0x1).pattern(), "8XYE");
+        assert_eq!(Opcode::Undefined(0xFABC).pattern(), "????");
+    }
+
+    #[test]
+    fn test_opcode_family() {
+        assert_eq!(Opcode::SkipIfRegEqual(0x0, 0x1).family(), Family::Flow);
+        assert_eq!(Opcode::ShiftLeft(0x0, 0x1).family(), Family::Register);
+        assert_eq!(Opcode::LoadLongI.family(), Family::Memory);
+        assert_eq!(Opcode::Draw(0x0, 0x1, 0x5).family(), Family::Display);
+        assert_eq!(Opcode::WaitForKey(0x0).family(), Family::Input);
+        assert_eq!(Opcode::SetSoundTimer(0x0).family(), Family::Timer);
+        assert_eq!(Opcode::Undefined(0xFABC).family(), Family::System);
+    }
+
+    #[test]
+    fn test_opcode_is_supported() {
+        assert!(Opcode::Draw(0x0, 0x1, 0x5).is_supported());
+        assert!(!Opcode::SysAddr(0x300).is_supported());
+        assert!(!Opcode::Undefined(0xFABC).is_supported());
     }
 
     #[test]
     fn test_opcode_from_bytes_with_invalid_address() {
-        let opcode = Opcode::from_bytes(0x2F, 0xFF);
+        let opcode = Opcode::from_bytes(0x29, 0x00, 0x800);
 
         assert!(opcode.is_err(), "{:?}", opcode);
-        assert_eq!(opcode.unwrap_err(), OpcodeError::InvalidAddress(0xFFF));
+        assert_eq!(opcode.unwrap_err(), OpcodeError::InvalidAddress(0x900));
+    }
+
+    #[test]
+    fn test_opcode_from_bytes_with_highest_address() {
+        let opcode = Opcode::from_bytes(0x2F, 0xFF, MEMORY_SIZE);
+
+        assert_eq!(opcode, Ok(Opcode::Call(0xFFF)));
     }
 }
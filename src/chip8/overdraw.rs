@@ -0,0 +1,52 @@
+// Counts pixels turned off and back on again within a frame, which is what a sprite that's erased
+// and redrawn looks like on screen: flicker. Displays are compared packed, as
+// Display::pack_into lays them out.
+#[derive(Debug, Default)]
+pub struct Overdraw {
+    // Pixels turned off since the frame started and not yet turned back on.
+    erased: Vec<u8>,
+    count: u32,
+}
+
+impl Overdraw {
+    pub fn new() -> Self {
+        Overdraw::default()
+    }
+
+    // Call with the display before and after anything that changed it.
+    pub fn record(&mut self, before: &[u8], after: &[u8]) {
+        if self.erased.len() != before.len() {
+            self.erased = vec![0; before.len()];
+        }
+        for ((erased, &before), &after) in self.erased.iter_mut().zip(before).zip(after) {
+            let lit = !before & after;
+            self.count += (lit & *erased).count_ones();
+            *erased = (*erased & !lit) | (before & !after);
+        }
+    }
+
+    // The count for the frame that's ending, starting the next one from 0.
+    pub fn end_frame(&mut self) -> u32 {
+        self.erased.fill(0);
+        std::mem::take(&mut self.count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overdraw_counts_pixels_lit_again() {
+        let mut overdraw = Overdraw::new();
+
+        overdraw.record(&[0b0000, 0], &[0b1111, 0]);
+        overdraw.record(&[0b1111, 0], &[0b0011, 0]);
+        overdraw.record(&[0b0011, 0], &[0b1011, 0b1]);
+
+        assert_eq!(overdraw.end_frame(), 1);
+        // Pixels erased in an earlier frame don't count.
+        overdraw.record(&[0b1011, 1], &[0b1111, 1]);
+        assert_eq!(overdraw.end_frame(), 0);
+    }
+}
@@ -0,0 +1,154 @@
+use std::time::Duration;
+
+const NANOS_PER_SECOND: u128 = 1_000_000_000;
+pub const TIMER_HZ: u64 = 60;
+
+#[derive(Debug, PartialEq)]
+pub struct Budget {
+    pub instructions: u64,
+    pub timer_ticks: u64,
+    pub dropped_instructions: u64,
+    pub dropped_timer_ticks: u64,
+}
+
+// Converts the wall-clock time elapsed between wakeups into the number of instructions and timer
+// ticks that are owed. Fractions are carried over to the next wakeup. With a limit of
+// `max_frames` 60 Hz frames, debt beyond it is discarded so that a long stall doesn't block the
+// loop catching up; the budget says how much was dropped.
+#[derive(Debug, Clone)]
+pub struct Pacer {
+    ops_per_second: u64,
+    max_frames: Option<u64>,
+    instruction_carry: u128,
+    timer_carry: u128,
+}
+
+impl Pacer {
+    pub fn new(ops_per_second: u64, max_frames: Option<u64>) -> Pacer {
+        Pacer {
+            ops_per_second,
+            max_frames,
+            instruction_carry: 0,
+            timer_carry: 0,
+        }
+    }
+
+    pub fn max_instructions(&self) -> u64 {
+        self.max_frames
+            .map_or(u64::MAX, |frames| self.ops_per_second * frames / TIMER_HZ)
+    }
+
+    pub fn max_timer_ticks(&self) -> u64 {
+        self.max_frames.unwrap_or(u64::MAX)
+    }
+
+    pub fn budget(&mut self, elapsed: Duration) -> Budget {
+        let (max_instructions, max_timer_ticks) = (self.max_instructions(), self.max_timer_ticks());
+        let (instructions, dropped_instructions) = owed(
+            &mut self.instruction_carry,
+            elapsed,
+            self.ops_per_second,
+            max_instructions,
+        );
+        let (timer_ticks, dropped_timer_ticks) =
+            owed(&mut self.timer_carry, elapsed, TIMER_HZ, max_timer_ticks);
+
+        Budget {
+            instructions,
+            timer_ticks,
+            dropped_instructions,
+            dropped_timer_ticks,
+        }
+    }
+}
+
+// Returns the (clamped, dropped) number of events with the given frequency that fit into the
+// elapsed time. The carry is kept in "events * nanoseconds" to avoid rounding drift.
+fn owed(carry: &mut u128, elapsed: Duration, hz: u64, max: u64) -> (u64, u64) {
+    let total = *carry + elapsed.as_nanos() * hz as u128;
+    let count = total / NANOS_PER_SECOND;
+
+    if count > max as u128 {
+        *carry = 0;
+        (max, (count - max as u128) as u64)
+    } else {
+        *carry = total % NANOS_PER_SECOND;
+        (count as u64, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_single_frame() {
+        let mut pacer = Pacer::new(600, Some(4));
+
+        let budget = pacer.budget(Duration::from_micros(16_667));
+
+        assert_eq!(budget.instructions, 10);
+        assert_eq!(budget.timer_ticks, 1);
+        assert_eq!(budget.dropped_instructions, 0);
+        assert_eq!(budget.dropped_timer_ticks, 0);
+    }
+
+    #[test]
+    fn test_budget_carries_fractions() {
+        let mut pacer = Pacer::new(600, Some(4));
+
+        // 1.5ms is 0.9 of an instruction, so the first call yields nothing.
+        assert_eq!(pacer.budget(Duration::from_micros(1_500)).instructions, 0);
+        assert_eq!(pacer.budget(Duration::from_micros(1_500)).instructions, 1);
+    }
+
+    #[test]
+    fn test_budget_clamped_after_long_stall() {
+        let mut pacer = Pacer::new(600, Some(4));
+
+        let budget = pacer.budget(Duration::from_secs(10));
+
+        assert_eq!(budget.instructions, pacer.max_instructions());
+        assert_eq!(budget.timer_ticks, pacer.max_timer_ticks());
+        assert_eq!(budget.dropped_instructions, 6000 - pacer.max_instructions());
+        assert_eq!(budget.dropped_timer_ticks, 600 - pacer.max_timer_ticks());
+    }
+
+    // At 700 ops/s four frames are 46.67 instructions, so a stall just past them drops
+    // instructions while every timer tick still fits.
+    #[test]
+    fn test_budget_drops_instructions_alone() {
+        let mut pacer = Pacer::new(700, Some(4));
+
+        let budget = pacer.budget(Duration::from_millis(70));
+
+        assert_eq!(budget.instructions, 46);
+        assert_eq!(budget.timer_ticks, 4);
+        assert_eq!(budget.dropped_instructions, 3);
+        assert_eq!(budget.dropped_timer_ticks, 0);
+    }
+
+    #[test]
+    fn test_budget_unclamped_without_a_limit() {
+        let mut pacer = Pacer::new(600, None);
+
+        let budget = pacer.budget(Duration::from_secs(10));
+
+        assert_eq!(budget.instructions, 6000);
+        assert_eq!(budget.timer_ticks, 600);
+        assert_eq!(budget.dropped_instructions, 0);
+        assert_eq!(budget.dropped_timer_ticks, 0);
+    }
+
+    #[test]
+    fn test_budget_debt_discarded_after_long_stall() {
+        let mut pacer = Pacer::new(600, Some(4));
+        pacer.budget(Duration::from_secs(10));
+
+        let budget = pacer.budget(Duration::from_millis(5));
+
+        assert_eq!(budget.instructions, 3);
+        assert_eq!(budget.timer_ticks, 0);
+        assert_eq!(budget.dropped_instructions, 0);
+    }
+}
@@ -0,0 +1,93 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+// Time spent on one kind of instruction, named by its pattern such as DXYN.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OpcodeProfile {
+    pub pattern: &'static str,
+    pub count: u64,
+    // Wall-clock time spent decoding and executing them.
+    pub time: Duration,
+}
+
+// Accumulates time per instruction pattern.
+#[derive(Debug, Default)]
+pub struct Profile {
+    entries: BTreeMap<&'static str, (u64, Duration)>,
+}
+
+impl Profile {
+    pub fn new() -> Self {
+        Profile::default()
+    }
+
+    pub fn record(&mut self, pattern: &'static str, time: Duration) {
+        let (count, total) = self.entries.entry(pattern).or_default();
+        *count += 1;
+        *total += time;
+    }
+
+    // The patterns executed, most time first.
+    pub fn report(&self) -> Vec<OpcodeProfile> {
+        let mut report: Vec<OpcodeProfile> = self
+            .entries
+            .iter()
+            .map(|(&pattern, &(count, time))| OpcodeProfile {
+                pattern,
+                count,
+                time,
+            })
+            .collect();
+        report.sort_by_key(|entry| std::cmp::Reverse(entry.time));
+        report
+    }
+}
+
+// One line per pattern, most time first.
+pub fn profile_text(report: &[OpcodeProfile]) -> String {
+    report
+        .iter()
+        .map(|entry| {
+            let average = entry.time.as_nanos() / u128::from(entry.count.max(1));
+            let average = Duration::from_nanos(average as u64);
+            format!(
+                "{}: {} executed, {:?} total, {:?} each",
+                entry.pattern, entry.count, entry.time, average
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_report() {
+        let mut profile = Profile::new();
+        profile.record("DXYN", Duration::from_micros(5));
+        profile.record("6XNN", Duration::from_micros(1));
+        profile.record("DXYN", Duration::from_micros(3));
+
+        assert_eq!(
+            profile.report(),
+            vec![
+                OpcodeProfile {
+                    pattern: "DXYN",
+                    count: 2,
+                    time: Duration::from_micros(8)
+                },
+                OpcodeProfile {
+                    pattern: "6XNN",
+                    count: 1,
+                    time: Duration::from_micros(1)
+                },
+            ]
+        );
+        assert_eq!(
+            profile_text(&profile.report()),
+            "DXYN: 2 executed, 8µs total, 4µs each\n6XNN: 1 executed, 1µs total, 1µs each"
+        );
+    }
+}
@@ -0,0 +1,313 @@
+use super::memory::MEMORY_SIZE;
+use std::str::FromStr;
+
+// Platforms whose quirks Quirks::preset knows about.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Target {
+    // The original COSMAC VIP interpreter.
+    Chip8,
+    // CHIP-48 on the HP 48, which SUPER-CHIP grew out of. Many ROM collections label games with
+    // it.
+    Chip48,
+    // SUPER-CHIP 1.1 on the HP 48.
+    SuperChipLegacy,
+    // SUPER-CHIP as implemented by modern interpreters such as Octo, without the display wait.
+    SuperChipModern,
+    XoChip,
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "chip8" => Ok(Target::Chip8),
+            "chip48" => Ok(Target::Chip48),
+            "schip-legacy" => Ok(Target::SuperChipLegacy),
+            "schip" => Ok(Target::SuperChipModern),
+            "xochip" => Ok(Target::XoChip),
+            _ => Err(format!("unknown target `{}`", value)),
+        }
+    }
+}
+
+impl Target {
+    pub fn name(self) -> &'static str {
+        match self {
+            Target::Chip8 => "chip8",
+            Target::Chip48 => "chip48",
+            Target::SuperChipLegacy => "schip-legacy",
+            Target::SuperChipModern => "schip",
+            Target::XoChip => "xochip",
+        }
+    }
+}
+
+// Where FX55 and FX65 leave I.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum LoadStoreIMode {
+    #[default]
+    Unchanged,
+    // I + X, as in SUPER-CHIP 1.0.
+    IncX,
+    // I + X + 1, past the last register stored or loaded, as on the COSMAC VIP.
+    IncXPlus1,
+}
+
+impl FromStr for LoadStoreIMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "unchanged" => Ok(LoadStoreIMode::Unchanged),
+            "inc-x" => Ok(LoadStoreIMode::IncX),
+            "inc-x-plus-1" => Ok(LoadStoreIMode::IncXPlus1),
+            _ => Err(format!("unknown load/store I mode `{}`", value)),
+        }
+    }
+}
+
+impl LoadStoreIMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            LoadStoreIMode::Unchanged => "unchanged",
+            LoadStoreIMode::IncX => "inc-x",
+            LoadStoreIMode::IncXPlus1 => "inc-x-plus-1",
+        }
+    }
+}
+
+// Behaviours that differ between CHIP-8 interpreters. The defaults are the behaviour this
+// emulator always had; Quirks::preset gives the documented behaviour of a specific platform.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quirks {
+    // FX1E sets VF to 1 when I overflows past the end of memory and to 0 otherwise, as the Amiga
+    // interpreter did. Spacefight 2091! relies on it.
+    pub addi_sets_vf: bool,
+    // Bytes of RAM. Addresses, I and pc are all bounded by it.
+    pub memory_size: usize,
+    // 8XY6 and 8XYE shift VY into VX instead of shifting VX in place.
+    pub shift_uses_vy: bool,
+    // Where FX55 and FX65 leave I.
+    pub load_store_i: LoadStoreIMode,
+    // BNNN jumps to XNN + VX instead of NNN + V0.
+    pub jump_uses_vx: bool,
+    // 8XY1, 8XY2 and 8XY3 reset VF to 0.
+    pub logic_resets_vf: bool,
+    // DXYN waits for the next 60 Hz frame, so a program draws at most one sprite per frame.
+    pub display_wait: bool,
+    // Sprites are cut off at the right and bottom edges instead of wrapping around. Their
+    // starting position always wraps.
+    pub clip_sprites: bool,
+    // Sprite rows past the end of memory are read from the start of memory instead of being
+    // blank.
+    pub sprite_reads_wrap: bool,
+    // The arithmetic and shift instructions write VF before the result, so when X is F the result
+    // ends up in VF rather than the flag. No documented platform does this.
+    pub vf_before_result: bool,
+    // 00FE and 00FF clear the display, as SUPER-CHIP does. Some interpreters keep what's drawn,
+    // and a few games count on it.
+    pub clear_on_resolution_switch: bool,
+    // FX0A stores the key once it's released rather than as soon as it's pressed, as the VIP
+    // did. Menus that open on FX0A then don't see the key that chose them still held. Off in
+    // every preset; it's asked for with --wait-for-key-release.
+    pub wait_for_key_on_release: bool,
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Quirks {
+            addi_sets_vf: false,
+            memory_size: MEMORY_SIZE,
+            shift_uses_vy: false,
+            load_store_i: LoadStoreIMode::Unchanged,
+            jump_uses_vx: false,
+            logic_resets_vf: false,
+            display_wait: false,
+            clip_sprites: false,
+            sprite_reads_wrap: false,
+            vf_before_result: false,
+            clear_on_resolution_switch: true,
+            wait_for_key_on_release: false,
+        }
+    }
+}
+
+impl Quirks {
+    // The values follow Timendus' quirks test suite. Flags not listed are off, except that every
+    // platform clears the display on a resolution switch:
+    //
+    //   target        shift-vy  load-store-i  jump-vx  vf-reset  display-wait  clip  memory
+    //   chip8         yes       inc-x-plus-1  no       yes       yes           yes   4K
+    //   chip48        no        inc-x         yes      no        no            yes   4K
+    //   schip-legacy  no        unchanged     yes      no        yes           yes   4K
+    //   schip         no        unchanged     yes      no        no            yes   4K
+    //   xochip        yes       inc-x-plus-1  no       no        no            no    64K
+    pub fn preset(target: Target) -> Quirks {
+        match target {
+            Target::Chip8 => Quirks {
+                shift_uses_vy: true,
+                load_store_i: LoadStoreIMode::IncXPlus1,
+                logic_resets_vf: true,
+                display_wait: true,
+                clip_sprites: true,
+                ..Quirks::default()
+            },
+            Target::Chip48 => Quirks {
+                load_store_i: LoadStoreIMode::IncX,
+                jump_uses_vx: true,
+                clip_sprites: true,
+                ..Quirks::default()
+            },
+            Target::SuperChipLegacy => Quirks {
+                jump_uses_vx: true,
+                display_wait: true,
+                clip_sprites: true,
+                ..Quirks::default()
+            },
+            Target::SuperChipModern => Quirks {
+                jump_uses_vx: true,
+                clip_sprites: true,
+                ..Quirks::default()
+            },
+            Target::XoChip => Quirks {
+                memory_size: 0x10000,
+                shift_uses_vy: true,
+                load_store_i: LoadStoreIMode::IncXPlus1,
+                ..Quirks::default()
+            },
+        }
+    }
+
+    // CHIP-48's quirks, for the many ROMs labelled with it.
+    pub fn chip48() -> Quirks {
+        Quirks::preset(Target::Chip48)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LoadStoreIMode::*;
+    use super::*;
+
+    #[test]
+    fn test_presets() {
+        // Every field spelled out, so that any change to a preset shows up here.
+        let presets = [
+            (
+                Target::Chip8,
+                Quirks {
+                    addi_sets_vf: false,
+                    memory_size: 0x1000,
+                    shift_uses_vy: true,
+                    load_store_i: IncXPlus1,
+                    jump_uses_vx: false,
+                    logic_resets_vf: true,
+                    display_wait: true,
+                    clip_sprites: true,
+                    sprite_reads_wrap: false,
+                    vf_before_result: false,
+                    clear_on_resolution_switch: true,
+                    wait_for_key_on_release: false,
+                },
+            ),
+            (
+                Target::Chip48,
+                Quirks {
+                    addi_sets_vf: false,
+                    memory_size: 0x1000,
+                    shift_uses_vy: false,
+                    load_store_i: IncX,
+                    jump_uses_vx: true,
+                    logic_resets_vf: false,
+                    display_wait: false,
+                    clip_sprites: true,
+                    sprite_reads_wrap: false,
+                    vf_before_result: false,
+                    clear_on_resolution_switch: true,
+                    wait_for_key_on_release: false,
+                },
+            ),
+            (
+                Target::SuperChipLegacy,
+                Quirks {
+                    addi_sets_vf: false,
+                    memory_size: 0x1000,
+                    shift_uses_vy: false,
+                    load_store_i: Unchanged,
+                    jump_uses_vx: true,
+                    logic_resets_vf: false,
+                    display_wait: true,
+                    clip_sprites: true,
+                    sprite_reads_wrap: false,
+                    vf_before_result: false,
+                    clear_on_resolution_switch: true,
+                    wait_for_key_on_release: false,
+                },
+            ),
+            (
+                Target::SuperChipModern,
+                Quirks {
+                    addi_sets_vf: false,
+                    memory_size: 0x1000,
+                    shift_uses_vy: false,
+                    load_store_i: Unchanged,
+                    jump_uses_vx: true,
+                    logic_resets_vf: false,
+                    display_wait: false,
+                    clip_sprites: true,
+                    sprite_reads_wrap: false,
+                    vf_before_result: false,
+                    clear_on_resolution_switch: true,
+                    wait_for_key_on_release: false,
+                },
+            ),
+            (
+                Target::XoChip,
+                Quirks {
+                    addi_sets_vf: false,
+                    memory_size: 0x10000,
+                    shift_uses_vy: true,
+                    load_store_i: IncXPlus1,
+                    jump_uses_vx: false,
+                    logic_resets_vf: false,
+                    display_wait: false,
+                    clip_sprites: false,
+                    sprite_reads_wrap: false,
+                    vf_before_result: false,
+                    clear_on_resolution_switch: true,
+                    wait_for_key_on_release: false,
+                },
+            ),
+        ];
+
+        for (target, quirks) in presets {
+            assert_eq!(Quirks::preset(target), quirks, "{:?}", target);
+        }
+        assert_eq!(Quirks::chip48(), presets[1].1);
+    }
+
+    #[test]
+    fn test_load_store_i_mode_from_str() {
+        for mode in [Unchanged, IncX, IncXPlus1] {
+            assert_eq!(mode.name().parse(), Ok(mode));
+        }
+        assert_eq!(
+            "inc-2x".parse::<LoadStoreIMode>(),
+            Err("unknown load/store I mode `inc-2x`".to_string())
+        );
+    }
+
+    #[test]
+    fn test_target_from_str() {
+        assert_eq!("schip-legacy".parse(), Ok(Target::SuperChipLegacy));
+        assert_eq!("chip48".parse(), Ok(Target::Chip48));
+        for target in [Target::Chip8, Target::SuperChipModern, Target::XoChip] {
+            assert_eq!(target.name().parse(), Ok(target));
+        }
+        assert_eq!(
+            "megachip".parse::<Target>(),
+            Err("unknown target `megachip`".to_string())
+        );
+    }
+}
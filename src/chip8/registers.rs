@@ -1,6 +1,6 @@
 const DATA_REGISTER_COUNT: usize = 16;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Hash)]
 pub struct Registers {
     v: [u8; DATA_REGISTER_COUNT],
     pub i: u16,
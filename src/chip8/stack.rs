@@ -4,9 +4,9 @@ pub enum StackError {
     StackUnderflow,
 }
 
-const STACK_SIZE: usize = 16;
+pub const STACK_SIZE: usize = 16;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Hash)]
 pub struct Stack {
     stack: [u16; STACK_SIZE], // Array to hold 16 levels of the stack
     sp: usize,                // Stack pointer to track the current level (0-15)
@@ -31,6 +31,16 @@ impl Stack {
         Ok(())
     }
 
+    // The return addresses pushed so far, oldest first
+    pub fn entries(&self) -> &[u16] {
+        &self.stack[..self.sp]
+    }
+
+    // The return addresses pushed so far, newest first: the order a debugger lists call frames in
+    pub fn frames(&self) -> impl Iterator<Item = u16> + '_ {
+        self.entries().iter().rev().copied()
+    }
+
     // Pops a value off the stack, if it's not empty
     pub fn pop(&mut self) -> Result<u16, StackError> {
         if self.sp == 0 {
@@ -76,6 +86,29 @@ mod tests {
         assert_eq!(stack.push(0x456), Err(StackError::StackOverflow));
     }
 
+    #[test]
+    fn test_entries() {
+        let mut stack = Stack::new();
+        assert_eq!(stack.entries(), &[] as &[u16]);
+
+        stack.push(0x123).unwrap();
+        stack.push(0x456).unwrap();
+        stack.pop().unwrap();
+
+        assert_eq!(stack.entries(), &[0x123]);
+    }
+
+    #[test]
+    fn test_frames() {
+        let mut stack = Stack::new();
+        assert_eq!(stack.frames().count(), 0);
+
+        stack.push(0x123).unwrap();
+        stack.push(0x456).unwrap();
+
+        assert_eq!(stack.frames().collect::<Vec<_>>(), vec![0x456, 0x123]);
+    }
+
     #[test]
     fn test_pop_empty_stack() {
         let mut stack = Stack::new();
@@ -1,4 +1,4 @@
-#[derive(Debug)]
+#[derive(Debug, Clone, Hash)]
 pub struct Timers {
     delay_timer: u8,
     sound_timer: u8,
@@ -0,0 +1,168 @@
+use crate::numfmt::{format_addr, format_word};
+use std::collections::BTreeMap;
+use std::fmt;
+
+// Questionable things a ROM did that the emulator recovered from. Each one has a strict option
+// that turns it into an error instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Violation {
+    UndefinedOpcode(u16),
+    // pc moved to the given odd address.
+    MisalignedPc(u16),
+    // Read of a byte that was never loaded or written.
+    UninitializedRead(u16),
+    // Write into the interpreter area below the program start.
+    WriteBelowProgram(u16),
+    // I moved past the end of memory.
+    IndexOverflow,
+    // A sprite starting at the given I had rows past the end of memory.
+    SpritePastMemory(u16),
+    // An FX65 starting at the given I had registers past the end of memory.
+    LoadPastMemory(u16),
+    // A 0NNN call of the machine code routine at the given address, which was skipped.
+    MachineCall(u16),
+    // A call nested the given number of levels deep, more than the original interpreter had room
+    // for.
+    DeepCall(u8),
+    // A sprite drawn from the given I, which isn't in the loaded ROM or the font.
+    SpriteOutsideProgram(u16),
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::UndefinedOpcode(opcode) => {
+                write!(f, "undefined opcode {}", format_word(*opcode))
+            }
+            Violation::MisalignedPc(pc) => {
+                write!(f, "pc moved to odd address {}", format_addr(*pc))
+            }
+            Violation::UninitializedRead(address) => {
+                write!(
+                    f,
+                    "read of uninitialized memory at {}",
+                    format_addr(*address)
+                )
+            }
+            Violation::WriteBelowProgram(address) => {
+                write!(
+                    f,
+                    "write below the program area at {}",
+                    format_addr(*address)
+                )
+            }
+            Violation::IndexOverflow => write!(f, "I overflowed past the end of memory"),
+            Violation::SpritePastMemory(i) => {
+                write!(
+                    f,
+                    "sprite at I={} extends past the end of memory",
+                    format_addr(*i)
+                )
+            }
+            Violation::LoadPastMemory(i) => {
+                write!(
+                    f,
+                    "load at I={} extends past the end of memory",
+                    format_addr(*i)
+                )
+            }
+            Violation::MachineCall(address) => {
+                write!(f, "skipped machine code call to {}", format_addr(*address))
+            }
+            Violation::DeepCall(depth) => {
+                write!(f, "call nested {} deep, past the VIP's 12 levels", depth)
+            }
+            Violation::SpriteOutsideProgram(i) => {
+                write!(
+                    f,
+                    "sprite at I={} is outside the ROM and font",
+                    format_addr(*i)
+                )
+            }
+        }
+    }
+}
+
+// Collects violations deduplicated by the instruction address they happened at.
+#[derive(Debug, Default)]
+pub struct Violations {
+    counts: BTreeMap<(u16, Violation), u64>,
+}
+
+impl Violations {
+    pub fn new() -> Self {
+        Violations::default()
+    }
+
+    // Returns whether this is the first time the violation happened at this address.
+    pub fn record(&mut self, pc: u16, violation: Violation) -> bool {
+        let count = self.counts.entry((pc, violation)).or_insert(0);
+        *count += 1;
+        *count == 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    // Distinct violations as (pc, violation, count), ordered by address.
+    pub fn entries(&self) -> Vec<(u16, Violation, u64)> {
+        self.counts
+            .iter()
+            .map(|(&(pc, violation), &count)| (pc, violation, count))
+            .collect()
+    }
+
+    // One line per distinct violation, ordered by address.
+    pub fn report(&self) -> String {
+        self.counts
+            .iter()
+            .map(|((pc, violation), count)| {
+                format!("{}: {} (x{})", format_addr(*pc), violation, count)
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_deduplicates() {
+        let mut violations = Violations::new();
+
+        assert!(violations.record(0x204, Violation::IndexOverflow));
+        assert!(!violations.record(0x204, Violation::IndexOverflow));
+        assert!(violations.record(0x206, Violation::IndexOverflow));
+        assert!(violations.record(0x204, Violation::UndefinedOpcode(0xFABC)));
+
+        assert_eq!(
+            violations.entries(),
+            vec![
+                (0x204, Violation::UndefinedOpcode(0xFABC), 1),
+                (0x204, Violation::IndexOverflow, 2),
+                (0x206, Violation::IndexOverflow, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_report() {
+        let mut violations = Violations::new();
+        assert_eq!(violations.report(), "");
+
+        violations.record(0x212, Violation::MisalignedPc(0x215));
+        violations.record(0x204, Violation::WriteBelowProgram(0x100));
+        violations.record(0x204, Violation::WriteBelowProgram(0x100));
+        violations.record(0x20A, Violation::UninitializedRead(0x300));
+
+        assert_eq!(
+            violations.report(),
+            "0x204: write below the program area at 0x100 (x2)\n\
+             0x20a: read of uninitialized memory at 0x300 (x1)\n\
+             0x212: pc moved to odd address 0x215 (x1)"
+        );
+    }
+}
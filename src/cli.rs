@@ -0,0 +1,424 @@
+use ch8emu::chip8;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Options {
+    pub rom_path: Option<String>,
+    // Stop executing instructions and ticking timers while the window is occluded.
+    pub pause_when_occluded: bool,
+    // Silence the beeper while the window is occluded.
+    pub mute_when_occluded: bool,
+    // Print frame pacing statistics and the time spent on each kind of instruction on exit.
+    pub profile: bool,
+    // Substring of the name of the audio output to use instead of the default one.
+    pub audio_device: Option<String>,
+    // Print the available audio outputs and exit.
+    pub list_audio_devices: bool,
+    // Start with the beeper silenced.
+    pub mute: bool,
+    // Stop on undefined opcodes instead of skipping them.
+    pub strict_decode: bool,
+    // Stop when pc becomes odd instead of warning.
+    pub strict_alignment: bool,
+    // Enable every strict option, turning each recoverable oddity into an error. With --headless
+    // the ROM runs on instead and fails at the end, with every violation in the report.
+    pub strict: bool,
+    // Run the ROM without a window and report how it went.
+    pub headless: bool,
+    // Number of 60 Hz frames a headless run lasts at most.
+    pub frames: Option<u64>,
+    // Print the final screen and machine status after a headless run.
+    pub ascii: bool,
+    // Print the effective keypad mapping and exit.
+    pub print_keymap: bool,
+    // Platform whose quirks to emulate.
+    pub target: Option<chip8::Target>,
+    // File to write a replay of the session to on exit.
+    pub record_replay: Option<String>,
+    // Replay file to play back without a window instead of running interactively.
+    pub replay: Option<String>,
+    // File to write a bundle of the replay and the ROM to on exit.
+    pub record_bundle: Option<String>,
+    // Bundle to play back without a window; it holds its own ROM.
+    pub play_bundle: Option<String>,
+    // Input script to compile to a replay of the ROM and play back without a window.
+    pub script: Option<String>,
+    // File to write the --replay as an input script to, instead of playing it.
+    pub export_script: Option<String>,
+    // Shortest sound timer value that beeps, if not the default.
+    pub min_beep_ticks: Option<u8>,
+    // Hold key presses until the ROM has seen them.
+    pub sticky_keys: bool,
+    // Run the ROM in the VIP's 64x64 hi-res mode even if it isn't marked as a hi-res ROM.
+    pub legacy_hires: bool,
+    // Have FX0A wait for the key to be released, as the VIP did.
+    pub wait_for_key_release: bool,
+    // Directory of ROMs to cycle through, for unattended machines.
+    pub kiosk: Option<String>,
+    // Seconds each ROM runs for in kiosk mode while nobody's playing.
+    pub kiosk_seconds: Option<u64>,
+    // Two platforms to run the ROM under side by side, stopping where they first differ.
+    pub compare: Option<(chip8::Target, chip8::Target)>,
+    // Show each frame a frame early by running ahead with the keys held, see RunAhead.
+    pub run_ahead: bool,
+    // Set by the `info` command: print what can be told about the ROM without running it, and
+    // exit.
+    pub info: bool,
+    // Set by the `gen-test` command: the self-test whose ROM to write to stdout instead of running
+    // anything.
+    pub gen_test: Option<String>,
+}
+
+pub fn parse_args<I: Iterator<Item = String>>(args: I) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = args.peekable();
+    if args.next_if(|arg| arg == "info").is_some() {
+        options.info = true;
+    } else if let Some(arg) = args.next_if(|arg| arg == "gen-test") {
+        options.gen_test = Some(value_of(&arg, &mut args)?);
+    }
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--pause-when-occluded" => options.pause_when_occluded = true,
+            "--mute-when-occluded" => options.mute_when_occluded = true,
+            "--profile" => options.profile = true,
+            "--audio-device" => options.audio_device = Some(value_of(&arg, &mut args)?),
+            "--list-audio-devices" => options.list_audio_devices = true,
+            "--mute" => options.mute = true,
+            "--strict-decode" => options.strict_decode = true,
+            "--strict-alignment" => options.strict_alignment = true,
+            "--strict" => options.strict = true,
+            "--headless" => options.headless = true,
+            "--ascii" => options.ascii = true,
+            "--frames" => options.frames = Some(number_of(&arg, &mut args)?),
+            "--min-beep-ticks" => options.min_beep_ticks = Some(number_of(&arg, &mut args)?),
+            "--sticky-keys" => options.sticky_keys = true,
+            "--legacy-hires" => options.legacy_hires = true,
+            "--wait-for-key-release" => options.wait_for_key_release = true,
+            "--run-ahead" => options.run_ahead = true,
+            "--kiosk" => options.kiosk = Some(value_of(&arg, &mut args)?),
+            "--kiosk-seconds" => options.kiosk_seconds = Some(number_of(&arg, &mut args)?),
+            "--print-keymap" => options.print_keymap = true,
+            "--target" => options.target = Some(value_of(&arg, &mut args)?.parse()?),
+            "--record-replay" => options.record_replay = Some(value_of(&arg, &mut args)?),
+            "--replay" => options.replay = Some(value_of(&arg, &mut args)?),
+            "--record-bundle" => options.record_bundle = Some(value_of(&arg, &mut args)?),
+            "--play-bundle" => options.play_bundle = Some(value_of(&arg, &mut args)?),
+            "--script" => options.script = Some(value_of(&arg, &mut args)?),
+            "--export-script" => options.export_script = Some(value_of(&arg, &mut args)?),
+            "--compare" => options.compare = Some(targets_of(&arg, &mut args)?),
+            flag if flag.starts_with("--") => return Err(format!("Unknown option: {}", flag)),
+            path => options.rom_path = Some(path.to_string()),
+        }
+    }
+    Ok(options)
+}
+
+impl Options {
+    // Whether the session is recorded, as a replay or a bundle.
+    pub fn is_recording(&self) -> bool {
+        self.record_replay.is_some() || self.record_bundle.is_some()
+    }
+
+    pub fn chip8_options(&self) -> chip8::Options {
+        let mut options = if self.strict {
+            chip8::Options::strict()
+        } else {
+            chip8::Options {
+                strict_decode: self.strict_decode,
+                strict_alignment: self.strict_alignment,
+                ..chip8::Options::default()
+            }
+        };
+        if let Some(target) = self.target {
+            options.quirks = chip8::Quirks::preset(target);
+        }
+        if let Some(min_beep_ticks) = self.min_beep_ticks {
+            options.min_beep_ticks = min_beep_ticks;
+        }
+        options.sticky_keys = self.sticky_keys;
+        options.legacy_hires = self.legacy_hires;
+        options.quirks.wait_for_key_on_release |= self.wait_for_key_release;
+        options
+    }
+}
+
+fn value_of<I: Iterator<Item = String>>(flag: &str, args: &mut I) -> Result<String, String> {
+    args.next()
+        .ok_or_else(|| format!("Missing value for option: {}", flag))
+}
+
+fn number_of<T: std::str::FromStr, I: Iterator<Item = String>>(
+    flag: &str,
+    args: &mut I,
+) -> Result<T, String> {
+    let value = value_of(flag, args)?;
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value for option {}: {}", flag, value))
+}
+
+// A pair of targets separated by a comma, such as `chip8,schip`.
+fn targets_of<I: Iterator<Item = String>>(
+    flag: &str,
+    args: &mut I,
+) -> Result<(chip8::Target, chip8::Target), String> {
+    let value = value_of(flag, args)?;
+    let (first, second) = value
+        .split_once(',')
+        .ok_or_else(|| format!("Invalid value for option {}: {}", flag, value))?;
+    Ok((first.trim().parse()?, second.trim().parse()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_parse_args_empty() {
+        assert_eq!(parse(&[]), Ok(Options::default()));
+    }
+
+    #[test]
+    fn test_parse_args_rom_and_flags() {
+        let options =
+            parse(&["--mute-when-occluded", "pong.ch8", "--pause-when-occluded"]).unwrap();
+
+        assert_eq!(options.rom_path, Some("pong.ch8".to_string()));
+        assert!(options.pause_when_occluded);
+        assert!(options.mute_when_occluded);
+        assert!(!options.profile);
+    }
+
+    #[test]
+    fn test_parse_args_profile() {
+        assert!(parse(&["--profile"]).unwrap().profile);
+    }
+
+    #[test]
+    fn test_parse_args_audio_device() {
+        let options = parse(&["--audio-device", "USB", "--list-audio-devices"]).unwrap();
+
+        assert_eq!(options.audio_device, Some("USB".to_string()));
+        assert!(options.list_audio_devices);
+    }
+
+    #[test]
+    fn test_parse_args_mute() {
+        assert!(parse(&["--mute"]).unwrap().mute);
+    }
+
+    #[test]
+    fn test_parse_args_strict_flags() {
+        let options = parse(&["--strict-decode", "--strict-alignment"]).unwrap();
+
+        assert!(options.strict_decode);
+        assert!(options.strict_alignment);
+    }
+
+    #[test]
+    fn test_parse_args_print_keymap() {
+        assert!(parse(&["--print-keymap"]).unwrap().print_keymap);
+    }
+
+    #[test]
+    fn test_parse_args_strict_enables_all_strict_options() {
+        let options = parse(&["--strict"]).unwrap();
+
+        assert_eq!(options.chip8_options(), chip8::Options::strict());
+        assert_eq!(
+            parse(&["--strict-decode"]).unwrap().chip8_options(),
+            chip8::Options {
+                strict_decode: true,
+                ..chip8::Options::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_args_target() {
+        let options = parse(&["--target", "xochip", "--strict"]).unwrap();
+
+        assert_eq!(options.target, Some(chip8::Target::XoChip));
+        assert_eq!(
+            options.chip8_options(),
+            chip8::Options {
+                quirks: chip8::Quirks::preset(chip8::Target::XoChip),
+                ..chip8::Options::strict()
+            }
+        );
+        assert_eq!(
+            parse(&["--target", "vip"]),
+            Err("unknown target `vip`".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_args_info() {
+        let options = parse(&["info", "pong.ch8"]).unwrap();
+
+        assert!(options.info);
+        assert_eq!(options.rom_path, Some("pong.ch8".to_string()));
+        // Only as the command, not as a ROM's name.
+        let options = parse(&["--mute", "info"]).unwrap();
+        assert!(!options.info);
+        assert_eq!(options.rom_path, Some("info".to_string()));
+    }
+
+    #[test]
+    fn test_parse_args_gen_test() {
+        let options = parse(&["gen-test", "shift"]).unwrap();
+
+        assert_eq!(options.gen_test, Some("shift".to_string()));
+        assert_eq!(options.rom_path, None);
+        assert_eq!(
+            parse(&["gen-test"]),
+            Err("Missing value for option: gen-test".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_args_compare() {
+        let options = parse(&["--compare", "chip8,schip", "brix.ch8"]).unwrap();
+
+        assert_eq!(
+            options.compare,
+            Some((chip8::Target::Chip8, chip8::Target::SuperChipModern))
+        );
+        assert_eq!(
+            parse(&["--compare", "chip8"]),
+            Err("Invalid value for option --compare: chip8".to_string())
+        );
+        assert_eq!(
+            parse(&["--compare", "chip8,vip"]),
+            Err("unknown target `vip`".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_args_replay() {
+        let options =
+            parse(&["--record-replay", "out.c8replay", "--replay", "in.c8replay"]).unwrap();
+
+        assert_eq!(options.record_replay, Some("out.c8replay".to_string()));
+        assert_eq!(options.replay, Some("in.c8replay".to_string()));
+    }
+
+    #[test]
+    fn test_parse_args_script() {
+        let options = parse(&["--script", "inputs.txt", "game.ch8"]).unwrap();
+
+        assert_eq!(options.script, Some("inputs.txt".to_string()));
+        assert_eq!(options.rom_path, Some("game.ch8".to_string()));
+
+        let options = parse(&["--replay", "in.c8replay", "--export-script", "inputs.txt"]).unwrap();
+
+        assert_eq!(options.export_script, Some("inputs.txt".to_string()));
+        assert!(!options.is_recording());
+    }
+
+    #[test]
+    fn test_parse_args_bundle() {
+        let options = parse(&[
+            "--record-bundle",
+            "out.c8bundle",
+            "--play-bundle",
+            "in.c8bundle",
+        ])
+        .unwrap();
+
+        assert_eq!(options.record_bundle, Some("out.c8bundle".to_string()));
+        assert_eq!(options.play_bundle, Some("in.c8bundle".to_string()));
+        assert!(options.is_recording());
+        assert!(!parse(&["--play-bundle", "in.c8bundle"])
+            .unwrap()
+            .is_recording());
+    }
+
+    #[test]
+    fn test_parse_args_headless() {
+        let options = parse(&["--headless", "--frames", "120", "--ascii", "test.ch8"]).unwrap();
+
+        assert!(options.headless);
+        assert!(options.ascii);
+        assert_eq!(options.frames, Some(120));
+        assert_eq!(
+            parse(&["--frames", "lots"]),
+            Err("Invalid value for option --frames: lots".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_args_min_beep_ticks() {
+        let options = parse(&["--min-beep-ticks", "1"]).unwrap();
+
+        assert_eq!(options.chip8_options().min_beep_ticks, 1);
+        assert_eq!(
+            parse(&[]).unwrap().chip8_options().min_beep_ticks,
+            chip8::DEFAULT_MIN_BEEP_TICKS
+        );
+        assert_eq!(
+            parse(&["--min-beep-ticks", "256"]),
+            Err("Invalid value for option --min-beep-ticks: 256".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_args_sticky_keys() {
+        assert!(
+            parse(&["--sticky-keys"])
+                .unwrap()
+                .chip8_options()
+                .sticky_keys
+        );
+        assert!(!parse(&[]).unwrap().chip8_options().sticky_keys);
+    }
+
+    #[test]
+    fn test_parse_args_legacy_hires() {
+        assert!(
+            parse(&["--legacy-hires"])
+                .unwrap()
+                .chip8_options()
+                .legacy_hires
+        );
+        assert!(!parse(&[]).unwrap().chip8_options().legacy_hires);
+        assert!(parse(&["--run-ahead"]).unwrap().run_ahead);
+    }
+
+    #[test]
+    fn test_parse_args_wait_for_key_release() {
+        let chip8 = |args: &[&str]| parse(args).unwrap().chip8_options().quirks;
+
+        assert!(chip8(&["--wait-for-key-release", "--target", "chip8"]).wait_for_key_on_release);
+        assert!(!chip8(&["--target", "chip8"]).wait_for_key_on_release);
+        assert!(!chip8(&[]).wait_for_key_on_release);
+    }
+
+    #[test]
+    fn test_parse_args_kiosk() {
+        let options = parse(&["--kiosk", "roms/", "--kiosk-seconds", "30"]).unwrap();
+
+        assert_eq!(options.kiosk, Some("roms/".to_string()));
+        assert_eq!(options.kiosk_seconds, Some(30));
+    }
+
+    #[test]
+    fn test_parse_args_missing_value() {
+        assert_eq!(
+            parse(&["--audio-device"]),
+            Err("Missing value for option: --audio-device".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_args_unknown_flag() {
+        assert_eq!(
+            parse(&["--bogus"]),
+            Err("Unknown option: --bogus".to_string())
+        );
+    }
+}
@@ -0,0 +1,66 @@
+use std::time::{Duration, Instant};
+
+pub trait Clock {
+    fn now(&self) -> Instant;
+    fn sleep(&self, duration: Duration);
+    fn yield_now(&self);
+}
+
+#[derive(Debug)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+
+    fn yield_now(&self) {
+        std::thread::yield_now();
+    }
+}
+
+// Virtual clock for tests: time only moves when slept or yielded on. Sleeps overshoot by a fixed
+// amount to mimic the OS timer resolution.
+#[cfg(test)]
+#[derive(Debug)]
+pub struct MockClock {
+    start: Instant,
+    elapsed: std::cell::Cell<Duration>,
+    sleep_overshoot: Duration,
+    yield_duration: Duration,
+}
+
+#[cfg(test)]
+impl MockClock {
+    pub fn new(sleep_overshoot: Duration) -> MockClock {
+        MockClock {
+            start: Instant::now(),
+            elapsed: std::cell::Cell::new(Duration::ZERO),
+            sleep_overshoot,
+            yield_duration: Duration::from_micros(10),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.elapsed.set(self.elapsed.get() + duration);
+    }
+}
+
+#[cfg(test)]
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed.get()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration + self.sleep_overshoot);
+    }
+
+    fn yield_now(&self) {
+        self.advance(self.yield_duration);
+    }
+}
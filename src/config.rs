@@ -0,0 +1,295 @@
+use crate::gamepad::Button;
+use crate::keymap::Preset;
+use crate::render::{PixelStyle, VisualBell};
+use crate::watchdog::HangResponse;
+use ch8emu::numfmt;
+use std::ops::Range;
+use std::path::PathBuf;
+
+const CONFIG_FILE_NAME: &str = "config.toml";
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Config {
+    pub visual_bell: VisualBell,
+    pub pixel_style: PixelStyle,
+    // What to do about a ROM that runs without drawing, reading keys or beeping for a while.
+    pub hang_response: HangResponse,
+    pub keypad: Preset,
+    // (host key, CHIP-8 key) bindings layered on top of the keypad preset, in file order.
+    pub key_overrides: Vec<(String, String)>,
+    // (gamepad button, CHIP-8 key) bindings layered on top of the default button map, in file
+    // order.
+    pub button_overrides: Vec<(Button, u8)>,
+    // Memory kept across sessions of each ROM, such as a high score table.
+    pub persist_range: Option<Range<usize>>,
+    // Colors for pixels set in no plane, the first, the second and both, if not the default.
+    pub palette: Option<[u32; 4]>,
+}
+
+// Directory holding the emulator's configuration, following the XDG base directory convention.
+pub fn config_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("ch8emu"))
+}
+
+// Loads the configuration file, falling back to defaults if it's missing or invalid.
+pub fn load() -> Config {
+    let Some(path) = config_dir().map(|dir| dir.join(CONFIG_FILE_NAME)) else {
+        return Config::default();
+    };
+    match std::fs::read_to_string(&path) {
+        Ok(contents) => parse(&contents).unwrap_or_else(|err| {
+            eprintln!("Warning: ignoring {}: {}", path.display(), err);
+            Config::default()
+        }),
+        Err(_) => Config::default(),
+    }
+}
+
+// Parses the flat `key = value` subset of TOML the configuration uses.
+pub fn parse(contents: &str) -> Result<Config, String> {
+    let mut config = Config::default();
+    for (line_idx, line) in contents.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("line {}: expected `key = value`", line_idx + 1))?;
+        let value = value.trim().trim_matches('"');
+
+        match key.trim() {
+            "visual-bell" => {
+                config.visual_bell = value
+                    .parse()
+                    .map_err(|err| format!("line {}: {}", line_idx + 1, err))?
+            }
+            "hung-rom" => {
+                config.hang_response = value
+                    .parse()
+                    .map_err(|err| format!("line {}: {}", line_idx + 1, err))?
+            }
+            "pixel-style" => {
+                config.pixel_style = value
+                    .parse()
+                    .map_err(|err| format!("line {}: {}", line_idx + 1, err))?
+            }
+            "keypad" => {
+                config.keypad = value
+                    .parse()
+                    .map_err(|err| format!("line {}: {}", line_idx + 1, err))?
+            }
+            "persist-range" => {
+                config.persist_range = Some(
+                    parse_range(value).map_err(|err| format!("line {}: {}", line_idx + 1, err))?,
+                )
+            }
+            "palette" => {
+                config.palette = Some(
+                    parse_palette(value)
+                        .map_err(|err| format!("line {}: {}", line_idx + 1, err))?,
+                )
+            }
+            key if key.starts_with("key.") => {
+                let host_key = key["key.".len()..].trim_matches('"').to_lowercase();
+                let chip8_key = value.to_lowercase();
+                if chip8_key.len() != 1 || u8::from_str_radix(&chip8_key, 16).is_err() {
+                    return Err(format!(
+                        "line {}: `{}` is not a keypad key",
+                        line_idx + 1,
+                        value
+                    ));
+                }
+                config.key_overrides.push((host_key, chip8_key));
+            }
+            key if key.starts_with("button.") => {
+                let button = key["button.".len()..]
+                    .trim_matches('"')
+                    .to_lowercase()
+                    .parse()
+                    .map_err(|err| format!("line {}: {}", line_idx + 1, err))?;
+                let chip8_key = match u8::from_str_radix(value, 16) {
+                    Ok(chip8_key) if value.len() == 1 => chip8_key,
+                    _ => {
+                        return Err(format!(
+                            "line {}: `{}` is not a keypad key",
+                            line_idx + 1,
+                            value
+                        ))
+                    }
+                };
+                config.button_overrides.push((button, chip8_key));
+            }
+            key => return Err(format!("line {}: unknown key `{}`", line_idx + 1, key)),
+        }
+    }
+    Ok(config)
+}
+
+// An inclusive range of addresses such as `0x3F0-0x3FF`.
+fn parse_range(value: &str) -> Result<Range<usize>, String> {
+    let not_a_range = || format!("`{}` is not an address range like 0x3F0-0x3FF", value);
+    let (start, end) = value.split_once('-').ok_or_else(not_a_range)?;
+    let start = usize::from(numfmt::parse_addr(start).map_err(|err| err.to_string())?);
+    let end = usize::from(numfmt::parse_addr(end).map_err(|err| err.to_string())?);
+    if start > end {
+        return Err(not_a_range());
+    }
+    Ok(start..end + 1)
+}
+
+// Four RRGGBB hex colors separated by commas, such as `000000, FFFFFF, FF6600, 662200`.
+fn parse_palette(value: &str) -> Result<[u32; 4], String> {
+    let colors: Vec<u32> = value
+        .split(',')
+        .map(|color| {
+            let text = color.trim();
+            match u32::from_str_radix(text, 16) {
+                Ok(color) if text.len() == 6 => Ok(color),
+                _ => Err(format!("`{}` is not an RRGGBB color", text)),
+            }
+        })
+        .collect::<Result<_, _>>()?;
+    colors
+        .try_into()
+        .map_err(|_| format!("`{}` is not a palette of four colors", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_empty() {
+        assert_eq!(parse(""), Ok(Config::default()));
+    }
+
+    #[test]
+    fn test_parse_visual_bell() {
+        let config = parse("# accessibility\nvisual-bell = \"border\"\n").unwrap();
+
+        assert_eq!(config.visual_bell, VisualBell::Border);
+    }
+
+    #[test]
+    fn test_parse_pixel_style() {
+        let config = parse("pixel-style = \"grid\"").unwrap();
+
+        assert_eq!(config.pixel_style, PixelStyle::Grid);
+        assert_eq!(
+            parse("pixel-style = \"round\""),
+            Err("line 1: unknown pixel style `round`".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_hung_rom() {
+        assert_eq!(parse("").unwrap().hang_response, HangResponse::Title);
+        assert_eq!(
+            parse("hung-rom = \"warn\"").unwrap().hang_response,
+            HangResponse::Warn
+        );
+        assert_eq!(
+            parse("hung-rom = \"pause\""),
+            Err("line 1: unknown hang response `pause`".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_keypad() {
+        let config = parse("keypad = \"wasd\"\nkey.p = \"5\"\nkey.\"numpad+\" = \"F\"\n").unwrap();
+
+        assert_eq!(config.keypad, Preset::Wasd);
+        assert_eq!(
+            config.key_overrides,
+            vec![
+                ("p".to_string(), "5".to_string()),
+                ("numpad+".to_string(), "f".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_button_overrides() {
+        let config = parse("button.south = \"A\"\nbutton.\"dpad-up\" = \"5\"\n").unwrap();
+
+        assert_eq!(
+            config.button_overrides,
+            vec![(Button::South, 0xA), (Button::DPadUp, 0x5)]
+        );
+        assert_eq!(
+            parse("button.south = \"10\""),
+            Err("line 1: `10` is not a keypad key".to_string())
+        );
+        assert_eq!(
+            parse("button.turbo = \"1\""),
+            Err("line 1: unknown gamepad button `turbo`".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_persist_range() {
+        let config = parse("persist-range = \"0x3F0-0x3FF\"").unwrap();
+
+        assert_eq!(config.persist_range, Some(0x3F0..0x400));
+        assert_eq!(
+            parse("persist-range = \"1008-3FFh\"")
+                .unwrap()
+                .persist_range,
+            Some(0x3F0..0x400)
+        );
+        assert_eq!(
+            parse("persist-range = \"0x3F0-0x3FG\""),
+            Err("line 1: `0x3FG` is not a number like 0x224, 224h or 548".to_string())
+        );
+        assert_eq!(
+            parse("persist-range = \"0x3FF-0x3F0\""),
+            Err("line 1: `0x3FF-0x3F0` is not an address range like 0x3F0-0x3FF".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_palette() {
+        let config = parse("palette = \"000000, FFFFFF, ff6600, 662200\"").unwrap();
+
+        assert_eq!(
+            config.palette,
+            Some([0x0000_0000, 0x00FF_FFFF, 0x00FF_6600, 0x0066_2200])
+        );
+        assert_eq!(
+            parse("palette = \"000000, FFFFFF\""),
+            Err("line 1: `000000, FFFFFF` is not a palette of four colors".to_string())
+        );
+        assert_eq!(
+            parse("palette = \"000000, FFF, FF6600, 662200\""),
+            Err("line 1: `FFF` is not an RRGGBB color".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_override() {
+        assert_eq!(
+            parse("key.p = \"g\""),
+            Err("line 1: `g` is not a keypad key".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_value() {
+        assert_eq!(
+            parse("visual-bell = \"flash\""),
+            Err("line 1: unknown visual bell `flash`".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_unknown_key() {
+        assert_eq!(
+            parse("\nvolume = 3"),
+            Err("line 2: unknown key `volume`".to_string())
+        );
+    }
+}
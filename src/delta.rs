@@ -0,0 +1,315 @@
+use crate::chip8::display::Display;
+
+// Display updates for a viewer that receives every frame over a connection, where resending the
+// whole display for a sprite that moved is wasteful. Each frame is either a keyframe with the
+// whole display, or a delta with only the rows that changed since the frame before. Frames are
+// packed as Display::pack_into lays them out.
+//
+// As bytes, a keyframe is `K`, the width and height as big-endian u16s and the packed display. A
+// delta is `D`, the number of rows as a big-endian u16 and for each row its index as a big-endian
+// u16 followed by its packed bytes, which are as long as a row of the last keyframe.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Update {
+    Keyframe {
+        width: u16,
+        height: u16,
+        pixels: Vec<u8>,
+    },
+    Delta {
+        rows: Vec<(u16, Vec<u8>)>,
+    },
+}
+
+const KEYFRAME: u8 = b'K';
+const DELTA: u8 = b'D';
+
+#[derive(Debug, PartialEq)]
+pub enum DeltaError {
+    // The message ended before everything its header promised.
+    Truncated,
+    UnknownKind(u8),
+    // A delta came before any keyframe, so there's nothing to apply it to.
+    NoKeyframe,
+    RowOutOfRange(u16),
+    // A keyframe's pixels don't match its size.
+    SizeMismatch,
+}
+
+impl Update {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        match self {
+            Update::Keyframe {
+                width,
+                height,
+                pixels,
+            } => {
+                bytes.push(KEYFRAME);
+                bytes.extend(width.to_be_bytes());
+                bytes.extend(height.to_be_bytes());
+                bytes.extend(pixels);
+            }
+            Update::Delta { rows } => {
+                bytes.push(DELTA);
+                bytes.extend((rows.len() as u16).to_be_bytes());
+                for (row, pixels) in rows {
+                    bytes.extend(row.to_be_bytes());
+                    bytes.extend(pixels);
+                }
+            }
+        }
+        bytes
+    }
+}
+
+// Turns frames into updates, sending a keyframe every `keyframe_interval` frames, whenever the
+// display changes size and after force_keyframe, so that a viewer that connects or drops a
+// message catches up within that many frames.
+#[derive(Debug)]
+pub struct Encoder {
+    keyframe_interval: u32,
+    // The last frame sent, with its size.
+    previous: Option<(u16, u16, Vec<u8>)>,
+    since_keyframe: u32,
+}
+
+impl Encoder {
+    pub fn new(keyframe_interval: u32) -> Self {
+        Encoder {
+            keyframe_interval: keyframe_interval.max(1),
+            previous: None,
+            since_keyframe: 0,
+        }
+    }
+
+    // Makes the next update a keyframe, for a viewer that just connected.
+    pub fn force_keyframe(&mut self) {
+        self.previous = None;
+    }
+
+    pub fn encode_display(&mut self, display: &Display) -> Update {
+        let mut pixels = vec![0; display.width * display.height / 8];
+        display.pack_into(&mut pixels);
+        self.encode(display.width as u16, display.height as u16, pixels)
+    }
+
+    pub fn encode(&mut self, width: u16, height: u16, pixels: Vec<u8>) -> Update {
+        let row_len = usize::from(width / 8);
+        let update = match &self.previous {
+            Some((previous_width, previous_height, previous))
+                if (*previous_width, *previous_height) == (width, height)
+                    && self.since_keyframe < self.keyframe_interval =>
+            {
+                self.since_keyframe += 1;
+                let rows = pixels
+                    .chunks(row_len.max(1))
+                    .zip(previous.chunks(row_len.max(1)))
+                    .enumerate()
+                    .filter(|(_, (row, previous))| row != previous)
+                    .map(|(index, (row, _))| (index as u16, row.to_vec()))
+                    .collect();
+                Update::Delta { rows }
+            }
+            _ => {
+                self.since_keyframe = 1;
+                Update::Keyframe {
+                    width,
+                    height,
+                    pixels: pixels.clone(),
+                }
+            }
+        };
+        self.previous = Some((width, height, pixels));
+        update
+    }
+}
+
+// Rebuilds frames from the updates an Encoder made.
+#[derive(Debug, Default)]
+pub struct Decoder {
+    // The current frame, with its width and height.
+    frame: Option<(u16, u16, Vec<u8>)>,
+}
+
+impl Decoder {
+    pub fn new() -> Self {
+        Decoder::default()
+    }
+
+    // Applies an update sent as bytes, returning the frame it makes. A message that can't be
+    // applied leaves the frame as it was.
+    pub fn decode(&mut self, bytes: &[u8]) -> Result<&[u8], DeltaError> {
+        let (&kind, rest) = bytes.split_first().ok_or(DeltaError::Truncated)?;
+        let update = match kind {
+            KEYFRAME => {
+                let width = read_u16(rest, 0)?;
+                let height = read_u16(rest, 2)?;
+                Update::Keyframe {
+                    width,
+                    height,
+                    pixels: rest[4..].to_vec(),
+                }
+            }
+            DELTA => {
+                let (width, _, _) = self.frame.as_ref().ok_or(DeltaError::NoKeyframe)?;
+                let row_len = usize::from(width / 8);
+                let count = usize::from(read_u16(rest, 0)?);
+                let mut rows = Vec::with_capacity(count);
+                let mut offset = 2;
+                for _ in 0..count {
+                    let row = read_u16(rest, offset)?;
+                    let pixels = rest
+                        .get(offset + 2..offset + 2 + row_len)
+                        .ok_or(DeltaError::Truncated)?;
+                    rows.push((row, pixels.to_vec()));
+                    offset += 2 + row_len;
+                }
+                Update::Delta { rows }
+            }
+            kind => return Err(DeltaError::UnknownKind(kind)),
+        };
+        self.apply(update)
+    }
+
+    pub fn apply(&mut self, update: Update) -> Result<&[u8], DeltaError> {
+        match update {
+            Update::Keyframe {
+                width,
+                height,
+                pixels,
+            } => {
+                if pixels.len() != usize::from(width / 8) * usize::from(height) {
+                    return Err(DeltaError::SizeMismatch);
+                }
+                self.frame = Some((width, height, pixels));
+            }
+            Update::Delta { rows } => {
+                let (width, height, frame) = self.frame.as_mut().ok_or(DeltaError::NoKeyframe)?;
+                let row_len = usize::from(*width / 8);
+                if let Some(&(row, _)) = rows
+                    .iter()
+                    .find(|(row, pixels)| *row >= *height || pixels.len() != row_len)
+                {
+                    return Err(DeltaError::RowOutOfRange(row));
+                }
+                for (row, pixels) in rows {
+                    let start = usize::from(row) * row_len;
+                    frame[start..start + row_len].copy_from_slice(&pixels);
+                }
+            }
+        }
+        Ok(self.frame().unwrap_or(&[]))
+    }
+
+    // The current frame, or None before the first keyframe.
+    pub fn frame(&self) -> Option<&[u8]> {
+        self.frame.as_ref().map(|(_, _, frame)| &frame[..])
+    }
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Result<u16, DeltaError> {
+    let bytes = bytes.get(offset..offset + 2).ok_or(DeltaError::Truncated)?;
+    Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn test_random_frames_round_trip() {
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut encoder = Encoder::new(rng.gen_range(1..10));
+            let mut decoder = Decoder::new();
+            let (mut width, mut height) = (64, 32);
+            let mut frame = vec![0; width * height / 8];
+            for number in 0..200 {
+                if rng.gen_ratio(1, 40) {
+                    (width, height) = if width == 64 { (128, 64) } else { (64, 32) };
+                    frame = vec![0; width * height / 8];
+                }
+                if rng.gen_ratio(1, 50) {
+                    encoder.force_keyframe();
+                }
+                for _ in 0..rng.gen_range(0..4) {
+                    let index = rng.gen_range(0..frame.len());
+                    frame[index] ^= rng.gen::<u8>();
+                }
+
+                let update = encoder.encode(width as u16, height as u16, frame.clone());
+
+                assert_eq!(
+                    decoder.decode(&update.to_bytes()),
+                    Ok(&frame[..]),
+                    "seed {} frame {}",
+                    seed,
+                    number
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_a_moved_sprite_sends_only_its_rows() {
+        let mut display = Display::new();
+        let mut encoder = Encoder::new(30);
+        let first = encoder.encode_display(&display);
+        display.draw_sprite(10, 8, &vec![0xFF, 0x81]).unwrap();
+
+        let second = encoder.encode_display(&display);
+
+        assert!(matches!(first, Update::Keyframe { .. }));
+        let row_len = display.width / 8;
+        let row = |byte| {
+            let mut row = vec![0; row_len];
+            row[1] = byte;
+            row
+        };
+        assert_eq!(
+            second,
+            Update::Delta {
+                rows: vec![(10, row(0xFF)), (11, row(0x81))]
+            }
+        );
+        assert_eq!(second.to_bytes().len(), 1 + 2 + 2 * (2 + row_len));
+    }
+
+    #[test]
+    fn test_keyframes_come_every_interval() {
+        let mut encoder = Encoder::new(3);
+        let kinds: Vec<bool> = (0..7)
+            .map(|_| matches!(encoder.encode(8, 1, vec![0]), Update::Keyframe { .. }))
+            .collect();
+
+        assert_eq!(kinds, [true, false, false, true, false, false, true]);
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let mut decoder = Decoder::new();
+
+        assert_eq!(decoder.decode(b"D\x00\x00"), Err(DeltaError::NoKeyframe));
+        assert_eq!(decoder.decode(b"X"), Err(DeltaError::UnknownKind(b'X')));
+        assert_eq!(decoder.decode(b"K\x00"), Err(DeltaError::Truncated));
+        assert_eq!(
+            decoder.decode(b"K\x00\x08\x00\x02\x00"),
+            Err(DeltaError::SizeMismatch)
+        );
+        assert_eq!(
+            decoder.decode(b"K\x00\x08\x00\x02\x00\x00"),
+            Ok(&[0, 0][..])
+        );
+        assert_eq!(
+            decoder.decode(b"D\x00\x01\x00\x05\xFF"),
+            Err(DeltaError::RowOutOfRange(5))
+        );
+        assert_eq!(
+            decoder.decode(b"D\x00\x02\x00\x01\xFF"),
+            Err(DeltaError::Truncated)
+        );
+        assert_eq!(decoder.frame(), Some(&[0, 0][..]));
+    }
+}
@@ -0,0 +1,287 @@
+use crate::keyqueue::{KeyQueue, KeyTransition};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Instant;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Button {
+    South,
+    East,
+    North,
+    West,
+    LeftTrigger,
+    LeftTrigger2,
+    RightTrigger,
+    RightTrigger2,
+    Select,
+    Start,
+    LeftThumb,
+    RightThumb,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+// Names as button overrides in the configuration refer to them, `button.dpad-up = "2"`.
+impl FromStr for Button {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "south" => Ok(Button::South),
+            "east" => Ok(Button::East),
+            "north" => Ok(Button::North),
+            "west" => Ok(Button::West),
+            "left-trigger" => Ok(Button::LeftTrigger),
+            "left-trigger-2" => Ok(Button::LeftTrigger2),
+            "right-trigger" => Ok(Button::RightTrigger),
+            "right-trigger-2" => Ok(Button::RightTrigger2),
+            "select" => Ok(Button::Select),
+            "start" => Ok(Button::Start),
+            "left-thumb" => Ok(Button::LeftThumb),
+            "right-thumb" => Ok(Button::RightThumb),
+            "dpad-up" => Ok(Button::DPadUp),
+            "dpad-down" => Ok(Button::DPadDown),
+            "dpad-left" => Ok(Button::DPadLeft),
+            "dpad-right" => Ok(Button::DPadRight),
+            _ => Err(format!("unknown gamepad button `{}`", value)),
+        }
+    }
+}
+
+// The D-pad drives the 2/4/6/8 keys most games use for movement, with 5 on the main face button.
+pub const BUTTON_MAP: [(Button, u8); 16] = [
+    (Button::DPadUp, 0x2),
+    (Button::DPadDown, 0x8),
+    (Button::DPadLeft, 0x4),
+    (Button::DPadRight, 0x6),
+    (Button::South, 0x5),
+    (Button::East, 0x0),
+    (Button::West, 0x7),
+    (Button::North, 0x9),
+    (Button::LeftTrigger, 0x1),
+    (Button::RightTrigger, 0x3),
+    (Button::LeftTrigger2, 0xC),
+    (Button::RightTrigger2, 0xD),
+    (Button::Select, 0xA),
+    (Button::Start, 0xB),
+    (Button::LeftThumb, 0xE),
+    (Button::RightThumb, 0xF),
+];
+
+#[derive(Debug, PartialEq)]
+pub enum ButtonEvent {
+    Pressed(Button),
+    Released(Button),
+}
+
+#[derive(Debug)]
+pub struct ButtonMap {
+    map: HashMap<Button, u8>,
+    // The button whose key is held. The keypad holds one key at a time, so only releasing that
+    // button releases it.
+    held: Option<Button>,
+}
+
+impl ButtonMap {
+    pub fn new(entries: &[(Button, u8)]) -> Self {
+        ButtonMap {
+            map: HashMap::from_iter(entries.iter().cloned()),
+            held: None,
+        }
+    }
+
+    // BUTTON_MAP with `overrides` applied in order, each rebinding its button.
+    pub fn resolve(overrides: &[(Button, u8)]) -> Self {
+        let mut button_map = ButtonMap::new(&BUTTON_MAP);
+        button_map.map.extend(overrides.iter().cloned());
+        button_map
+    }
+
+    pub fn get_mapped_key(&self, button: Button) -> Option<u8> {
+        self.map.get(&button).copied()
+    }
+
+    // The keypad transition `event` makes, if any.
+    pub fn apply(&mut self, event: ButtonEvent) -> Option<KeyTransition> {
+        match event {
+            ButtonEvent::Pressed(button) => {
+                let key = self.get_mapped_key(button)?;
+                self.held = Some(button);
+                Some(KeyTransition::Pressed(key))
+            }
+            ButtonEvent::Released(button) if self.held == Some(button) => {
+                self.held = None;
+                Some(KeyTransition::Released)
+            }
+            ButtonEvent::Released(_) => None,
+        }
+    }
+
+    // Queues the transition `event` makes at `at`, like a key on the keyboard.
+    pub fn queue(&mut self, event: ButtonEvent, at: Instant, queue: &mut KeyQueue) {
+        if let Some(transition) = self.apply(event) {
+            queue.push(at, transition);
+        }
+    }
+}
+
+#[cfg(feature = "gamepad")]
+pub struct GamepadInput {
+    gilrs: gilrs::Gilrs,
+    button_map: ButtonMap,
+}
+
+#[cfg(feature = "gamepad")]
+impl GamepadInput {
+    pub fn new(button_map: ButtonMap) -> Result<Self, String> {
+        Ok(GamepadInput {
+            gilrs: gilrs::Gilrs::new().map_err(|err| err.to_string())?,
+            button_map,
+        })
+    }
+
+    // Queues the keypad transitions of the buttons pressed and released since the last poll.
+    pub fn poll(&mut self, queue: &mut KeyQueue) {
+        let now = Instant::now();
+        while let Some(gilrs::Event { event, .. }) = self.gilrs.next_event() {
+            let event = match event {
+                gilrs::EventType::ButtonPressed(button, _) => {
+                    from_gilrs(button).map(ButtonEvent::Pressed)
+                }
+                gilrs::EventType::ButtonReleased(button, _) => {
+                    from_gilrs(button).map(ButtonEvent::Released)
+                }
+                _ => None,
+            };
+            if let Some(event) = event {
+                self.button_map.queue(event, now, queue);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "gamepad")]
+impl std::fmt::Debug for GamepadInput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GamepadInput")
+            .field("button_map", &self.button_map)
+            .finish()
+    }
+}
+
+#[cfg(feature = "gamepad")]
+fn from_gilrs(button: gilrs::Button) -> Option<Button> {
+    match button {
+        gilrs::Button::South => Some(Button::South),
+        gilrs::Button::East => Some(Button::East),
+        gilrs::Button::North => Some(Button::North),
+        gilrs::Button::West => Some(Button::West),
+        gilrs::Button::LeftTrigger => Some(Button::LeftTrigger),
+        gilrs::Button::LeftTrigger2 => Some(Button::LeftTrigger2),
+        gilrs::Button::RightTrigger => Some(Button::RightTrigger),
+        gilrs::Button::RightTrigger2 => Some(Button::RightTrigger2),
+        gilrs::Button::Select => Some(Button::Select),
+        gilrs::Button::Start => Some(Button::Start),
+        gilrs::Button::LeftThumb => Some(Button::LeftThumb),
+        gilrs::Button::RightThumb => Some(Button::RightThumb),
+        gilrs::Button::DPadUp => Some(Button::DPadUp),
+        gilrs::Button::DPadDown => Some(Button::DPadDown),
+        gilrs::Button::DPadLeft => Some(Button::DPadLeft),
+        gilrs::Button::DPadRight => Some(Button::DPadRight),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_button_map_covers_all_keys() {
+        let button_map = ButtonMap::new(&BUTTON_MAP);
+        let mut keys: Vec<u8> = BUTTON_MAP
+            .iter()
+            .filter_map(|&(button, _)| button_map.get_mapped_key(button))
+            .collect();
+        keys.sort();
+
+        assert_eq!(keys, (0x0..=0xF).collect::<Vec<u8>>());
+    }
+
+    #[test]
+    fn test_custom_button_map() {
+        let button_map = ButtonMap::new(&[(Button::South, 0xA)]);
+
+        assert_eq!(button_map.get_mapped_key(Button::South), Some(0xA));
+        assert_eq!(button_map.get_mapped_key(Button::East), None);
+    }
+
+    #[test]
+    fn test_resolve_overrides() {
+        let button_map = ButtonMap::resolve(&[(Button::South, 0xA), (Button::South, 0xB)]);
+
+        assert_eq!(button_map.get_mapped_key(Button::South), Some(0xB));
+        assert_eq!(button_map.get_mapped_key(Button::DPadUp), Some(0x2));
+    }
+
+    #[test]
+    fn test_button_names() {
+        assert_eq!("dpad-up".parse(), Ok(Button::DPadUp));
+        assert_eq!("right-trigger-2".parse(), Ok(Button::RightTrigger2));
+        assert_eq!(
+            "dpad".parse::<Button>(),
+            Err("unknown gamepad button `dpad`".to_string())
+        );
+    }
+
+    #[test]
+    fn test_apply_press_and_release() {
+        let mut button_map = ButtonMap::new(&BUTTON_MAP);
+
+        assert_eq!(
+            button_map.apply(ButtonEvent::Pressed(Button::DPadUp)),
+            Some(KeyTransition::Pressed(0x2))
+        );
+        assert_eq!(
+            button_map.apply(ButtonEvent::Released(Button::DPadUp)),
+            Some(KeyTransition::Released)
+        );
+    }
+
+    #[test]
+    fn test_apply_releases_only_for_the_holding_button() {
+        let mut button_map = ButtonMap::new(&BUTTON_MAP);
+
+        button_map.apply(ButtonEvent::Pressed(Button::DPadUp));
+        assert_eq!(
+            button_map.apply(ButtonEvent::Pressed(Button::South)),
+            Some(KeyTransition::Pressed(0x5))
+        );
+        // Letting go of the D-pad keeps 5, which the face button still holds.
+        assert_eq!(
+            button_map.apply(ButtonEvent::Released(Button::DPadUp)),
+            None
+        );
+        assert_eq!(
+            button_map.apply(ButtonEvent::Released(Button::South)),
+            Some(KeyTransition::Released)
+        );
+        assert_eq!(button_map.apply(ButtonEvent::Released(Button::South)), None);
+    }
+
+    #[test]
+    fn test_queue_goes_through_the_key_queue() {
+        let mut button_map = ButtonMap::new(&BUTTON_MAP);
+        let mut queue = KeyQueue::new();
+        let now = Instant::now();
+
+        button_map.queue(ButtonEvent::Pressed(Button::DPadLeft), now, &mut queue);
+        button_map.queue(ButtonEvent::Released(Button::DPadLeft), now, &mut queue);
+
+        // The press and its release land in separate batches, so the ROM sees the press.
+        assert_eq!(queue.next_batch(now), vec![KeyTransition::Pressed(0x4)]);
+        assert_eq!(queue.next_batch(now), vec![KeyTransition::Released]);
+    }
+}
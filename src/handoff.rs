@@ -0,0 +1,220 @@
+use crate::chip8::display::Display;
+use crate::chip8::Chip8;
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+// A complete frame as published by the emulation thread.
+#[derive(Debug, Clone, PartialEq)]
+struct Frame {
+    // 1 for the first published frame, 0 before any.
+    sequence: u64,
+    pixels: Vec<u8>,
+}
+
+// Hands complete frames from the thread running the emulator to the thread drawing them. The
+// writer fills a back buffer without holding the lock and only takes it to swap that buffer with
+// the published one; the reader only takes it to copy the published frame out. Neither side can
+// see a frame that's half written, and neither waits on the other for longer than that.
+pub fn frame_handoff(frame_len: usize) -> (FrameWriter, FrameReader) {
+    let blank = Frame {
+        sequence: 0,
+        pixels: vec![0; frame_len],
+    };
+    let published = Arc::new(Mutex::new(blank.clone()));
+    let writer = FrameWriter {
+        published: Arc::clone(&published),
+        back: blank.clone(),
+        sequence: 0,
+    };
+    let reader = FrameReader {
+        published,
+        front: blank,
+        missed: 0,
+    };
+    (writer, reader)
+}
+
+#[derive(Debug)]
+pub struct FrameWriter {
+    published: Arc<Mutex<Frame>>,
+    back: Frame,
+    sequence: u64,
+}
+
+impl FrameWriter {
+    // The frame being written. It holds an older frame, so it has to be filled in completely.
+    pub fn back_buffer(&mut self) -> &mut [u8] {
+        &mut self.back.pixels
+    }
+
+    // Makes the back buffer the frame the reader sees.
+    pub fn publish(&mut self) {
+        self.sequence += 1;
+        self.back.sequence = self.sequence;
+        let mut published = self.published.lock().unwrap_or_else(|err| err.into_inner());
+        std::mem::swap(&mut *published, &mut self.back);
+    }
+
+    // Publishes the display packed 8 pixels to a byte, as Display::pack_into lays it out.
+    pub fn publish_display(&mut self, display: &Display) {
+        display.pack_into(self.back_buffer());
+        self.publish();
+    }
+}
+
+#[derive(Debug)]
+pub struct FrameReader {
+    published: Arc<Mutex<Frame>>,
+    front: Frame,
+    missed: u64,
+}
+
+impl FrameReader {
+    // Copies out the most recently published frame if there's a new one, returning whether there
+    // was.
+    pub fn update(&mut self) -> bool {
+        let published = self.published.lock().unwrap_or_else(|err| err.into_inner());
+        if published.sequence == self.front.sequence {
+            return false;
+        }
+        self.missed += published.sequence - self.front.sequence - 1;
+        self.front.sequence = published.sequence;
+        self.front.pixels.copy_from_slice(&published.pixels);
+        true
+    }
+
+    // The frame copied out by the last update.
+    pub fn frame(&self) -> &[u8] {
+        &self.front.pixels
+    }
+
+    pub fn sequence(&self) -> u64 {
+        self.front.sequence
+    }
+
+    // Frames published that the reader never saw, because a newer one was published first.
+    pub fn missed(&self) -> u64 {
+        self.missed
+    }
+}
+
+// A keypad transition sent from the thread handling input to the thread running the emulator.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyInput {
+    Pressed(u8),
+    Released,
+}
+
+pub fn key_channel() -> (Sender<KeyInput>, Receiver<KeyInput>) {
+    std::sync::mpsc::channel()
+}
+
+// Applies every key transition sent so far, without waiting for more. Invalid keys are skipped
+// with a warning.
+pub fn apply_key_inputs(keys: &Receiver<KeyInput>, system: &mut Chip8) {
+    for input in keys.try_iter() {
+        match input {
+            KeyInput::Pressed(key) => {
+                if let Err(err) = system.press_key(key) {
+                    eprintln!("Warning: ignoring key {:#X}: {:?}", key, err);
+                }
+            }
+            KeyInput::Released => system.release_key(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::Fnv1a;
+    use std::hash::Hasher;
+
+    // Kept small so that the stress test spends its time handing frames over rather than filling
+    // them.
+    const FRAME_LEN: usize = 64;
+    const FRAMES: u64 = 300_000;
+    const CHECKSUM_LEN: usize = 8;
+
+    fn checksum(bytes: &[u8]) -> [u8; CHECKSUM_LEN] {
+        let mut hasher = Fnv1a::default();
+        hasher.write(bytes);
+        hasher.finish().to_be_bytes()
+    }
+
+    // Fills a frame with bytes derived from its number, ending in a checksum of the rest.
+    fn fill(frame: &mut [u8], number: u64) {
+        let (body, sum) = frame.split_at_mut(FRAME_LEN - CHECKSUM_LEN);
+        for (index, byte) in body.iter_mut().enumerate() {
+            *byte = (number as usize).wrapping_mul(31).wrapping_add(index) as u8;
+        }
+        sum.copy_from_slice(&checksum(body));
+    }
+
+    #[test]
+    fn test_reader_sees_only_complete_frames() {
+        let (mut writer, mut reader) = frame_handoff(FRAME_LEN);
+        let (keys, key_receiver) = key_channel();
+
+        let emulation = std::thread::spawn(move || {
+            let mut system = Chip8::new();
+            for number in 1..=FRAMES {
+                apply_key_inputs(&key_receiver, &mut system);
+                fill(writer.back_buffer(), number);
+                writer.publish();
+            }
+        });
+
+        let mut frames_seen = 0;
+        while reader.sequence() < FRAMES {
+            if !reader.update() {
+                continue;
+            }
+            frames_seen += 1;
+            let (body, sum) = reader.frame().split_at(FRAME_LEN - CHECKSUM_LEN);
+            assert_eq!(sum, checksum(body), "frame {} is torn", reader.sequence());
+            let mut expected = vec![0; FRAME_LEN];
+            fill(&mut expected, reader.sequence());
+            assert_eq!(reader.frame(), &expected[..]);
+            // Fails once the emulation thread is done, which is fine.
+            keys.send(KeyInput::Pressed((reader.sequence() % 16) as u8))
+                .ok();
+        }
+
+        emulation.join().unwrap();
+        assert_eq!(frames_seen + reader.missed(), FRAMES);
+    }
+
+    #[test]
+    fn test_publish_display() {
+        let mut display = Display::new();
+        let (mut writer, mut reader) = frame_handoff(display.width * display.height / 8);
+        display.draw_sprite(1, 8, &vec![0b1010_0000]).unwrap();
+
+        assert!(!reader.update());
+        writer.publish_display(&display);
+        writer.publish_display(&display);
+
+        assert!(reader.update());
+        assert_eq!(reader.sequence(), 2);
+        assert_eq!(reader.missed(), 1);
+        let mut expected = vec![0; display.width * display.height / 8];
+        expected[display.width / 8 + 1] = 0b1010_0000;
+        assert_eq!(reader.frame(), &expected[..]);
+    }
+
+    #[test]
+    fn test_apply_key_inputs() {
+        let (keys, key_receiver) = key_channel();
+        let mut system = Chip8::new();
+        keys.send(KeyInput::Pressed(0x4)).unwrap();
+        keys.send(KeyInput::Pressed(0x20)).unwrap();
+
+        apply_key_inputs(&key_receiver, &mut system);
+        assert_eq!(system.get_pressed_key(), Some(0x4));
+
+        keys.send(KeyInput::Released).unwrap();
+        apply_key_inputs(&key_receiver, &mut system);
+        assert_eq!(system.get_pressed_key(), None);
+    }
+}
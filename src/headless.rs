@@ -0,0 +1,194 @@
+use ch8emu::chip8::{Chip8, Chip8Error, Options, StopReason, TIMER_HZ};
+use std::time::Duration;
+
+const FRAME_DURATION: Duration = Duration::from_micros(1_000_000 / TIMER_HZ);
+pub const DEFAULT_FRAMES: u64 = 10 * TIMER_HZ;
+
+#[derive(Debug, PartialEq)]
+pub enum Ending {
+    // Ran for all the requested frames.
+    Completed,
+    Stopped(StopReason),
+    Failed(Chip8Error),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Outcome {
+    pub frames: u64,
+    pub ending: Ending,
+}
+
+// Runs the loaded ROM without a window for up to `frames` 60 Hz frames. There's no input, so the
+// run also ends when the program waits for a key.
+pub fn run(system: &mut Chip8, frames: u64) -> Outcome {
+    for frame in 0..frames {
+        match system.run_for(FRAME_DURATION) {
+            Ok(summary) => {
+                if let Some(reason) = summary.stopped {
+                    return Outcome {
+                        frames: frame + 1,
+                        ending: Ending::Stopped(reason),
+                    };
+                }
+            }
+            Err(err) => {
+                return Outcome {
+                    frames: frame + 1,
+                    ending: Ending::Failed(err),
+                }
+            }
+        }
+    }
+    Outcome {
+        frames,
+        ending: Ending::Completed,
+    }
+}
+
+// `options` with the strict ones turned off, for a --strict validation run: the ROM carries on
+// past each violation so that the report lists all of them, and report fails the run instead.
+pub fn validation_options(options: Options) -> Options {
+    Options {
+        strict_decode: false,
+        strict_alignment: false,
+        strict_memory: false,
+        strict_index: false,
+        strict_stack: false,
+        strict_sys: false,
+        ..options
+    }
+}
+
+// Summarizes the run and the violations the ROM committed. A run fails if emulation stopped with
+// an error or, when `strict`, if there was any violation.
+pub fn report(system: &Chip8, outcome: &Outcome, strict: bool) -> (String, bool) {
+    let ending = match &outcome.ending {
+        Ending::Completed => "completed".to_string(),
+        Ending::Stopped(reason) => format!("stopped: {:?}", reason),
+        Ending::Failed(err) => format!("failed: {:?}", err),
+    };
+    let mut lines = vec![format!("Ran {} frames, {}", outcome.frames, ending)];
+    let violations = system.violations();
+    if !violations.is_empty() {
+        lines.push("Violations:".to_string());
+        lines.push(violations.report());
+    }
+    let failed = matches!(outcome.ending, Ending::Failed(_)) || (strict && !violations.is_empty());
+    (lines.join("\n"), failed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ch8emu::chip8::{BootConfig, Options, Violation};
+
+    // Commits one of each violation, then halts at an odd address.
+    const NAUGHTY_ROM: [u8; 25] = [
+        0xA1, 0x00, // 0x200: I = 0x100
+        0x60, 0x01, // 0x202: V0 = 1
+        0xF0, 0x55, // 0x204: store V0 at I, below the program area
+        0xFA, 0xBC, // 0x206: undefined
+        0xA3, 0x00, // 0x208: I = 0x300
+        0xF0, 0x65, // 0x20A: load V0 from uninitialized memory
+        0xF0, 0x00, 0x0F, 0xFF, // 0x20C: I = 0xFFF
+        0x61, 0x02, // 0x210: V1 = 2
+        0xF1, 0x1E, // 0x212: I += V1, past 0xFFF
+        0x12, 0x17, // 0x214: jump to an odd address
+        0x00, // 0x216: padding
+        0x12, 0x17, // 0x217: halt
+    ];
+
+    fn naughty_machine(options: Options) -> Chip8 {
+        let mut system = Chip8::with_options(options).unwrap();
+        system.boot(BootConfig::default()).unwrap();
+        system.load_rom(&NAUGHTY_ROM).unwrap();
+        system
+    }
+
+    #[test]
+    fn test_run_lenient_collects_every_violation() {
+        let mut system = naughty_machine(Options::default());
+
+        let outcome = run(&mut system, DEFAULT_FRAMES);
+
+        assert_eq!(outcome.ending, Ending::Stopped(StopReason::Halted));
+        assert_eq!(
+            system.violations().entries(),
+            vec![
+                (0x204, Violation::WriteBelowProgram(0x100), 1),
+                (0x206, Violation::UndefinedOpcode(0xFABC), 1),
+                (0x20A, Violation::UninitializedRead(0x300), 1),
+                (0x212, Violation::IndexOverflow, 1),
+                (0x214, Violation::MisalignedPc(0x217), 1),
+            ]
+        );
+
+        let (report, failed) = report(&system, &outcome, false);
+        assert!(!failed);
+        assert_eq!(report.lines().count(), 7);
+    }
+
+    #[test]
+    fn test_run_strict_fails_on_first_violation() {
+        let mut system = naughty_machine(Options::strict());
+
+        let outcome = run(&mut system, DEFAULT_FRAMES);
+
+        assert_eq!(
+            outcome.ending,
+            Ending::Failed(Chip8Error::ViolationError(Violation::WriteBelowProgram(
+                0x100
+            )))
+        );
+        let (report, failed) = report(&system, &outcome, true);
+        assert!(failed);
+        assert_eq!(
+            report,
+            "Ran 1 frames, failed: ViolationError(WriteBelowProgram(256))\n\
+             Violations:\n\
+             0x204: write below the program area at 0x100 (x1)"
+        );
+    }
+
+    #[test]
+    fn test_run_validation_reports_every_violation() {
+        let mut system = naughty_machine(validation_options(Options::strict()));
+
+        let outcome = run(&mut system, DEFAULT_FRAMES);
+
+        assert_eq!(outcome.ending, Ending::Stopped(StopReason::Halted));
+        let (report, failed) = report(&system, &outcome, true);
+        assert!(failed);
+        assert_eq!(
+            report,
+            "Ran 2 frames, stopped: Halted\n\
+             Violations:\n\
+             0x204: write below the program area at 0x100 (x1)\n\
+             0x206: undefined opcode 0xfabc (x1)\n\
+             0x20a: read of uninitialized memory at 0x300 (x1)\n\
+             0x212: I overflowed past the end of memory (x1)\n\
+             0x214: pc moved to odd address 0x217 (x1)"
+        );
+        assert!(!super::report(&system, &outcome, false).1);
+    }
+
+    #[test]
+    fn test_run_completes_requested_frames() {
+        let mut system = Chip8::booted();
+        system.load_rom(&[0x70, 0x01, 0x12, 0x00]).unwrap();
+
+        let outcome = run(&mut system, 3);
+
+        assert_eq!(
+            outcome,
+            Outcome {
+                frames: 3,
+                ending: Ending::Completed
+            }
+        );
+        assert_eq!(
+            report(&system, &outcome, true),
+            ("Ran 3 frames, completed".to_string(), false)
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use crate::render::PIXEL_OFF;
+use crate::text;
+use std::time::Duration;
+
+pub const HUD_COLOR: u32 = 0x0040_FF40;
+// How often the shown numbers change, so they can be read.
+pub const HUD_REFRESH: Duration = Duration::from_millis(250);
+// Frame pixels per font pixel are added as the window grows past this width.
+const SCALE_STEP: usize = 320;
+
+// What the performance HUD shows.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Stats {
+    // Instructions per second actually achieved.
+    pub ips: f64,
+    // Frames presented per second.
+    pub fps: f64,
+    // Time the last frame's batch of instructions took to run.
+    pub cpu_time: Duration,
+    // Time the last redraw took, painting and presenting.
+    pub render_time: Duration,
+    // Frames the pacer gave up on after stalls.
+    pub dropped: u64,
+    // Frames that weren't redrawn because nothing on screen changed.
+    pub skipped: u64,
+}
+
+impl Stats {
+    pub fn lines(&self) -> [String; 4] {
+        [
+            format!("{:.0} ips", self.ips),
+            format!("{:.1} fps", self.fps),
+            format!(
+                "cpu {:.2}ms draw {:.2}ms",
+                millis(self.cpu_time),
+                millis(self.render_time)
+            ),
+            format!("dropped {} skipped {}", self.dropped, self.skipped),
+        ]
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+// Frame pixels per font pixel for text over a window `width` pixels wide.
+pub fn text_scale(width: usize) -> usize {
+    width / SCALE_STEP + 1
+}
+
+// Draws the stats in the top left corner on a blank box, so they can be read over the game.
+pub fn draw_hud(frame: &mut [u32], width: usize, height: usize, stats: &Stats) {
+    let scale = text_scale(width);
+    let lines = stats.lines();
+    let line_height = (text::GLYPH_HEIGHT + 1) * scale;
+    let box_width = lines
+        .iter()
+        .map(|line| text::text_width(line, scale))
+        .max()
+        .unwrap_or(0)
+        + 2 * scale;
+    let box_height = lines.len() * line_height + scale;
+    for y in 0..box_height.min(height) {
+        frame[y * width..y * width + box_width.min(width)].fill(PIXEL_OFF);
+    }
+    for (index, line) in lines.iter().enumerate() {
+        let origin = (scale, scale + index * line_height);
+        text::draw_text(frame, width, height, origin, line, scale, HUD_COLOR);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::PIXEL_ON;
+
+    #[test]
+    fn test_stats_lines() {
+        let stats = Stats {
+            ips: 699.6,
+            fps: 59.94,
+            cpu_time: Duration::from_micros(1_250),
+            render_time: Duration::from_micros(406),
+            dropped: 3,
+            skipped: 120,
+        };
+
+        assert_eq!(
+            stats.lines(),
+            [
+                "700 ips".to_string(),
+                "59.9 fps".to_string(),
+                "cpu 1.25ms draw 0.41ms".to_string(),
+                "dropped 3 skipped 120".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_draw_hud() {
+        let (width, height) = (128, 64);
+        let mut frame = vec![PIXEL_ON; width * height];
+
+        draw_hud(&mut frame, width, height, &Stats::default());
+
+        // The box is blanked behind the text, and the rest of the frame is left alone.
+        assert_eq!(frame[0], PIXEL_OFF);
+        assert_eq!(frame[width - 1], PIXEL_ON);
+        assert_eq!(frame[(height - 1) * width], PIXEL_ON);
+        // The top of the first line's "0".
+        assert_eq!(frame[width + 1], HUD_COLOR);
+        assert_eq!(frame[24 * width + 88], PIXEL_OFF);
+        assert_eq!(frame[25 * width], PIXEL_ON);
+        assert_eq!(frame[24 * width + 89], PIXEL_ON);
+    }
+}
@@ -0,0 +1,183 @@
+use crate::chip8::{
+    disassemble, extension_target, Family, Line, Opcode, Sweep, Target, MEMORY_SIZE,
+    PROGRAM_START_ADDRESS,
+};
+use crate::replay::rom_hash;
+
+const FAMILIES: [(Family, &str); 7] = [
+    (Family::Flow, "flow"),
+    (Family::Register, "register"),
+    (Family::Memory, "memory"),
+    (Family::Display, "display"),
+    (Family::Input, "input"),
+    (Family::Timer, "timer"),
+    (Family::System, "system"),
+];
+
+// What can be told about a ROM without running it, from its reachable code.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RomInfo {
+    pub size: usize,
+    // The hash replays and saves identify the ROM by.
+    pub hash: u64,
+    pub load_address: u16,
+    // Bytes of reachable instructions, and of everything else.
+    pub code_bytes: usize,
+    pub data_bytes: usize,
+    // Reachable instructions in each family, in the order of FAMILIES.
+    pub families: [usize; FAMILIES.len()],
+    // The earliest platform with every reachable instruction, and the first instruction that
+    // needs it if that's not plain CHIP-8.
+    pub target: Target,
+    pub needed_by: Option<(u16, u16)>,
+    // The lowest and highest addresses instructions jump to, call or point I at.
+    pub address_range: Option<(u16, u16)>,
+}
+
+impl RomInfo {
+    pub fn new(rom: &[u8]) -> Self {
+        let load_address = PROGRAM_START_ADDRESS as u16;
+        let mut info = RomInfo {
+            size: rom.len(),
+            hash: rom_hash(rom),
+            load_address,
+            code_bytes: 0,
+            data_bytes: 0,
+            families: [0; FAMILIES.len()],
+            target: Target::Chip8,
+            needed_by: None,
+            address_range: None,
+        };
+        for line in disassemble(rom, load_address, Sweep::Reachable) {
+            let instruction = match line {
+                Line::Code(instruction) => instruction,
+                Line::Data { .. } => {
+                    info.data_bytes += 1;
+                    continue;
+                }
+            };
+            info.code_bytes += instruction.len as usize;
+            let family = instruction.op.family();
+            if let Some(index) = FAMILIES.iter().position(|(f, _)| *f == family) {
+                info.families[index] += 1;
+            }
+            let needs_more = match (extension_target(instruction.word), info.target) {
+                (None, _) | (Some(Target::XoChip), Target::XoChip) => None,
+                (Some(Target::XoChip), _) => Some(Target::XoChip),
+                (Some(extension), Target::Chip8) => Some(extension),
+                (Some(_), _) => None,
+            };
+            if let Some(target) = needs_more {
+                info.target = target;
+                info.needed_by = Some((instruction.address, instruction.word));
+            }
+            let referenced = match instruction.op {
+                Opcode::Jump(address)
+                | Opcode::Call(address)
+                | Opcode::SetIndex(address)
+                | Opcode::JumpV0(address) => Some(address),
+                Opcode::LoadLongI => instruction.operand,
+                _ => None,
+            };
+            if let Some(address) = referenced {
+                info.address_range = Some(match info.address_range {
+                    Some((low, high)) => (low.min(address), high.max(address)),
+                    None => (address, address),
+                });
+            }
+        }
+        info
+    }
+
+    // Whether the ROM fits in the original 4 KB from its load address.
+    pub fn fits_in_memory(&self) -> bool {
+        usize::from(self.load_address) + self.size <= MEMORY_SIZE
+    }
+
+    pub fn to_text(&self) -> String {
+        let end = usize::from(self.load_address) + self.size;
+        let families: Vec<String> = FAMILIES
+            .iter()
+            .zip(self.families)
+            .map(|((_, name), count)| format!("{} {}", name, count))
+            .collect();
+        let mut lines = vec![
+            format!("size: {} bytes", self.size),
+            format!("hash: {:016x}", self.hash),
+            format!(
+                "loads at: {:#05X} to {:#05X}, {}",
+                self.load_address,
+                end.saturating_sub(1),
+                if self.fits_in_memory() {
+                    "fits in 4 KB"
+                } else {
+                    "needs more than 4 KB"
+                }
+            ),
+            format!(
+                "reachable code: {} bytes, data: {} bytes",
+                self.code_bytes, self.data_bytes
+            ),
+            format!("instructions: {}", families.join(", ")),
+        ];
+        lines.push(match self.needed_by {
+            Some((address, word)) => format!(
+                "needs: {} for {:04X} at {:#05X}",
+                self.target.name(),
+                word,
+                address
+            ),
+            None => format!("needs: {}", self.target.name()),
+        });
+        lines.push(match self.address_range {
+            Some((low, high)) => format!("referenced addresses: {:#05X} to {:#05X}", low, high),
+            None => "referenced addresses: none".to_string(),
+        });
+        lines.push(String::new());
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::assemble_opcodes;
+
+    #[test]
+    fn test_rom_info() {
+        let mut rom = assemble_opcodes(&[
+            Opcode::SetIndex(0x20A),
+            Opcode::Draw(0x0, 0x0, 0),
+            Opcode::Call(0x208),
+            Opcode::Jump(0x206),
+            Opcode::Return,
+        ]);
+        rom.extend([0xFF, 0x81]);
+
+        let info = RomInfo::new(&rom);
+
+        assert_eq!(info.size, 12);
+        assert_eq!((info.code_bytes, info.data_bytes), (10, 2));
+        assert_eq!(info.families, [3, 0, 1, 1, 0, 0, 0]);
+        assert_eq!(info.target, Target::SuperChipLegacy);
+        assert_eq!(info.needed_by, Some((0x202, 0xD000)));
+        assert_eq!(info.address_range, Some((0x206, 0x20A)));
+        assert!(info.fits_in_memory());
+    }
+
+    #[test]
+    fn test_rom_info_xo_chip_wins_over_super_chip() {
+        let rom = assemble_opcodes(&[
+            Opcode::Draw(0x0, 0x0, 0),
+            Opcode::ScrollUp(2),
+            Opcode::Draw(0x0, 0x0, 0),
+            Opcode::Jump(0x206),
+        ]);
+
+        let info = RomInfo::new(&rom);
+
+        assert_eq!(info.target, Target::XoChip);
+        assert_eq!(info.needed_by, Some((0x202, 0x00D2)));
+        assert!(!RomInfo::new(&vec![0; MEMORY_SIZE]).fits_in_memory());
+    }
+}
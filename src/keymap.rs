@@ -0,0 +1,389 @@
+use ch8emu::chip8::input;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+// Host keys are named after the logical key the window reports: lowercase characters, "space",
+// and "numpad" followed by the character for keys on the numeric keypad.
+const CLASSIC: [(&str, &str); 16] = [
+    ("1", "1"),
+    ("2", "2"),
+    ("3", "3"),
+    ("4", "c"),
+    ("q", "4"),
+    ("w", "5"),
+    ("e", "6"),
+    ("r", "d"),
+    ("a", "7"),
+    ("s", "8"),
+    ("d", "9"),
+    ("f", "e"),
+    ("z", "a"),
+    ("x", "0"),
+    ("c", "b"),
+    ("v", "f"),
+];
+
+// WASD drives the 2/4/6/8 keys most games use as a D-pad, with space on 5 and the surrounding
+// keys on the diagonals. The rarely used A-F keys sit on the number row.
+const WASD: [(&str, &str); 16] = [
+    ("w", "2"),
+    ("a", "4"),
+    ("s", "8"),
+    ("d", "6"),
+    ("space", "5"),
+    ("q", "1"),
+    ("e", "3"),
+    ("z", "7"),
+    ("c", "9"),
+    ("x", "0"),
+    ("1", "a"),
+    ("2", "b"),
+    ("3", "c"),
+    ("4", "d"),
+    ("5", "e"),
+    ("6", "f"),
+];
+
+// The numeric keypad's physical layout mapped onto the 4x4 grid, so 7/8/9 on the top row of the
+// numpad are 1/2/3 on the CHIP-8 keypad.
+const NUMPAD: [(&str, &str); 16] = [
+    ("numpad7", "1"),
+    ("numpad8", "2"),
+    ("numpad9", "3"),
+    ("numpad/", "c"),
+    ("numpad4", "4"),
+    ("numpad5", "5"),
+    ("numpad6", "6"),
+    ("numpad*", "d"),
+    ("numpad1", "7"),
+    ("numpad2", "8"),
+    ("numpad3", "9"),
+    ("numpad-", "e"),
+    ("numpad0", "a"),
+    ("numpad.", "0"),
+    ("numpadenter", "b"),
+    ("numpad+", "f"),
+];
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Preset {
+    #[default]
+    Classic,
+    Wasd,
+    Numpad,
+}
+
+impl Preset {
+    pub fn entries(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Preset::Classic => &CLASSIC,
+            Preset::Wasd => &WASD,
+            Preset::Numpad => &NUMPAD,
+        }
+    }
+
+    pub fn next(&self) -> Preset {
+        match self {
+            Preset::Classic => Preset::Wasd,
+            Preset::Wasd => Preset::Numpad,
+            Preset::Numpad => Preset::Classic,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preset::Classic => "classic",
+            Preset::Wasd => "wasd",
+            Preset::Numpad => "numpad",
+        }
+    }
+}
+
+impl FromStr for Preset {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "classic" => Ok(Preset::Classic),
+            "wasd" => Ok(Preset::Wasd),
+            "numpad" => Ok(Preset::Numpad),
+            _ => Err(format!("unknown keypad preset `{}`", value)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Binding {
+    pub chip8_key: String,
+    // The preset's binding for the same host key, if an override replaced it.
+    pub replaced: Option<String>,
+}
+
+// The effective host key to CHIP-8 key mapping: a preset with per-key overrides layered on top.
+// An override wins over the preset's binding of the same host key. Several host keys may drive
+// the same CHIP-8 key, so an override never removes the preset's other bindings.
+#[derive(Debug, PartialEq)]
+pub struct KeyMap {
+    preset: Preset,
+    bindings: BTreeMap<String, Binding>,
+}
+
+impl KeyMap {
+    pub fn resolve(preset: Preset, overrides: &[(String, String)]) -> Self {
+        let mut bindings: BTreeMap<String, Binding> = preset
+            .entries()
+            .iter()
+            .map(|&(host_key, chip8_key)| {
+                let binding = Binding {
+                    chip8_key: chip8_key.to_string(),
+                    replaced: None,
+                };
+                (host_key.to_string(), binding)
+            })
+            .collect();
+
+        for (host_key, chip8_key) in overrides {
+            let replaced = bindings
+                .remove(host_key)
+                .map(|binding| binding.replaced.unwrap_or(binding.chip8_key))
+                .filter(|previous| previous != chip8_key);
+            let binding = Binding {
+                chip8_key: chip8_key.clone(),
+                replaced,
+            };
+            bindings.insert(host_key.clone(), binding);
+        }
+
+        KeyMap { preset, bindings }
+    }
+
+    pub fn preset(&self) -> Preset {
+        self.preset
+    }
+
+    pub fn get_mapped_key(&self, host_key: &str) -> Option<&str> {
+        self.bindings
+            .get(host_key)
+            .map(|binding| binding.chip8_key.as_str())
+    }
+
+    // The keypad key a host key presses, or None if it isn't bound or is bound to something
+    // that isn't a keypad key. Bindings name keys in either case.
+    pub fn keypad_key(&self, host_key: &str) -> Option<u8> {
+        input::parse_key(self.get_mapped_key(host_key)?).ok()
+    }
+
+    // Every (host key, CHIP-8 key) pair in effect, ordered by host key, for help screens that
+    // show the real mapping. Overrides naming something other than a keypad key are left out,
+    // since they never press anything.
+    pub fn bindings(&self) -> impl Iterator<Item = (&str, u8)> + '_ {
+        self.bindings.iter().filter_map(|(host_key, binding)| {
+            let key = input::parse_key(&binding.chip8_key).ok()?;
+            Some((host_key.as_str(), key))
+        })
+    }
+
+    // The keypad's rows as laid out on the VIP, each key followed by a host key that presses it,
+    // or `-` if none does: `1:1 2:2 3:3 C:4` for the first row of the classic preset.
+    pub fn help_rows(&self) -> Vec<String> {
+        const ROWS: [[u8; 4]; 4] = [
+            [0x1, 0x2, 0x3, 0xC],
+            [0x4, 0x5, 0x6, 0xD],
+            [0x7, 0x8, 0x9, 0xE],
+            [0xA, 0x0, 0xB, 0xF],
+        ];
+        ROWS.iter()
+            .map(|row| {
+                let keys: Vec<String> = row
+                    .iter()
+                    .map(|&key| {
+                        let host_key = self
+                            .bindings()
+                            .find(|&(_, bound)| bound == key)
+                            .map_or("-", |(host_key, _)| host_key);
+                        format!("{:X}:{}", key, host_key)
+                    })
+                    .collect();
+                keys.join(" ")
+            })
+            .collect()
+    }
+
+    // One line per binding, ordered by CHIP-8 key.
+    pub fn describe(&self) -> String {
+        let mut bindings: Vec<(&String, &Binding)> = self.bindings.iter().collect();
+        bindings.sort_by(|a, b| a.1.chip8_key.cmp(&b.1.chip8_key).then(a.0.cmp(b.0)));
+
+        let mut lines = vec![format!("preset: {}", self.preset.name())];
+        for (host_key, binding) in bindings {
+            let mut line = format!("{} -> {}", host_key, binding.chip8_key);
+            if let Some(replaced) = &binding.replaced {
+                line.push_str(&format!(" (overrides preset {})", replaced));
+            }
+            lines.push(line);
+        }
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overrides(entries: &[(&str, &str)]) -> Vec<(String, String)> {
+        entries
+            .iter()
+            .map(|&(host_key, chip8_key)| (host_key.to_string(), chip8_key.to_string()))
+            .collect()
+    }
+
+    fn chip8_keys(preset: Preset) -> Vec<&'static str> {
+        let mut keys: Vec<&str> = preset.entries().iter().map(|&(_, key)| key).collect();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn test_presets_cover_all_keys() {
+        let all_keys = vec![
+            "0", "1", "2", "3", "4", "5", "6", "7", "8", "9", "a", "b", "c", "d", "e", "f",
+        ];
+        for preset in [Preset::Classic, Preset::Wasd, Preset::Numpad] {
+            assert_eq!(chip8_keys(preset), all_keys, "{:?}", preset);
+        }
+    }
+
+    #[test]
+    fn test_keypad_key_uses_the_binding() {
+        let key_map = KeyMap::resolve(Preset::Classic, &overrides(&[("p", "B")]));
+
+        // Host keys that are hex digits themselves press what they're bound to.
+        assert_eq!(key_map.keypad_key("a"), Some(0x7));
+        assert_eq!(key_map.keypad_key("4"), Some(0xC));
+        assert_eq!(key_map.keypad_key("v"), Some(0xF));
+        assert_eq!(key_map.keypad_key("p"), Some(0xB));
+        assert_eq!(key_map.keypad_key("b"), None);
+        for preset in [Preset::Classic, Preset::Wasd, Preset::Numpad] {
+            let key_map = KeyMap::resolve(preset, &[]);
+            for &(host_key, _) in preset.entries() {
+                assert!(key_map.keypad_key(host_key).is_some(), "{}", host_key);
+            }
+        }
+    }
+
+    #[test]
+    fn test_preset_from_str() {
+        assert_eq!("wasd".parse(), Ok(Preset::Wasd));
+        assert_eq!(
+            "dvorak".parse::<Preset>(),
+            Err("unknown keypad preset `dvorak`".to_string())
+        );
+    }
+
+    #[test]
+    fn test_resolve_preset_only() {
+        let key_map = KeyMap::resolve(Preset::Wasd, &[]);
+
+        assert_eq!(key_map.get_mapped_key("w"), Some("2"));
+        assert_eq!(key_map.get_mapped_key("space"), Some("5"));
+        assert_eq!(key_map.get_mapped_key("v"), None);
+    }
+
+    #[test]
+    fn test_resolve_override_wins_over_preset() {
+        let key_map = KeyMap::resolve(Preset::Classic, &overrides(&[("w", "2")]));
+
+        assert_eq!(key_map.get_mapped_key("w"), Some("2"));
+        assert_eq!(
+            key_map.bindings["w"].replaced,
+            Some("5".to_string()),
+            "the replaced preset binding is kept for reporting"
+        );
+        // The preset's own binding of 2 is left alone.
+        assert_eq!(key_map.get_mapped_key("2"), Some("2"));
+    }
+
+    #[test]
+    fn test_resolve_override_adds_new_host_key() {
+        let key_map = KeyMap::resolve(Preset::Classic, &overrides(&[("p", "5")]));
+
+        assert_eq!(key_map.get_mapped_key("p"), Some("5"));
+        assert_eq!(key_map.get_mapped_key("w"), Some("5"));
+        assert_eq!(key_map.bindings["p"].replaced, None);
+    }
+
+    #[test]
+    fn test_resolve_later_override_wins_and_remembers_preset() {
+        let key_map = KeyMap::resolve(Preset::Classic, &overrides(&[("w", "2"), ("w", "8")]));
+
+        assert_eq!(key_map.get_mapped_key("w"), Some("8"));
+        assert_eq!(key_map.bindings["w"].replaced, Some("5".to_string()));
+    }
+
+    #[test]
+    fn test_resolve_override_matching_preset_is_not_a_conflict() {
+        let key_map = KeyMap::resolve(Preset::Classic, &overrides(&[("w", "5")]));
+
+        assert_eq!(key_map.bindings["w"].replaced, None);
+    }
+
+    #[test]
+    fn test_resolve_overrides_survive_preset_switch() {
+        let overrides = overrides(&[("w", "0")]);
+        let classic = KeyMap::resolve(Preset::Classic, &overrides);
+        let wasd = KeyMap::resolve(classic.preset().next(), &overrides);
+
+        assert_eq!(wasd.preset(), Preset::Wasd);
+        assert_eq!(wasd.get_mapped_key("w"), Some("0"));
+        assert_eq!(wasd.bindings["w"].replaced, Some("2".to_string()));
+    }
+
+    #[test]
+    fn test_bindings_of_default_map() {
+        let key_map = KeyMap::resolve(Preset::default(), &[]);
+        let bindings: Vec<(&str, u8)> = key_map.bindings().collect();
+
+        assert_eq!(bindings.len(), 16);
+        assert!(bindings.contains(&("q", 0x4)));
+        assert!(bindings.contains(&("v", 0xF)));
+        let mut keys: Vec<u8> = bindings.iter().map(|&(_, key)| key).collect();
+        keys.sort();
+        assert_eq!(keys, (0..16).collect::<Vec<u8>>());
+    }
+
+    #[test]
+    fn test_bindings_skip_invalid_overrides() {
+        let key_map = KeyMap::resolve(Preset::Classic, &overrides(&[("p", "5"), ("o", "g")]));
+        let bindings: Vec<(&str, u8)> = key_map.bindings().collect();
+
+        assert_eq!(bindings.len(), 17);
+        assert!(bindings.contains(&("p", 0x5)));
+    }
+
+    #[test]
+    fn test_help_rows() {
+        let key_map = KeyMap::resolve(Preset::Wasd, &overrides(&[("space", "q")]));
+
+        assert_eq!(
+            key_map.help_rows(),
+            vec![
+                "1:q 2:w 3:e C:3",
+                "4:a 5:- 6:d D:4",
+                "7:z 8:s 9:c E:5",
+                "A:1 0:x B:2 F:6"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_describe() {
+        let key_map = KeyMap::resolve(Preset::Numpad, &overrides(&[("numpad5", "0")]));
+        let description = key_map.describe();
+        let lines: Vec<&str> = description.lines().collect();
+
+        assert_eq!(lines[0], "preset: numpad");
+        assert_eq!(lines[1], "numpad. -> 0");
+        assert_eq!(lines[2], "numpad5 -> 0 (overrides preset 5)");
+        assert_eq!(lines.len(), 17);
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::VecDeque;
+use std::time::Instant;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyTransition {
+    // A keypad key, 0x0 to 0xF.
+    Pressed(u8),
+    Released,
+}
+
+// Buffers keypad transitions between instruction batches. All of a frame's instructions run in one
+// burst, so a key pressed and released between two bursts would never be seen by the ROM. Instead
+// each batch only delivers transitions of one kind, so a press stays held for at least one full
+// batch and so does the release that ends it. Transitions that are held back wait their turn.
+#[derive(Debug, Default)]
+pub struct KeyQueue {
+    pending: VecDeque<(Instant, KeyTransition)>,
+}
+
+impl KeyQueue {
+    pub fn new() -> Self {
+        KeyQueue::default()
+    }
+
+    pub fn push(&mut self, at: Instant, transition: KeyTransition) {
+        self.pending.push_back((at, transition));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    // Returns the transitions to apply before running the batch of instructions that ends at `now`.
+    pub fn next_batch(&mut self, now: Instant) -> Vec<KeyTransition> {
+        let mut batch: Vec<KeyTransition> = Vec::new();
+        while let Some((at, transition)) = self.pending.front() {
+            let changes_kind = batch.last().is_some_and(|last| {
+                matches!(last, KeyTransition::Released)
+                    != matches!(transition, KeyTransition::Released)
+            });
+            if *at > now || changes_kind {
+                break;
+            }
+            if let Some((_, transition)) = self.pending.pop_front() {
+                batch.push(transition);
+            }
+        }
+        batch
+    }
+}
+
+// The transition made by a keypad key's host key going down or up. While a key is held the OS
+// repeats its key down; those repeats are dropped so the ROM sees one press until the release.
+pub fn transition(pressed: bool, repeat: bool, key: u8) -> Option<KeyTransition> {
+    match (pressed, repeat) {
+        (true, true) => None,
+        (true, false) => Some(KeyTransition::Pressed(key)),
+        (false, _) => Some(KeyTransition::Released),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    const FRAME: Duration = Duration::from_micros(16_667);
+    const INSTRUCTIONS_PER_FRAME: usize = 10;
+
+    // Mimics a ROM counting taps of one key: spin on EX9E until it's pressed, then on EXA1 until
+    // it's released.
+    #[derive(Debug, Default)]
+    struct TapCounter {
+        waiting_for_release: bool,
+        taps: u32,
+    }
+
+    impl TapCounter {
+        fn poll(&mut self, pressed_key: Option<u8>, key: u8) {
+            let pressed = pressed_key == Some(key);
+            if !self.waiting_for_release && pressed {
+                self.taps += 1;
+                self.waiting_for_release = true;
+            } else if self.waiting_for_release && !pressed {
+                self.waiting_for_release = false;
+            }
+        }
+    }
+
+    // Feeds a timeline of (milliseconds, transition) through the queue, running one batch of polls
+    // per frame, and returns the number of taps the polling loop saw.
+    fn count_taps(timeline: &[(u64, KeyTransition)], frames: u32) -> u32 {
+        let start = Instant::now();
+        let mut queue = KeyQueue::new();
+        for (millis, transition) in timeline {
+            queue.push(start + Duration::from_millis(*millis), transition.clone());
+        }
+
+        let mut pressed_key: Option<u8> = None;
+        let mut counter = TapCounter::default();
+        for frame in 1..=frames {
+            for transition in queue.next_batch(start + FRAME * frame) {
+                pressed_key = match transition {
+                    KeyTransition::Pressed(key) => Some(key),
+                    KeyTransition::Released => None,
+                };
+            }
+            for _ in 0..INSTRUCTIONS_PER_FRAME {
+                counter.poll(pressed_key, 0x5);
+            }
+        }
+        counter.taps
+    }
+
+    fn tap(press_millis: u64, release_millis: u64) -> [(u64, KeyTransition); 2] {
+        [
+            (press_millis, KeyTransition::Pressed(0x5)),
+            (release_millis, KeyTransition::Released),
+        ]
+    }
+
+    #[test]
+    fn test_transition_drops_held_repeats() {
+        assert_eq!(
+            transition(true, false, 0x5),
+            Some(KeyTransition::Pressed(0x5))
+        );
+        assert_eq!(transition(true, true, 0x5), None);
+        assert_eq!(transition(false, false, 0x5), Some(KeyTransition::Released));
+    }
+
+    #[test]
+    fn test_next_batch_only_returns_due_transitions() {
+        let start = Instant::now();
+        let mut queue = KeyQueue::new();
+        queue.push(start, KeyTransition::Pressed(0x1));
+        queue.push(start + FRAME * 2, KeyTransition::Released);
+
+        assert_eq!(
+            queue.next_batch(start + FRAME),
+            vec![KeyTransition::Pressed(0x1)]
+        );
+        assert_eq!(queue.next_batch(start + FRAME), vec![]);
+        assert_eq!(
+            queue.next_batch(start + FRAME * 2),
+            vec![KeyTransition::Released]
+        );
+    }
+
+    #[test]
+    fn test_next_batch_holds_release_of_press_in_same_batch() {
+        let start = Instant::now();
+        let mut queue = KeyQueue::new();
+        queue.push(start, KeyTransition::Pressed(0x1));
+        queue.push(start, KeyTransition::Released);
+
+        assert_eq!(
+            queue.next_batch(start + FRAME),
+            vec![KeyTransition::Pressed(0x1)]
+        );
+        assert_eq!(
+            queue.next_batch(start + FRAME * 2),
+            vec![KeyTransition::Released]
+        );
+    }
+
+    #[test]
+    fn test_next_batch_holds_press_after_release_in_same_batch() {
+        let start = Instant::now();
+        let mut queue = KeyQueue::new();
+        queue.push(start, KeyTransition::Released);
+        queue.push(start, KeyTransition::Pressed(0x1));
+
+        assert_eq!(
+            queue.next_batch(start + FRAME),
+            vec![KeyTransition::Released]
+        );
+        assert_eq!(
+            queue.next_batch(start + FRAME),
+            vec![KeyTransition::Pressed(0x1)]
+        );
+    }
+
+    #[test]
+    fn test_long_press_is_one_tap() {
+        assert_eq!(count_taps(&tap(5, 200), 20), 1);
+    }
+
+    #[test]
+    fn test_tap_between_batches_is_observed() {
+        // Pressed and released within the first frame, before any instruction ran.
+        assert_eq!(count_taps(&tap(2, 6), 5), 1);
+    }
+
+    #[test]
+    fn test_fast_taps_are_all_observed() {
+        // Three taps within a single frame, then two more straddling a frame boundary.
+        let timeline: Vec<(u64, KeyTransition)> =
+            [tap(1, 3), tap(5, 8), tap(10, 12), tap(30, 34), tap(35, 36)]
+                .into_iter()
+                .flatten()
+                .collect();
+
+        assert_eq!(count_taps(&timeline, 20), 5);
+    }
+}
@@ -0,0 +1,305 @@
+use ch8emu::replay::Replay;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_ROM_SECONDS: u64 = 90;
+// Time without a key press after which a player is considered gone and the rotation resumes.
+pub const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+const ROM_EXTENSIONS: [&str; 4] = ["ch8", "c8", "sc8", "xo8"];
+const REPLAY_EXTENSION: &str = "c8replay";
+
+// The ROMs in `dir`, by file name.
+pub fn playlist(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let entries = std::fs::read_dir(dir).map_err(|err| err.to_string())?;
+    let mut roms: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .and_then(|extension| extension.to_str())
+                .is_some_and(|extension| {
+                    ROM_EXTENSIONS.contains(&extension.to_lowercase().as_str())
+                })
+        })
+        .collect();
+    roms.sort();
+    if roms.is_empty() {
+        return Err("no ROMs found".to_string());
+    }
+    Ok(roms)
+}
+
+// The replay played as attract mode input for a ROM, if there's one next to it.
+pub fn attract_replay_path(rom: &Path) -> PathBuf {
+    rom.with_extension(REPLAY_EXTENSION)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    // Rotating through the ROMs on their own; the current one started at the given instant.
+    Attract(Instant),
+    // Someone is playing; the last key was pressed at the given instant.
+    Player(Instant),
+    // Every ROM failed to load, so there's nothing to rotate through.
+    Stopped,
+}
+
+// Decides when to move on to the next ROM of a playlist. It doesn't load anything itself: the
+// caller loads the ROM it's told to and reports back with `loaded` or `load_failed`.
+#[derive(Debug)]
+pub struct Kiosk {
+    roms: Vec<PathBuf>,
+    // Index of the ROM running, or being loaded.
+    current: usize,
+    rom_duration: Duration,
+    mode: Mode,
+    // ROMs that failed to load in a row.
+    failures: usize,
+}
+
+impl Kiosk {
+    // The playlist must not be empty. Load `current()` first.
+    pub fn new(roms: Vec<PathBuf>, rom_duration: Duration, now: Instant) -> Kiosk {
+        assert!(!roms.is_empty(), "kiosk playlist is empty");
+        Kiosk {
+            roms,
+            current: 0,
+            rom_duration,
+            mode: Mode::Attract(now),
+            failures: 0,
+        }
+    }
+
+    pub fn current(&self) -> &Path {
+        &self.roms[self.current]
+    }
+
+    // Call on every key press. Returns whether it took over from the rotation.
+    pub fn input(&mut self, now: Instant) -> bool {
+        match self.mode {
+            Mode::Attract(_) => {
+                self.mode = Mode::Player(now);
+                true
+            }
+            Mode::Player(_) => {
+                self.mode = Mode::Player(now);
+                false
+            }
+            Mode::Stopped => false,
+        }
+    }
+
+    // Call once per frame with whether the ROM halted or stopped. Returns the ROM to load next,
+    // if it's time to move on: after the ROM's time is up or it halted while nobody was
+    // playing, or after a player left it idle.
+    pub fn poll(&mut self, now: Instant, halted: bool) -> Option<PathBuf> {
+        let advance = match self.mode {
+            Mode::Attract(started) => halted || now - started >= self.rom_duration,
+            Mode::Player(last_input) => now - last_input >= IDLE_TIMEOUT,
+            Mode::Stopped => false,
+        };
+        if !advance {
+            return None;
+        }
+        self.advance();
+        Some(self.current().to_path_buf())
+    }
+
+    // Call once the ROM from `current` or `poll` is running.
+    pub fn loaded(&mut self, now: Instant) {
+        self.failures = 0;
+        self.mode = Mode::Attract(now);
+    }
+
+    // Call when the ROM from `current` or `poll` couldn't be loaded. Returns the next one to try,
+    // or None once every ROM has failed in a row.
+    pub fn load_failed(&mut self) -> Option<PathBuf> {
+        self.failures += 1;
+        if self.failures >= self.roms.len() {
+            self.mode = Mode::Stopped;
+            return None;
+        }
+        self.advance();
+        Some(self.current().to_path_buf())
+    }
+
+    fn advance(&mut self) {
+        self.current = (self.current + 1) % self.roms.len();
+    }
+}
+
+// Plays back a replay's key presses one frame at a time, for a ROM running in attract mode.
+#[derive(Debug)]
+pub struct AttractInput {
+    inputs: VecDeque<(u64, Option<u8>)>,
+    frame: u64,
+}
+
+impl AttractInput {
+    pub fn new(replay: &Replay) -> AttractInput {
+        AttractInput {
+            inputs: replay.inputs.iter().copied().collect(),
+            frame: 0,
+        }
+    }
+
+    // The key changes at the start of the next frame, in order. None is a release.
+    pub fn next_frame(&mut self) -> Vec<Option<u8>> {
+        let mut keys = Vec::new();
+        while let Some((_, key)) = self
+            .inputs
+            .front()
+            .filter(|(frame, _)| *frame == self.frame)
+        {
+            keys.push(*key);
+            self.inputs.pop_front();
+        }
+        self.frame += 1;
+        keys
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROM_DURATION: Duration = Duration::from_secs(90);
+
+    fn kiosk(names: &[&str], now: Instant) -> Kiosk {
+        let roms = names.iter().map(PathBuf::from).collect();
+        Kiosk::new(roms, ROM_DURATION, now)
+    }
+
+    #[test]
+    fn test_playlist() {
+        let dir = std::env::temp_dir().join(format!("ch8emu-kiosk-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        assert_eq!(playlist(&dir), Err("no ROMs found".to_string()));
+
+        for name in [
+            "pong.ch8",
+            "brix.CH8",
+            "pong.c8replay",
+            "README.txt",
+            "ant.sc8",
+        ] {
+            std::fs::write(dir.join(name), [0x12, 0x00]).unwrap();
+        }
+
+        assert_eq!(
+            playlist(&dir),
+            Ok(vec![
+                dir.join("ant.sc8"),
+                dir.join("brix.CH8"),
+                dir.join("pong.ch8")
+            ])
+        );
+        assert_eq!(
+            attract_replay_path(&dir.join("pong.ch8")),
+            dir.join("pong.c8replay")
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(playlist(&dir).is_err());
+    }
+
+    #[test]
+    fn test_rotates_after_timeout_and_loops() {
+        let start = Instant::now();
+        let mut kiosk = kiosk(&["a.ch8", "b.ch8"], start);
+        kiosk.loaded(start);
+
+        assert_eq!(kiosk.poll(start + ROM_DURATION / 2, false), None);
+        let next = start + ROM_DURATION;
+        assert_eq!(kiosk.poll(next, false), Some(PathBuf::from("b.ch8")));
+        kiosk.loaded(next);
+        assert_eq!(kiosk.poll(next + ROM_DURATION / 2, false), None);
+        assert_eq!(
+            kiosk.poll(next + ROM_DURATION, false),
+            Some(PathBuf::from("a.ch8"))
+        );
+    }
+
+    #[test]
+    fn test_rotates_when_rom_halts() {
+        let start = Instant::now();
+        let mut kiosk = kiosk(&["a.ch8", "b.ch8"], start);
+        kiosk.loaded(start);
+
+        assert_eq!(
+            kiosk.poll(start + Duration::from_secs(1), true),
+            Some(PathBuf::from("b.ch8"))
+        );
+    }
+
+    #[test]
+    fn test_player_interrupts_until_idle() {
+        let start = Instant::now();
+        let mut kiosk = kiosk(&["a.ch8", "b.ch8"], start);
+        kiosk.loaded(start);
+
+        let pressed = start + Duration::from_secs(10);
+        assert!(kiosk.input(pressed));
+        assert_eq!(kiosk.mode, Mode::Player(pressed));
+        // The ROM's time and halting no longer matter while someone plays.
+        assert_eq!(kiosk.poll(pressed + Duration::from_secs(30), true), None);
+
+        let pressed_again = pressed + Duration::from_secs(50);
+        assert!(!kiosk.input(pressed_again));
+        assert_eq!(kiosk.poll(pressed + IDLE_TIMEOUT, false), None);
+        assert_eq!(
+            kiosk.poll(pressed_again + IDLE_TIMEOUT, false),
+            Some(PathBuf::from("b.ch8"))
+        );
+        kiosk.loaded(pressed_again + IDLE_TIMEOUT);
+        assert_eq!(kiosk.mode, Mode::Attract(pressed_again + IDLE_TIMEOUT));
+    }
+
+    #[test]
+    fn test_skips_roms_that_fail_to_load() {
+        let start = Instant::now();
+        let mut kiosk = kiosk(&["a.ch8", "b.ch8", "c.ch8"], start);
+
+        assert_eq!(kiosk.load_failed(), Some(PathBuf::from("b.ch8")));
+        kiosk.loaded(start);
+        assert_eq!(
+            kiosk.poll(start + ROM_DURATION, false),
+            Some(PathBuf::from("c.ch8"))
+        );
+        // Each success starts the count of failures in a row over.
+        assert_eq!(kiosk.load_failed(), Some(PathBuf::from("a.ch8")));
+        assert_eq!(kiosk.load_failed(), Some(PathBuf::from("b.ch8")));
+        assert_eq!(kiosk.load_failed(), None);
+        assert_eq!(kiosk.mode, Mode::Stopped);
+        assert_eq!(kiosk.poll(start + ROM_DURATION * 10, true), None);
+        assert!(!kiosk.input(start));
+    }
+
+    #[test]
+    fn test_attract_input() {
+        let replay = Replay {
+            rom_hash: 0,
+            quirks: Default::default(),
+            sticky_keys: false,
+            rng_seed: 0,
+            load_address: 0x200,
+            ops_per_second: 700,
+            frames: 10,
+            inputs: vec![(1, Some(0x5)), (3, None), (3, Some(0xA))],
+        };
+        let mut input = AttractInput::new(&replay);
+
+        let frames: Vec<Vec<Option<u8>>> = (0..5).map(|_| input.next_frame()).collect();
+
+        assert_eq!(
+            frames,
+            vec![
+                vec![],
+                vec![Some(0x5)],
+                vec![],
+                vec![None, Some(0xA)],
+                vec![]
+            ]
+        );
+    }
+}
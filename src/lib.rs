@@ -0,0 +1,11 @@
+pub mod chip8;
+pub mod delta;
+pub mod handoff;
+pub mod info;
+pub mod numfmt;
+pub mod replay;
+pub mod script;
+pub mod selftest;
+pub mod splash;
+
+pub use chip8::Opcode;
@@ -1,92 +1,1392 @@
-mod chip8;
+mod audio;
+mod cli;
+mod clock;
+mod config;
+#[cfg_attr(not(feature = "gamepad"), allow(dead_code))]
+mod gamepad;
+mod headless;
+mod hud;
+mod keymap;
+mod keyqueue;
+mod kiosk;
+mod overlay;
+mod pacing;
+mod persist;
+mod render;
+mod runahead;
+mod session;
+mod text;
+mod toast;
+mod watchdog;
 
-use std::collections::HashMap;
-use std::iter;
-use std::time::Duration;
+use audio::OutputDevices;
+use ch8emu::chip8;
+use ch8emu::info;
+use ch8emu::replay;
+use ch8emu::script;
+use ch8emu::selftest;
+use ch8emu::splash;
+use std::collections::HashSet;
+use std::io::Write;
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 use winit::application::ApplicationHandler;
-use winit::event::{KeyEvent, WindowEvent};
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::event::{ElementState, KeyEvent, MouseButton, WindowEvent};
 use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
-use winit::keyboard::Key;
-use winit::window::Window;
+use winit::keyboard::{Key, KeyLocation, ModifiersState, NamedKey};
+use winit::monitor::MonitorHandle;
+use winit::window::{Fullscreen, Window};
 
-const CHIP8_OPS_PER_SECOND: u64 = 600;
-const WAIT_DURATION: Duration = Duration::from_micros(1_000_000 / CHIP8_OPS_PER_SECOND);
+const FRAME_DURATION: Duration = Duration::from_micros(1_000_000 / chip8::TIMER_HZ);
+// Frames of emulation caught up on after the window stalls, e.g. while it's dragged; the rest
+// is dropped so that the loop doesn't block catching up.
+const MAX_CATCH_UP_FRAMES: u64 = 4;
 const WINDOW_TITLE: &str = "Chip8 Emulator";
+// Window pixels per display pixel when there's no saved window to restore.
+const WINDOW_SCALE: usize = 8;
+const MUTE_KEY: &str = "m";
+const HELP_KEY: NamedKey = NamedKey::F1;
+const CYCLE_KEYPAD_KEY: NamedKey = NamedKey::F2;
+const SPRITE_OUTLINE_KEY: NamedKey = NamedKey::F3;
+const KEYPAD_OVERLAY_KEY: NamedKey = NamedKey::F4;
+const HUD_KEY: NamedKey = NamedKey::F5;
+const PIXEL_STYLE_KEY: NamedKey = NamedKey::F6;
+const CURSOR_READOUT_MODIFIER: ModifiersState = ModifiersState::ALT;
 
-const KEY_MAP: [(&str, &str); 16] = [
-    ("1", "1"),
-    ("2", "2"),
-    ("3", "3"),
-    ("4", "c"),
-    ("q", "4"),
-    ("w", "5"),
-    ("e", "6"),
-    ("r", "d"),
-    ("a", "7"),
-    ("s", "8"),
-    ("d", "9"),
-    ("f", "e"),
-    ("z", "a"),
-    ("x", "0"),
-    ("c", "b"),
-    ("v", "f"),
-];
+type WindowSurface = softbuffer::Surface<Rc<Window>, Rc<Window>>;
 
 #[derive(Debug)]
-struct Emulator<'a> {
+struct Emulator {
     system: chip8::Chip8,
-    window: Option<Window>,
-    key_map: HashMap<&'a str, &'a str>,
+    options: cli::Options,
+    config: config::Config,
+    // The session restored at startup, and saved over on exit.
+    session: session::Session,
+    // The settings the run started with, command line flags included.
+    started: session::Settings,
+    window: Option<Rc<Window>>,
+    surface: Option<WindowSurface>,
+    frame_cache: render::FrameCache,
+    key_map: keymap::KeyMap,
+    #[cfg(feature = "gamepad")]
+    gamepad: Option<gamepad::GamepadInput>,
+    frame_pacer: pacing::FramePacer,
+    beeper: audio::Beeper<audio::ResilientSink<audio::TerminalDevices>>,
+    key_queue: keyqueue::KeyQueue,
+    // Host keys already reported as unsupported, so holding or tapping one doesn't flood the log.
+    unsupported_keys: HashSet<String>,
+    rate_meter: pacing::RateMeter,
+    last_title_update: Instant,
+    // Time since the previous frame, which is what the next one has to emulate.
+    since_last_frame: pacing::ElapsedTimer,
+    // Set with --record-replay. Frames then run for exactly FRAME_DURATION each, so the replay
+    // matches the session.
+    recorder: Option<replay::Recorder>,
+    // Memory of the running ROM kept across sessions, if configured.
+    persistence: Option<persist::Persistence>,
+    // Set with --kiosk.
+    kiosk: Option<kiosk::Kiosk>,
+    // Key presses of the running kiosk ROM's replay, until a player takes over. Frames then run
+    // for exactly FRAME_DURATION each, as they were recorded.
+    attract: Option<kiosk::AttractInput>,
+    // Outline the sprites drawn each frame, toggled with SPRITE_OUTLINE_KEY.
+    outline_sprites: bool,
+    // Show a keypad below the game that can be clicked or tapped, toggled with
+    // KEYPAD_OVERLAY_KEY.
+    keypad_overlay: bool,
+    // Starts as configured, cycled with PIXEL_STYLE_KEY.
+    pixel_style: render::PixelStyle,
+    // Last cursor position in window pixels, while it's over the window. Like the frame buffer,
+    // it's in physical pixels, so the two line up on HiDPI screens.
+    cursor: Option<(usize, usize)>,
+    // Modifier keys held; holding CURSOR_READOUT_MODIFIER shows the pixel under the cursor.
+    modifiers: ModifiersState,
+    // The window title, less the cursor readout, and the readout while it's shown after it.
+    title: String,
+    cursor_readout: Option<String>,
+    // Whether the held key was pressed on the on-screen keypad, so releasing the button
+    // releases it.
+    keypad_clicked: bool,
+    // The performance HUD's numbers while it's shown, toggled with HUD_KEY. Nothing is timed
+    // while it's hidden.
+    hud: Option<hud::Stats>,
+    last_hud_update: Instant,
+    // Messages shown in a corner of the window for a few seconds, see notify.
+    toasts: toast::Toasts,
+    // Diagnostics::pushed of the running machine when its diagnostics were last shown.
+    diagnostics_seen: u64,
+    // Whether the beeper had an audio device last frame, to tell when it's lost or found.
+    audio_available: bool,
+    // Notices a ROM that runs without showing anything, see HangResponse.
+    watchdog: watchdog::Watchdog,
+    present_meter: pacing::RateMeter,
+    cpu_time: Duration,
+    render_time: Duration,
+    // Set with --compare: a second machine run in lockstep with `system`.
+    comparison: Option<Comparison>,
+    // Set with --run-ahead, unless comparing.
+    run_ahead: Option<runahead::RunAhead>,
+    // Frames stop while nothing can change until an input event arrives, see pacing::should_idle.
+    idle: bool,
+    running: bool,
+    occluded: bool,
+}
+
+// The second machine of --compare. It runs the same ROM with the same seed and keys as the
+// first one, under the second target's quirks.
+#[derive(Debug)]
+struct Comparison {
+    system: chip8::Chip8,
+    lockstep: chip8::Lockstep,
+    instructions_per_frame: u64,
+}
+
+impl Comparison {
+    // Runs a frame's worth of instructions on both machines, printing where they diverged if they
+    // just did.
+    fn run_frame(
+        &mut self,
+        first: &mut chip8::Chip8,
+    ) -> Result<chip8::RunSummary, chip8::Chip8Error> {
+        let before = first.cycles();
+        let agree = self
+            .lockstep
+            .run(first, &mut self.system, self.instructions_per_frame)?;
+        first.tick_timers();
+        self.system.tick_timers();
+        if !agree {
+            if let Some(divergence) = self.lockstep.divergence() {
+                println!("{}", divergence.report());
+            }
+        }
+        Ok(chip8::RunSummary {
+            instructions: first.cycles() - before,
+            frames: 1,
+            sound_active: first.sound_active(),
+            ..chip8::RunSummary::default()
+        })
+    }
+
+    fn diverged(&self) -> bool {
+        self.lockstep.divergence().is_some()
+    }
 }
 
 fn main() {
-    let mut ch8 = chip8::Chip8::new();
-    ch8.boot().unwrap();
+    let options = match cli::parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    };
+
+    let config = config::load();
+    let session = session::load();
+    let settings = session::merge(&config, &session, &options);
+    let key_map = keymap::KeyMap::resolve(settings.keypad, &config.key_overrides);
+
+    if options.print_keymap {
+        println!("{}", key_map.describe());
+        return;
+    }
+
+    if options.list_audio_devices {
+        for name in audio::TerminalDevices.list() {
+            println!("{}", name);
+        }
+        return;
+    }
+
+    if let Some(name) = &options.gen_test {
+        let Some(test) = selftest::find(name) else {
+            eprintln!("Unknown self-test `{}`, expected one of:", name);
+            for test in &selftest::SELF_TESTS {
+                eprintln!("  {:<14} {}", test.name, test.description);
+            }
+            std::process::exit(2);
+        };
+        if let Err(err) = std::io::stdout().write_all(&test.rom()) {
+            eprintln!("Failed to write the ROM: {}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if options.info {
+        let Some(path) = &options.rom_path else {
+            eprintln!("info needs a ROM");
+            std::process::exit(2);
+        };
+        match std::fs::read(path) {
+            Ok(rom) => print!("{}", info::RomInfo::new(&rom).to_text()),
+            Err(err) => {
+                eprintln!("Failed to load {}: {}", path, err);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let rom = match &options.rom_path {
+        Some(path) => std::fs::read(path).unwrap_or_else(|err| {
+            eprintln!("Failed to load {}: {}", path, err);
+            std::process::exit(1);
+        }),
+        None => splash::SPLASH_ROM.to_vec(),
+    };
+
+    if let Some(path) = &options.play_bundle {
+        play_bundle(path, &options);
+    }
+    if options.export_script.is_some() && options.replay.is_none() {
+        eprintln!("--export-script needs a --replay to export");
+        std::process::exit(2);
+    }
+    if let Some(path) = &options.replay {
+        play_replay(path, &rom, &options);
+    }
+    if let Some(path) = &options.script {
+        play_script(path, &rom, &options);
+    }
+
+    if options.compare.is_some()
+        && (options.headless || options.is_recording() || options.kiosk.is_some())
+    {
+        eprintln!("--compare can't be combined with --headless, recording or --kiosk");
+        std::process::exit(2);
+    }
+
+    let kiosk = options.kiosk.as_ref().map(|dir| {
+        if options.is_recording() {
+            eprintln!("Can't record a replay in kiosk mode");
+            std::process::exit(2);
+        }
+        let roms = kiosk::playlist(Path::new(dir)).unwrap_or_else(|err| {
+            eprintln!("Failed to load ROMs from {}: {}", dir, err);
+            std::process::exit(1);
+        });
+        let seconds = options.kiosk_seconds.unwrap_or(kiosk::DEFAULT_ROM_SECONDS);
+        kiosk::Kiosk::new(roms, Duration::from_secs(seconds), Instant::now())
+    });
+
+    let chip8_options = chip8_options_for(&options, &rom);
+    let recorder = options
+        .is_recording()
+        .then(|| replay::Recorder::new(&rom, chip8_options));
+    let chip8_options = match &recorder {
+        Some(recorder) => recorder.options(),
+        None => chip8_options,
+    };
+    let (chip8_options, comparison) = match options.compare {
+        Some(targets) => {
+            let (first, second) = compare_options(&options, targets);
+            let mut system = boot_rom(&rom, second).unwrap_or_else(|err| {
+                eprintln!("Failed to load ROM: {}", err);
+                std::process::exit(1);
+            });
+            if let Some(palette) = config.palette {
+                system.set_palette(palette);
+            }
+            let comparison = Comparison {
+                system,
+                lockstep: chip8::Lockstep::new(),
+                instructions_per_frame: second.ops_per_second / chip8::TIMER_HZ,
+            };
+            (first, Some(comparison))
+        }
+        None => (chip8_options, None),
+    };
+    let chip8_options = if options.headless && options.strict {
+        headless::validation_options(chip8_options)
+    } else {
+        chip8_options
+    };
+    let run_ahead = (options.run_ahead && comparison.is_none())
+        .then(|| runahead::RunAhead::new(FRAME_DURATION));
+    let mut ch8 = boot_rom(&rom, chip8_options).unwrap_or_else(|err| {
+        eprintln!("Failed to load ROM: {}", err);
+        std::process::exit(1);
+    });
+    ch8.set_profiling(options.profile);
+    if let Some(palette) = config.palette {
+        ch8.set_palette(palette);
+    }
+
+    if options.headless {
+        let frames = options.frames.unwrap_or(headless::DEFAULT_FRAMES);
+        let outcome = headless::run(&mut ch8, frames);
+        let (report, failed) = headless::report(&ch8, &outcome, options.strict);
+        if options.ascii {
+            println!("{}", ch8.render_ascii_with_status());
+        }
+        println!("{}", report);
+        std::process::exit(if failed { 1 } else { 0 });
+    }
+
+    let persistence = if recorder.is_some() || comparison.is_some() {
+        None
+    } else {
+        persistence_for(&config, &rom)
+    };
+    if let Some(persistence) = &persistence {
+        persistence.restore(&mut ch8);
+    }
 
     let event_loop = EventLoop::new().unwrap();
-    let control_flow = ControlFlow::wait_duration(WAIT_DURATION);
-    event_loop.set_control_flow(control_flow);
+    event_loop.set_control_flow(ControlFlow::Poll);
 
+    let mut beeper = audio::Beeper::new(audio::ResilientSink::new(
+        audio::TerminalDevices,
+        options.audio_device.clone(),
+        Instant::now(),
+    ));
+    beeper.set_muted(settings.muted);
+    let audio_available = beeper.sink_mut().is_available();
+
+    let pixel_style = config.pixel_style;
+    #[cfg(feature = "gamepad")]
+    let button_map = gamepad::ButtonMap::resolve(&config.button_overrides);
     let mut emulator = Emulator {
         system: ch8,
+        running: true,
+        options,
+        config,
+        session,
+        started: settings,
         window: None,
-        key_map: HashMap::from_iter(KEY_MAP.iter().cloned()),
+        surface: None,
+        frame_cache: render::FrameCache::new(),
+        key_map,
+        #[cfg(feature = "gamepad")]
+        gamepad: gamepad::GamepadInput::new(button_map)
+            .map_err(|err| println!("Gamepad input not available: {}", err))
+            .ok(),
+        frame_pacer: pacing::FramePacer::new(FRAME_DURATION),
+        beeper,
+        key_queue: keyqueue::KeyQueue::new(),
+        unsupported_keys: HashSet::new(),
+        rate_meter: pacing::RateMeter::new(pacing::RATE_WINDOW),
+        last_title_update: Instant::now(),
+        since_last_frame: pacing::ElapsedTimer::new(&clock::SystemClock),
+        recorder,
+        persistence,
+        kiosk,
+        attract: None,
+        outline_sprites: false,
+        keypad_overlay: false,
+        pixel_style,
+        cursor: None,
+        modifiers: ModifiersState::empty(),
+        title: String::new(),
+        cursor_readout: None,
+        keypad_clicked: false,
+        hud: None,
+        last_hud_update: Instant::now(),
+        toasts: toast::Toasts::new(),
+        diagnostics_seen: 0,
+        audio_available,
+        watchdog: watchdog::Watchdog::new(watchdog::HUNG_FRAMES),
+        present_meter: pacing::RateMeter::new(pacing::RATE_WINDOW),
+        cpu_time: Duration::ZERO,
+        render_time: Duration::ZERO,
+        run_ahead,
+        comparison,
+        idle: false,
+        occluded: false,
     };
 
+    let first_kiosk_rom = emulator
+        .kiosk
+        .as_ref()
+        .map(|kiosk| kiosk.current().to_path_buf());
+    emulator.load_kiosk_roms(first_kiosk_rom, Instant::now());
+
     event_loop.run_app(&mut emulator).unwrap();
 }
 
-impl Emulator<'_> {
-    fn get_mapped_key(&self, pressed_key: &str) -> Option<&str> {
-        self.key_map.get(pressed_key).copied()
+// Plays a replay of `rom` without a window and exits with the final state hash, so that two
+// playbacks can be compared.
+fn play_replay(path: &str, rom: &[u8], options: &cli::Options) -> ! {
+    let replay = match std::fs::read_to_string(path)
+        .map_err(|err| err.to_string())
+        .and_then(|contents| replay::Replay::parse(&contents))
+    {
+        Ok(replay) => replay,
+        Err(err) => {
+            eprintln!("Failed to load {}: {}", path, err);
+            std::process::exit(1);
+        }
+    };
+    if let Some(script_path) = &options.export_script {
+        let text = script::Script::from_replay(&replay).to_text();
+        if let Err(err) = std::fs::write(script_path, text) {
+            eprintln!("Failed to write {}: {}", script_path, err);
+            std::process::exit(1);
+        }
+        std::process::exit(0);
+    }
+    match replay::Bundle::new(rom, replay) {
+        Ok(bundle) => play(&bundle, options),
+        Err(err) => {
+            eprintln!("Can't play {}: {}", path, err);
+            std::process::exit(1);
+        }
+    }
+}
+
+// Plays an input script like play_replay, with the machine the command line asks for.
+fn play_script(path: &str, rom: &[u8], options: &cli::Options) -> ! {
+    let script = match std::fs::read_to_string(path)
+        .map_err(|err| err.to_string())
+        .and_then(|contents| script::Script::parse(&contents))
+    {
+        Ok(script) => script,
+        Err(err) => {
+            eprintln!("Failed to load {}: {}", path, err);
+            std::process::exit(1);
+        }
+    };
+    let replay = script.replay(rom, options.chip8_options());
+    match replay::Bundle::new(rom, replay) {
+        Ok(bundle) => play(&bundle, options),
+        Err(err) => {
+            eprintln!("Can't play {}: {}", path, err);
+            std::process::exit(1);
+        }
+    }
+}
+
+// Plays a bundle like play_replay, with the ROM it holds.
+fn play_bundle(path: &str, options: &cli::Options) -> ! {
+    match replay::Bundle::load(Path::new(path)) {
+        Ok(bundle) => play(&bundle, options),
+        Err(err) => {
+            eprintln!("Failed to load {}: {}", path, err);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn play(bundle: &replay::Bundle, options: &cli::Options) -> ! {
+    let mut system = match bundle.machine(options.chip8_options()) {
+        Ok(system) => system,
+        Err(err) => {
+            eprintln!("Failed to load ROM: {}", err);
+            std::process::exit(1);
+        }
+    };
+    let replay = &bundle.replay;
+    let result = replay.play(&mut system);
+    if options.ascii {
+        println!("{}", system.render_ascii_with_status());
+    }
+    println!(
+        "Replayed {} frames, state hash {:#018X}",
+        replay.frames,
+        system.state_hash()
+    );
+    if let Err(err) = result {
+        println!("Emulation stopped: {:?}", err);
+        std::process::exit(1);
+    }
+    std::process::exit(0);
+}
+
+// The options to run `rom` with. Without --target, the target is guessed from the ROM, and only
+// used if something points to it.
+fn chip8_options_for(options: &cli::Options, rom: &[u8]) -> chip8::Options {
+    let mut chip8_options = options.chip8_options();
+    if options.target.is_none() {
+        let best = chip8::detect_targets(rom).into_iter().next();
+        if let Some(best) = best.filter(|detection| detection.score > 0) {
+            println!(
+                "Using {} quirks: {}",
+                best.target.name(),
+                best.evidence.join(", ")
+            );
+            chip8_options.quirks = chip8::Quirks::preset(best.target);
+        }
+    }
+    if !options.legacy_hires && chip8::is_legacy_hires(rom) {
+        println!("This may be a ROM for the VIP's 64x64 hi-res mode, try --legacy-hires");
+    }
+    chip8_options
+}
+
+// The options of the two machines compared with --compare. They share a seed, so CXNN gives
+// both the same numbers.
+fn compare_options(
+    options: &cli::Options,
+    (first, second): (chip8::Target, chip8::Target),
+) -> (chip8::Options, chip8::Options) {
+    let base = chip8::Options {
+        rng_seed: Some(rand::random()),
+        ..options.chip8_options()
+    };
+    let with_target = |target| chip8::Options {
+        quirks: chip8::Quirks::preset(target),
+        ..base
+    };
+    (with_target(first), with_target(second))
+}
+
+fn monitor_rect(monitor: &MonitorHandle) -> session::Rect {
+    let (position, size) = (monitor.position(), monitor.size());
+    session::Rect {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+    }
+}
+
+// `options` for the machine the window runs, which is paced by the wall clock and so can stall.
+fn windowed(options: chip8::Options) -> chip8::Options {
+    chip8::Options {
+        max_catch_up_frames: Some(MAX_CATCH_UP_FRAMES),
+        ..options
+    }
+}
+
+// A machine booted with `options` that has `rom` loaded.
+fn boot_rom(rom: &[u8], options: chip8::Options) -> Result<chip8::Chip8, String> {
+    let mut system =
+        chip8::Chip8::with_options(windowed(options)).map_err(|err| err.to_string())?;
+    system.boot(chip8::BootConfig::default()).unwrap();
+    system.load_rom(rom).map_err(|err| err.to_string())?;
+    Ok(system)
+}
+
+// The replay next to a kiosk ROM, if there's one recorded with it.
+fn load_attract_replay(rom_path: &Path, rom: &[u8]) -> Option<replay::Replay> {
+    let path = kiosk::attract_replay_path(rom_path);
+    let contents = std::fs::read_to_string(&path).ok()?;
+    let replay = replay::Replay::parse(&contents).and_then(|replay| {
+        replay.check_rom(rom)?;
+        Ok(replay)
+    });
+    match replay {
+        Ok(replay) => Some(replay),
+        Err(err) => {
+            eprintln!("Warning: ignoring {}: {}", path.display(), err);
+            None
+        }
+    }
+}
+
+// Saves go in the config directory. A replay wouldn't restore them, so they're left alone while
+// recording one.
+fn persistence_for(config: &config::Config, rom: &[u8]) -> Option<persist::Persistence> {
+    let range = config.persist_range.clone()?;
+    let dir = config::config_dir()?.join("saves");
+    Some(persist::Persistence::new(&dir, rom, range))
+}
+
+// Names a key the way keypad presets and overrides refer to it.
+fn host_key_name(key_event: &KeyEvent) -> Option<String> {
+    let name = match key_event.logical_key.as_ref() {
+        Key::Character(x) => x.to_lowercase(),
+        Key::Named(NamedKey::Space) => "space".to_string(),
+        Key::Named(NamedKey::Enter) => "enter".to_string(),
+        _ => return None,
+    };
+    if key_event.location == KeyLocation::Numpad {
+        Some(format!("numpad{}", name))
+    } else {
+        Some(name)
+    }
+}
+
+impl Emulator {
+    fn cycle_keypad_preset(&mut self) {
+        let preset = self.key_map.preset().next();
+        self.key_map = keymap::KeyMap::resolve(preset, &self.config.key_overrides);
+        self.notify(toast::Notice::info(format!(
+            "Keypad preset: {}",
+            preset.name()
+        )));
     }
 
     fn handle_key_event(&mut self, key_event: KeyEvent) {
+        if key_event.state.is_pressed() && !key_event.repeat {
+            self.kiosk_input();
+        }
         match key_event.logical_key.as_ref() {
-            Key::Character(x) => {
-                if key_event.state.is_pressed() {
-                    let key = self.get_mapped_key(x);
-                    if key.is_some() {
-                        let some_key = key.unwrap().to_owned();
-                        self.system.press_key(some_key.as_str());
-                    } else {
-                        println!("Key not supported");
+            Key::Named(key) if key == CYCLE_KEYPAD_KEY => {
+                if key_event.state.is_pressed() && !key_event.repeat {
+                    self.cycle_keypad_preset();
+                }
+            }
+            Key::Named(key) if key == SPRITE_OUTLINE_KEY => {
+                if key_event.state.is_pressed() && !key_event.repeat {
+                    self.outline_sprites = !self.outline_sprites;
+                    self.system.set_draw_recording(self.outline_sprites);
+                    self.notify(toast::Notice::info(format!(
+                        "Sprite outlines {}",
+                        if self.outline_sprites { "on" } else { "off" }
+                    )));
+                }
+            }
+            Key::Named(key) if key == KEYPAD_OVERLAY_KEY => {
+                if key_event.state.is_pressed() && !key_event.repeat {
+                    self.keypad_overlay = !self.keypad_overlay;
+                    if !self.keypad_overlay {
+                        self.release_clicked_key();
                     }
-                } else {
-                    self.system.release_key();
+                    self.notify(toast::Notice::info(format!(
+                        "On-screen keypad {}",
+                        if self.keypad_overlay { "on" } else { "off" }
+                    )));
+                }
+            }
+            Key::Named(key) if key == HUD_KEY => {
+                if key_event.state.is_pressed() && !key_event.repeat {
+                    self.hud = match self.hud {
+                        Some(_) => None,
+                        None => Some(hud::Stats::default()),
+                    };
+                    self.notify(toast::Notice::info(format!(
+                        "Performance HUD {}",
+                        if self.hud.is_some() { "on" } else { "off" }
+                    )));
+                }
+            }
+            Key::Named(key) if key == HELP_KEY => {
+                if key_event.state.is_pressed() && !key_event.repeat {
+                    self.notify(toast::Notice::info(format!(
+                        "Keypad preset: {}",
+                        self.key_map.preset().name()
+                    )));
+                    // Two rows to a toast, so the whole keypad fits in MAX_TOASTS.
+                    for rows in self.key_map.help_rows().chunks(2) {
+                        self.notify(toast::Notice::info(rows.join("   ")));
+                    }
+                }
+            }
+            Key::Named(key) if key == PIXEL_STYLE_KEY => {
+                if key_event.state.is_pressed() && !key_event.repeat {
+                    self.pixel_style = self.pixel_style.next();
+                    self.notify(toast::Notice::info(format!(
+                        "Pixel style: {}",
+                        self.pixel_style.name()
+                    )));
+                }
+            }
+            Key::Character(x) if x == MUTE_KEY => {
+                if key_event.state.is_pressed() && !key_event.repeat {
+                    let muted = !self.beeper.is_muted();
+                    self.beeper.set_muted(muted);
+                    self.notify(toast::Notice::info(format!(
+                        "Sound {}",
+                        if muted { "muted" } else { "unmuted" }
+                    )));
                 }
             }
             _ => {
-                println!("Key not supported");
+                let name = host_key_name(&key_event)
+                    .unwrap_or_else(|| format!("{:?}", key_event.logical_key));
+                let Some(key) = self.key_map.keypad_key(&name) else {
+                    if key_event.state.is_pressed() && self.unsupported_keys.insert(name.clone()) {
+                        println!("Key {} not supported", name);
+                    }
+                    return;
+                };
+                if let Some(transition) =
+                    keyqueue::transition(key_event.state.is_pressed(), key_event.repeat, key)
+                {
+                    self.key_queue.push(Instant::now(), transition);
+                }
+            }
+        }
+    }
+
+    // Presses the on-screen keypad key under the cursor, or releases the key it pressed.
+    fn handle_click(&mut self, state: ElementState) {
+        if !state.is_pressed() {
+            self.release_clicked_key();
+            return;
+        }
+        if !self.keypad_overlay {
+            return;
+        }
+        self.kiosk_input();
+        let (Some(window), Some((x, y))) = (&self.window, self.cursor) else {
+            return;
+        };
+        let size = window.inner_size();
+        let Some(key) = overlay::key_at(x, y, size.width as usize, size.height as usize) else {
+            return;
+        };
+        self.keypad_clicked = true;
+        self.key_queue
+            .push(Instant::now(), keyqueue::KeyTransition::Pressed(key));
+    }
+
+    // Releases the key held on the on-screen keypad, if there is one. Hiding the keypad does too,
+    // rather than leave its key held with no keypad showing.
+    fn release_clicked_key(&mut self) {
+        if self.keypad_clicked {
+            self.keypad_clicked = false;
+            self.key_queue
+                .push(Instant::now(), keyqueue::KeyTransition::Released);
+        }
+    }
+
+    // Swaps in a fresh machine running the dropped ROM, keeping the current one if it can't be
+    // loaded.
+    fn load_dropped_rom(&mut self, path: &Path) {
+        if self.recorder.is_some() {
+            self.notify(toast::Notice::error(
+                "Can't switch ROMs while recording a replay",
+            ));
+            return;
+        }
+        if self.comparison.is_some() {
+            self.notify(toast::Notice::error("Can't switch ROMs while comparing"));
+            return;
+        }
+        let loaded = std::fs::read(path)
+            .map_err(|err| err.to_string())
+            .and_then(|rom| {
+                let system = boot_rom(&rom, chip8_options_for(&self.options, &rom))?;
+                Ok((system, rom))
+            });
+        match loaded {
+            Ok((system, rom)) => {
+                self.swap_rom(system, &rom);
+                self.notify(toast::Notice::info(format!("Loaded {}", path.display())));
+            }
+            Err(err) => self.notify(toast::Notice::error(format!(
+                "Failed to load {}: {}",
+                path.display(),
+                err
+            ))),
+        }
+    }
+
+    // Replaces the running machine with `system`, which has `rom` loaded.
+    fn swap_rom(&mut self, mut system: chip8::Chip8, rom: &[u8]) {
+        self.save_persistent_memory();
+        self.persistence = persistence_for(&self.config, rom);
+        if let Some(persistence) = &self.persistence {
+            persistence.restore(&mut system);
+        }
+        system.set_draw_recording(self.outline_sprites);
+        system.set_profiling(self.options.profile);
+        if let Some(palette) = self.config.palette {
+            system.set_palette(palette);
+        }
+        self.system = system;
+        self.diagnostics_seen = 0;
+        if let Some(run_ahead) = &mut self.run_ahead {
+            run_ahead.clear();
+        }
+        self.attract = None;
+        self.running = true;
+        self.frame_cache.invalidate();
+        if self.watchdog.is_hung() {
+            self.respond_to_hang(watchdog::Verdict::Recovered);
+        }
+        self.watchdog = watchdog::Watchdog::new(watchdog::HUNG_FRAMES);
+    }
+
+    // Acts on the watchdog's verdict as configured, and pauses a hung ROM under --strict. Returns
+    // whether the ROM was stopped.
+    fn respond_to_hang(&mut self, verdict: watchdog::Verdict) -> bool {
+        let reaction = self
+            .config
+            .hang_response
+            .react(verdict, self.options.strict);
+        if reaction.warn {
+            println!(
+                "Warning: the ROM hasn't drawn, read a key or beeped for {} frames, it may be \
+                 hung at {:#05X}",
+                watchdog::HUNG_FRAMES,
+                self.system.pc()
+            );
+        }
+        if reaction.set_title {
+            let title = match verdict {
+                watchdog::Verdict::Hung => format!("{} - possibly hung", self.window_title()),
+                watchdog::Verdict::Recovered => self.window_title(),
+            };
+            self.set_title(title);
+        }
+        if reaction.pause {
+            println!("Paused: --strict stops hung ROMs");
+            self.running = false;
+        }
+        reaction.pause
+    }
+
+    fn window_title(&self) -> String {
+        match self.options.compare {
+            Some((first, second)) => {
+                format!("{} - {} | {}", WINDOW_TITLE, first.name(), second.name())
+            }
+            None => WINDOW_TITLE.to_string(),
+        }
+    }
+
+    // Sets the window title, keeping the cursor readout after it while that's shown.
+    fn set_title(&mut self, title: String) {
+        self.title = title;
+        self.apply_title();
+    }
+
+    fn apply_title(&self) {
+        let Some(window) = &self.window else {
+            return;
+        };
+        match &self.cursor_readout {
+            Some(readout) => window.set_title(&format!("{} - {}", self.title, readout)),
+            None => window.set_title(&self.title),
+        }
+    }
+
+    // While CURSOR_READOUT_MODIFIER is held, shows the CHIP-8 pixel under the cursor and whether
+    // it's lit in the window title, for finding out where a draw landed. Once it's released the
+    // title goes back to what it was.
+    fn show_cursor_readout(&mut self) {
+        let Some(window) = &self.window else {
+            return;
+        };
+        if !self.modifiers.contains(CURSOR_READOUT_MODIFIER) {
+            if self.cursor_readout.take().is_some() {
+                self.apply_title();
+            }
+            return;
+        }
+        let size = window.inner_size();
+        let (width, height) = (size.width as usize, size.height as usize);
+        let game_height = if self.keypad_overlay {
+            overlay::game_height(height)
+        } else {
+            height
+        };
+        let mut displays = vec![self.shown_display().1];
+        if let Some(comparison) = &self.comparison {
+            displays.push(comparison.system.display());
+        }
+        let sizes: Vec<(usize, usize)> = displays
+            .iter()
+            .map(|display| (display.width, display.height))
+            .collect();
+        let under = self
+            .cursor
+            .and_then(|(x, y)| render::pixel_under(x, y, width, game_height, &sizes));
+        let readout = match under {
+            Some((index, x, y)) => format!(
+                "({}, {}) = {}",
+                x,
+                y,
+                if displays[index].is_pixel_set(x, y) {
+                    "on"
+                } else {
+                    "off"
+                }
+            ),
+            None => "outside".to_string(),
+        };
+        // Setting the title goes to the window system, so only when the text changes.
+        if self.cursor_readout.as_ref() != Some(&readout) {
+            self.cursor_readout = Some(readout);
+            self.apply_title();
+        }
+    }
+
+    // Loads a kiosk ROM, playing its replay as attract mode input if it has one.
+    fn load_kiosk_rom(&mut self, path: &Path) -> Result<(), String> {
+        let rom = std::fs::read(path).map_err(|err| err.to_string())?;
+        let replay = load_attract_replay(path, &rom);
+        let options = match &replay {
+            Some(replay) => replay.options(self.options.chip8_options()),
+            None => chip8_options_for(&self.options, &rom),
+        };
+        let system = boot_rom(&rom, options)?;
+        self.swap_rom(system, &rom);
+        self.attract = replay.as_ref().map(kiosk::AttractInput::new);
+        println!("Kiosk: running {}", path.display());
+        Ok(())
+    }
+
+    // Loads `next` and tells the kiosk how it went, moving on to the ROMs it suggests instead
+    // until one loads.
+    fn load_kiosk_roms(&mut self, mut next: Option<PathBuf>, now: Instant) {
+        while let Some(path) = next {
+            next = match self.load_kiosk_rom(&path) {
+                Ok(()) => {
+                    if let Some(kiosk) = &mut self.kiosk {
+                        kiosk.loaded(now);
+                    }
+                    None
+                }
+                Err(err) => {
+                    eprintln!("Kiosk: skipping {}: {}", path.display(), err);
+                    let next = self.kiosk.as_mut().and_then(|kiosk| kiosk.load_failed());
+                    if next.is_none() {
+                        eprintln!("Kiosk: none of the ROMs could be loaded");
+                    }
+                    next
+                }
+            };
+        }
+    }
+
+    // Moves on to the next kiosk ROM when it's time.
+    fn rotate_kiosk(&mut self, halted: bool) {
+        let now = Instant::now();
+        let next = self
+            .kiosk
+            .as_mut()
+            .and_then(|kiosk| kiosk.poll(now, halted));
+        self.load_kiosk_roms(next, now);
+    }
+
+    // A key press hands a kiosk ROM over to the player, so its replay stops pressing keys.
+    fn kiosk_input(&mut self) {
+        let took_over = self
+            .kiosk
+            .as_mut()
+            .is_some_and(|kiosk| kiosk.input(Instant::now()));
+        if took_over && self.attract.take().is_some() {
+            self.system.release_key();
+        }
+    }
+
+    // Saves the window and the settings changed while running for the next launch. A fullscreen
+    // window keeps the geometry it had before going fullscreen.
+    fn save_session(&self) {
+        let Some(window) = &self.window else {
+            return;
+        };
+        let fullscreen = window.fullscreen().is_some();
+        let geometry = match window.outer_position() {
+            Ok(position) if !fullscreen => {
+                let size = window.inner_size();
+                Some(session::Rect {
+                    x: position.x,
+                    y: position.y,
+                    width: size.width,
+                    height: size.height,
+                })
+            }
+            _ => self.session.window,
+        };
+        let settings = session::Settings {
+            keypad: self.key_map.preset(),
+            muted: self.beeper.is_muted(),
+        };
+        let session = session::Session::new(
+            geometry,
+            fullscreen,
+            &self.config,
+            &self.session,
+            self.started,
+            settings,
+        );
+        if let Err(err) = session::save(&session) {
+            eprintln!("Failed to save the session: {}", err);
+        }
+    }
+
+    fn save_persistent_memory(&self) {
+        if let Some(persistence) = &self.persistence {
+            if let Err(err) = persistence.save(&self.system) {
+                eprintln!(
+                    "Failed to save memory to {}: {}",
+                    persistence.path().display(),
+                    err
+                );
+            }
+        }
+    }
+
+    fn paused(&self) -> bool {
+        self.occluded && self.options.pause_when_occluded
+    }
+
+    fn set_occluded(&mut self, occluded: bool) {
+        self.occluded = occluded;
+        self.beeper
+            .set_paused(occluded && (self.options.mute_when_occluded || self.paused()));
+
+        if !occluded {
+            // Don't treat the time spent paused as owed work.
+            self.since_last_frame.reset(&clock::SystemClock);
+            self.frame_cache.invalidate();
+            if let Some(window) = &self.window {
+                window.request_redraw();
+            }
+        }
+    }
+
+    // The display the window shows: a frame ahead of the machine's under run-ahead. With its
+    // generation.
+    fn shown_display(&self) -> (u64, &chip8::display::Display) {
+        self.run_ahead
+            .as_ref()
+            .and_then(runahead::RunAhead::ahead)
+            .unwrap_or((self.system.display_generation(), self.system.display()))
+    }
+
+    // What the window would show now, or None while it has no area to draw on.
+    fn frame_key(&self) -> Option<render::FrameKey> {
+        let size = self.window.as_ref()?.inner_size();
+        if size.width == 0 || size.height == 0 {
+            return None;
+        }
+        Some(render::FrameKey {
+            display_generation: self.shown_display().0,
+            compared_generation: self
+                .comparison
+                .as_ref()
+                .map(|comparison| comparison.system.display_generation()),
+            width: size.width as usize,
+            height: size.height as usize,
+            bell_shown: self.system.sound_active()
+                && self.config.visual_bell != render::VisualBell::Off,
+            pixel_style: self.pixel_style,
+            outlines: self.system.draw_records().len(),
+            keypad: self.keypad_overlay.then(|| self.system.get_pressed_key()),
+            hud: self.hud,
+            toasts: self.toasts.key(Instant::now()),
+        })
+    }
+
+    fn redraw(&mut self) {
+        let started = self.hud.is_some().then(Instant::now);
+        // The pixel under a still cursor can change too.
+        self.show_cursor_readout();
+        let Some(key) = self.frame_key() else {
+            return;
+        };
+        let Some(surface) = &mut self.surface else {
+            return;
+        };
+        let (Some(width), Some(height)) = (
+            NonZeroU32::new(key.width as u32),
+            NonZeroU32::new(key.height as u32),
+        ) else {
+            return;
+        };
+
+        let (system, visual_bell, toasts) = (&self.system, self.config.visual_bell, &self.toasts);
+        let now = Instant::now();
+        let shown = self
+            .run_ahead
+            .as_ref()
+            .and_then(runahead::RunAhead::ahead)
+            .map_or(system.display(), |(_, display)| display);
+        let compared = self
+            .comparison
+            .as_ref()
+            .map(|comparison| comparison.system.display());
+        let pixels = self.frame_cache.frame(key, |frame| {
+            // The game gets the part of the window above the keypad.
+            let game_height = match key.keypad {
+                Some(_) => overlay::game_height(key.height),
+                None => key.height,
+            };
+            let game = &mut frame[..key.width * game_height];
+            match compared {
+                Some(second) => {
+                    render::draw_side_by_side(
+                        system.display(),
+                        second,
+                        game,
+                        key.width,
+                        game_height,
+                        key.pixel_style,
+                    );
+                }
+                None => {
+                    render::draw_frame(shown, game, key.width, game_height, key.pixel_style);
+                    render::outline_draws(
+                        game,
+                        key.width,
+                        game_height,
+                        shown,
+                        system.draw_records(),
+                    );
+                }
+            }
+            render::apply_visual_bell(
+                game,
+                key.width,
+                game_height,
+                visual_bell,
+                system.sound_active(),
+            );
+            if let Some(pressed_key) = key.keypad {
+                overlay::draw_keypad(frame, key.width, key.height, pressed_key);
+            }
+            if let Some(stats) = &key.hud {
+                hud::draw_hud(frame, key.width, key.height, stats);
+            }
+            toast::draw_toasts(frame, key.width, key.height, toasts, now);
+        });
+        surface.resize(width, height).unwrap();
+        let mut frame = surface.buffer_mut().unwrap();
+        frame.copy_from_slice(pixels);
+        frame.present().unwrap();
+
+        if let Some(started) = started {
+            let now = Instant::now();
+            self.render_time = now - started;
+            self.present_meter.record(now, 1);
+        }
+    }
+
+    fn run_frame(&mut self) {
+        #[cfg(feature = "gamepad")]
+        if let Some(gamepad) = &mut self.gamepad {
+            gamepad.poll(&mut self.key_queue);
+        }
+
+        let mut halted = !self.running;
+        let mut waiting_for_key = false;
+        if self.running && !self.paused() {
+            let started = self.hud.is_some().then(Instant::now);
+            let result = self.run_cycles();
+            if let Some(started) = started {
+                self.cpu_time = started.elapsed();
+            }
+            match result {
+                Ok(_) if self.comparison.as_ref().is_some_and(Comparison::diverged) => {
+                    // Both machines stay paused where they diverged, to be looked at.
+                    self.running = false;
+                    halted = true;
+                }
+                Ok(summary) => {
+                    halted = summary.stopped == Some(chip8::StopReason::Halted);
+                    waiting_for_key = summary.stopped == Some(chip8::StopReason::WaitingForKey);
+                    let report = self.system.frame();
+                    if report.buzzer_edge {
+                        self.beeper.update(report.buzzer_on);
+                    }
+                    if let Some(verdict) = self.watchdog.observe(&summary) {
+                        halted |= self.respond_to_hang(verdict);
+                    }
+                    let too_slow = self
+                        .run_ahead
+                        .as_mut()
+                        .is_some_and(|run_ahead| run_ahead.speculate(&mut self.system));
+                    if too_slow {
+                        self.notify(toast::Notice::warning(
+                            "Run-ahead turned off, running ahead takes longer than a frame",
+                        ));
+                    }
+                }
+                Err(err) => {
+                    println!("Emulation stopped: {:?}", err);
+                    self.running = false;
+                    halted = true;
+                }
+            }
+            self.beeper.sink_mut().poll(Instant::now());
+        }
+        self.update_toasts();
+        if !self.paused() {
+            self.rotate_kiosk(halted);
+        }
+        if self.options.profile {
+            self.update_rate_title();
+        }
+        self.update_hud();
+        self.idle = pacing::should_idle(&pacing::Activity {
+            halted,
+            waiting_for_key,
+            timers_running: self.system.timers_running(),
+            input_pending: !self.key_queue.is_empty(),
+            frontend_busy: self.needs_frames(),
+        });
+        // Most frames don't change what's on screen.
+        let changed = self
+            .frame_key()
+            .is_some_and(|key| self.frame_cache.wants_redraw(key));
+        if !self.occluded && changed {
+            if let Some(window) = &self.window {
+                window.request_redraw();
             }
         }
     }
+
+    fn run_cycles(&mut self) -> Result<chip8::RunSummary, chip8::Chip8Error> {
+        let now = Instant::now();
+        let elapsed = self.since_last_frame.lap(&clock::SystemClock);
+
+        for transition in self.key_queue.next_batch(now) {
+            let second = self
+                .comparison
+                .as_mut()
+                .map(|comparison| &mut comparison.system);
+            for system in std::iter::once(&mut self.system).chain(second) {
+                match transition {
+                    keyqueue::KeyTransition::Pressed(key) => system.press_key(key)?,
+                    keyqueue::KeyTransition::Released => system.release_key(),
+                }
+            }
+        }
+        if let Some(comparison) = &mut self.comparison {
+            let summary = comparison.run_frame(&mut self.system)?;
+            self.rate_meter.record(now, summary.instructions);
+            return Ok(summary);
+        }
+        let summary = match (&mut self.recorder, &mut self.attract) {
+            (Some(recorder), _) => {
+                recorder.record_frame(self.system.get_pressed_key());
+                self.system.run_for(FRAME_DURATION)?
+            }
+            (None, Some(attract)) => {
+                for key in attract.next_frame() {
+                    match key {
+                        Some(key) => self.system.press_key(key)?,
+                        None => self.system.release_key(),
+                    }
+                }
+                self.system.run_for(FRAME_DURATION)?
+            }
+            (None, None) => {
+                let summary = self.system.run_for(elapsed)?;
+                if summary.dropped_instructions > 0 || summary.dropped_frames > 0 {
+                    eprintln!(
+                        "Warning: emulation fell behind by {:?}, dropped {} instructions and {} frames",
+                        elapsed, summary.dropped_instructions, summary.dropped_frames
+                    );
+                }
+                summary
+            }
+        };
+        self.rate_meter.record(now, summary.instructions);
+        Ok(summary)
+    }
+
+    // Whether the front end has to keep running frames no matter what the machine does: to keep
+    // numbers on screen current, rotate kiosk ROMs, record frames or poll a gamepad.
+    fn needs_frames(&self) -> bool {
+        #[cfg(feature = "gamepad")]
+        if self.gamepad.is_some() {
+            return true;
+        }
+        self.hud.is_some()
+            || !self.toasts.is_empty()
+            || self.options.profile
+            || self.kiosk.is_some()
+            || self.recorder.is_some()
+    }
+
+    // Picks frames back up after idling. Nothing ran while idle, so that time isn't owed.
+    fn wake(&mut self) {
+        if !self.idle {
+            return;
+        }
+        self.idle = false;
+        self.since_last_frame.reset(&clock::SystemClock);
+        self.frame_pacer.restart();
+    }
+
+    // Prints `notice` and shows it in the window.
+    fn notify(&mut self, notice: toast::Notice) {
+        notice.log();
+        self.toasts.push(Instant::now(), notice);
+    }
+
+    // Shows what happened since the last frame that wasn't the user's doing: the machine's
+    // diagnostics, which it has printed itself, and the audio device coming and going. Then takes
+    // down toasts whose time is up.
+    fn update_toasts(&mut self) {
+        let now = Instant::now();
+        for notice in
+            toast::diagnostic_notices(self.system.recent_diagnostics(), &mut self.diagnostics_seen)
+        {
+            // Errors are printed where the frame that hit them is handled.
+            if notice.level == toast::Level::Warning {
+                notice.log();
+            }
+            self.toasts.push(now, notice);
+        }
+        let audio_available = self.beeper.sink_mut().is_available();
+        if audio_available != self.audio_available {
+            self.audio_available = audio_available;
+            let notice = if audio_available {
+                toast::Notice::info("Audio device acquired")
+            } else {
+                toast::Notice::warning("Audio device lost, running silent")
+            };
+            self.toasts.push(now, notice);
+        }
+        self.toasts.expire(now);
+    }
+
+    // Takes new numbers for the HUD once per HUD_REFRESH while it's shown.
+    fn update_hud(&mut self) {
+        if self.hud.is_none() {
+            return;
+        }
+        let now = Instant::now();
+        if now - self.last_hud_update < hud::HUD_REFRESH {
+            return;
+        }
+        self.last_hud_update = now;
+        self.hud = Some(hud::Stats {
+            ips: self.rate_meter.effective_hz(),
+            fps: self.present_meter.effective_hz(),
+            cpu_time: self.cpu_time,
+            render_time: self.render_time,
+            dropped: self.frame_pacer.stats().dropped,
+            skipped: self.frame_cache.skipped(),
+        });
+    }
+
+    // Shows the achieved instruction rate in the window title, refreshed once per rate window.
+    fn update_rate_title(&mut self) {
+        let now = Instant::now();
+        if now - self.last_title_update < pacing::RATE_WINDOW {
+            return;
+        }
+        self.last_title_update = now;
+        self.set_title(format!(
+            "{} - {:.0} Hz",
+            WINDOW_TITLE,
+            self.rate_meter.effective_hz()
+        ));
+    }
 }
 
-impl ApplicationHandler for Emulator<'_> {
+impl ApplicationHandler for Emulator {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-        let window_attrs = Window::default_attributes().with_title(WINDOW_TITLE);
-        self.window = Some(event_loop.create_window(window_attrs).unwrap());
+        self.title = self.window_title();
+        let mut window_attrs = Window::default_attributes().with_title(&self.title);
+        // A new window takes the display's shape, side by side when comparing.
+        let display = self.system.display();
+        let displays = if self.comparison.is_some() { 2 } else { 1 };
+        window_attrs = window_attrs.with_inner_size(PhysicalSize::new(
+            (display.width * WINDOW_SCALE * displays) as u32,
+            (display.height * WINDOW_SCALE) as u32,
+        ));
+        if let Some(saved) = self.session.window {
+            let monitors: Vec<session::Rect> = event_loop
+                .available_monitors()
+                .map(|m| monitor_rect(&m))
+                .collect();
+            let primary = event_loop.primary_monitor().map(|m| monitor_rect(&m));
+            let placed = session::place(saved, &monitors, primary);
+            window_attrs = window_attrs
+                .with_position(PhysicalPosition::new(placed.x, placed.y))
+                .with_inner_size(PhysicalSize::new(placed.width, placed.height));
+        }
+        if self.session.fullscreen {
+            window_attrs = window_attrs.with_fullscreen(Some(Fullscreen::Borderless(None)));
+        }
+        let window = Rc::new(event_loop.create_window(window_attrs).unwrap());
+        let context = softbuffer::Context::new(window.clone()).unwrap();
+        self.surface = Some(softbuffer::Surface::new(&context, window.clone()).unwrap());
+        self.window = Some(window);
     }
 
     fn window_event(
@@ -99,8 +1399,74 @@ impl ApplicationHandler for Emulator<'_> {
             WindowEvent::CloseRequested => {
                 event_loop.exit();
             }
-            WindowEvent::KeyboardInput { event, .. } => self.handle_key_event(event),
+            WindowEvent::KeyboardInput { event, .. } => {
+                self.wake();
+                self.handle_key_event(event)
+            }
+            WindowEvent::Occluded(occluded) => self.set_occluded(occluded),
+            WindowEvent::RedrawRequested => self.redraw(),
+            WindowEvent::DroppedFile(path) => {
+                self.wake();
+                self.load_dropped_rom(&path)
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor = Some((position.x.max(0.0) as usize, position.y.max(0.0) as usize));
+                self.show_cursor_readout();
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.cursor = None;
+                self.show_cursor_readout();
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
+                self.show_cursor_readout();
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => {
+                self.wake();
+                self.handle_click(state)
+            }
             _ => {}
         }
     }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        if self.idle {
+            event_loop.set_control_flow(ControlFlow::Wait);
+            return;
+        }
+        let control_flow = match self.frame_pacer.poll(&clock::SystemClock) {
+            pacing::PaceAction::WaitUntil(until) => ControlFlow::WaitUntil(until),
+            pacing::PaceAction::Run => {
+                self.run_frame();
+                ControlFlow::Poll
+            }
+        };
+        event_loop.set_control_flow(control_flow);
+    }
+
+    fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
+        if self.options.profile {
+            println!("{}", self.frame_pacer.report());
+            println!("{}", self.frame_cache.report());
+            println!("{}", chip8::profile_text(&self.system.profile_report()));
+        }
+        self.save_persistent_memory();
+        self.save_session();
+        if let (Some(recorder), Some(path)) = (&self.recorder, &self.options.record_replay) {
+            match std::fs::write(path, recorder.replay().to_text()) {
+                Ok(()) => println!("Replay saved to {}", path),
+                Err(err) => eprintln!("Failed to save replay to {}: {}", path, err),
+            }
+        }
+        if let (Some(recorder), Some(path)) = (&self.recorder, &self.options.record_bundle) {
+            match std::fs::write(path, recorder.bundle().to_text()) {
+                Ok(()) => println!("Bundle saved to {}", path),
+                Err(err) => eprintln!("Failed to save bundle to {}: {}", path, err),
+            }
+        }
+    }
 }
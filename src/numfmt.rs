@@ -0,0 +1,140 @@
+use std::fmt;
+
+// Numbers as the user types them in config files and tools, and as they're shown back. Input
+// can be hex with a prefix, `0x224`, hex with a suffix, `224h`, or decimal, `548`. Output is
+// always lowercase hex with a `0x` prefix, padded to the width of the kind of value: 2 digits
+// for bytes, 3 for addresses and 4 for words.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NumberError {
+    Empty,
+    // Not a number in any of the accepted syntaxes.
+    Invalid(String),
+    // A number, but larger than the kind of value allows.
+    TooLarge { text: String, max: u16 },
+}
+
+impl fmt::Display for NumberError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NumberError::Empty => write!(f, "expected a number"),
+            NumberError::Invalid(text) => {
+                write!(f, "`{}` is not a number like 0x224, 224h or 548", text)
+            }
+            NumberError::TooLarge { text, max } => {
+                // The bound at the width of the kind of value parsed.
+                let max = match u8::try_from(*max) {
+                    Ok(max) => format_byte(max),
+                    Err(_) => format_word(*max),
+                };
+                write!(f, "`{}` is larger than {}", text, max)
+            }
+        }
+    }
+}
+
+fn parse(text: &str, max: u16) -> Result<u16, NumberError> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err(NumberError::Empty);
+    }
+    let (digits, radix) =
+        if let Some(digits) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+            (digits, 16)
+        } else if let Some(digits) = text.strip_suffix(['h', 'H']) {
+            (digits, 16)
+        } else {
+            (text, 10)
+        };
+    // from_str_radix takes a sign, which none of the syntaxes have.
+    if digits.is_empty() || !digits.chars().all(|digit| digit.is_digit(radix)) {
+        return Err(NumberError::Invalid(text.to_string()));
+    }
+    match u64::from_str_radix(digits, radix) {
+        Ok(value) if value <= u64::from(max) => Ok(value as u16),
+        _ => Err(NumberError::TooLarge {
+            text: text.to_string(),
+            max,
+        }),
+    }
+}
+
+pub fn parse_byte(text: &str) -> Result<u8, NumberError> {
+    parse(text, u8::MAX.into()).map(|value| value as u8)
+}
+
+// Any address in the largest memory, XO-CHIP's 64K; callers check it against the machine's own.
+pub fn parse_addr(text: &str) -> Result<u16, NumberError> {
+    parse(text, u16::MAX)
+}
+
+pub fn parse_word(text: &str) -> Result<u16, NumberError> {
+    parse(text, u16::MAX)
+}
+
+pub fn format_byte(value: u8) -> String {
+    format!("0x{:02x}", value)
+}
+
+pub fn format_addr(address: u16) -> String {
+    format!("0x{:03x}", address)
+}
+
+pub fn format_word(value: u16) -> String {
+    format!("0x{:04x}", value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_syntaxes() {
+        for text in ["0x224", "0X224", "224h", "224H", "548", " 0x224 ", "0x0224"] {
+            assert_eq!(parse_addr(text), Ok(0x224), "{}", text);
+        }
+        assert_eq!(parse_byte("0xfF"), Ok(0xFF));
+        assert_eq!(parse_byte("0"), Ok(0));
+        assert_eq!(parse_word("00e0h"), Ok(0x00E0));
+    }
+
+    #[test]
+    fn test_parse_rejects() {
+        assert_eq!(parse_addr("  "), Err(NumberError::Empty));
+        for text in ["0x", "h", "12a", "0x12g", "+12", "-1", "0x-1", "1 2"] {
+            assert_eq!(
+                parse_addr(text),
+                Err(NumberError::Invalid(text.to_string())),
+                "{}",
+                text
+            );
+        }
+        assert_eq!(
+            parse_byte("256"),
+            Err(NumberError::TooLarge {
+                text: "256".to_string(),
+                max: 0xFF
+            })
+        );
+        assert!(parse_byte("100h").is_err());
+        assert!(parse_addr("0x10000").is_err());
+        assert!(parse_word("99999999999999999999999").is_err());
+        assert_eq!(
+            parse_byte("0x100").unwrap_err().to_string(),
+            "`0x100` is larger than 0xff"
+        );
+        assert_eq!(
+            parse_addr("0x10000").unwrap_err().to_string(),
+            "`0x10000` is larger than 0xffff"
+        );
+    }
+
+    #[test]
+    fn test_format_widths() {
+        assert_eq!(format_byte(0x0A), "0x0a");
+        assert_eq!(format_addr(0x2A), "0x02a");
+        assert_eq!(format_addr(0xFFFF), "0xffff");
+        assert_eq!(format_word(0xE0), "0x00e0");
+        assert_eq!(parse_addr(&format_addr(0x3F0)), Ok(0x3F0));
+    }
+}
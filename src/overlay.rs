@@ -0,0 +1,122 @@
+use crate::render::{PIXEL_OFF, PIXEL_ON};
+use ch8emu::chip8::display::Display;
+
+// The keypad as laid out on the COSMAC VIP, top row first.
+const LAYOUT: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+pub const KEY_COLOR: u32 = 0x0030_3030;
+pub const PRESSED_KEY_COLOR: u32 = 0x0000_70C0;
+// Built-in glyphs are 4 pixels wide and SPRITE_LEN tall.
+const GLYPH_WIDTH: usize = 4;
+
+// Rows of the window left for the game while the keypad is shown. The keypad takes the rest.
+pub fn game_height(height: usize) -> usize {
+    height / 2
+}
+
+// The key under a point of a window `width` by `height` pixels, if it's on the keypad.
+pub fn key_at(x: usize, y: usize, width: usize, height: usize) -> Option<u8> {
+    let top = game_height(height);
+    if x >= width || y < top || y >= height {
+        return None;
+    }
+    let row = (y - top) * LAYOUT.len() / (height - top);
+    let column = x * LAYOUT[0].len() / width;
+    Some(LAYOUT[row][column])
+}
+
+// Draws the keypad below the game, with each key labelled by its glyph from the built-in font and
+// the pressed key highlighted. Keys are separated by a one pixel gap.
+pub fn draw_keypad(frame: &mut [u32], width: usize, height: usize, pressed_key: Option<u8>) {
+    let top = game_height(height);
+    let keypad_height = height - top;
+    for (row, keys) in LAYOUT.iter().enumerate() {
+        for (column, &key) in keys.iter().enumerate() {
+            let left = column * width / keys.len();
+            let right = (column + 1) * width / keys.len();
+            let cell_top = top + row * keypad_height / LAYOUT.len();
+            let cell_bottom = top + (row + 1) * keypad_height / LAYOUT.len();
+            let color = if pressed_key == Some(key) {
+                PRESSED_KEY_COLOR
+            } else {
+                KEY_COLOR
+            };
+            for y in cell_top..cell_bottom {
+                for x in left..right {
+                    let gap = x == left || y == cell_top;
+                    frame[y * width + x] = if gap { PIXEL_OFF } else { color };
+                }
+            }
+
+            let Some(glyph) = Display::font_glyph(key) else {
+                continue;
+            };
+            // Half the cell tall, but never less than one window pixel per glyph pixel.
+            let scale = ((cell_bottom - cell_top) / (2 * glyph.len())).max(1);
+            let glyph_left = (left + right).saturating_sub(GLYPH_WIDTH * scale) / 2;
+            let glyph_top = (cell_top + cell_bottom).saturating_sub(glyph.len() * scale) / 2;
+            for (glyph_y, &bits) in glyph.iter().enumerate() {
+                for glyph_x in 0..GLYPH_WIDTH {
+                    if bits & (0b1000_0000 >> glyph_x) == 0 {
+                        continue;
+                    }
+                    for y in 0..scale {
+                        for x in 0..scale {
+                            let (x, y) = (
+                                glyph_left + glyph_x * scale + x,
+                                glyph_top + glyph_y * scale + y,
+                            );
+                            if x < right && y < cell_bottom {
+                                frame[y * width + x] = PIXEL_ON;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_at() {
+        let (width, height) = (400, 400);
+
+        assert_eq!(key_at(0, 200, width, height), Some(0x1));
+        assert_eq!(key_at(399, 200, width, height), Some(0xC));
+        assert_eq!(key_at(150, 260, width, height), Some(0x5));
+        assert_eq!(key_at(100, 399, width, height), Some(0x0));
+        assert_eq!(key_at(399, 399, width, height), Some(0xF));
+        // The game above the keypad, and points outside the window.
+        assert_eq!(key_at(0, 199, width, height), None);
+        assert_eq!(key_at(400, 300, width, height), None);
+        assert_eq!(key_at(0, 400, width, height), None);
+    }
+
+    #[test]
+    fn test_key_at_matches_drawn_keys() {
+        let (width, height) = (64, 64);
+        let mut frame = vec![PIXEL_ON; width * height];
+
+        draw_keypad(&mut frame, width, height, Some(0x6));
+
+        assert_eq!(frame[(game_height(height) - 1) * width], PIXEL_ON);
+        assert_eq!(frame[game_height(height) * width], PIXEL_OFF);
+        for y in game_height(height)..height {
+            for x in 0..width {
+                let pixel = frame[y * width + x];
+                if pixel == PRESSED_KEY_COLOR {
+                    assert_eq!(key_at(x, y, width, height), Some(0x6));
+                }
+            }
+        }
+        assert_eq!(frame[41 * width + 33], PRESSED_KEY_COLOR);
+    }
+}
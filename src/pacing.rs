@@ -0,0 +1,424 @@
+use crate::clock::Clock;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+// Span of the sliding window the effective instruction rate is measured over.
+pub const RATE_WINDOW: Duration = Duration::from_secs(1);
+// OS sleeps can overshoot by a few milliseconds, so the last stretch before a deadline is spun.
+const SPIN_THRESHOLD: Duration = Duration::from_micros(1_500);
+
+#[derive(Debug, PartialEq)]
+pub enum PaceAction {
+    // Not due yet, wait (while still handling events) until the given instant, then poll again.
+    WaitUntil(Instant),
+    // The frame deadline was reached and the next frame should be run.
+    Run,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct JitterStats {
+    pub frames: u64,
+    pub total_lateness: Duration,
+    pub max_lateness: Duration,
+    // Frames skipped to restart the schedule after stalls.
+    pub dropped: u64,
+}
+
+impl JitterStats {
+    pub fn mean_lateness(&self) -> Duration {
+        if self.frames == 0 {
+            Duration::ZERO
+        } else {
+            self.total_lateness / self.frames as u32
+        }
+    }
+
+    fn record(&mut self, lateness: Duration) {
+        self.frames += 1;
+        self.total_lateness += lateness;
+        self.max_lateness = self.max_lateness.max(lateness);
+    }
+}
+
+// Schedules frames at a fixed rate. The coarse part of the wait is left to the OS and the final
+// SPIN_THRESHOLD before each deadline is spun to hit it precisely.
+#[derive(Debug)]
+pub struct FramePacer {
+    frame_duration: Duration,
+    deadline: Option<Instant>,
+    stats: JitterStats,
+}
+
+impl FramePacer {
+    pub fn new(frame_duration: Duration) -> FramePacer {
+        FramePacer {
+            frame_duration,
+            deadline: None,
+            stats: JitterStats::default(),
+        }
+    }
+
+    pub fn poll<C: Clock>(&mut self, clock: &C) -> PaceAction {
+        let now = clock.now();
+        let deadline = *self.deadline.get_or_insert(now);
+
+        if deadline > now + SPIN_THRESHOLD {
+            return PaceAction::WaitUntil(deadline - SPIN_THRESHOLD);
+        }
+
+        let mut now = now;
+        while now < deadline {
+            clock.yield_now();
+            now = clock.now();
+        }
+
+        self.stats.record(now - deadline);
+        let next_deadline = deadline + self.frame_duration;
+        // After a stall, restart the schedule instead of running a burst of late frames.
+        self.deadline = Some(if next_deadline < now {
+            let missed = (now - next_deadline).as_nanos() / self.frame_duration.as_nanos();
+            self.stats.dropped += missed as u64 + 1;
+            now + self.frame_duration
+        } else {
+            next_deadline
+        });
+        PaceAction::Run
+    }
+
+    // Blocks until the next frame is due, for loops that don't have an event loop to wait in.
+    #[allow(dead_code)]
+    pub fn wait<C: Clock>(&mut self, clock: &C) {
+        while let PaceAction::WaitUntil(until) = self.poll(clock) {
+            clock.sleep(until.saturating_duration_since(clock.now()));
+        }
+    }
+
+    // Starts the schedule over at the next poll, for when frames stopped on purpose. Unlike a
+    // stall, the frames that weren't run don't count as dropped.
+    pub fn restart(&mut self) {
+        self.deadline = None;
+    }
+
+    pub fn stats(&self) -> &JitterStats {
+        &self.stats
+    }
+
+    pub fn report(&self) -> String {
+        format!(
+            "frames: {}, target frame time: {:?}, mean lateness: {:?}, max lateness: {:?}, \
+             dropped frames: {}",
+            self.stats.frames,
+            self.frame_duration,
+            self.stats.mean_lateness(),
+            self.stats.max_lateness,
+            self.stats.dropped
+        )
+    }
+}
+
+// What the last frame left going, for deciding whether the event loop can sleep until the next
+// event instead of waking for every frame.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Activity {
+    // The ROM jumped to itself, or emulation stopped.
+    pub halted: bool,
+    // The ROM is blocked in FX0A.
+    pub waiting_for_key: bool,
+    // The delay or sound timer is still counting down, which has to happen in real time.
+    pub timers_running: bool,
+    // Key transitions are queued for later frames.
+    pub input_pending: bool,
+    // Something outside the machine needs frames to keep coming, like the HUD or a kiosk.
+    pub frontend_busy: bool,
+}
+
+// Whether nothing can change until an event arrives, so frames can stop until one does.
+pub fn should_idle(activity: &Activity) -> bool {
+    (activity.halted || activity.waiting_for_key)
+        && !activity.timers_running
+        && !activity.input_pending
+        && !activity.frontend_busy
+}
+
+// Measures the time owed to the emulator at each frame: the time since the previous frame ran.
+#[derive(Debug)]
+pub struct ElapsedTimer {
+    last: Instant,
+}
+
+impl ElapsedTimer {
+    pub fn new<C: Clock>(clock: &C) -> ElapsedTimer {
+        ElapsedTimer { last: clock.now() }
+    }
+
+    // The time since the previous lap, or since the timer was created or reset.
+    pub fn lap<C: Clock>(&mut self, clock: &C) -> Duration {
+        let now = clock.now();
+        let elapsed = now.saturating_duration_since(self.last);
+        self.last = now;
+        elapsed
+    }
+
+    // Forgets the time since the previous lap, so that time spent paused isn't treated as owed.
+    pub fn reset<C: Clock>(&mut self, clock: &C) {
+        self.last = clock.now();
+    }
+}
+
+// Measures the instruction rate actually achieved over a sliding window of wall-clock time.
+#[derive(Debug)]
+pub struct RateMeter {
+    window: Duration,
+    // (time, instructions executed since the previous sample). The oldest sample is only used as
+    // the start of the window.
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl RateMeter {
+    pub fn new(window: Duration) -> RateMeter {
+        RateMeter {
+            window,
+            samples: VecDeque::new(),
+        }
+    }
+
+    pub fn record(&mut self, now: Instant, instructions: u64) {
+        self.samples.push_back((now, instructions));
+        while self.samples.len() > 2 && now.duration_since(self.samples[1].0) >= self.window {
+            self.samples.pop_front();
+        }
+    }
+
+    pub fn effective_hz(&self) -> f64 {
+        let (Some(&(start, _)), Some(&(end, _))) = (self.samples.front(), self.samples.back())
+        else {
+            return 0.0;
+        };
+        let span = end.duration_since(start).as_secs_f64();
+        if span == 0.0 {
+            return 0.0;
+        }
+        let instructions: u64 = self.samples.iter().skip(1).map(|&(_, count)| count).sum();
+        instructions as f64 / span
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{MockClock, SystemClock};
+    use ch8emu::chip8::{assemble_opcodes, BootConfig, Chip8, Opcode, Options};
+
+    const FRAME: Duration = Duration::from_micros(16_667);
+
+    #[test]
+    fn test_frame_pacer_first_poll_runs_immediately() {
+        let clock = MockClock::new(Duration::ZERO);
+        let mut pacer = FramePacer::new(FRAME);
+
+        assert_eq!(pacer.poll(&clock), PaceAction::Run);
+    }
+
+    #[test]
+    fn test_frame_pacer_waits_until_spin_threshold() {
+        let clock = MockClock::new(Duration::ZERO);
+        let mut pacer = FramePacer::new(FRAME);
+        pacer.poll(&clock);
+
+        assert_eq!(
+            pacer.poll(&clock),
+            PaceAction::WaitUntil(clock.now() + FRAME - SPIN_THRESHOLD)
+        );
+    }
+
+    #[test]
+    fn test_frame_pacer_spins_to_deadline_despite_sleep_overshoot() {
+        // The OS sleep overshoots by 1ms, which is absorbed by the spin window.
+        let clock = MockClock::new(Duration::from_millis(1));
+        let mut pacer = FramePacer::new(FRAME);
+        let start = clock.now();
+
+        for _ in 0..10 {
+            pacer.wait(&clock);
+        }
+
+        let expected_end = start + FRAME * 9;
+        assert!(clock.now() >= expected_end);
+        assert!(clock.now() - expected_end < Duration::from_micros(20));
+        assert_eq!(pacer.stats.frames, 10);
+        assert!(pacer.stats.max_lateness < Duration::from_micros(20));
+    }
+
+    #[test]
+    fn test_frame_pacer_records_lateness_of_overshooting_sleep() {
+        // An overshoot larger than the spin window shows up in the stats.
+        let clock = MockClock::new(Duration::from_millis(3));
+        let mut pacer = FramePacer::new(FRAME);
+
+        pacer.wait(&clock);
+        pacer.wait(&clock);
+
+        assert_eq!(pacer.stats.frames, 2);
+        assert_eq!(pacer.stats.max_lateness, Duration::from_micros(1_500));
+    }
+
+    #[test]
+    fn test_frame_pacer_restarts_schedule_after_stall() {
+        let clock = MockClock::new(Duration::ZERO);
+        let mut pacer = FramePacer::new(FRAME);
+        pacer.poll(&clock);
+
+        clock.advance(FRAME * 10);
+        assert_eq!(pacer.poll(&clock), PaceAction::Run);
+        assert_eq!(pacer.stats().dropped, 9);
+
+        assert_eq!(
+            pacer.poll(&clock),
+            PaceAction::WaitUntil(clock.now() + FRAME - SPIN_THRESHOLD)
+        );
+    }
+
+    #[test]
+    fn test_frame_pacer_restart_drops_nothing() {
+        let clock = MockClock::new(Duration::ZERO);
+        let mut pacer = FramePacer::new(FRAME);
+        pacer.poll(&clock);
+
+        clock.advance(FRAME * 10);
+        pacer.restart();
+        assert_eq!(pacer.poll(&clock), PaceAction::Run);
+        assert_eq!(pacer.stats().dropped, 0);
+        assert_eq!(
+            pacer.poll(&clock),
+            PaceAction::WaitUntil(clock.now() + FRAME - SPIN_THRESHOLD)
+        );
+    }
+
+    #[test]
+    fn test_should_idle() {
+        let halted = Activity {
+            halted: true,
+            ..Activity::default()
+        };
+        let waiting = Activity {
+            waiting_for_key: true,
+            ..Activity::default()
+        };
+
+        assert!(should_idle(&halted));
+        assert!(should_idle(&waiting));
+        assert!(!should_idle(&Activity::default()));
+        // Anything still counting down or queued keeps frames coming.
+        for busy in [
+            Activity {
+                timers_running: true,
+                ..waiting
+            },
+            Activity {
+                input_pending: true,
+                ..waiting
+            },
+            Activity {
+                frontend_busy: true,
+                ..halted
+            },
+        ] {
+            assert!(!should_idle(&busy), "{:?}", busy);
+        }
+    }
+
+    #[test]
+    fn test_frames_paced_by_mock_clock() {
+        // Sleeps overshoot, so frames run a little late and the time owed varies between them.
+        let clock = MockClock::new(Duration::from_micros(700));
+        let mut pacer = FramePacer::new(FRAME);
+        let mut elapsed = ElapsedTimer::new(&clock);
+        let mut system = Chip8::with_options(Options {
+            ops_per_second: 600,
+            ..Options::default()
+        })
+        .unwrap();
+        system.boot(BootConfig::default()).unwrap();
+        system
+            .load_rom(&assemble_opcodes(&[
+                Opcode::AddByte(0x0, 0x1),
+                Opcode::Jump(0x200),
+            ]))
+            .unwrap();
+        let start = clock.now();
+
+        let (mut instructions, mut timer_ticks) = (0, 0);
+        for _ in 0..60 {
+            pacer.wait(&clock);
+            let summary = system.run_for(elapsed.lap(&clock)).unwrap();
+            instructions += summary.instructions;
+            timer_ticks += summary.frames;
+        }
+
+        // Everything owed for the virtual time that passed ran, no more and no less.
+        let nanos = (clock.now() - start).as_nanos() as u64;
+        assert_eq!(timer_ticks, nanos * 60 / 1_000_000_000);
+        assert_eq!(instructions, nanos * 600 / 1_000_000_000);
+        assert_eq!(pacer.stats.frames, 60);
+
+        // Time spent paused isn't owed.
+        clock.advance(Duration::from_secs(5));
+        elapsed.reset(&clock);
+        pacer.wait(&clock);
+        assert!(elapsed.lap(&clock) < FRAME * 2);
+    }
+
+    #[test]
+    fn test_rate_meter_empty() {
+        let mut meter = RateMeter::new(RATE_WINDOW);
+        assert_eq!(meter.effective_hz(), 0.0);
+
+        meter.record(Instant::now(), 10);
+        assert_eq!(meter.effective_hz(), 0.0);
+    }
+
+    #[test]
+    fn test_rate_meter_steady_rate() {
+        let clock = MockClock::new(Duration::ZERO);
+        let mut meter = RateMeter::new(RATE_WINDOW);
+        meter.record(clock.now(), 0);
+
+        for _ in 0..120 {
+            clock.advance(FRAME);
+            meter.record(clock.now(), 10);
+        }
+
+        assert!((meter.effective_hz() - 600.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_rate_meter_forgets_samples_outside_window() {
+        let clock = MockClock::new(Duration::ZERO);
+        let mut meter = RateMeter::new(RATE_WINDOW);
+        meter.record(clock.now(), 0);
+
+        for _ in 0..10 {
+            clock.advance(Duration::from_millis(100));
+            meter.record(clock.now(), 100);
+        }
+        for _ in 0..10 {
+            clock.advance(Duration::from_millis(100));
+            meter.record(clock.now(), 10);
+        }
+
+        assert!((meter.effective_hz() - 100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    #[ignore]
+    fn test_frame_pacer_real_jitter() {
+        let mut pacer = FramePacer::new(FRAME);
+
+        for _ in 0..60 {
+            pacer.wait(&SystemClock);
+        }
+
+        println!("{}", pacer.report());
+        assert!(pacer.stats.mean_lateness() < Duration::from_millis(1));
+    }
+}
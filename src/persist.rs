@@ -0,0 +1,195 @@
+use ch8emu::chip8::{Chip8, Fnv1a};
+use ch8emu::replay::rom_hash;
+use std::hash::Hasher;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8; 8] = b"CH8SAVE1";
+// Magic, start address and length before the saved bytes, checksum after them.
+const HEADER_LEN: usize = MAGIC.len() + 4 + 4;
+const CHECKSUM_LEN: usize = 8;
+
+// Keeps a range of a ROM's memory across sessions, for games that hold a high score table in RAM.
+// Each ROM gets its own file in `dir`, named after its hash.
+#[derive(Debug)]
+pub struct Persistence {
+    range: Range<usize>,
+    path: PathBuf,
+}
+
+impl Persistence {
+    pub fn new(dir: &Path, rom: &[u8], range: Range<usize>) -> Self {
+        Persistence {
+            range,
+            path: dir.join(format!("{:016x}.sav", rom_hash(rom))),
+        }
+    }
+
+    // Copies the saved bytes into memory. Call after loading the ROM, since it may cover the
+    // range. A missing file is a first session; an unreadable one is skipped with a warning.
+    pub fn restore(&self, system: &mut Chip8) {
+        let contents = match std::fs::read(&self.path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return,
+            Err(err) => {
+                eprintln!("Warning: ignoring {}: {}", self.path.display(), err);
+                return;
+            }
+        };
+        let restored = self.decode(&contents).and_then(|bytes| {
+            system
+                .load_bytes(self.range.start, bytes)
+                .map_err(|err| format!("doesn't fit in memory: {:?}", err))
+        });
+        if let Err(err) = restored {
+            eprintln!("Warning: ignoring {}: {}", self.path.display(), err);
+        }
+    }
+
+    pub fn save(&self, system: &Chip8) -> Result<(), String> {
+        let bytes = system
+            .memory_bytes(self.range.start, self.range.len())
+            .map_err(|err| format!("range is outside memory: {:?}", err))?;
+        let mut contents = MAGIC.to_vec();
+        contents.extend_from_slice(&(self.range.start as u32).to_be_bytes());
+        contents.extend_from_slice(&(self.range.len() as u32).to_be_bytes());
+        contents.extend_from_slice(&bytes);
+        contents.extend_from_slice(&checksum(&contents).to_be_bytes());
+
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+        std::fs::write(&self.path, contents).map_err(|err| err.to_string())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Returns the saved bytes if the file is intact and was saved for the same range.
+    fn decode<'a>(&self, contents: &'a [u8]) -> Result<&'a [u8], String> {
+        if contents.len() < HEADER_LEN + CHECKSUM_LEN || &contents[..MAGIC.len()] != MAGIC {
+            return Err("not a save file".to_string());
+        }
+        let (body, stored) = contents.split_at(contents.len() - CHECKSUM_LEN);
+        if checksum(body).to_be_bytes() != stored {
+            return Err("checksum mismatch".to_string());
+        }
+        let field = |offset: usize| {
+            let bytes = &body[MAGIC.len() + offset..MAGIC.len() + offset + 4];
+            u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize
+        };
+        let saved = field(0)..field(0) + field(4);
+        let bytes = &body[HEADER_LEN..];
+        if saved != self.range || bytes.len() != self.range.len() {
+            return Err(format!(
+                "saved {:#05X}-{:#05X}, but {:#05X}-{:#05X} is configured",
+                saved.start,
+                saved.end.saturating_sub(1),
+                self.range.start,
+                self.range.end - 1
+            ));
+        }
+        Ok(bytes)
+    }
+}
+
+fn checksum(bytes: &[u8]) -> u64 {
+    let mut hasher = Fnv1a::default();
+    hasher.write(bytes);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROM: [u8; 2] = [0x12, 0x00];
+
+    // A fresh directory standing in for the config dir.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ch8emu-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn session() -> Chip8 {
+        let mut system = Chip8::booted();
+        system.load_rom(&ROM).unwrap();
+        system
+    }
+
+    #[test]
+    fn test_bytes_survive_sessions() {
+        let dir = temp_dir("persist-sessions");
+        let persistence = Persistence::new(&dir, &ROM, 0x3F0..0x3F4);
+
+        let mut first = session();
+        persistence.restore(&mut first);
+        assert_eq!(first.memory_bytes(0x3F0, 4), Ok(vec![0; 4]));
+        first.load_bytes(0x3F0, &[0x12, 0x34, 0x56, 0x78]).unwrap();
+        persistence.save(&first).unwrap();
+
+        let mut second = session();
+        persistence.restore(&mut second);
+        assert_eq!(
+            second.memory_bytes(0x3F0, 4),
+            Ok(vec![0x12, 0x34, 0x56, 0x78])
+        );
+
+        // Another ROM has its own file.
+        let mut other = session();
+        Persistence::new(&dir, &[0x12, 0x02], 0x3F0..0x3F4).restore(&mut other);
+        assert_eq!(other.memory_bytes(0x3F0, 4), Ok(vec![0; 4]));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_corrupt_file_is_ignored() {
+        let dir = temp_dir("persist-corrupt");
+        let persistence = Persistence::new(&dir, &ROM, 0x3F0..0x3F4);
+        let mut system = session();
+        system.load_bytes(0x3F0, &[0x12, 0x34, 0x56, 0x78]).unwrap();
+        persistence.save(&system).unwrap();
+        let mut contents = std::fs::read(persistence.path()).unwrap();
+        contents[HEADER_LEN] ^= 0xFF;
+        std::fs::write(persistence.path(), &contents).unwrap();
+
+        let mut restored = session();
+        persistence.restore(&mut restored);
+
+        assert_eq!(restored.memory_bytes(0x3F0, 4), Ok(vec![0; 4]));
+        assert_eq!(
+            persistence.decode(&contents),
+            Err("checksum mismatch".to_string())
+        );
+        assert_eq!(
+            persistence.decode(b"garbage"),
+            Err("not a save file".to_string())
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_file_for_another_range_is_ignored() {
+        let dir = temp_dir("persist-range");
+        let mut system = session();
+        system.load_bytes(0x3F0, &[0x12, 0x34, 0x56, 0x78]).unwrap();
+        Persistence::new(&dir, &ROM, 0x3F0..0x3F4)
+            .save(&system)
+            .unwrap();
+        let persistence = Persistence::new(&dir, &ROM, 0x3F0..0x3F8);
+
+        let mut restored = session();
+        persistence.restore(&mut restored);
+
+        assert_eq!(restored.memory_bytes(0x3F0, 8), Ok(vec![0; 8]));
+        let contents = std::fs::read(persistence.path()).unwrap();
+        assert_eq!(
+            persistence.decode(&contents),
+            Err("saved 0x3F0-0x3F3, but 0x3F0-0x3F7 is configured".to_string())
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
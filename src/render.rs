@@ -0,0 +1,711 @@
+use crate::hud::Stats;
+use crate::toast::ToastKey;
+use ch8emu::chip8::display::{Display, DEFAULT_PALETTE};
+use ch8emu::chip8::DrawRecord;
+use std::str::FromStr;
+
+pub const PIXEL_ON: u32 = DEFAULT_PALETTE[1];
+pub const PIXEL_OFF: u32 = DEFAULT_PALETTE[0];
+pub const BELL_BORDER_COLOR: u32 = 0x00FF_A000;
+const BELL_BORDER_SIZE: usize = 4;
+pub const OUTLINE_COLOR: u32 = 0x0000_C0FF;
+pub const COLLISION_OUTLINE_COLOR: u32 = 0x00FF_3030;
+// Replaces cleared pixels inside sprites that collided.
+pub const COLLISION_TINT: u32 = 0x0050_0000;
+pub const DIVIDER_COLOR: u32 = 0x0080_8080;
+const DIVIDER_WIDTH: usize = 2;
+
+// Visual indicator shown while the sound timer is active.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum VisualBell {
+    Border,
+    Invert,
+    #[default]
+    Off,
+}
+
+impl FromStr for VisualBell {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "border" => Ok(VisualBell::Border),
+            "invert" => Ok(VisualBell::Invert),
+            "off" => Ok(VisualBell::Off),
+            _ => Err(format!("unknown visual bell `{}`", value)),
+        }
+    }
+}
+
+// How each CHIP-8 pixel is drawn on the window's pixels.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum PixelStyle {
+    #[default]
+    Solid,
+    // Solid, less one row and column of window pixels on the bottom right to leave a gap, like
+    // an LCD. Only once pixels are at least 4 window pixels across.
+    Grid,
+    // A square inset by a quarter of the pixel's size on each side.
+    Dot,
+}
+
+impl PixelStyle {
+    pub fn next(&self) -> PixelStyle {
+        match self {
+            PixelStyle::Solid => PixelStyle::Grid,
+            PixelStyle::Grid => PixelStyle::Dot,
+            PixelStyle::Dot => PixelStyle::Solid,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PixelStyle::Solid => "solid",
+            PixelStyle::Grid => "grid",
+            PixelStyle::Dot => "dot",
+        }
+    }
+
+    // Whether the window pixel at `offset` into a CHIP-8 pixel `size` window pixels across is
+    // part of it, along one axis.
+    fn covers(&self, offset: usize, size: usize) -> bool {
+        match self {
+            PixelStyle::Solid => true,
+            PixelStyle::Grid => size < 4 || offset < size - 1,
+            PixelStyle::Dot => (size / 4..size - size / 4).contains(&offset),
+        }
+    }
+}
+
+impl FromStr for PixelStyle {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "solid" => Ok(PixelStyle::Solid),
+            "grid" => Ok(PixelStyle::Grid),
+            "dot" => Ok(PixelStyle::Dot),
+            _ => Err(format!("unknown pixel style `{}`", value)),
+        }
+    }
+}
+
+// Scales the display contents onto a window-sized frame buffer using nearest-neighbour sampling.
+pub fn draw_frame(
+    display: &Display,
+    frame: &mut [u32],
+    width: usize,
+    height: usize,
+    style: PixelStyle,
+) {
+    draw_viewport(display, frame, width, 0, width, height, style);
+}
+
+// Draws two displays next to each other, each scaled onto its half of the frame, with a divider
+// between them.
+pub fn draw_side_by_side(
+    left: &Display,
+    right: &Display,
+    frame: &mut [u32],
+    width: usize,
+    height: usize,
+    style: PixelStyle,
+) {
+    let [(_, half), (divider_end, right_width)] = halves(width);
+    draw_viewport(left, frame, width, 0, half, height, style);
+    draw_viewport(right, frame, width, divider_end, right_width, height, style);
+    for row in frame.chunks_mut(width) {
+        row[half..divider_end].fill(DIVIDER_COLOR);
+    }
+}
+
+// The columns of a side-by-side frame `width` pixels across that each display is drawn in, as
+// (left, width). The divider is between them.
+fn halves(width: usize) -> [(usize, usize); 2] {
+    let half = width.saturating_sub(DIVIDER_WIDTH) / 2;
+    let divider_end = (half + DIVIDER_WIDTH).min(width);
+    [(0, half), (divider_end, width - divider_end)]
+}
+
+// The CHIP-8 row or column shown at window row or column `position`, when `display` of them are
+// spread over `window`.
+fn display_position(position: usize, window: usize, display: usize) -> usize {
+    position * display / window
+}
+
+// What's under window pixel (x, y) of a frame `width` by `height` pixels drawn by draw_frame, or
+// by draw_side_by_side when there are two display sizes: which display, 0 for the left one, and
+// the pixel of it. None over the divider and past the frame, such as over the keypad overlay
+// below it.
+pub fn pixel_under(
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    displays: &[(usize, usize)],
+) -> Option<(usize, usize, usize)> {
+    if y >= height {
+        return None;
+    }
+    let viewports = match displays.len() {
+        1 => vec![(0, width)],
+        _ => halves(width).to_vec(),
+    };
+    viewports.iter().zip(displays).enumerate().find_map(
+        |(index, (&(left, viewport), &(display_width, display_height)))| {
+            let x = x.checked_sub(left).filter(|&x| x < viewport)?;
+            Some((
+                index,
+                display_position(x, viewport, display_width),
+                display_position(y, height, display_height),
+            ))
+        },
+    )
+}
+
+// Scales the display onto the `width` columns of the frame starting at `left`. The parts of a
+// pixel its style leaves out show the background color.
+fn draw_viewport(
+    display: &Display,
+    frame: &mut [u32],
+    frame_width: usize,
+    left: usize,
+    width: usize,
+    height: usize,
+    style: PixelStyle,
+) {
+    let background = display.palette()[0];
+    // The first window row or column of CHIP-8 row or column `index`, when `display` of them
+    // are spread over `window`.
+    let start = |index: usize, display: usize, window: usize| (index * window).div_ceil(display);
+    for y in 0..height {
+        let display_y = display_position(y, height, display.height);
+        let top = start(display_y, display.height, height);
+        let cell_height = start(display_y + 1, display.height, height) - top;
+        let row_covered = style.covers(y - top, cell_height);
+        for x in 0..width {
+            let display_x = display_position(x, width, display.width);
+            let cell_left = start(display_x, display.width, width);
+            let cell_width = start(display_x + 1, display.width, width) - cell_left;
+            frame[y * frame_width + left + x] =
+                if row_covered && style.covers(x - cell_left, cell_width) {
+                    display.color_at(display_x, display_y)
+                } else {
+                    background
+                };
+        }
+    }
+}
+
+// Everything a painted frame depends on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameKey {
+    pub display_generation: u64,
+    // The second display's generation, while two machines are compared side by side.
+    pub compared_generation: Option<u64>,
+    pub width: usize,
+    pub height: usize,
+    pub bell_shown: bool,
+    pub pixel_style: PixelStyle,
+    // Number of sprite outlines shown.
+    pub outlines: usize,
+    // The on-screen keypad's pressed key, if the keypad is shown.
+    pub keypad: Option<Option<u8>>,
+    // The performance HUD's numbers, if it's shown.
+    pub hud: Option<Stats>,
+    pub toasts: ToastKey,
+}
+
+// Remembers what was last painted, and the painted pixels. Redraws are only requested when the
+// key changed, and the window system's own redraws of the same state (expose events, resizes back)
+// present the kept pixels instead of painting them again.
+#[derive(Debug, Default)]
+pub struct FrameCache {
+    last_painted: Option<FrameKey>,
+    pixels: Vec<u32>,
+    paints: u64,
+    presented: u64,
+    skipped: u64,
+}
+
+impl FrameCache {
+    pub fn new() -> Self {
+        FrameCache::default()
+    }
+
+    // Call once per emulated frame: returns whether a redraw is needed to show `key`.
+    pub fn wants_redraw(&mut self, key: FrameKey) -> bool {
+        if self.last_painted == Some(key) {
+            self.skipped += 1;
+            return false;
+        }
+        true
+    }
+
+    // Call on every redraw: returns the pixels to present, painted by `paint` first unless they
+    // already show `key`.
+    pub fn frame(&mut self, key: FrameKey, paint: impl FnOnce(&mut [u32])) -> &[u32] {
+        if self.last_painted != Some(key) {
+            self.pixels.resize(key.width * key.height, PIXEL_OFF);
+            paint(&mut self.pixels);
+            self.last_painted = Some(key);
+            self.paints += 1;
+        }
+        self.presented += 1;
+        &self.pixels
+    }
+
+    // Frames that didn't need a redraw.
+    pub fn skipped(&self) -> u64 {
+        self.skipped
+    }
+
+    pub fn report(&self) -> String {
+        format!(
+            "frames presented: {} ({} painted), frames skipped: {}",
+            self.presented, self.paints, self.skipped
+        )
+    }
+
+    // Forces the next frame to be painted, e.g. when the window contents may have been lost.
+    pub fn invalidate(&mut self) {
+        self.last_painted = None;
+    }
+}
+
+// Outlines where each recorded sprite was drawn, in red with a tinted background if it collided.
+// Like the visual bell, this only touches the window frame buffer. Sprites are always 8 pixels
+// wide; parts that wrapped around an edge aren't outlined.
+pub fn outline_draws(
+    frame: &mut [u32],
+    width: usize,
+    height: usize,
+    display: &Display,
+    records: &[DrawRecord],
+) {
+    for record in records {
+        let to_frame_x = |x: usize| x.min(display.width) * width / display.width;
+        let to_frame_y = |y: usize| y.min(display.height) * height / display.height;
+        let (left, right) = (to_frame_x(record.x), to_frame_x(record.x + 8));
+        let (top, bottom) = (
+            to_frame_y(record.y),
+            to_frame_y(record.y + record.height as usize),
+        );
+        let color = if record.collided {
+            COLLISION_OUTLINE_COLOR
+        } else {
+            OUTLINE_COLOR
+        };
+        for y in top..bottom {
+            for x in left..right {
+                let pixel = &mut frame[y * width + x];
+                if x == left || x == right - 1 || y == top || y == bottom - 1 {
+                    *pixel = color;
+                } else if record.collided && *pixel == PIXEL_OFF {
+                    *pixel = COLLISION_TINT;
+                }
+            }
+        }
+    }
+}
+
+// Composites the visual bell on top of an already drawn frame. This only touches the window frame
+// buffer, never the emulated display, so collision detection is unaffected.
+pub fn apply_visual_bell(
+    frame: &mut [u32],
+    width: usize,
+    height: usize,
+    visual_bell: VisualBell,
+    sound_active: bool,
+) {
+    if !sound_active {
+        return;
+    }
+    match visual_bell {
+        VisualBell::Border => {
+            for y in 0..height {
+                for x in 0..width {
+                    let on_border = x < BELL_BORDER_SIZE
+                        || y < BELL_BORDER_SIZE
+                        || x >= width.saturating_sub(BELL_BORDER_SIZE)
+                        || y >= height.saturating_sub(BELL_BORDER_SIZE);
+                    if on_border {
+                        frame[y * width + x] = BELL_BORDER_COLOR;
+                    }
+                }
+            }
+        }
+        VisualBell::Invert => {
+            for pixel in frame.iter_mut() {
+                *pixel ^= PIXEL_ON;
+            }
+        }
+        VisualBell::Off => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_draw_frame_blank_display() {
+        let display = Display::new();
+        let (width, height) = (display.width, display.height);
+        let mut frame = vec![PIXEL_ON; width * height];
+
+        draw_frame(&display, &mut frame, width, height, PixelStyle::Solid);
+
+        assert!(frame.iter().all(|&pixel| pixel == PIXEL_OFF));
+    }
+
+    #[test]
+    fn test_draw_frame_scales_pixels() {
+        let mut display = Display::new();
+        display.draw_sprite(0, 0, &vec![0b1000_0000]).unwrap();
+        let (width, height) = (display.width * 2, display.height * 2);
+        let mut frame = vec![PIXEL_OFF; width * height];
+
+        draw_frame(&display, &mut frame, width, height, PixelStyle::Solid);
+
+        assert_eq!(frame[0], PIXEL_ON);
+        assert_eq!(frame[1], PIXEL_ON);
+        assert_eq!(frame[width], PIXEL_ON);
+        assert_eq!(frame[width + 1], PIXEL_ON);
+        assert_eq!(frame[2], PIXEL_OFF);
+        assert_eq!(frame[2 * width], PIXEL_OFF);
+    }
+
+    #[test]
+    fn test_draw_side_by_side() {
+        let mut left = Display::with_mode(64, 32).unwrap();
+        let right = Display::with_mode(64, 32).unwrap();
+        left.draw_sprite(0, 0, &vec![0b1000_0000]).unwrap();
+        let (width, height) = (130, 32);
+        let mut frame = vec![PIXEL_ON; width * height];
+
+        draw_side_by_side(&left, &right, &mut frame, width, height, PixelStyle::Solid);
+
+        assert_eq!(frame[0], PIXEL_ON);
+        assert_eq!(frame[1], PIXEL_OFF);
+        assert_eq!(&frame[64..66], &[DIVIDER_COLOR, DIVIDER_COLOR]);
+        assert_eq!(frame[(height - 1) * width + 65], DIVIDER_COLOR);
+        assert!(frame[66..width].iter().all(|&pixel| pixel == PIXEL_OFF));
+    }
+
+    // A display with a pattern that's different in every row and column.
+    fn patterned(width: usize, height: usize) -> Display {
+        let mut display = Display::with_mode(width, height).unwrap();
+        for y in 0..height {
+            for x in (0..width).step_by(8) {
+                let row = (x * 7 + y * 13) as u8 | 0b1000_0001;
+                display.draw_sprite(y, x, &vec![row]).unwrap();
+            }
+        }
+        display
+    }
+
+    #[test]
+    fn test_pixel_under_matches_draw_frame() {
+        // Whole and fractional scales, in both resolutions and the VIP's 64x64.
+        for (display_width, display_height, width, height) in [
+            (64, 32, 64, 32),
+            (64, 32, 640, 320),
+            (128, 64, 1024, 512),
+            (64, 32, 100, 77),
+            (128, 64, 300, 130),
+            (64, 64, 512, 300),
+        ] {
+            let display = patterned(display_width, display_height);
+            let mut frame = vec![0; width * height];
+            draw_frame(&display, &mut frame, width, height, PixelStyle::Solid);
+
+            for y in 0..height {
+                for x in 0..width {
+                    let (index, pixel_x, pixel_y) =
+                        pixel_under(x, y, width, height, &[(display_width, display_height)])
+                            .unwrap();
+                    assert_eq!(index, 0);
+                    assert_eq!(
+                        frame[y * width + x],
+                        display.color_at(pixel_x, pixel_y),
+                        "({}, {}) in {}x{}",
+                        x,
+                        y,
+                        width,
+                        height
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_pixel_under_outside_the_displays() {
+        let sizes = [(64, 32), (128, 64)];
+
+        // Left of the divider, on it, and right of it.
+        assert_eq!(pixel_under(63, 0, 130, 32, &sizes), Some((0, 63, 0)));
+        assert_eq!(pixel_under(64, 5, 130, 32, &sizes), None);
+        assert_eq!(pixel_under(65, 5, 130, 32, &sizes), None);
+        assert_eq!(pixel_under(66, 31, 130, 32, &sizes), Some((1, 0, 62)));
+        assert_eq!(pixel_under(129, 31, 130, 32, &sizes), Some((1, 126, 62)));
+        // Past the frame, such as over the keypad below it.
+        assert_eq!(pixel_under(10, 32, 130, 32, &sizes), None);
+        assert_eq!(pixel_under(130, 0, 130, 32, &sizes), None);
+        assert_eq!(pixel_under(640, 0, 640, 160, &sizes[..1]), None);
+        assert_eq!(
+            pixel_under(639, 159, 640, 160, &sizes[..1]),
+            Some((0, 63, 31))
+        );
+    }
+
+    // The window pixels a single lit pixel covers at 4 window pixels per CHIP-8 pixel.
+    fn lit_at_scale_4(style: PixelStyle) -> Vec<(usize, usize)> {
+        let mut display = Display::with_mode(8, 2).unwrap();
+        display.draw_sprite(0, 1, &vec![0b1000_0000]).unwrap();
+        let (width, height) = (32, 8);
+        let mut frame = vec![PIXEL_ON; width * height];
+
+        draw_frame(&display, &mut frame, width, height, style);
+
+        (0..width * height)
+            .filter(|&index| frame[index] == PIXEL_ON)
+            .map(|index| (index % width, index / width))
+            .collect()
+    }
+
+    #[test]
+    fn test_pixel_styles_at_scale_4() {
+        let square = |from: usize, to: usize| -> Vec<(usize, usize)> {
+            (from..to)
+                .flat_map(|y| (4 + from..4 + to).map(move |x| (x, y)))
+                .collect()
+        };
+
+        assert_eq!(lit_at_scale_4(PixelStyle::Solid), square(0, 4));
+        assert_eq!(lit_at_scale_4(PixelStyle::Grid), square(0, 3));
+        assert_eq!(lit_at_scale_4(PixelStyle::Dot), square(1, 3));
+    }
+
+    #[test]
+    fn test_grid_needs_scale_4() {
+        let mut display = Display::with_mode(8, 1).unwrap();
+        display.draw_sprite(0, 0, &vec![0b1000_0000]).unwrap();
+        let mut frame = vec![PIXEL_OFF; 24 * 3];
+
+        draw_frame(&display, &mut frame, 24, 3, PixelStyle::Grid);
+
+        assert!([0, 1, 2, 24, 50]
+            .iter()
+            .all(|&index| frame[index] == PIXEL_ON));
+        assert_eq!(frame[3], PIXEL_OFF);
+        assert_eq!("dot".parse(), Ok(PixelStyle::Dot));
+        assert_eq!(PixelStyle::Dot.next(), PixelStyle::Solid);
+    }
+
+    fn frame_key(display_generation: u64) -> FrameKey {
+        FrameKey {
+            display_generation,
+            compared_generation: None,
+            width: 128,
+            height: 64,
+            bell_shown: false,
+            pixel_style: PixelStyle::Solid,
+            outlines: 0,
+            keypad: None,
+            hud: None,
+            toasts: ToastKey::default(),
+        }
+    }
+
+    // Presents a frame, returning whether it had to be painted.
+    fn present(cache: &mut FrameCache, key: FrameKey) -> bool {
+        let mut painted = false;
+        let pixels = cache.frame(key, |pixels| {
+            pixels.fill(key.display_generation as u32);
+            painted = true;
+        });
+        assert_eq!(pixels.len(), key.width * key.height);
+        assert!(pixels
+            .iter()
+            .all(|&pixel| pixel == key.display_generation as u32));
+        painted
+    }
+
+    #[test]
+    fn test_frame_cache_skips_unchanged_frame() {
+        let mut cache = FrameCache::new();
+
+        assert!(cache.wants_redraw(frame_key(1)));
+        assert!(present(&mut cache, frame_key(1)));
+        assert!(!cache.wants_redraw(frame_key(1)));
+        assert!(!cache.wants_redraw(frame_key(1)));
+
+        assert!(cache.wants_redraw(frame_key(2)));
+        assert!(present(&mut cache, frame_key(2)));
+        let resized = FrameKey {
+            width: 256,
+            ..frame_key(2)
+        };
+        assert!(cache.wants_redraw(resized));
+        assert!(present(&mut cache, resized));
+        let bell = FrameKey {
+            bell_shown: true,
+            ..resized
+        };
+        assert!(cache.wants_redraw(bell));
+        assert!(present(&mut cache, bell));
+
+        assert_eq!((cache.presented, cache.paints, cache.skipped), (4, 4, 2));
+    }
+
+    #[test]
+    fn test_frame_cache_window_redraws_reuse_pixels() {
+        let mut cache = FrameCache::new();
+        // Frames with the display generation they end on, and window system redraws in between.
+        let script = [
+            Some(1),
+            None,
+            Some(1),
+            Some(2),
+            None,
+            None,
+            Some(2),
+            Some(3),
+        ];
+        let mut generation = 0;
+        let mut paints = 0;
+
+        for step in script {
+            match step {
+                Some(frame_generation) => {
+                    generation = frame_generation;
+                    if cache.wants_redraw(frame_key(generation))
+                        && present(&mut cache, frame_key(generation))
+                    {
+                        paints += 1;
+                    }
+                }
+                None => assert!(!present(&mut cache, frame_key(generation))),
+            }
+        }
+
+        assert_eq!(paints, 3);
+        assert_eq!((cache.presented, cache.paints, cache.skipped), (6, 3, 2));
+    }
+
+    #[test]
+    fn test_frame_cache_invalidate() {
+        let mut cache = FrameCache::new();
+        present(&mut cache, frame_key(1));
+
+        cache.invalidate();
+
+        assert!(cache.wants_redraw(frame_key(1)));
+        assert!(present(&mut cache, frame_key(1)));
+        assert_eq!(cache.paints, 2);
+    }
+
+    #[test]
+    fn test_outline_draws() {
+        let display = Display::new();
+        let (width, height) = (display.width * 2, display.height * 2);
+        let mut frame = vec![PIXEL_OFF; width * height];
+        let record = DrawRecord {
+            x: 1,
+            y: 2,
+            height: 3,
+            i_addr: 0x300,
+            collided: false,
+            pc: 0x200,
+        };
+
+        outline_draws(
+            &mut frame,
+            width,
+            height,
+            &display,
+            &[
+                record,
+                DrawRecord {
+                    x: 20,
+                    collided: true,
+                    ..record
+                },
+            ],
+        );
+
+        assert_eq!(frame[4 * width + 2], OUTLINE_COLOR);
+        assert_eq!(frame[9 * width + 17], OUTLINE_COLOR);
+        assert_eq!(frame[5 * width + 3], PIXEL_OFF);
+        assert_eq!(frame[10 * width + 2], PIXEL_OFF);
+        assert_eq!(frame[4 * width + 40], COLLISION_OUTLINE_COLOR);
+        assert_eq!(frame[5 * width + 41], COLLISION_TINT);
+    }
+
+    #[test]
+    fn test_outline_draws_clips_at_the_edges() {
+        let display = Display::new();
+        let (width, height) = (display.width, display.height);
+        let mut frame = vec![PIXEL_OFF; width * height];
+        let record = DrawRecord {
+            x: width - 4,
+            y: height - 2,
+            height: 15,
+            i_addr: 0x300,
+            collided: false,
+            pc: 0x200,
+        };
+
+        outline_draws(&mut frame, width, height, &display, &[record]);
+
+        assert_eq!(frame[(height - 2) * width + width - 4], OUTLINE_COLOR);
+        assert_eq!(frame[height * width - 1], OUTLINE_COLOR);
+        assert_eq!(frame[0], PIXEL_OFF);
+    }
+
+    #[test]
+    fn test_apply_visual_bell_border() {
+        let (width, height) = (16, 16);
+        let mut frame = vec![PIXEL_OFF; width * height];
+
+        apply_visual_bell(&mut frame, width, height, VisualBell::Border, true);
+
+        assert_eq!(frame[0], BELL_BORDER_COLOR);
+        assert_eq!(frame[BELL_BORDER_SIZE - 1], BELL_BORDER_COLOR);
+        assert_eq!(frame[width * height - 1], BELL_BORDER_COLOR);
+        assert_eq!(
+            frame[BELL_BORDER_SIZE * width + BELL_BORDER_SIZE],
+            PIXEL_OFF
+        );
+        assert_eq!(frame[8 * width + 8], PIXEL_OFF);
+    }
+
+    #[test]
+    fn test_apply_visual_bell_inactive_sound() {
+        let (width, height) = (16, 16);
+        let mut frame = vec![PIXEL_OFF; width * height];
+
+        apply_visual_bell(&mut frame, width, height, VisualBell::Border, false);
+
+        assert!(frame.iter().all(|&pixel| pixel == PIXEL_OFF));
+    }
+
+    #[test]
+    fn test_apply_visual_bell_invert() {
+        let mut frame = vec![PIXEL_OFF, PIXEL_ON];
+
+        apply_visual_bell(&mut frame, 2, 1, VisualBell::Invert, true);
+
+        assert_eq!(frame, vec![PIXEL_ON, PIXEL_OFF]);
+    }
+
+    #[test]
+    fn test_visual_bell_from_str() {
+        assert_eq!("border".parse(), Ok(VisualBell::Border));
+        assert_eq!("invert".parse(), Ok(VisualBell::Invert));
+        assert_eq!("off".parse(), Ok(VisualBell::Off));
+        assert!("loud".parse::<VisualBell>().is_err());
+    }
+}
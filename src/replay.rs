@@ -0,0 +1,560 @@
+use crate::chip8::{
+    check_memory_size, input, BootConfig, Chip8, Chip8Error, Fnv1a, Options, Quirks, TIMER_HZ,
+};
+use std::hash::Hasher;
+use std::path::Path;
+use std::time::Duration;
+
+// Replays step the machine one 60 Hz frame at a time, so a recording made at this pace plays back
+// exactly.
+pub const FRAME_DURATION: Duration = Duration::from_micros(1_000_000 / TIMER_HZ);
+pub const LOAD_ADDRESS: u16 = 0x200;
+// ROM bytes per `rom` line of a bundle.
+const ROM_LINE_BYTES: usize = 32;
+
+type QuirkFlag = fn(&mut Quirks) -> &mut bool;
+
+// Boolean quirks as they're named in replay files.
+const QUIRK_FLAGS: [(&str, QuirkFlag); 10] = [
+    ("addi-sets-vf", |quirks| &mut quirks.addi_sets_vf),
+    ("shift-uses-vy", |quirks| &mut quirks.shift_uses_vy),
+    ("jump-uses-vx", |quirks| &mut quirks.jump_uses_vx),
+    ("logic-resets-vf", |quirks| &mut quirks.logic_resets_vf),
+    ("display-wait", |quirks| &mut quirks.display_wait),
+    ("clip-sprites", |quirks| &mut quirks.clip_sprites),
+    ("sprite-reads-wrap", |quirks| &mut quirks.sprite_reads_wrap),
+    ("vf-before-result", |quirks| &mut quirks.vf_before_result),
+    ("wait-for-key-on-release", |quirks| {
+        &mut quirks.wait_for_key_on_release
+    }),
+    ("clear-on-resolution-switch", |quirks| {
+        &mut quirks.clear_on_resolution_switch
+    }),
+];
+
+// Identifies a ROM in a replay file.
+pub fn rom_hash(rom: &[u8]) -> u64 {
+    let mut hasher = Fnv1a::default();
+    hasher.write(rom);
+    hasher.finish()
+}
+
+// Everything needed to reproduce a run: the machine it ran on and the keypad state over time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    pub rom_hash: u64,
+    pub quirks: Quirks,
+    pub sticky_keys: bool,
+    pub rng_seed: u64,
+    pub load_address: u16,
+    pub ops_per_second: u64,
+    // Number of frames the run lasted.
+    pub frames: u64,
+    // (frame, key) for every frame at whose start the held key changed, in frame order. None
+    // means the key was released.
+    pub inputs: Vec<(u64, Option<u8>)>,
+}
+
+impl Replay {
+    // The options to replay with, keeping the strict options of `base`.
+    pub fn options(&self, base: Options) -> Options {
+        Options {
+            quirks: self.quirks,
+            sticky_keys: self.sticky_keys,
+            ops_per_second: self.ops_per_second,
+            rng_seed: Some(self.rng_seed),
+            ..base
+        }
+    }
+
+    // Refuses to replay against a different ROM than the one recorded, since the inputs would
+    // mean nothing there.
+    pub fn check_rom(&self, rom: &[u8]) -> Result<(), String> {
+        if self.load_address != LOAD_ADDRESS {
+            return Err(format!(
+                "replay loads the ROM at {:#05X}, only {:#05X} is supported",
+                self.load_address, LOAD_ADDRESS
+            ));
+        }
+        let hash = rom_hash(rom);
+        if hash != self.rom_hash {
+            return Err(format!(
+                "ROM hash {:#018X} doesn't match the replay's {:#018X}",
+                hash, self.rom_hash
+            ));
+        }
+        Ok(())
+    }
+
+    // Runs all the recorded frames on a machine built with `options` that has the ROM loaded.
+    // Stopping to wait for a key doesn't end playback, since the key may come in a later frame.
+    pub fn play(&self, system: &mut Chip8) -> Result<(), Chip8Error> {
+        let mut inputs = self.inputs.iter().peekable();
+        for frame in 0..self.frames {
+            while let Some((_, key)) = inputs.next_if(|(at, _)| *at == frame) {
+                match key {
+                    Some(key) => system.press_key(*key)?,
+                    None => system.release_key(),
+                }
+            }
+            system.run_for(FRAME_DURATION)?;
+        }
+        Ok(())
+    }
+
+    // Parses the `key = value` format written by to_text.
+    pub fn parse(contents: &str) -> Result<Replay, String> {
+        let mut rom_hash = None;
+        let mut rng_seed = None;
+        let mut load_address = None;
+        let mut ops_per_second = None;
+        let mut frames = None;
+        let mut quirks = Quirks::default();
+        let mut sticky_keys = false;
+        let mut inputs = Vec::new();
+
+        for (line_idx, line) in contents.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = |message: String| format!("line {}: {}", line_idx + 1, message);
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| error("expected `key = value`".to_string()))?;
+            let (key, value) = (key.trim(), value.trim());
+
+            match key {
+                "rom-hash" => rom_hash = Some(parse_hex(value).map_err(error)?),
+                "rng-seed" => rng_seed = Some(parse_number(value).map_err(error)?),
+                "load-address" => {
+                    let address = parse_hex(value).map_err(error)?;
+                    let address = u16::try_from(address)
+                        .map_err(|_| error(format!("`{}` is not an address", value)))?;
+                    load_address = Some(address);
+                }
+                "speed" => ops_per_second = Some(parse_number(value).map_err(error)?),
+                "frames" => frames = Some(parse_number(value).map_err(error)?),
+                "input" => {
+                    let (frame, key) = value
+                        .split_once(' ')
+                        .ok_or_else(|| error("expected `input = <frame> <key>`".to_string()))?;
+                    let frame = parse_number(frame).map_err(error)?;
+                    if inputs.last().is_some_and(|&(last, _)| frame < last) {
+                        return Err(error("inputs are out of order".to_string()));
+                    }
+                    inputs.push((frame, parse_key(key.trim()).map_err(error)?));
+                }
+                key => {
+                    if !set_machine_setting(&mut quirks, &mut sticky_keys, key, value)
+                        .map_err(error)?
+                    {
+                        return Err(error(format!("unknown key `{}`", key)));
+                    }
+                }
+            }
+        }
+
+        let missing = |key: &str| format!("missing `{}`", key);
+        Ok(Replay {
+            rom_hash: rom_hash.ok_or_else(|| missing("rom-hash"))?,
+            quirks,
+            sticky_keys,
+            rng_seed: rng_seed.ok_or_else(|| missing("rng-seed"))?,
+            load_address: load_address.ok_or_else(|| missing("load-address"))?,
+            ops_per_second: ops_per_second.ok_or_else(|| missing("speed"))?,
+            frames: frames.ok_or_else(|| missing("frames"))?,
+            inputs,
+        })
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut lines = vec![
+            "# ch8emu replay".to_string(),
+            format!("rom-hash = {:#018X}", self.rom_hash),
+            format!("load-address = {:#05X}", self.load_address),
+            format!("speed = {}", self.ops_per_second),
+            format!("rng-seed = {}", self.rng_seed),
+        ];
+        for (key, value) in quirk_settings(self.quirks) {
+            lines.push(format!("{} = {}", key, value));
+        }
+        lines.push(format!("sticky-keys = {}", self.sticky_keys));
+        lines.push(format!("frames = {}", self.frames));
+        for (frame, key) in &self.inputs {
+            match key {
+                Some(key) => lines.push(format!("input = {} {:X}", frame, key)),
+                None => lines.push(format!("input = {} -", frame)),
+            }
+        }
+        lines.push(String::new());
+        lines.join("\n")
+    }
+}
+
+// Sets the quirk or sticky keys setting named `key`, as replay files and input scripts name
+// them. Returns false if `key` isn't one.
+pub(crate) fn set_machine_setting(
+    quirks: &mut Quirks,
+    sticky_keys: &mut bool,
+    key: &str,
+    value: &str,
+) -> Result<bool, String> {
+    let parse_flag = |value: &str| {
+        value
+            .parse()
+            .map_err(|_| format!("`{}` is not true or false", value))
+    };
+    match key {
+        "load-store-i" => quirks.load_store_i = value.parse()?,
+        "memory-size" => {
+            // Checked here so that a bundle from a bug report can't ask for memory no machine
+            // could have.
+            let size = usize::try_from(parse_number(value)?).unwrap_or(usize::MAX);
+            check_memory_size(size).map_err(|err| err.to_string())?;
+            quirks.memory_size = size;
+        }
+        "sticky-keys" => *sticky_keys = parse_flag(value)?,
+        key if key.starts_with("quirk.") => {
+            let name = &key["quirk.".len()..];
+            let (_, flag) = QUIRK_FLAGS
+                .iter()
+                .find(|(flag_name, _)| *flag_name == name)
+                .ok_or_else(|| format!("unknown quirk `{}`", name))?;
+            *flag(quirks) = parse_flag(value)?;
+        }
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+// Every quirk setting set_machine_setting takes, as (key, value).
+pub(crate) fn quirk_settings(quirks: Quirks) -> Vec<(String, String)> {
+    let mut settings = vec![
+        ("memory-size".to_string(), quirks.memory_size.to_string()),
+        (
+            "load-store-i".to_string(),
+            quirks.load_store_i.name().to_string(),
+        ),
+    ];
+    let mut quirks = quirks;
+    for (name, flag) in QUIRK_FLAGS {
+        settings.push((format!("quirk.{}", name), flag(&mut quirks).to_string()));
+    }
+    settings
+}
+
+fn parse_number(value: &str) -> Result<u64, String> {
+    value
+        .parse()
+        .map_err(|_| format!("`{}` is not a number", value))
+}
+
+fn parse_hex(value: &str) -> Result<u64, String> {
+    value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .and_then(|digits| u64::from_str_radix(digits, 16).ok())
+        .ok_or_else(|| format!("`{}` is not a hex number", value))
+}
+
+// `-` is a release, anything else a keypad key.
+fn parse_key(value: &str) -> Result<Option<u8>, String> {
+    if value == "-" {
+        return Ok(None);
+    }
+    input::parse_key(value)
+        .map(Some)
+        .map_err(|_| format!("`{}` is not a keypad key", value))
+}
+
+// A replay together with the ROM it was recorded on, so that a run can be reproduced from a
+// single file, say one attached to a bug report.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bundle {
+    pub rom: Vec<u8>,
+    pub replay: Replay,
+}
+
+impl Bundle {
+    pub fn new(rom: &[u8], replay: Replay) -> Result<Bundle, String> {
+        replay.check_rom(rom)?;
+        Ok(Bundle {
+            rom: rom.to_vec(),
+            replay,
+        })
+    }
+
+    // Parses the replay format with the ROM added in `rom = <hex bytes>` lines, as written by
+    // to_text.
+    pub fn parse(contents: &str) -> Result<Bundle, String> {
+        let mut rom = Vec::new();
+        // The other lines go to Replay::parse; ROM lines are blanked so line numbers still match.
+        let mut replay_lines = Vec::new();
+        for (line_idx, line) in contents.lines().enumerate() {
+            let rom_bytes = line
+                .split_once('=')
+                .filter(|(key, _)| key.trim() == "rom")
+                .map(|(_, value)| value);
+            let Some(value) = rom_bytes else {
+                replay_lines.push(line);
+                continue;
+            };
+            for byte in value.split_whitespace() {
+                let byte = u8::from_str_radix(byte, 16)
+                    .ok()
+                    .filter(|_| byte.len() == 2)
+                    .ok_or_else(|| {
+                        format!("line {}: `{}` is not a hex byte", line_idx + 1, byte)
+                    })?;
+                rom.push(byte);
+            }
+            replay_lines.push("");
+        }
+        if rom.is_empty() {
+            return Err("missing `rom`".to_string());
+        }
+        Bundle::new(&rom, Replay::parse(&replay_lines.join("\n"))?)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = self.replay.to_text();
+        for chunk in self.rom.chunks(ROM_LINE_BYTES) {
+            let bytes: Vec<String> = chunk.iter().map(|byte| format!("{:02X}", byte)).collect();
+            text.push_str(&format!("rom = {}\n", bytes.join(" ")));
+        }
+        text
+    }
+
+    pub fn load(path: &Path) -> Result<Bundle, String> {
+        let contents = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+        Bundle::parse(&contents)
+    }
+
+    // A machine set up the way the replay was recorded, with the ROM loaded and nothing run yet.
+    // The strict options of `base` are kept.
+    pub fn machine(&self, base: Options) -> Result<Chip8, Chip8Error> {
+        let mut system = Chip8::with_options(self.replay.options(base))?;
+        system.boot(BootConfig::default())?;
+        system.load_rom(&self.rom)?;
+        Ok(system)
+    }
+}
+
+// Loads a bundle written by Bundle::to_text and plays it through, reproducing the recorded run
+// from that one file.
+pub fn replay_bundle(path: &Path) -> Result<Chip8, String> {
+    let bundle = Bundle::load(path)?;
+    let mut system = bundle
+        .machine(Options::default())
+        .map_err(|err| format!("Failed to set up the machine: {:?}", err))?;
+    bundle
+        .replay
+        .play(&mut system)
+        .map_err(|err| format!("Emulation stopped: {:?}", err))?;
+    Ok(system)
+}
+
+// Builds a replay from a run stepped one frame at a time. The machine must be built with
+// `options()`, which carries the seed.
+#[derive(Debug)]
+pub struct Recorder {
+    replay: Replay,
+    options: Options,
+    key: Option<u8>,
+    rom: Vec<u8>,
+}
+
+impl Recorder {
+    // Uses the seed in `options`, or picks one if it's unset.
+    pub fn new(rom: &[u8], options: Options) -> Recorder {
+        let rng_seed = options.rng_seed.unwrap_or_else(rand::random);
+        Recorder {
+            replay: Replay {
+                rom_hash: rom_hash(rom),
+                quirks: options.quirks,
+                sticky_keys: options.sticky_keys,
+                rng_seed,
+                load_address: LOAD_ADDRESS,
+                ops_per_second: options.ops_per_second,
+                frames: 0,
+                inputs: Vec::new(),
+            },
+            options: Options {
+                rng_seed: Some(rng_seed),
+                ..options
+            },
+            key: None,
+            rom: rom.to_vec(),
+        }
+    }
+
+    pub fn options(&self) -> Options {
+        self.options
+    }
+
+    // Call once before running each frame for FRAME_DURATION, with the key held for it.
+    pub fn record_frame(&mut self, key: Option<u8>) {
+        if self.key != key {
+            self.key = key;
+            self.replay.inputs.push((self.replay.frames, key));
+        }
+        self.replay.frames += 1;
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    pub fn bundle(&self) -> Bundle {
+        Bundle {
+            rom: self.rom.clone(),
+            replay: self.replay.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::{Quirks, Target};
+
+    fn sample() -> Replay {
+        Replay {
+            rom_hash: 0x0123_4567_89AB_CDEF,
+            quirks: Quirks::preset(Target::Chip8),
+            sticky_keys: true,
+            rng_seed: 7,
+            load_address: LOAD_ADDRESS,
+            ops_per_second: 700,
+            frames: 90,
+            inputs: vec![(3, Some(0x5)), (10, None), (10, Some(0xA))],
+        }
+    }
+
+    #[test]
+    fn test_to_text_round_trips() {
+        let replay = sample();
+
+        assert_eq!(Replay::parse(&replay.to_text()), Ok(replay));
+    }
+
+    #[test]
+    fn test_parse_missing_field() {
+        let text = sample().to_text().replace("frames = 90\n", "");
+
+        assert_eq!(Replay::parse(&text), Err("missing `frames`".to_string()));
+    }
+
+    #[test]
+    fn test_parse_invalid_lines() {
+        assert_eq!(
+            Replay::parse("quirk.wrap-sprites = true"),
+            Err("line 1: unknown quirk `wrap-sprites`".to_string())
+        );
+        assert_eq!(
+            Replay::parse("input = 4 G"),
+            Err("line 1: `G` is not a keypad key".to_string())
+        );
+        assert_eq!(
+            Replay::parse("input = 4 5\ninput = 2 -"),
+            Err("line 2: inputs are out of order".to_string())
+        );
+        assert_eq!(
+            Replay::parse("rom-hash = 1234"),
+            Err("line 1: `1234` is not a hex number".to_string())
+        );
+        assert_eq!(
+            Replay::parse("memory-size = 70000"),
+            Err("line 1: a memory size of 70000 bytes is outside 514 to 65536".to_string())
+        );
+        assert_eq!(
+            Replay::parse("memory-size = 256"),
+            Err("line 1: a memory size of 256 bytes is outside 514 to 65536".to_string())
+        );
+    }
+
+    #[test]
+    fn test_check_rom() {
+        let rom = [0x12, 0x00];
+        let replay = Replay {
+            rom_hash: rom_hash(&rom),
+            ..sample()
+        };
+
+        assert_eq!(replay.check_rom(&rom), Ok(()));
+        assert!(replay.check_rom(&[0x12, 0x02]).is_err());
+        let moved = Replay {
+            load_address: 0x600,
+            ..replay
+        };
+        assert_eq!(
+            moved.check_rom(&rom),
+            Err("replay loads the ROM at 0x600, only 0x200 is supported".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_without_sticky_keys() {
+        let text = sample().to_text().replace("sticky-keys = true\n", "");
+
+        assert!(!Replay::parse(&text).unwrap().sticky_keys);
+    }
+
+    #[test]
+    fn test_bundle_to_text_round_trips() {
+        let rom: Vec<u8> = (0..=40).collect();
+        let bundle = Bundle::new(
+            &rom,
+            Replay {
+                rom_hash: rom_hash(&rom),
+                ..sample()
+            },
+        )
+        .unwrap();
+
+        let text = bundle.to_text();
+
+        assert_eq!(
+            text.lines()
+                .filter(|line| line.starts_with("rom ="))
+                .count(),
+            2
+        );
+        assert_eq!(Bundle::parse(&text), Ok(bundle));
+    }
+
+    #[test]
+    fn test_bundle_parse_errors() {
+        let rom = [0x12, 0x00];
+        let replay = Replay {
+            rom_hash: rom_hash(&rom),
+            ..sample()
+        };
+        let text = replay.to_text();
+
+        assert_eq!(Bundle::parse(&text), Err("missing `rom`".to_string()));
+        assert_eq!(
+            Bundle::parse(&format!("rom = 12 0\n{}", text)),
+            Err("line 1: `0` is not a hex byte".to_string())
+        );
+        assert!(Bundle::parse(&format!("{}rom = 12 02\n", text)).is_err());
+        assert!(Bundle::new(&[0x12, 0x02], replay).is_err());
+    }
+
+    #[test]
+    fn test_recorder_logs_key_changes() {
+        let mut recorder = Recorder::new(&[0x12, 0x00], Options::default());
+        for key in [None, Some(0x5), Some(0x5), None, Some(0xA), Some(0xB)] {
+            recorder.record_frame(key);
+        }
+
+        let replay = recorder.replay();
+        assert_eq!(replay.frames, 6);
+        assert_eq!(
+            replay.inputs,
+            vec![(1, Some(0x5)), (3, None), (4, Some(0xA)), (5, Some(0xB))]
+        );
+        assert_eq!(recorder.options().rng_seed, Some(replay.rng_seed));
+    }
+}
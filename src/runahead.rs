@@ -0,0 +1,237 @@
+use ch8emu::chip8::display::Display;
+use ch8emu::chip8::Chip8;
+use std::time::{Duration, Instant};
+
+// Frames in a row that can go over budget before run-ahead turns itself off, so that one slow
+// frame doesn't.
+const SLOW_FRAMES_LIMIT: u32 = 30;
+
+// Single-frame run-ahead, to show the effect of a key press a frame sooner. After each real frame
+// the machine is snapshotted, run for one more frame with the keys as they are now, and restored;
+// the window shows that frame from the future. The next real frame runs from the restored state
+// with whatever keys are held by then, so a guess that turned out wrong is simply never seen
+// again. The speculative frame runs silenced, so nothing it does is heard, logged or counted: a
+// warning it runs into is reported when the real frame gets there.
+#[derive(Debug)]
+pub struct RunAhead {
+    frame: Duration,
+    slow_frames: u32,
+    enabled: bool,
+    // The display a frame ahead, with its generation.
+    ahead: Option<(u64, Display)>,
+}
+
+impl RunAhead {
+    pub fn new(frame: Duration) -> Self {
+        RunAhead {
+            frame,
+            slow_frames: 0,
+            enabled: true,
+            ahead: None,
+        }
+    }
+
+    // Runs `system` a frame ahead and back again, keeping the display it got to. Returns true if
+    // that took so long, for long enough, that run-ahead turned itself off.
+    pub fn speculate(&mut self, system: &mut Chip8) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        let started = Instant::now();
+        let snapshot = system.snapshot();
+        system.set_silenced(true);
+        // A frame that fails is left for the real run to fail on.
+        self.ahead = system
+            .run_for(self.frame)
+            .ok()
+            .map(|_| (system.display_generation(), system.display().clone()));
+        system.set_silenced(false);
+        system.restore(&snapshot);
+        self.record_cost(started.elapsed())
+    }
+
+    // Counts a speculative frame that cost more than a frame's time, turning run-ahead off after
+    // SLOW_FRAMES_LIMIT of them in a row. Returns whether it turned off.
+    pub fn record_cost(&mut self, cost: Duration) -> bool {
+        if cost <= self.frame {
+            self.slow_frames = 0;
+            return false;
+        }
+        self.slow_frames += 1;
+        if self.slow_frames < SLOW_FRAMES_LIMIT || !self.enabled {
+            return false;
+        }
+        self.enabled = false;
+        self.ahead = None;
+        true
+    }
+
+    // The display to show instead of the machine's, and its generation.
+    pub fn ahead(&self) -> Option<(u64, &Display)> {
+        self.ahead
+            .as_ref()
+            .map(|(generation, display)| (*generation, display))
+    }
+
+    // Forgets the frame ahead, for when the machine is replaced.
+    pub fn clear(&mut self) {
+        self.ahead = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ch8emu::chip8::{assemble_opcodes, Opcode};
+
+    const FRAME: Duration = Duration::from_micros(1_000_000 / 60);
+
+    // Draws a line of pixels, one more each frame, and moves down a row for each frame key 5 is
+    // held.
+    fn machine() -> Chip8 {
+        let rom = assemble_opcodes(&[
+            Opcode::SetIndex(0x300),
+            Opcode::LoadByte(0x2, 5),
+            Opcode::SkipIfKeyNotPressed(0x2),
+            Opcode::AddByte(0x1, 1),
+            Opcode::Draw(0x0, 0x1, 1),
+            Opcode::AddByte(0x0, 1),
+            Opcode::LoadDelayTimer(0x3),
+            Opcode::SkipIfEqual(0x3, 0),
+            Opcode::Jump(0x20C),
+            Opcode::LoadByte(0x3, 1),
+            Opcode::SetDelayTimer(0x3),
+            Opcode::Jump(0x204),
+        ]);
+        let mut system = Chip8::booted();
+        system.load_rom(&rom).unwrap();
+        system.load_bytes(0x300, &[0x80]).unwrap();
+        system
+    }
+
+    fn hold(system: &mut Chip8, key: Option<u8>) {
+        match key {
+            Some(key) => system.press_key(key).unwrap(),
+            None => system.release_key(),
+        }
+    }
+
+    // The displays shown for each frame with `keys` held, with and without run-ahead.
+    fn shown(keys: &[Option<u8>], run_ahead: bool) -> Vec<String> {
+        let mut system = machine();
+        let mut ahead = RunAhead::new(FRAME);
+        keys.iter()
+            .map(|&key| {
+                hold(&mut system, key);
+                system.run_for(FRAME).unwrap();
+                if !run_ahead {
+                    return system.display().to_string();
+                }
+                ahead.speculate(&mut system);
+                ahead.ahead().unwrap().1.to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_run_ahead_shows_the_next_frame() {
+        let keys = [
+            None,
+            None,
+            Some(0x5),
+            Some(0x5),
+            Some(0x5),
+            None,
+            None,
+            None,
+        ];
+
+        let normal = shown(&keys, false);
+        let ahead = shown(&keys[..keys.len() - 1], true);
+
+        // Each frame shown is the next one, as long as the keys stay the same.
+        for (frame, shown) in ahead.iter().enumerate() {
+            if keys[frame] == keys[frame + 1] {
+                assert_eq!(shown, &normal[frame + 1], "frame {}", frame);
+            }
+        }
+        assert_ne!(ahead[1], normal[2]);
+        assert_ne!(normal[0], normal[1]);
+    }
+
+    #[test]
+    fn test_run_ahead_discards_wrong_guesses() {
+        let keys = [None, None, Some(0x5), None, Some(0x5), Some(0x5), None];
+        let mut normal = machine();
+        let mut system = machine();
+        let mut ahead = RunAhead::new(FRAME);
+
+        for key in keys {
+            hold(&mut normal, key);
+            normal.run_for(FRAME).unwrap();
+            hold(&mut system, key);
+            system.run_for(FRAME).unwrap();
+            ahead.speculate(&mut system);
+
+            assert_eq!(system.state_hash(), normal.state_hash());
+            assert_eq!(system.cycles(), normal.cycles());
+        }
+    }
+
+    #[test]
+    fn test_run_ahead_reports_violations_once() {
+        // Waits two frames, then runs an undefined instruction and halts.
+        let mut rom = assemble_opcodes(&[
+            Opcode::LoadByte(0x0, 2),
+            Opcode::SetDelayTimer(0x0),
+            Opcode::LoadDelayTimer(0x1),
+            Opcode::SkipIfEqual(0x1, 0),
+            Opcode::Jump(0x204),
+        ]);
+        rom.extend([0xFA, 0xBC]);
+        rom.extend(assemble_opcodes(&[Opcode::Jump(0x20C)]));
+        let machine = || {
+            let mut system = Chip8::booted();
+            system.load_rom(&rom).unwrap();
+            system
+        };
+        let mut normal = machine();
+        let mut system = machine();
+        let mut ahead = RunAhead::new(FRAME);
+
+        for _ in 0..5 {
+            normal.run_for(FRAME).unwrap();
+            system.run_for(FRAME).unwrap();
+            ahead.speculate(&mut system);
+
+            assert_eq!(system.violations().entries(), normal.violations().entries());
+            assert_eq!(
+                system.recent_diagnostics().pushed(),
+                normal.recent_diagnostics().pushed()
+            );
+        }
+        assert_eq!(system.violations().entries().len(), 1);
+        assert_eq!(system.recent_diagnostics().pushed(), 1);
+    }
+
+    #[test]
+    fn test_run_ahead_turns_off_when_too_slow() {
+        let mut ahead = RunAhead::new(FRAME);
+        let slow = FRAME * 2;
+
+        for _ in 1..SLOW_FRAMES_LIMIT {
+            assert!(!ahead.record_cost(slow));
+        }
+        // A fast frame starts the count over.
+        assert!(!ahead.record_cost(FRAME / 2));
+        for _ in 1..SLOW_FRAMES_LIMIT {
+            assert!(!ahead.record_cost(slow));
+        }
+        assert!(ahead.record_cost(slow));
+        assert!(!ahead.record_cost(slow));
+
+        let mut system = machine();
+        assert!(!ahead.speculate(&mut system));
+        assert!(ahead.ahead().is_none());
+    }
+}
@@ -0,0 +1,506 @@
+use crate::chip8::{input, Options, Quirks};
+use crate::replay::{quirk_settings, rom_hash, set_machine_setting, Replay, LOAD_ADDRESS};
+
+// A day of frames at 60 Hz, which is far longer than any run but keeps a typo in a frame number
+// from asking for gigabytes.
+pub const MAX_FRAMES: u64 = 60 * 60 * 60 * 24;
+
+// Inputs written by hand for a tool-assisted run, compiled to a replay's key changes. One
+// command per line, frames counted from 0:
+//
+//     frames 600                      how long the run lasts
+//     seed 42                         the seed for CXNN
+//     120-180: hold 5                 5 is held from frame 120 to 180
+//     200: press A                    A is held from frame 200 on
+//     250: release                    no key is held from frame 250 on
+//     260: tap 4                      4 is held for frame 260 only
+//     tap 6 every 30 starting 300     6 is tapped on frames 300, 330, ... to the end
+//
+// `every` also takes `until <frame>` to stop before the end. Later lines win on the frames they
+// share with earlier ones. Without a `frames` line the run ends after the last frame a line
+// names.
+//
+// The machine's settings can be pinned with the keys of a replay file, as in `quirk.clip-sprites
+// true` or `sticky-keys false`. Quirks named that way start from the defaults rather than the
+// command line's.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Script {
+    pub frames: u64,
+    pub seed: Option<u64>,
+    // None to run with the command line's.
+    pub quirks: Option<Quirks>,
+    pub sticky_keys: Option<bool>,
+    // As in Replay::inputs.
+    pub inputs: Vec<(u64, Option<u8>)>,
+}
+
+// What a line sets the held key to, over which frames.
+enum Command {
+    // Frames `from` to `to`, both included.
+    Hold {
+        from: u64,
+        to: u64,
+        key: u8,
+    },
+    // Every frame from `from` to the end.
+    Set {
+        from: u64,
+        key: Option<u8>,
+    },
+    Every {
+        key: u8,
+        period: u64,
+        from: u64,
+        until: Option<u64>,
+    },
+}
+
+impl Script {
+    pub fn parse(contents: &str) -> Result<Script, String> {
+        let mut frames = None;
+        let mut seed = None;
+        let mut quirks = None;
+        let mut sticky_keys = None;
+        let mut commands = Vec::new();
+        for (line_idx, line) in contents.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = |message: String| format!("line {}: {}", line_idx + 1, message);
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let command = match words[..] {
+                ["frames", value] => {
+                    frames =
+                        Some(parse_frames(parse_number(value).map_err(error)?).map_err(error)?);
+                    continue;
+                }
+                ["seed", value] => {
+                    seed = Some(parse_number(value).map_err(error)?);
+                    continue;
+                }
+                [key, value] if !key.contains(':') => {
+                    let mut pinned_quirks = quirks.unwrap_or_default();
+                    let mut pinned_sticky_keys = sticky_keys.unwrap_or(false);
+                    if !set_machine_setting(&mut pinned_quirks, &mut pinned_sticky_keys, key, value)
+                        .map_err(error)?
+                    {
+                        return Err(error(format!("unknown command `{}`", line)));
+                    }
+                    if key == "sticky-keys" {
+                        sticky_keys = Some(pinned_sticky_keys);
+                    } else {
+                        quirks = Some(pinned_quirks);
+                    }
+                    continue;
+                }
+                ["tap", key, "every", period, "starting", from] => Command::Every {
+                    key: parse_key(key).map_err(error)?,
+                    period: parse_period(period).map_err(error)?,
+                    from: parse_number(from).map_err(error)?,
+                    until: None,
+                },
+                ["tap", key, "every", period, "starting", from, "until", until] => Command::Every {
+                    key: parse_key(key).map_err(error)?,
+                    period: parse_period(period).map_err(error)?,
+                    from: parse_number(from).map_err(error)?,
+                    until: Some(parse_number(until).map_err(error)?),
+                },
+                _ => parse_timed(line).map_err(error)?,
+            };
+            commands.push((line_idx + 1, command));
+        }
+
+        let frames = match frames {
+            Some(frames) => frames,
+            None if commands.is_empty() => 0,
+            None => {
+                let mut last = None;
+                for (line, command) in &commands {
+                    let end = match *command {
+                        Command::Hold { to, .. } => to,
+                        Command::Set { from, .. } => from,
+                        Command::Every {
+                            until: Some(until), ..
+                        } => until,
+                        Command::Every { until: None, .. } => {
+                            return Err(format!(
+                                "line {}: `every` without `until` needs a `frames` line",
+                                line
+                            ));
+                        }
+                    };
+                    if last.is_none_or(|(last, _)| end > last) {
+                        last = Some((end, *line));
+                    }
+                }
+                let (last, line) = last.expect("there's a command");
+                parse_frames(last.saturating_add(1))
+                    .map_err(|message| format!("line {}: {}", line, message))?
+            }
+        };
+
+        let mut held: Vec<Option<u8>> = vec![None; frames as usize];
+        for (line, command) in &commands {
+            let (first, last) = match *command {
+                Command::Hold { from, to, .. } => (from, to),
+                Command::Set { from, .. } => (from, from),
+                Command::Every { from, until, .. } => (from, until.unwrap_or(from)),
+            };
+            if last >= frames || first > last {
+                return Err(format!(
+                    "line {}: frame {} is past the end of the run at {}",
+                    line,
+                    last.max(first),
+                    frames
+                ));
+            }
+            match *command {
+                Command::Hold { from, to, key } => {
+                    held[from as usize..=to as usize].fill(Some(key));
+                }
+                Command::Set { from, key } => held[from as usize..].fill(key),
+                Command::Every {
+                    key,
+                    period,
+                    from,
+                    until,
+                } => {
+                    let end = until.map_or(frames, |until| until + 1);
+                    for frame in (from..end).step_by(period as usize) {
+                        held[frame as usize] = Some(key);
+                    }
+                }
+            }
+        }
+
+        let mut inputs = Vec::new();
+        let mut previous = None;
+        for (frame, &key) in held.iter().enumerate() {
+            if key != previous {
+                inputs.push((frame as u64, key));
+                previous = key;
+            }
+        }
+        Ok(Script {
+            frames,
+            seed,
+            quirks,
+            sticky_keys,
+            inputs,
+        })
+    }
+
+    // The script for a replay's inputs and machine, for editing by hand.
+    pub fn from_replay(replay: &Replay) -> Script {
+        Script {
+            frames: replay.frames,
+            seed: Some(replay.rng_seed),
+            quirks: Some(replay.quirks),
+            sticky_keys: Some(replay.sticky_keys),
+            inputs: replay.inputs.clone(),
+        }
+    }
+
+    // A replay of `rom` with the script's inputs, run with `options` where the script doesn't
+    // pin the machine. Without a `seed` line the seed in `options` is used, or 0.
+    pub fn replay(&self, rom: &[u8], options: Options) -> Replay {
+        Replay {
+            rom_hash: rom_hash(rom),
+            quirks: self.quirks.unwrap_or(options.quirks),
+            sticky_keys: self.sticky_keys.unwrap_or(options.sticky_keys),
+            rng_seed: self.seed.or(options.rng_seed).unwrap_or(0),
+            load_address: LOAD_ADDRESS,
+            ops_per_second: options.ops_per_second,
+            frames: self.frames,
+            inputs: self.inputs.clone(),
+        }
+    }
+
+    // Each press as one line: a tap if it lasts a frame, a hold if it ends, a press if it lasts
+    // to the end of the run.
+    pub fn to_text(&self) -> String {
+        let mut lines = vec![
+            "# ch8emu input script".to_string(),
+            format!("frames {}", self.frames),
+        ];
+        if let Some(seed) = self.seed {
+            lines.push(format!("seed {}", seed));
+        }
+        if let Some(quirks) = self.quirks {
+            for (key, value) in quirk_settings(quirks) {
+                lines.push(format!("{} {}", key, value));
+            }
+        }
+        if let Some(sticky_keys) = self.sticky_keys {
+            lines.push(format!("sticky-keys {}", sticky_keys));
+        }
+        for (index, &(frame, key)) in self.inputs.iter().enumerate() {
+            let Some(key) = key else {
+                continue;
+            };
+            let end = self.inputs.get(index + 1).map(|&(end, _)| end);
+            lines.push(match end {
+                Some(end) if end == frame + 1 => format!("{}: tap {:X}", frame, key),
+                Some(end) => format!("{}-{}: hold {:X}", frame, end - 1, key),
+                None => format!("{}: press {:X}", frame, key),
+            });
+        }
+        lines.push(String::new());
+        lines.join("\n")
+    }
+}
+
+// A `<frames>: <action>` line.
+fn parse_timed(line: &str) -> Result<Command, String> {
+    let (frames, action) = line
+        .split_once(':')
+        .ok_or_else(|| format!("unknown command `{}`", line))?;
+    let (from, to) = match frames.trim().split_once('-') {
+        Some((from, to)) => (parse_number(from.trim())?, Some(parse_number(to.trim())?)),
+        None => (parse_number(frames.trim())?, None),
+    };
+    if let Some(to) = to.filter(|&to| to < from) {
+        return Err(format!("the range {}-{} ends before it starts", from, to));
+    }
+    let words: Vec<&str> = action.split_whitespace().collect();
+    match (&words[..], to) {
+        (["hold", key], Some(to)) => Ok(Command::Hold {
+            from,
+            to,
+            key: parse_key(key)?,
+        }),
+        (["hold", key], None) | (["tap", key], None) => Ok(Command::Hold {
+            from,
+            to: from,
+            key: parse_key(key)?,
+        }),
+        (["press", key], None) => Ok(Command::Set {
+            from,
+            key: Some(parse_key(key)?),
+        }),
+        (["release"], None) => Ok(Command::Set { from, key: None }),
+        (["tap", _] | ["press", _] | ["release"], Some(_)) => {
+            Err(format!("`{}` takes a single frame", action.trim()))
+        }
+        _ => Err(format!("unknown action `{}`", action.trim())),
+    }
+}
+
+fn parse_number(value: &str) -> Result<u64, String> {
+    value
+        .parse()
+        .map_err(|_| format!("`{}` is not a number", value))
+}
+
+fn parse_frames(frames: u64) -> Result<u64, String> {
+    if frames > MAX_FRAMES {
+        return Err(format!(
+            "a run of {} frames is longer than the {} a script can last",
+            frames, MAX_FRAMES
+        ));
+    }
+    Ok(frames)
+}
+
+fn parse_period(value: &str) -> Result<u64, String> {
+    match parse_number(value)? {
+        0 => Err("a period of 0 frames".to_string()),
+        period => Ok(period),
+    }
+}
+
+fn parse_key(value: &str) -> Result<u8, String> {
+    input::parse_key(value).map_err(|_| format!("`{}` is not a keypad key", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let script = Script::parse(
+            "# a run\n\
+             frames 40\n\
+             seed 7\n\
+             2-4: hold 5\n\
+             10: press a\n\
+             20: release\n\
+             tap 6 every 6 starting 24 until 31\n\
+             36: tap F\n",
+        )
+        .unwrap();
+
+        assert_eq!(script.frames, 40);
+        assert_eq!(script.seed, Some(7));
+        assert_eq!(
+            script.inputs,
+            vec![
+                (2, Some(0x5)),
+                (5, None),
+                (10, Some(0xA)),
+                (20, None),
+                (24, Some(0x6)),
+                (25, None),
+                (30, Some(0x6)),
+                (31, None),
+                (36, Some(0xF)),
+                (37, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_later_lines_win() {
+        let script =
+            Script::parse("frames 10\n0: press 1\n3-4: hold 2\ntap 3 every 4 starting 0").unwrap();
+
+        assert_eq!(
+            script.inputs,
+            vec![
+                (0, Some(0x3)),
+                (1, Some(0x1)),
+                (3, Some(0x2)),
+                (4, Some(0x3)),
+                (5, Some(0x1)),
+                (8, Some(0x3)),
+                (9, Some(0x1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_frames_default_to_the_last_line() {
+        let script = Script::parse("3-5: hold 1\n8: tap 2").unwrap();
+
+        assert_eq!(script.frames, 9);
+        assert_eq!(script.seed, None);
+        assert_eq!(
+            Script::parse("tap 6 every 30 starting 300"),
+            Err("line 1: `every` without `until` needs a `frames` line".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for (contents, expected) in [
+            ("frames 10\n2: hold G", "line 2: `G` is not a keypad key"),
+            ("frames 10\n\n4: jump 1", "line 3: unknown action `jump 1`"),
+            (
+                "frames 10\n2-4: tap 1",
+                "line 2: `tap 1` takes a single frame",
+            ),
+            ("frames 10\nx: tap 1", "line 2: `x` is not a number"),
+            (
+                "frames 10\n8-12: hold 1",
+                "line 2: frame 12 is past the end of the run at 10",
+            ),
+            (
+                "frames 10\n5-2: hold 1",
+                "line 2: the range 5-2 ends before it starts",
+            ),
+            (
+                "frames 99999999999",
+                "line 1: a run of 99999999999 frames is longer than the 5184000 a script can last",
+            ),
+            (
+                "0: tap 1\n99999999999: tap 2",
+                "line 2: a run of 100000000000 frames is longer than the 5184000 a script can \
+                 last",
+            ),
+            (
+                "quirk.warp-speed true",
+                "line 1: unknown quirk `warp-speed`",
+            ),
+            (
+                "frames 10\ntap 1 every 0 starting 2",
+                "line 2: a period of 0 frames",
+            ),
+            ("walk left", "line 1: unknown command `walk left`"),
+        ] {
+            assert_eq!(
+                Script::parse(contents),
+                Err(expected.to_string()),
+                "{}",
+                contents
+            );
+        }
+    }
+
+    #[test]
+    fn test_to_text_round_trips() {
+        let script = Script {
+            frames: 100,
+            seed: Some(3),
+            quirks: None,
+            sticky_keys: None,
+            inputs: vec![
+                (0, Some(0x1)),
+                (1, Some(0x2)),
+                (5, None),
+                (9, Some(0xB)),
+                (10, None),
+                (90, Some(0xC)),
+            ],
+        };
+
+        let text = script.to_text();
+
+        assert_eq!(
+            text,
+            "# ch8emu input script\nframes 100\nseed 3\n0: tap 1\n1-4: hold 2\n9: tap B\n\
+             90: press C\n"
+        );
+        assert_eq!(Script::parse(&text), Ok(script));
+    }
+
+    #[test]
+    fn test_replay_round_trips() {
+        let script = Script::parse("frames 30\nseed 9\n2-4: hold 5\n20: press 1").unwrap();
+
+        let replay = script.replay(&[0x12, 0x00], Options::default());
+
+        assert_eq!(replay.rng_seed, 9);
+        assert_eq!(replay.frames, 30);
+        assert_eq!(replay.rom_hash, rom_hash(&[0x12, 0x00]));
+        let exported = Script::from_replay(&replay);
+        assert_eq!(exported.inputs, script.inputs);
+        assert_eq!(Script::parse(&exported.to_text()), Ok(exported));
+    }
+
+    #[test]
+    fn test_replay_keeps_its_machine() {
+        let options = Options {
+            quirks: Quirks {
+                clip_sprites: false,
+                shift_uses_vy: true,
+                ..Quirks::default()
+            },
+            sticky_keys: true,
+            ..Options::default()
+        };
+        let replay = Script::parse("frames 3")
+            .unwrap()
+            .replay(&[0x12, 0x00], options);
+
+        let exported = Script::parse(&Script::from_replay(&replay).to_text()).unwrap();
+
+        assert_eq!(exported.quirks, Some(options.quirks));
+        assert_eq!(exported.sticky_keys, Some(true));
+        // Played back with other settings on the command line, it still runs on its own.
+        let replayed = exported.replay(&[0x12, 0x00], Options::default());
+        assert_eq!(replayed.quirks, options.quirks);
+        assert!(replayed.sticky_keys);
+
+        let pinned = Script::parse("frames 3\nquirk.clip-sprites false").unwrap();
+        assert_eq!(pinned.sticky_keys, None);
+        assert_eq!(
+            pinned.quirks,
+            Some(Quirks {
+                clip_sprites: false,
+                ..Quirks::default()
+            })
+        );
+    }
+}
@@ -0,0 +1,328 @@
+use crate::chip8::display::Display;
+use crate::chip8::{
+    assemble_opcodes, BootConfig, Chip8, Fnv1a, Opcode, Options, Quirks, StopReason, Target,
+    PROGRAM_START_ADDRESS, TIMER_HZ,
+};
+use std::hash::{Hash, Hasher};
+use std::time::Duration;
+
+// Tiny ROMs that each check one behaviour, for eyeballing quirks and for testing the emulator
+// without third-party ROMs. A test's body leaves VE at 1 if it saw the behaviour it looks for,
+// then every test clears the screen and shows the result in the built-in font: `A` for a pass,
+// `F` for a fail.
+#[derive(Debug)]
+pub struct SelfTest {
+    pub name: &'static str,
+    pub description: &'static str,
+    // Runs from 0x200, falling through to the result screen after its last instruction.
+    body: &'static [Opcode],
+    // The platforms whose quirks have the behaviour; it fails under the rest.
+    pub passes_under: &'static [Target],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    Pass,
+    Fail,
+}
+
+pub const ALL_TARGETS: [Target; 5] = [
+    Target::Chip8,
+    Target::Chip48,
+    Target::SuperChipLegacy,
+    Target::SuperChipModern,
+    Target::XoChip,
+];
+
+// Hashes of the display showing each result, see screen_hash.
+pub const PASS_SCREEN: u64 = 0x7B8C94950418133F;
+pub const FAIL_SCREEN: u64 = 0xB4DCE50500808F7F;
+
+// Long enough for the slowest test, the display wait one, to reach its result.
+const RUN_FRAMES: u32 = 60;
+const FRAME: Duration = Duration::from_micros(1_000_000 / TIMER_HZ);
+
+// Where a test's body ends and the result screen starts, for bodies that jump to it.
+const fn result_at(body_len: usize) -> u16 {
+    (PROGRAM_START_ADDRESS + 2 * body_len) as u16
+}
+
+const SHIFT: [Opcode; 6] = [
+    Opcode::LoadByte(0xE, 0),
+    Opcode::LoadByte(0x1, 0x01),
+    Opcode::LoadByte(0x2, 0x04),
+    Opcode::ShiftRight(0x1, 0x2),
+    Opcode::SkipIfNotEqual(0x1, 0x02),
+    Opcode::LoadByte(0xE, 1),
+];
+
+// Stores two registers, then one more wherever FX55 left I, and reads back the byte after the
+// first two: only if I moved past both does it hold the third.
+const LOAD_STORE_I: [Opcode; 11] = [
+    Opcode::LoadByte(0xE, 0),
+    Opcode::LoadByte(0x0, 0x11),
+    Opcode::LoadByte(0x1, 0x22),
+    Opcode::SetIndex(0x300),
+    Opcode::RegDump(0x1),
+    Opcode::LoadByte(0x0, 0x33),
+    Opcode::RegDump(0x0),
+    Opcode::SetIndex(0x302),
+    Opcode::RegLoad(0x0),
+    Opcode::SkipIfNotEqual(0x0, 0x33),
+    Opcode::LoadByte(0xE, 1),
+];
+
+// As LOAD_STORE_I, reading back the byte after the first: only if I moved by X, one short of
+// both registers, does the third overwrite the second.
+const LOAD_STORE_I_X: [Opcode; 11] = [
+    Opcode::LoadByte(0xE, 0),
+    Opcode::LoadByte(0x0, 0x11),
+    Opcode::LoadByte(0x1, 0x22),
+    Opcode::SetIndex(0x300),
+    Opcode::RegDump(0x1),
+    Opcode::LoadByte(0x0, 0x33),
+    Opcode::RegDump(0x0),
+    Opcode::SetIndex(0x301),
+    Opcode::RegLoad(0x0),
+    Opcode::SkipIfNotEqual(0x0, 0x33),
+    Opcode::LoadByte(0xE, 1),
+];
+
+// B20A lands on 0x20A with V0, and on 0x20E with V2.
+const JUMP_V0: [Opcode; 8] = [
+    Opcode::LoadByte(0x0, 0),
+    Opcode::LoadByte(0x2, 4),
+    Opcode::LoadByte(0xE, 0),
+    Opcode::JumpV0(0x20A),
+    Opcode::Jump(result_at(8)),
+    Opcode::LoadByte(0xE, 1),
+    Opcode::Jump(result_at(8)),
+    Opcode::LoadByte(0xE, 0),
+];
+
+const LOGIC_VF: [Opcode; 6] = [
+    Opcode::LoadByte(0xE, 0),
+    Opcode::LoadByte(0xF, 5),
+    Opcode::LoadByte(0x1, 0),
+    Opcode::Or(0x1, 0x1),
+    Opcode::SkipIfNotEqual(0xF, 0),
+    Opcode::LoadByte(0xE, 1),
+];
+
+// Draws the top of the 0 glyph across the right edge, then again in the top left corner. It
+// only collides if the first one wrapped.
+const CLIP: [Opcode; 9] = [
+    Opcode::LoadByte(0xE, 0),
+    Opcode::SetIndex(0x000),
+    Opcode::LoadByte(0x0, 126),
+    Opcode::LoadByte(0x1, 0),
+    Opcode::Draw(0x0, 0x1, 1),
+    Opcode::LoadByte(0x2, 0),
+    Opcode::Draw(0x2, 0x2, 1),
+    Opcode::SkipIfNotEqual(0xF, 0),
+    Opcode::LoadByte(0xE, 1),
+];
+
+// Counts the draws that fit in six frames in V0. Waiting for each frame allows one a frame, not
+// waiting a good many more, so it passes on ten or fewer.
+const DISPLAY_WAIT: [Opcode; 14] = [
+    Opcode::LoadByte(0xE, 0),
+    Opcode::LoadByte(0x0, 0),
+    Opcode::SetIndex(0x000),
+    Opcode::LoadByte(0x1, 6),
+    Opcode::SetDelayTimer(0x1),
+    Opcode::Draw(0x2, 0x2, 1),
+    Opcode::AddByte(0x0, 1),
+    Opcode::LoadDelayTimer(0x1),
+    Opcode::SkipIfEqual(0x1, 0),
+    Opcode::Jump(0x20A),
+    Opcode::LoadByte(0x3, 10),
+    Opcode::Sub(0x3, 0x0),
+    Opcode::SkipIfNotEqual(0xF, 1),
+    Opcode::LoadByte(0xE, 1),
+];
+
+const BCD: [Opcode; 12] = [
+    Opcode::LoadByte(0xE, 0),
+    Opcode::LoadByte(0x0, 234),
+    Opcode::SetIndex(0x300),
+    Opcode::StoreBCD(0x0),
+    Opcode::RegLoad(0x2),
+    Opcode::SkipIfEqual(0x0, 2),
+    Opcode::Jump(result_at(12)),
+    Opcode::SkipIfEqual(0x1, 3),
+    Opcode::Jump(result_at(12)),
+    Opcode::SkipIfEqual(0x2, 4),
+    Opcode::Jump(result_at(12)),
+    Opcode::LoadByte(0xE, 1),
+];
+
+// Draws the 0 glyph, again on top of itself, then next to it: only the second collides.
+const COLLISION: [Opcode; 11] = [
+    Opcode::LoadByte(0xE, 0),
+    Opcode::SetIndex(0x000),
+    Opcode::Draw(0x2, 0x2, 5),
+    Opcode::Draw(0x2, 0x2, 5),
+    Opcode::LoadReg(0x3, 0xF),
+    Opcode::LoadByte(0x4, 10),
+    Opcode::Draw(0x4, 0x2, 5),
+    Opcode::SkipIfEqual(0x3, 1),
+    Opcode::Jump(result_at(11)),
+    Opcode::SkipIfNotEqual(0xF, 0),
+    Opcode::LoadByte(0xE, 1),
+];
+
+pub const SELF_TESTS: [SelfTest; 9] = [
+    SelfTest {
+        name: "shift",
+        description: "8XY6 shifts VY into VX",
+        body: &SHIFT,
+        passes_under: &[Target::Chip8, Target::XoChip],
+    },
+    SelfTest {
+        name: "load-store-i",
+        description: "FX55 leaves I past the last register stored",
+        body: &LOAD_STORE_I,
+        passes_under: &[Target::Chip8, Target::XoChip],
+    },
+    SelfTest {
+        name: "load-store-i-x",
+        description: "FX55 leaves I on the last register stored, as CHIP-48 does",
+        body: &LOAD_STORE_I_X,
+        passes_under: &[Target::Chip48],
+    },
+    SelfTest {
+        name: "jump-v0",
+        description: "BNNN jumps to NNN + V0",
+        body: &JUMP_V0,
+        passes_under: &[Target::Chip8, Target::XoChip],
+    },
+    SelfTest {
+        name: "logic-vf",
+        description: "8XY1 resets VF",
+        body: &LOGIC_VF,
+        passes_under: &[Target::Chip8],
+    },
+    SelfTest {
+        name: "clip",
+        description: "sprites are cut off at the right edge",
+        body: &CLIP,
+        passes_under: &[
+            Target::Chip8,
+            Target::Chip48,
+            Target::SuperChipLegacy,
+            Target::SuperChipModern,
+        ],
+    },
+    SelfTest {
+        name: "display-wait",
+        description: "DXYN waits for the next frame",
+        body: &DISPLAY_WAIT,
+        passes_under: &[Target::Chip8, Target::SuperChipLegacy],
+    },
+    SelfTest {
+        name: "bcd",
+        description: "FX33 stores hundreds, tens and ones",
+        body: &BCD,
+        passes_under: &ALL_TARGETS,
+    },
+    SelfTest {
+        name: "collision",
+        description: "DXYN sets VF when it erases a pixel and clears it when not",
+        body: &COLLISION,
+        passes_under: &ALL_TARGETS,
+    },
+];
+
+// The result screen every test ends in: `A` or `F` from the font in the middle of the display.
+const RESULT: [Opcode; 9] = [
+    Opcode::ClearDisplay,
+    Opcode::LoadByte(0xD, 0xF),
+    Opcode::SkipIfNotEqual(0xE, 1),
+    Opcode::LoadByte(0xD, 0xA),
+    Opcode::LoadSpriteAddr(0xD),
+    Opcode::LoadByte(0x0, 30),
+    Opcode::LoadByte(0x1, 13),
+    Opcode::Draw(0x0, 0x1, 5),
+    // Jumps to itself, see rom.
+    Opcode::Jump(0),
+];
+
+pub fn find(name: &str) -> Option<&'static SelfTest> {
+    SELF_TESTS.iter().find(|test| test.name == name)
+}
+
+// Hash of what's on a display, to tell the result screens apart.
+pub fn screen_hash(display: &Display) -> u64 {
+    let mut hasher = Fnv1a::default();
+    display.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl SelfTest {
+    pub fn rom(&self) -> Vec<u8> {
+        let mut rom = assemble_opcodes(self.body);
+        rom.extend(assemble_opcodes(&RESULT[..RESULT.len() - 1]));
+        let halt = result_at(self.body.len() + RESULT.len() - 1);
+        rom.extend(assemble_opcodes(&[Opcode::Jump(halt)]));
+        rom
+    }
+
+    // Whether the test passes under `target`'s quirks, or None if it didn't get to the result
+    // screen.
+    pub fn run(&self, target: Target) -> Option<Outcome> {
+        self.run_with(Quirks::preset(target))
+    }
+
+    // As run, under any quirks.
+    pub fn run_with(&self, quirks: Quirks) -> Option<Outcome> {
+        let mut system = Chip8::with_options(Options {
+            quirks,
+            rng_seed: Some(0),
+            ..Options::default()
+        })
+        .ok()?;
+        system.boot(BootConfig::default()).ok()?;
+        system.load_rom(&self.rom()).ok()?;
+        for _ in 0..RUN_FRAMES {
+            let summary = system.run_for(FRAME).ok()?;
+            if summary.stopped == Some(StopReason::Halted) {
+                break;
+            }
+        }
+        match screen_hash(system.display()) {
+            PASS_SCREEN => Some(Outcome::Pass),
+            FAIL_SCREEN => Some(Outcome::Fail),
+            _ => None,
+        }
+    }
+
+    pub fn expected(&self, target: Target) -> Outcome {
+        if self.passes_under.contains(&target) {
+            Outcome::Pass
+        } else {
+            Outcome::Fail
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rom_ends_on_a_jump_to_itself() {
+        let rom = find("shift").unwrap().rom();
+
+        let halt = PROGRAM_START_ADDRESS as u16 + rom.len() as u16 - 2;
+        assert_eq!(rom[rom.len() - 2..], (0x1000 | halt).to_be_bytes());
+        assert_eq!(rom.len(), 2 * (SHIFT.len() + RESULT.len()));
+        assert!(find("nope").is_none());
+    }
+
+    #[test]
+    fn test_result_screens_differ() {
+        assert_ne!(PASS_SCREEN, FAIL_SCREEN);
+        assert_ne!(screen_hash(&Display::new()), PASS_SCREEN);
+    }
+}
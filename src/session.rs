@@ -0,0 +1,408 @@
+use crate::cli;
+use crate::config::{self, Config};
+use crate::keymap::Preset;
+use std::path::Path;
+
+const SESSION_FILE_NAME: &str = "session.toml";
+
+// A rectangle in physical screen pixels.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    fn contains(&self, x: i32, y: i32) -> bool {
+        let (x, y) = (i64::from(x), i64::from(y));
+        let (left, top) = (i64::from(self.x), i64::from(self.y));
+        x >= left
+            && y >= top
+            && x < left + i64::from(self.width)
+            && y < top + i64::from(self.height)
+    }
+}
+
+// What the last session left behind: the window, and the settings changed while it ran that
+// differ from the configuration file. Values that weren't changed are None, so that editing the
+// configuration file still takes effect.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Session {
+    // The window's position and inner size.
+    pub window: Option<Rect>,
+    pub fullscreen: bool,
+    pub keypad: Option<Preset>,
+    pub muted: Option<bool>,
+}
+
+// The settings a run starts with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Settings {
+    pub keypad: Preset,
+    pub muted: bool,
+}
+
+// Command line flags win over the last session, which wins over the configuration file.
+pub fn merge(config: &Config, session: &Session, options: &cli::Options) -> Settings {
+    Settings {
+        keypad: session.keypad.unwrap_or(config.keypad),
+        muted: options.mute || session.muted.unwrap_or(false),
+    }
+}
+
+impl Session {
+    // The session to save for `settings` as they are at exit, keeping only what differs from
+    // the configuration file. A setting still as the run `started` with wasn't changed while
+    // running, so it keeps what `previous` saved rather than a one-off command line flag.
+    pub fn new(
+        window: Option<Rect>,
+        fullscreen: bool,
+        config: &Config,
+        previous: &Session,
+        started: Settings,
+        settings: Settings,
+    ) -> Self {
+        Session {
+            window,
+            fullscreen,
+            keypad: (settings.keypad != config.keypad).then_some(settings.keypad),
+            muted: if settings.muted == started.muted {
+                previous.muted
+            } else {
+                settings.muted.then_some(true)
+            },
+        }
+    }
+
+    // Parses the `key = value` format written by to_text.
+    pub fn parse(contents: &str) -> Result<Session, String> {
+        let mut session = Session::default();
+        let mut window = [None; 4];
+        for (line_idx, line) in contents.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = |message: String| format!("line {}: {}", line_idx + 1, message);
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| error("expected `key = value`".to_string()))?;
+            let (key, value) = (key.trim(), value.trim().trim_matches('"'));
+            let number = || {
+                value
+                    .parse::<i64>()
+                    .map_err(|_| error(format!("`{}` is not a number", value)))
+            };
+            let flag = || {
+                value
+                    .parse::<bool>()
+                    .map_err(|_| error(format!("`{}` is not true or false", value)))
+            };
+
+            match key {
+                "window-x" => window[0] = Some(number()?),
+                "window-y" => window[1] = Some(number()?),
+                "window-width" => window[2] = Some(number()?),
+                "window-height" => window[3] = Some(number()?),
+                "fullscreen" => session.fullscreen = flag()?,
+                "keypad" => session.keypad = Some(value.parse().map_err(error)?),
+                "muted" => session.muted = Some(flag()?),
+                key => return Err(error(format!("unknown key `{}`", key))),
+            }
+        }
+
+        session.window = match window {
+            [Some(x), Some(y), Some(width), Some(height)] => Some(Rect {
+                x: i32::try_from(x).map_err(|err| err.to_string())?,
+                y: i32::try_from(y).map_err(|err| err.to_string())?,
+                width: u32::try_from(width).map_err(|err| err.to_string())?,
+                height: u32::try_from(height).map_err(|err| err.to_string())?,
+            }),
+            [None, None, None, None] => None,
+            _ => return Err("incomplete window geometry".to_string()),
+        };
+        Ok(session)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut lines = vec!["# Written by ch8emu on exit".to_string()];
+        if let Some(window) = self.window {
+            lines.push(format!("window-x = {}", window.x));
+            lines.push(format!("window-y = {}", window.y));
+            lines.push(format!("window-width = {}", window.width));
+            lines.push(format!("window-height = {}", window.height));
+        }
+        lines.push(format!("fullscreen = {}", self.fullscreen));
+        if let Some(keypad) = self.keypad {
+            lines.push(format!("keypad = \"{}\"", keypad.name()));
+        }
+        if let Some(muted) = self.muted {
+            lines.push(format!("muted = {}", muted));
+        }
+        lines.push(String::new());
+        lines.join("\n")
+    }
+}
+
+// Loads the last session. A missing or unreadable file is a fresh start, without a warning:
+// the file is only ever written by the emulator and holds nothing worth reporting.
+pub fn load() -> Session {
+    config::config_dir()
+        .and_then(|dir| load_from(&dir))
+        .unwrap_or_default()
+}
+
+fn load_from(dir: &Path) -> Option<Session> {
+    let contents = std::fs::read_to_string(dir.join(SESSION_FILE_NAME)).ok()?;
+    Session::parse(&contents).ok()
+}
+
+pub fn save(session: &Session) -> Result<(), String> {
+    let dir = config::config_dir().ok_or("no config directory")?;
+    save_to(&dir, session)
+}
+
+fn save_to(dir: &Path, session: &Session) -> Result<(), String> {
+    std::fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    std::fs::write(dir.join(SESSION_FILE_NAME), session.to_text()).map_err(|err| err.to_string())
+}
+
+// Where to put a window saved at `window`. It stays put if its top left corner is on one of the
+// monitors; otherwise, say because its monitor was unplugged, it's moved onto the primary monitor
+// and shrunk to fit it.
+pub fn place(window: Rect, monitors: &[Rect], primary: Option<Rect>) -> Rect {
+    if monitors
+        .iter()
+        .any(|monitor| monitor.contains(window.x, window.y))
+    {
+        return window;
+    }
+    let Some(target) = primary.or_else(|| monitors.first().copied()) else {
+        return window;
+    };
+    let width = window.width.min(target.width);
+    let height = window.height.min(target.height);
+    let clamp = |position: i32, start: i32, free: u32| {
+        let end = (i64::from(start) + i64::from(free)) as i32;
+        position.clamp(start, end)
+    };
+    Rect {
+        x: clamp(window.x, target.x, target.width - width),
+        y: clamp(window.y, target.y, target.height - height),
+        width,
+        height,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEFT: Rect = Rect {
+        x: 0,
+        y: 0,
+        width: 1920,
+        height: 1080,
+    };
+    const RIGHT: Rect = Rect {
+        x: 1920,
+        y: -200,
+        width: 1280,
+        height: 1024,
+    };
+
+    fn window(x: i32, y: i32, width: u32, height: u32) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn test_place_keeps_window_on_a_monitor() {
+        let on_right = window(2000, -100, 640, 320);
+
+        assert_eq!(place(on_right, &[LEFT, RIGHT], Some(LEFT)), on_right);
+        // Hanging off the edge is fine as long as the corner can be grabbed.
+        let overhanging = window(1800, 900, 640, 320);
+        assert_eq!(place(overhanging, &[LEFT], Some(LEFT)), overhanging);
+    }
+
+    #[test]
+    fn test_place_moves_window_off_missing_monitor() {
+        let on_right = window(2000, -100, 640, 320);
+
+        assert_eq!(
+            place(on_right, &[LEFT], Some(LEFT)),
+            window(1280, 0, 640, 320)
+        );
+        assert_eq!(
+            place(window(-900, 500, 640, 320), &[LEFT], Some(LEFT)),
+            window(0, 500, 640, 320)
+        );
+    }
+
+    #[test]
+    fn test_place_shrinks_window_larger_than_primary() {
+        assert_eq!(
+            place(window(-3000, 0, 2560, 1440), &[RIGHT, LEFT], Some(RIGHT)),
+            window(1920, -200, 1280, 1024)
+        );
+        // Without a primary monitor the first one is used.
+        assert_eq!(
+            place(window(-3000, 0, 2560, 1440), &[LEFT, RIGHT], None),
+            window(0, 0, 1920, 1080)
+        );
+        assert_eq!(
+            place(window(-3000, 0, 64, 32), &[], None),
+            window(-3000, 0, 64, 32)
+        );
+    }
+
+    #[test]
+    fn test_merge_precedence() {
+        let config = Config {
+            keypad: Preset::Wasd,
+            ..Config::default()
+        };
+        let options = cli::Options::default();
+        let fresh = Session::default();
+        let changed = Session {
+            keypad: Some(Preset::Numpad),
+            muted: Some(true),
+            ..Session::default()
+        };
+
+        assert_eq!(
+            merge(&config, &fresh, &options),
+            Settings {
+                keypad: Preset::Wasd,
+                muted: false
+            }
+        );
+        assert_eq!(
+            merge(&config, &changed, &options),
+            Settings {
+                keypad: Preset::Numpad,
+                muted: true
+            }
+        );
+        let muted = cli::Options {
+            mute: true,
+            ..cli::Options::default()
+        };
+        assert!(merge(&config, &fresh, &muted).muted);
+    }
+
+    #[test]
+    fn test_new_keeps_only_changed_settings() {
+        let config = Config {
+            keypad: Preset::Wasd,
+            ..Config::default()
+        };
+        let unchanged = Settings {
+            keypad: Preset::Wasd,
+            muted: false,
+        };
+
+        let fresh = Session::default();
+        assert_eq!(
+            Session::new(None, false, &config, &fresh, unchanged, unchanged),
+            Session::default()
+        );
+        let changed = Session::new(
+            None,
+            false,
+            &config,
+            &fresh,
+            unchanged,
+            Settings {
+                keypad: Preset::Classic,
+                muted: true,
+            },
+        );
+        assert_eq!(changed.keypad, Some(Preset::Classic));
+        assert_eq!(changed.muted, Some(true));
+    }
+
+    #[test]
+    fn test_new_leaves_out_command_line_mute() {
+        let config = Config::default();
+        let options = cli::Options {
+            mute: true,
+            ..cli::Options::default()
+        };
+        let fresh = Session::default();
+        let started = merge(&config, &fresh, &options);
+        assert!(started.muted);
+
+        let saved = Session::new(None, false, &config, &fresh, started, started);
+        assert_eq!(saved.muted, None);
+
+        // A session saved muted stays muted, unless unmuted while running.
+        let unmuted = Settings {
+            muted: false,
+            ..started
+        };
+        assert_eq!(
+            Session::new(None, false, &config, &fresh, started, unmuted).muted,
+            None
+        );
+        let was_muted = Session {
+            muted: Some(true),
+            ..Session::default()
+        };
+        assert_eq!(
+            Session::new(None, false, &config, &was_muted, started, started).muted,
+            Some(true)
+        );
+        assert_eq!(
+            Session::new(None, false, &config, &was_muted, started, unmuted).muted,
+            None
+        );
+    }
+
+    #[test]
+    fn test_to_text_round_trips() {
+        let session = Session {
+            window: Some(window(-20, 40, 640, 320)),
+            fullscreen: true,
+            keypad: Some(Preset::Numpad),
+            muted: Some(true),
+        };
+
+        assert_eq!(Session::parse(&session.to_text()), Ok(session));
+        assert_eq!(
+            Session::parse(&Session::default().to_text()),
+            Ok(Session::default())
+        );
+    }
+
+    #[test]
+    fn test_corrupt_session_is_ignored() {
+        let dir = std::env::temp_dir().join(format!("ch8emu-session-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(load_from(&dir), None);
+
+        let session = Session {
+            muted: Some(true),
+            ..Session::default()
+        };
+        save_to(&dir, &session).unwrap();
+        assert_eq!(load_from(&dir), Some(session));
+
+        for contents in [
+            "window-x = 10\n",
+            "fullscreen = maybe\n",
+            "\u{0}\u{1}garbage",
+        ] {
+            std::fs::write(dir.join(SESSION_FILE_NAME), contents).unwrap();
+            assert_eq!(load_from(&dir), None, "{:?}", contents);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,32 @@
+// Shown when the emulator starts without a ROM. It spells "CH8 EMU" with its own sprites, so it
+// doesn't depend on the built-in font, then idles by jumping to itself.
+pub const SPLASH_ROM: [u8; 74] = [
+    0x00, 0xE0, // 0x200: clear the screen
+    0x6A, 0x10, // 0x202: VA = 16, x of the first line
+    0x6B, 0x08, // 0x204: VB = 8, y of the first line
+    0xA2, 0x2C, // 0x206: I = C
+    0xDA, 0xB5, // 0x208: draw it
+    0x7A, 0x05, // 0x20A: VA += 5
+    0xA2, 0x31, // 0x20C: I = H
+    0xDA, 0xB5, // 0x20E: draw it
+    0x7A, 0x05, // 0x210: VA += 5
+    0xA2, 0x36, // 0x212: I = 8
+    0xDA, 0xB5, // 0x214: draw it
+    0x6A, 0x10, // 0x216: VA = 16, x of the second line
+    0x6B, 0x10, // 0x218: VB = 16, y of the second line
+    0xA2, 0x3B, // 0x21A: I = E
+    0xDA, 0xB5, // 0x21C: draw it
+    0x7A, 0x05, // 0x21E: VA += 5
+    0xA2, 0x40, // 0x220: I = M
+    0xDA, 0xB5, // 0x222: draw it
+    0x7A, 0x06, // 0x224: VA += 6, M is a pixel wider
+    0xA2, 0x45, // 0x226: I = U
+    0xDA, 0xB5, // 0x228: draw it
+    0x12, 0x2A, // 0x22A: idle
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // 0x22C: C
+    0x90, 0x90, 0xF0, 0x90, 0x90, // 0x231: H
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 0x236: 8
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // 0x23B: E
+    0x88, 0xD8, 0xA8, 0x88, 0x88, // 0x240: M
+    0x90, 0x90, 0x90, 0x90, 0xF0, // 0x245: U
+];
@@ -0,0 +1,152 @@
+// Glyphs are 3 pixels wide and 5 tall, each row in the low 3 bits with the leftmost pixel first.
+pub const GLYPH_WIDTH: usize = 3;
+pub const GLYPH_HEIGHT: usize = 5;
+// Blank columns between glyphs.
+const SPACING: usize = 1;
+
+// A small font for text drawn over the game: digits, letters and a little punctuation. Letters
+// are uppercase only; anything else without a glyph is drawn as `?`.
+fn glyph(c: char) -> [u8; GLYPH_HEIGHT] {
+    match c.to_ascii_uppercase() {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        ' ' => [0b000, 0b000, 0b000, 0b000, 0b000],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '%' => [0b101, 0b001, 0b010, 0b100, 0b101],
+        _ => [0b111, 0b001, 0b010, 0b000, 0b010],
+    }
+}
+
+// Width in pixels of `text` drawn at `scale`, without trailing spacing.
+pub fn text_width(text: &str, scale: usize) -> usize {
+    let chars = text.chars().count();
+    (chars * (GLYPH_WIDTH + SPACING)).saturating_sub(SPACING) * scale
+}
+
+// Draws a line of text with its top left corner at `origin`, each font pixel `scale` frame pixels
+// square. Only set pixels are written, and anything past the frame's edges is cut off.
+pub fn draw_text(
+    frame: &mut [u32],
+    width: usize,
+    height: usize,
+    origin: (usize, usize),
+    text: &str,
+    scale: usize,
+    color: u32,
+) {
+    let (left, top) = origin;
+    for (index, c) in text.chars().enumerate() {
+        let glyph_left = left + index * (GLYPH_WIDTH + SPACING) * scale;
+        for (glyph_y, bits) in glyph(c).iter().enumerate() {
+            for glyph_x in 0..GLYPH_WIDTH {
+                if bits & (0b100 >> glyph_x) == 0 {
+                    continue;
+                }
+                for y in top + glyph_y * scale..top + (glyph_y + 1) * scale {
+                    for x in glyph_left + glyph_x * scale..glyph_left + (glyph_x + 1) * scale {
+                        if x < width && y < height {
+                            frame[y * width + x] = color;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The frame as text, `#` for pixels of `color`.
+    fn render(frame: &[u32], width: usize, color: u32) -> Vec<String> {
+        frame
+            .chunks(width)
+            .map(|row| {
+                row.iter()
+                    .map(|&pixel| if pixel == color { '#' } else { '.' })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_draw_text() {
+        let (width, height) = (10, 7);
+        let mut frame = vec![0; width * height];
+
+        draw_text(&mut frame, width, height, (1, 1), "7a", 1, 0xFF);
+
+        assert_eq!(
+            render(&frame, width, 0xFF),
+            vec![
+                "..........",
+                ".###..#...",
+                "...#.#.#..",
+                "..#..###..",
+                "..#..#.#..",
+                "..#..#.#..",
+                "..........",
+            ]
+        );
+        assert_eq!(text_width("7a", 1), 7);
+    }
+
+    #[test]
+    fn test_draw_text_scaled_and_clipped() {
+        let (width, height) = (5, 8);
+        let mut frame = vec![0; width * height];
+
+        draw_text(&mut frame, width, height, (0, 0), "1.", 2, 0xFF);
+
+        assert_eq!(
+            render(&frame, width, 0xFF),
+            vec!["..##.", "..##.", "####.", "####.", "..##.", "..##.", "..##.", "..##.",]
+        );
+        assert_eq!(text_width("1.", 2), 14);
+    }
+
+    #[test]
+    fn test_unknown_characters_drawn_as_question_mark() {
+        assert_eq!(glyph('~'), glyph('?'));
+        assert_eq!(glyph('q'), glyph('Q'));
+        assert_eq!(text_width("", 3), 0);
+    }
+}
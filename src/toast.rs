@@ -0,0 +1,356 @@
+use crate::hud;
+use crate::render::PIXEL_OFF;
+use crate::text;
+use ch8emu::chip8::{Diagnostics, Severity};
+use ch8emu::numfmt::format_addr;
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+// How long a toast stays up, including its fade.
+pub const TOAST_LIFETIME: Duration = Duration::from_millis(2500);
+// The end of a toast's lifetime, over which it fades out.
+const FADE_TIME: Duration = Duration::from_millis(500);
+// Steps a toast fades out in, each one a repaint.
+const FADE_STEPS: u32 = 5;
+// Toasts shown at once. A new one pushes the oldest out early.
+pub const MAX_TOASTS: usize = 3;
+
+pub const INFO_COLOR: u32 = 0x00E0_E0E0;
+pub const WARNING_COLOR: u32 = 0x00FF_C040;
+pub const ERROR_COLOR: u32 = 0x00FF_4040;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Level {
+    Info,
+    Warning,
+    Error,
+}
+
+impl From<Severity> for Level {
+    fn from(severity: Severity) -> Self {
+        match severity {
+            Severity::Warning => Level::Warning,
+            Severity::Error => Level::Error,
+        }
+    }
+}
+
+impl Level {
+    fn color(self) -> u32 {
+        match self {
+            Level::Info => INFO_COLOR,
+            Level::Warning => WARNING_COLOR,
+            Level::Error => ERROR_COLOR,
+        }
+    }
+}
+
+// Something the front end tells the user: "Sound muted", "Audio device lost".
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notice {
+    pub level: Level,
+    pub message: String,
+}
+
+impl Notice {
+    pub fn info(message: impl Into<String>) -> Self {
+        Notice {
+            level: Level::Info,
+            message: message.into(),
+        }
+    }
+
+    pub fn warning(message: impl Into<String>) -> Self {
+        Notice {
+            level: Level::Warning,
+            message: message.into(),
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Notice {
+            level: Level::Error,
+            message: message.into(),
+        }
+    }
+
+    // Prints the notice, warnings and errors to stderr.
+    pub fn log(&self) {
+        match self.level {
+            Level::Info => println!("{}", self),
+            Level::Warning | Level::Error => eprintln!("{}", self),
+        }
+    }
+}
+
+// As printed to the terminal.
+impl fmt::Display for Notice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.level {
+            Level::Info => write!(f, "{}", self.message),
+            Level::Warning => write!(f, "Warning: {}", self.message),
+            Level::Error => write!(f, "Error: {}", self.message),
+        }
+    }
+}
+
+// Notices for the diagnostics the machine logged since `seen`, which is moved past them.
+pub fn diagnostic_notices(diagnostics: &Diagnostics, seen: &mut u64) -> Vec<Notice> {
+    let notices = diagnostics
+        .since(*seen)
+        .map(|diagnostic| Notice {
+            level: diagnostic.severity.into(),
+            message: format!("{} at {}", diagnostic.message, format_addr(diagnostic.pc)),
+        })
+        .collect();
+    *seen = diagnostics.pushed();
+    notices
+}
+
+#[derive(Debug)]
+struct Toast {
+    notice: Notice,
+    expires: Instant,
+}
+
+// What the toasts look like at some moment, for FrameKey: it changes whenever a toast comes, goes
+// or fades another step.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ToastKey {
+    changes: u64,
+    faded: u32,
+}
+
+// The toasts on screen, oldest first.
+#[derive(Debug, Default)]
+pub struct Toasts {
+    shown: VecDeque<Toast>,
+    // Toasts pushed or expired.
+    changes: u64,
+}
+
+impl Toasts {
+    pub fn new() -> Self {
+        Toasts::default()
+    }
+
+    pub fn push(&mut self, now: Instant, notice: Notice) {
+        if self.shown.len() == MAX_TOASTS {
+            self.shown.pop_front();
+        }
+        self.shown.push_back(Toast {
+            notice,
+            expires: now + TOAST_LIFETIME,
+        });
+        self.changes += 1;
+    }
+
+    // Takes down the toasts whose time is up.
+    pub fn expire(&mut self, now: Instant) {
+        while self.shown.front().is_some_and(|toast| toast.expires <= now) {
+            self.shown.pop_front();
+            self.changes += 1;
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shown.is_empty()
+    }
+
+    // The toasts, oldest first, each with how far it has faded: from 0, not at all, to
+    // FADE_STEPS.
+    pub fn visible(&self, now: Instant) -> impl Iterator<Item = (&Notice, u32)> {
+        self.shown
+            .iter()
+            .filter(move |toast| toast.expires > now)
+            .map(move |toast| (&toast.notice, fade_step(toast.expires - now)))
+    }
+
+    pub fn key(&self, now: Instant) -> ToastKey {
+        ToastKey {
+            changes: self.changes,
+            faded: self.visible(now).map(|(_, faded)| faded).sum(),
+        }
+    }
+}
+
+fn fade_step(remaining: Duration) -> u32 {
+    if remaining >= FADE_TIME {
+        return 0;
+    }
+    // Rounded up, so a toast is only fully faded when it goes.
+    let left = (remaining.as_micros() * u128::from(FADE_STEPS)).div_ceil(FADE_TIME.as_micros());
+    FADE_STEPS - left as u32
+}
+
+// `color` darkened `faded` steps of FADE_STEPS towards black.
+fn faded(color: u32, faded: u32) -> u32 {
+    let channel = |shift: u32| {
+        let value = (color >> shift) & 0xFF;
+        (value * (FADE_STEPS - faded) / FADE_STEPS) << shift
+    };
+    channel(16) | channel(8) | channel(0)
+}
+
+// Boxes, as (left, top, width, height), for messages `widths` pixels wide at `scale`: stacked up
+// from the bottom right corner, the last message at the bottom.
+pub fn layout(width: usize, height: usize, scale: usize, widths: &[usize]) -> Vec<[usize; 4]> {
+    let box_height = (text::GLYPH_HEIGHT + 2) * scale;
+    let mut bottom = height.saturating_sub(scale);
+    let mut boxes: Vec<[usize; 4]> = widths
+        .iter()
+        .rev()
+        .map(|&text_width| {
+            let box_width = (text_width + 2 * scale).min(width);
+            let top = bottom.saturating_sub(box_height);
+            let left = width.saturating_sub(box_width + scale);
+            bottom = top.saturating_sub(scale);
+            [left, top, box_width, box_height]
+        })
+        .collect();
+    boxes.reverse();
+    boxes
+}
+
+// Draws the toasts in the bottom right corner, each on a blank box in its level's color.
+pub fn draw_toasts(frame: &mut [u32], width: usize, height: usize, toasts: &Toasts, now: Instant) {
+    let scale = hud::text_scale(width);
+    let visible: Vec<(&Notice, u32)> = toasts.visible(now).collect();
+    let widths: Vec<usize> = visible
+        .iter()
+        .map(|(notice, _)| text::text_width(&notice.message, scale))
+        .collect();
+    for ((notice, fade), [left, top, box_width, box_height]) in
+        visible.iter().zip(layout(width, height, scale, &widths))
+    {
+        for y in top..(top + box_height).min(height) {
+            frame[y * width + left..y * width + left + box_width].fill(PIXEL_OFF);
+        }
+        let color = faded(notice.level.color(), *fade);
+        let origin = (left + scale, top + scale);
+        text::draw_text(frame, width, height, origin, &notice.message, scale, color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::PIXEL_ON;
+    use ch8emu::chip8::Diagnostic;
+
+    #[test]
+    fn test_toasts_expire_oldest_first() {
+        let start = Instant::now();
+        let mut toasts = Toasts::new();
+        toasts.push(start, Notice::info("one"));
+        toasts.push(start + Duration::from_secs(1), Notice::warning("two"));
+
+        let messages = |toasts: &Toasts, now| -> Vec<(String, u32)> {
+            toasts
+                .visible(now)
+                .map(|(notice, faded)| (notice.message.clone(), faded))
+                .collect()
+        };
+        assert_eq!(
+            messages(&toasts, start + Duration::from_millis(2300)),
+            vec![("one".to_string(), 3), ("two".to_string(), 0)]
+        );
+        toasts.expire(start + TOAST_LIFETIME);
+        assert_eq!(
+            messages(&toasts, start + TOAST_LIFETIME),
+            vec![("two".to_string(), 0)]
+        );
+        toasts.expire(start + Duration::from_secs(4));
+        assert!(toasts.is_empty());
+    }
+
+    #[test]
+    fn test_toasts_push_out_the_oldest() {
+        let now = Instant::now();
+        let mut toasts = Toasts::new();
+
+        for number in 0..5 {
+            toasts.push(now, Notice::info(number.to_string()));
+        }
+
+        let messages: Vec<&str> = toasts
+            .visible(now)
+            .map(|(notice, _)| notice.message.as_str())
+            .collect();
+        assert_eq!(messages, vec!["2", "3", "4"]);
+    }
+
+    #[test]
+    fn test_toast_key_changes_as_they_fade() {
+        let start = Instant::now();
+        let mut toasts = Toasts::new();
+        toasts.push(start, Notice::info("saved"));
+
+        let keys: Vec<ToastKey> = [0, 1000, 2100, 2200, 2499]
+            .iter()
+            .map(|&millis| toasts.key(start + Duration::from_millis(millis)))
+            .collect();
+
+        assert_eq!(keys[0], keys[1]);
+        assert_ne!(keys[1], keys[2]);
+        assert_ne!(keys[2], keys[3]);
+        assert_eq!(keys[4].faded, FADE_STEPS - 1);
+    }
+
+    #[test]
+    fn test_layout_stacks_up_from_the_corner() {
+        assert_eq!(
+            layout(100, 50, 1, &[20, 30]),
+            vec![[77, 34, 22, 7], [67, 42, 32, 7]]
+        );
+        // Too wide for the window, and too many for its height.
+        assert_eq!(
+            layout(10, 8, 1, &[40, 2]),
+            vec![[0, 0, 10, 7], [5, 0, 4, 7]]
+        );
+    }
+
+    #[test]
+    fn test_draw_toasts() {
+        let (width, height) = (128, 64);
+        let mut frame = vec![PIXEL_ON; width * height];
+        let now = Instant::now();
+        let mut toasts = Toasts::new();
+        toasts.push(now, Notice::error("1"));
+
+        draw_toasts(&mut frame, width, height, &toasts, now);
+
+        // A 3 pixel glyph in a box 5 wide and 7 tall, one pixel from the corner.
+        assert_eq!(frame[(height - 8) * width + width - 6], PIXEL_OFF);
+        assert_eq!(frame[(height - 7) * width + width - 4], ERROR_COLOR);
+        assert_eq!(frame[(height - 1) * width + width - 1], PIXEL_ON);
+        assert_eq!(frame[(height - 9) * width + width - 7], PIXEL_ON);
+    }
+
+    #[test]
+    fn test_diagnostic_notices() {
+        let mut diagnostics = Diagnostics::new(8);
+        let mut seen = 0;
+        diagnostics.push(Diagnostic {
+            severity: Severity::Warning,
+            cycle: 3,
+            pc: 0x204,
+            message: "read of uninitialized memory".to_string(),
+        });
+
+        let notices = diagnostic_notices(&diagnostics, &mut seen);
+
+        assert_eq!(
+            notices,
+            vec![Notice::warning("read of uninitialized memory at 0x204")]
+        );
+        assert_eq!(
+            notices[0].to_string(),
+            "Warning: read of uninitialized memory at 0x204"
+        );
+        assert!(diagnostic_notices(&diagnostics, &mut seen).is_empty());
+        assert_eq!(faded(0x00FF_8040, 0), 0x00FF_8040);
+        assert_eq!(faded(0x00FF_8040, FADE_STEPS), 0);
+    }
+}
@@ -0,0 +1,198 @@
+use ch8emu::chip8::RunSummary;
+use std::str::FromStr;
+
+// Five seconds of frames at 60 Hz.
+pub const HUNG_FRAMES: u32 = 300;
+
+// What the frontend does about a ROM that looks hung, beyond pausing it under --strict.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum HangResponse {
+    Off,
+    // Print a warning.
+    Warn,
+    // Print a warning and say so in the window title until the ROM does something.
+    #[default]
+    Title,
+}
+
+impl FromStr for HangResponse {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "off" => Ok(HangResponse::Off),
+            "warn" => Ok(HangResponse::Warn),
+            "title" => Ok(HangResponse::Title),
+            _ => Err(format!("unknown hang response `{}`", value)),
+        }
+    }
+}
+
+// A change in whether the ROM looks hung.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Verdict {
+    Hung,
+    Recovered,
+}
+
+// What to do about a verdict.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Reaction {
+    pub warn: bool,
+    pub set_title: bool,
+    pub pause: bool,
+}
+
+impl HangResponse {
+    // The reaction to `verdict`. --strict pauses a hung ROM whatever the response, even off.
+    pub fn react(self, verdict: Verdict, strict: bool) -> Reaction {
+        Reaction {
+            warn: verdict == Verdict::Hung && self != HangResponse::Off,
+            set_title: self == HangResponse::Title,
+            pause: verdict == Verdict::Hung && strict,
+        }
+    }
+}
+
+// Notices a ROM that spends every instruction it's given without showing anything for it: frames
+// that run their whole budget without drawing, reading the keypad or starting or stopping the
+// beeper. A ROM that's busy computing but eventually shows something resets the count when it
+// does, so it only trips if it goes quiet for longer than `limit` frames.
+#[derive(Debug)]
+pub struct Watchdog {
+    limit: u32,
+    quiet_frames: u32,
+    sound_active: bool,
+    hung: bool,
+}
+
+impl Watchdog {
+    pub fn new(limit: u32) -> Self {
+        Watchdog {
+            limit,
+            quiet_frames: 0,
+            sound_active: false,
+            hung: false,
+        }
+    }
+
+    // Call with every frame's summary. Returns a verdict when the ROM starts or stops looking
+    // hung. Frames that ran nothing, or stopped short of their budget, don't count either way.
+    pub fn observe(&mut self, summary: &RunSummary) -> Option<Verdict> {
+        let sound_changed = summary.sound_active != self.sound_active;
+        self.sound_active = summary.sound_active;
+        if summary.display_dirty || summary.keys_polled || sound_changed {
+            self.quiet_frames = 0;
+            if self.hung {
+                self.hung = false;
+                return Some(Verdict::Recovered);
+            }
+            return None;
+        }
+        if !summary.budget_used || summary.instructions == 0 {
+            return None;
+        }
+        self.quiet_frames += 1;
+        if self.quiet_frames >= self.limit && !self.hung {
+            self.hung = true;
+            return Some(Verdict::Hung);
+        }
+        None
+    }
+
+    pub fn is_hung(&self) -> bool {
+        self.hung
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ch8emu::chip8::{assemble_opcodes, Chip8, Opcode};
+    use std::time::Duration;
+
+    const FRAME: Duration = Duration::from_micros(1_000_000 / 60);
+
+    // Runs `rom` for `frames` frames, returning the frame each verdict came on.
+    fn verdicts(rom: &[u8], frames: u32, limit: u32) -> Vec<(u32, Verdict)> {
+        let mut system = Chip8::booted();
+        system.load_rom(rom).unwrap();
+        let mut watchdog = Watchdog::new(limit);
+        (0..frames)
+            .filter_map(|frame| {
+                let summary = system.run_for(FRAME).unwrap();
+                watchdog.observe(&summary).map(|verdict| (frame, verdict))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_watchdog_trips_on_a_hung_rom() {
+        let rom = assemble_opcodes(&[Opcode::AddByte(0x0, 1), Opcode::Jump(0x200)]);
+
+        assert_eq!(verdicts(&rom, 20, 10), vec![(9, Verdict::Hung)]);
+    }
+
+    #[test]
+    fn test_watchdog_spares_a_slow_rom_that_draws() {
+        // Counts V1 around 16 times for every 4 times round V0, then clears the screen and starts
+        // over: at 10 instructions a frame that's a draw every 20 or so frames.
+        let rom = assemble_opcodes(&[
+            Opcode::AddByte(0x1, 0x10),
+            Opcode::SkipIfEqual(0x1, 0),
+            Opcode::Jump(0x200),
+            Opcode::AddByte(0x0, 0x40),
+            Opcode::SkipIfEqual(0x0, 0),
+            Opcode::Jump(0x200),
+            Opcode::ClearDisplay,
+            Opcode::Jump(0x200),
+        ]);
+
+        assert_eq!(verdicts(&rom, 600, 30), vec![]);
+        // A watchdog shorter than the wait trips, then takes it back on the draw.
+        assert_eq!(
+            verdicts(&rom, 25, 10),
+            vec![(9, Verdict::Hung), (20, Verdict::Recovered)]
+        );
+    }
+
+    #[test]
+    fn test_watchdog_ignores_frames_waiting_for_a_key() {
+        let rom = assemble_opcodes(&[Opcode::WaitForKey(0x0)]);
+
+        assert_eq!(verdicts(&rom, 20, 10), vec![]);
+        assert_eq!("warn".parse(), Ok(HangResponse::Warn));
+    }
+
+    #[test]
+    fn test_strict_pauses_whatever_the_response() {
+        for response in [HangResponse::Off, HangResponse::Warn, HangResponse::Title] {
+            assert!(response.react(Verdict::Hung, true).pause, "{:?}", response);
+            assert!(
+                !response.react(Verdict::Hung, false).pause,
+                "{:?}",
+                response
+            );
+            assert!(
+                !response.react(Verdict::Recovered, true).pause,
+                "{:?}",
+                response
+            );
+        }
+        assert_eq!(
+            HangResponse::Off.react(Verdict::Hung, true),
+            Reaction {
+                pause: true,
+                ..Reaction::default()
+            }
+        );
+        assert_eq!(
+            HangResponse::Title.react(Verdict::Hung, false),
+            Reaction {
+                warn: true,
+                set_title: true,
+                pause: false,
+            }
+        );
+    }
+}
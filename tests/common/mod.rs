@@ -0,0 +1,190 @@
+// Shared by the integration tests that run whole ROMs.
+//
+// Snapshots are the ASCII display of a finished run, kept in tests/snapshots/<name>.snap. After
+// a deliberate change to what a ROM draws, review the printed diff and bless the new output with
+//
+//     UPDATE_SNAPSHOTS=1 cargo test --test snapshots
+//
+// then check the rewritten .snap files in along with the change.
+
+use ch8emu::chip8::{BootConfig, Chip8, Options, StopConditions, StopReason};
+use std::path::PathBuf;
+
+// Instructions per 60 Hz timer tick at the default speed of 600 per second.
+const INSTRUCTIONS_PER_TICK: u32 = 10;
+
+// How run_until_halt ended, so a test can tell a ROM that finished from one that ran out of time
+// or was stopped by something else.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HaltOutcome {
+    // The ROM jumped to itself or waited for a key, after `cycles` instructions.
+    Halted { cycles: u64 },
+    // A breakpoint or watchpoint stopped the ROM after `cycles` instructions.
+    Stopped { reason: StopReason, cycles: u64 },
+    // The ROM was still running when `cycles` reached the limit.
+    StepLimitReached { cycles: u64 },
+}
+
+// Boots a machine with `options`, loads `rom` and runs it for `cycles` instructions, or until it
+// halts or waits for a key.
+pub fn run_rom(rom: &[u8], options: Options, cycles: u64) -> Chip8 {
+    let mut system = Chip8::with_options(options).unwrap();
+    system.boot(BootConfig::default()).unwrap();
+    system.load_rom(rom).unwrap();
+    run_until_halt(&mut system, cycles);
+    system
+}
+
+// Runs `system` for up to `max_steps` instructions, stopping when it halts or waits for a key.
+// Timers are ticked by instruction count rather than wall-clock time, so every run of the same ROM
+// and options ends in the same state.
+pub fn run_until_halt(system: &mut Chip8, max_steps: u64) -> HaltOutcome {
+    let stop = StopConditions {
+        halted: true,
+        ..StopConditions::default()
+    };
+    let mut cycles = 0;
+    while cycles < max_steps {
+        let max = (max_steps - cycles).min(u64::from(INSTRUCTIONS_PER_TICK)) as u32;
+        let result = system.run_steps(max, stop.clone()).unwrap();
+        cycles += u64::from(result.instructions);
+        match result.stopped {
+            Some(StopReason::Halted | StopReason::WaitingForKey) => {
+                return HaltOutcome::Halted { cycles }
+            }
+            Some(reason) => return HaltOutcome::Stopped { reason, cycles },
+            None => {}
+        }
+        if result.instructions == INSTRUCTIONS_PER_TICK {
+            system.tick_timers();
+        }
+    }
+    HaltOutcome::StepLimitReached { cycles }
+}
+
+fn snapshot_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("snapshots")
+        .join(format!("{}.snap", name))
+}
+
+// Compares `actual` with the snapshot called `name`, failing with a diff if they differ. With
+// UPDATE_SNAPSHOTS set, the snapshot is written instead.
+pub fn assert_snapshot(name: &str, actual: &str) {
+    let path = snapshot_path(name);
+    let actual = format!("{}\n", actual);
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        std::fs::write(&path, &actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(&path).unwrap_or_else(|err| {
+        panic!(
+            "can't read snapshot {}: {}; run with UPDATE_SNAPSHOTS=1 to create it",
+            path.display(),
+            err
+        )
+    });
+    if expected != actual {
+        panic!(
+            "snapshot {} doesn't match, run with UPDATE_SNAPSHOTS=1 to bless it\n{}",
+            name,
+            unified_diff(&expected, &actual)
+        );
+    }
+}
+
+// Lines of context kept around each change.
+const CONTEXT: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Edit<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+// A line diff from the longest common subsequence of lines. Snapshots are short enough for the
+// quadratic table.
+fn edits<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Edit<'a>> {
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut edits = Vec::new();
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            edits.push(Edit::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || common[i + 1][j] >= common[i][j + 1]) {
+            edits.push(Edit::Removed(old[i]));
+            i += 1;
+        } else {
+            edits.push(Edit::Added(new[j]));
+            j += 1;
+        }
+    }
+    edits
+}
+
+// The changes from `expected` to `actual` in unified diff format.
+pub fn unified_diff(expected: &str, actual: &str) -> String {
+    let old: Vec<&str> = expected.lines().collect();
+    let new: Vec<&str> = actual.lines().collect();
+    let edits = edits(&old, &new);
+    let changed: Vec<usize> = (0..edits.len())
+        .filter(|&index| !matches!(edits[index], Edit::Same(_)))
+        .collect();
+
+    let mut lines = vec!["--- expected".to_string(), "+++ actual".to_string()];
+    let mut index = 0;
+    while index < changed.len() {
+        // Changes closer together than twice the context share a hunk.
+        let start = changed[index].saturating_sub(CONTEXT);
+        let mut end = changed[index];
+        while index < changed.len() && changed[index] <= end + 2 * CONTEXT {
+            end = changed[index];
+            index += 1;
+        }
+        let end = (end + CONTEXT + 1).min(edits.len());
+
+        let old_start = edits[..start]
+            .iter()
+            .filter(|edit| !matches!(edit, Edit::Added(_)))
+            .count();
+        let new_start = edits[..start]
+            .iter()
+            .filter(|edit| !matches!(edit, Edit::Removed(_)))
+            .count();
+        let hunk = &edits[start..end];
+        let old_len = hunk
+            .iter()
+            .filter(|edit| !matches!(edit, Edit::Added(_)))
+            .count();
+        let new_len = hunk
+            .iter()
+            .filter(|edit| !matches!(edit, Edit::Removed(_)))
+            .count();
+        lines.push(format!(
+            "@@ -{},{} +{},{} @@",
+            old_start + 1,
+            old_len,
+            new_start + 1,
+            new_len
+        ));
+        lines.extend(hunk.iter().map(|edit| match edit {
+            Edit::Same(line) => format!(" {}", line),
+            Edit::Removed(line) => format!("-{}", line),
+            Edit::Added(line) => format!("+{}", line),
+        }));
+    }
+    lines.join("\n")
+}
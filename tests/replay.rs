@@ -0,0 +1,138 @@
+use ch8emu::chip8::{BootConfig, Chip8, Options, Quirks, Target};
+use ch8emu::replay::{replay_bundle, rom_hash, Bundle, Recorder, Replay, FRAME_DURATION};
+use ch8emu::script::Script;
+
+// Draws the glyph of each key pressed at a random position.
+const KEY_ROM: [u8; 12] = [
+    0xF0, 0x0A, // 0x200: V0 = next key
+    0xC1, 0xFF, // 0x202: V1 = random x
+    0xC2, 0x1F, // 0x204: V2 = random y
+    0xF0, 0x29, // 0x206: I = glyph of V0
+    0xD1, 0x25, // 0x208: draw it
+    0x12, 0x00, // 0x20A: repeat
+];
+
+// The keypad state for each frame of the recorded session.
+fn held_key(frame: u64) -> Option<u8> {
+    match frame {
+        10..=14 => Some(0x5),
+        30..=31 => Some(0xA),
+        50..=79 => Some(0xF),
+        _ => None,
+    }
+}
+
+fn machine(options: Options) -> Chip8 {
+    let mut system = Chip8::with_options(options).unwrap();
+    system.boot(BootConfig::default()).unwrap();
+    system.load_rom(&KEY_ROM).unwrap();
+    system
+}
+
+// Runs a session the way the windowed frontend does while recording, returning the replay and
+// the state the session ended in.
+fn record_session(options: Options) -> (Replay, u64) {
+    let (recorder, hash) = record(options);
+    (recorder.replay().clone(), hash)
+}
+
+fn record(options: Options) -> (Recorder, u64) {
+    let mut recorder = Recorder::new(&KEY_ROM, options);
+    let mut system = machine(recorder.options());
+    for frame in 0..120 {
+        match held_key(frame) {
+            Some(key) => system.press_key(key).unwrap(),
+            None => system.release_key(),
+        }
+        recorder.record_frame(system.get_pressed_key());
+        system.run_for(FRAME_DURATION).unwrap();
+    }
+    (recorder, system.state_hash())
+}
+
+fn play(replay: &Replay) -> u64 {
+    replay.check_rom(&KEY_ROM).unwrap();
+    let mut system = machine(replay.options(Options::default()));
+    replay.play(&mut system).unwrap();
+    system.state_hash()
+}
+
+#[test]
+fn test_replay_reproduces_the_session() {
+    let options = Options {
+        quirks: Quirks::preset(Target::Chip8),
+        ..Options::default()
+    };
+    let (recorded, session_hash) = record_session(options);
+    let replay = Replay::parse(&recorded.to_text()).unwrap();
+
+    assert_eq!(replay, recorded);
+    assert_eq!(play(&replay), session_hash);
+    assert_eq!(play(&replay), session_hash);
+}
+
+#[test]
+fn test_replay_depends_on_the_seed() {
+    let (replay, session_hash) = record_session(Options::default());
+    let reseeded = Replay {
+        rng_seed: replay.rng_seed.wrapping_add(1),
+        ..replay.clone()
+    };
+
+    assert_eq!(play(&replay), session_hash);
+    assert_ne!(play(&reseeded), session_hash);
+}
+
+#[test]
+fn test_replay_refuses_another_rom() {
+    let (replay, _) = record_session(Options::default());
+    let mut other_rom = KEY_ROM;
+    other_rom[3] = 0x0F;
+
+    assert!(replay.check_rom(&other_rom).is_err());
+}
+
+#[test]
+fn test_bundle_reproduces_the_session() {
+    let options = Options {
+        quirks: Quirks::preset(Target::SuperChipLegacy),
+        sticky_keys: true,
+        ..Options::default()
+    };
+    let (recorder, session_hash) = record(options);
+    let path = std::env::temp_dir().join(format!("ch8emu-bundle-{}.c8bundle", std::process::id()));
+    std::fs::write(&path, recorder.bundle().to_text()).unwrap();
+
+    let bundle = Bundle::load(&path).unwrap();
+    let system = replay_bundle(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(bundle, recorder.bundle());
+    assert_eq!(bundle.rom, KEY_ROM);
+    assert_eq!(system.state_hash(), session_hash);
+}
+
+#[test]
+fn test_script_reaches_a_known_display() {
+    let script = Script::parse(
+        "frames 120\n\
+         seed 1234\n\
+         10-14: hold 5\n\
+         30: tap A\n\
+         tap F every 20 starting 50 until 110\n",
+    )
+    .unwrap();
+    let replay = script.replay(&KEY_ROM, Options::default());
+    let exported = Script::parse(&Script::from_replay(&replay).to_text()).unwrap();
+
+    let mut system = machine(replay.options(Options::default()));
+    replay.play(&mut system).unwrap();
+
+    assert_eq!(exported.inputs, script.inputs);
+    assert_eq!(exported.seed, script.seed);
+    assert_eq!(exported.quirks, Some(replay.quirks));
+    assert_eq!(
+        rom_hash(system.render_ascii().as_bytes()),
+        0x05A0_D4E9_092F_BF1B
+    );
+}
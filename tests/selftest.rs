@@ -0,0 +1,36 @@
+use ch8emu::chip8::{Quirks, Target};
+use ch8emu::selftest::{ALL_TARGETS, SELF_TESTS};
+
+#[test]
+fn test_self_tests_pass_under_their_profiles_and_fail_under_the_rest() {
+    for test in &SELF_TESTS {
+        for target in ALL_TARGETS {
+            assert_eq!(
+                test.run(target),
+                Some(test.expected(target)),
+                "{} under {:?}",
+                test.name,
+                target
+            );
+        }
+    }
+}
+
+#[test]
+fn test_chip48_quirks_match_only_chip48() {
+    let outcomes: Vec<_> = SELF_TESTS
+        .iter()
+        .map(|test| test.run_with(Quirks::chip48()))
+        .collect();
+
+    let matching: Vec<Target> = ALL_TARGETS
+        .into_iter()
+        .filter(|&target| {
+            SELF_TESTS
+                .iter()
+                .map(|test| Some(test.expected(target)))
+                .eq(outcomes.iter().copied())
+        })
+        .collect();
+    assert_eq!(matching, vec![Target::Chip48]);
+}
@@ -0,0 +1,170 @@
+mod common;
+
+use ch8emu::chip8::{assemble_opcodes, BootConfig, Chip8, Opcode, Options, StopReason};
+use ch8emu::info::RomInfo;
+use ch8emu::splash::SPLASH_ROM;
+use common::{assert_snapshot, run_rom, run_until_halt, unified_diff, HaltOutcome};
+
+// Enough for each of these ROMs to finish drawing and reach its final self-jump.
+const CYCLES: u64 = 2_000;
+
+// The IBM logo ROM that comes with most CHIP-8 test suites.
+const IBM_LOGO_ROM: [u8; 132] = [
+    0x00, 0xE0, 0xA2, 0x2A, 0x60, 0x0C, 0x61, 0x08, 0xD0, 0x1F, 0x70, 0x09, 0xA2, 0x39, 0xD0, 0x1F,
+    0xA2, 0x48, 0x70, 0x08, 0xD0, 0x1F, 0x70, 0x04, 0xA2, 0x57, 0xD0, 0x1F, 0x70, 0x08, 0xA2, 0x66,
+    0xD0, 0x1F, 0x70, 0x08, 0xA2, 0x75, 0xD0, 0x1F, 0x12, 0x28, 0xFF, 0x00, 0xFF, 0x00, 0x3C, 0x00,
+    0x3C, 0x00, 0x3C, 0x00, 0x3C, 0x00, 0xFF, 0x00, 0xFF, 0xFF, 0x00, 0xFF, 0x00, 0x38, 0x00, 0x3F,
+    0x00, 0x3F, 0x00, 0x38, 0x00, 0xFF, 0x00, 0xFF, 0x80, 0x00, 0xE0, 0x00, 0xE0, 0x00, 0x80, 0x00,
+    0x80, 0x00, 0xE0, 0x00, 0xE0, 0x00, 0x80, 0xF8, 0x00, 0xFC, 0x00, 0x3E, 0x00, 0x3F, 0x00, 0x3B,
+    0x00, 0x39, 0x00, 0xF8, 0x00, 0xF8, 0x03, 0x00, 0x07, 0x00, 0x0F, 0x00, 0xBF, 0x00, 0xFB, 0x00,
+    0xF3, 0x00, 0xE3, 0x00, 0x43, 0xE0, 0x00, 0xE0, 0x00, 0x80, 0x00, 0x80, 0x00, 0x80, 0x00, 0x80,
+    0x00, 0xE0, 0x00, 0xE0,
+];
+
+// Fills 64x32 pixels with randomly chosen diagonals, like David Winter's Maze.
+const MAZE_ROM: [u8; 34] = [
+    0xA2, 0x1E, // 0x200: I = `/`
+    0xC2, 0x01, // 0x202: V2 = random bit
+    0x32, 0x01, // 0x204: keep `/` if it's 1
+    0xA2, 0x1A, // 0x206: otherwise I = `\`
+    0xD0, 0x14, // 0x208: draw it at V0, V1
+    0x70, 0x04, // 0x20A: V0 += 4
+    0x30, 0x40, // 0x20C: until the end of the row
+    0x12, 0x00, // 0x20E: next cell
+    0x60, 0x00, // 0x210: V0 = 0
+    0x71, 0x04, // 0x212: V1 += 4
+    0x31, 0x20, // 0x214: until the last row
+    0x12, 0x00, // 0x216: next row
+    0x12, 0x18, // 0x218: done
+    0x80, 0x40, 0x20, 0x10, // 0x21A: `\`
+    0x10, 0x20, 0x40, 0x80, // 0x21E: `/`
+];
+
+// Draws the built-in hex digits in two rows of eight.
+const FONT_ROM: [u8; 28] = [
+    0x60, 0x00, // 0x200: V0 = 0, the digit
+    0x61, 0x01, // 0x202: V1 = 1, x
+    0x62, 0x01, // 0x204: V2 = 1, y
+    0xF0, 0x29, // 0x206: I = glyph of V0
+    0xD1, 0x25, // 0x208: draw it
+    0x70, 0x01, // 0x20A: next digit
+    0x71, 0x06, // 0x20C: x += 6
+    0x31, 0x31, // 0x20E: until eight are drawn
+    0x12, 0x06, // 0x210: draw the next
+    0x61, 0x01, // 0x212: x = 1
+    0x72, 0x06, // 0x214: y += 6
+    0x30, 0x10, // 0x216: until all sixteen are drawn
+    0x12, 0x06, // 0x218: draw the next
+    0x12, 0x1A, // 0x21A: done
+];
+
+fn booted_with(rom: &[u8]) -> Chip8 {
+    let mut system = Chip8::with_options(Options::default()).unwrap();
+    system.boot(BootConfig::default()).unwrap();
+    system.load_rom(rom).unwrap();
+    system
+}
+
+#[test]
+fn test_run_until_halt_on_a_self_jump() {
+    let mut system = booted_with(&IBM_LOGO_ROM);
+
+    // 20 instructions of drawing, then the jump to itself.
+    assert_eq!(
+        run_until_halt(&mut system, CYCLES),
+        HaltOutcome::Halted { cycles: 21 }
+    );
+}
+
+#[test]
+fn test_run_until_halt_on_an_endless_loop() {
+    let rom = assemble_opcodes(&[Opcode::AddByte(0x0, 1), Opcode::Jump(0x200)]);
+    let mut system = booted_with(&rom);
+
+    assert_eq!(
+        run_until_halt(&mut system, 25),
+        HaltOutcome::StepLimitReached { cycles: 25 }
+    );
+}
+
+#[test]
+fn test_run_until_halt_on_a_breakpoint() {
+    let mut system = booted_with(&IBM_LOGO_ROM);
+    system.add_breakpoint(0x20A);
+
+    assert_eq!(
+        run_until_halt(&mut system, CYCLES),
+        HaltOutcome::Stopped {
+            reason: StopReason::Breakpoint(0x20A),
+            cycles: 5
+        }
+    );
+}
+
+#[test]
+fn test_ibm_logo_snapshot() {
+    let system = run_rom(&IBM_LOGO_ROM, Options::default(), CYCLES);
+
+    assert_snapshot("ibm_logo", &system.render_ascii());
+}
+
+#[test]
+fn test_maze_snapshot() {
+    let options = Options {
+        rng_seed: Some(0xC8),
+        ..Options::default()
+    };
+    let system = run_rom(&MAZE_ROM, options, CYCLES);
+
+    assert_snapshot("maze", &system.render_ascii());
+}
+
+#[test]
+fn test_font_snapshot() {
+    let system = run_rom(&FONT_ROM, Options::default(), CYCLES);
+
+    assert_snapshot("font", &system.render_ascii());
+}
+
+#[test]
+fn test_splash_snapshot() {
+    let system = run_rom(&SPLASH_ROM, Options::default(), CYCLES);
+
+    assert_snapshot("splash", &system.render_ascii_with_status());
+}
+
+#[test]
+fn test_ibm_logo_info_snapshot() {
+    assert_snapshot("ibm_logo_info", &RomInfo::new(&IBM_LOGO_ROM).to_text());
+}
+
+#[test]
+fn test_maze_info_snapshot() {
+    assert_snapshot("maze_info", &RomInfo::new(&MAZE_ROM).to_text());
+}
+
+#[test]
+fn test_unified_diff() {
+    let expected = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj";
+    let actual = "a\nb\nC\nd\ne\nf\ng\nh\ni\nj\nk";
+
+    assert_eq!(
+        unified_diff(expected, actual),
+        [
+            "--- expected",
+            "+++ actual",
+            "@@ -1,5 +1,5 @@",
+            " a",
+            " b",
+            "-c",
+            "+C",
+            " d",
+            " e",
+            "@@ -9,2 +9,3 @@",
+            " i",
+            " j",
+            "+k",
+        ]
+        .join("\n")
+    );
+}
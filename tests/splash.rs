@@ -0,0 +1,28 @@
+use ch8emu::chip8::{Chip8, StopReason};
+use ch8emu::splash::SPLASH_ROM;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::time::Duration;
+
+fn display_hash(system: &Chip8) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    system.render_ascii().hash(&mut hasher);
+    hasher.finish()
+}
+
+#[test]
+fn test_splash_rom_draws_and_idles() {
+    let mut system = Chip8::booted();
+    let blank = display_hash(&system);
+    system.load_rom(&SPLASH_ROM).unwrap();
+
+    let summary = system.run_for(Duration::from_millis(100)).unwrap();
+
+    assert_eq!(summary.stopped, Some(StopReason::Halted));
+    assert!(system.violations().is_empty());
+    assert_ne!(display_hash(&system), blank);
+
+    let lines: Vec<String> = system.render_ascii().lines().map(String::from).collect();
+    assert_eq!(&lines[8][16..30], "####.#..#.####");
+    assert_eq!(&lines[16][16..31], "####.#...#.#..#");
+}